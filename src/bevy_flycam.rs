@@ -1,7 +1,8 @@
 // modified from https://github.com/sburris0/bevy_flycam
 use bevy::app::{Events, ManualEventReader};
-use bevy::input::mouse::MouseMotion;
+use bevy::input::mouse::{MouseMotion, MouseWheel};
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 /// Keeps track of mouse motion events, pitch, and yaw
 #[derive(Default)]
@@ -16,6 +17,32 @@ impl InputState {
         self.pitch = angle * axis.dot(Vec3::X);
         self.yaw = angle * axis.dot(Vec3::Y);
     }
+
+    /// Point the camera along dir, without roll
+    pub fn look_along(&mut self, dir: Vec3) {
+        let dir = dir.normalize();
+        self.pitch = dir.y.asin().clamp(-1.54, 1.54);
+        self.yaw = (-dir.x).atan2(-dir.z);
+    }
+
+    /// Turn the camera about the vertical axis, by angle in radians
+    pub fn turn(&mut self, angle: f32) {
+        self.yaw = (self.yaw + angle) % std::f32::consts::TAU;
+    }
+
+    /// Rotation of the camera given by pitch and yaw
+    pub fn rotation(&self) -> Quat {
+        Quat::from_axis_angle(Vec3::Y, self.yaw) * Quat::from_axis_angle(Vec3::X, self.pitch)
+    }
+}
+
+/// Place the camera distance away from target, keeping the direction it looks in as far as possible
+pub fn focus_on(transform: &mut Transform, state: &mut InputState, target: Vec3, distance: f32) {
+    let dir = (target - transform.translation).normalize_or_zero();
+    let dir = if dir == Vec3::ZERO { -Vec3::Z } else { dir };
+    state.look_along(dir);
+    transform.rotation = state.rotation();
+    transform.translation = target - dir * distance;
 }
 /// Mouse sensitivity and movement speed
 pub struct MovementSettings {
@@ -35,6 +62,44 @@ impl Default for MovementSettings {
 /// Used in queries when you want flycams and not other cameras
 pub struct FlyCam;
 
+/// How the camera is controlled, O switches between the modes
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CameraMode {
+    Fly,   // WASD and the locked mouse move the camera freely
+    Orbit, // right mouse drag rotates around the orbit center, scrolling zooms
+}
+
+impl Default for CameraMode {
+    fn default() -> Self {
+        CameraMode::Fly
+    }
+}
+
+impl CameraMode {
+    pub const ALL: [CameraMode; 2] = [CameraMode::Fly, CameraMode::Orbit];
+}
+
+/// Point the orbit camera circles around, and how far from it
+pub struct Orbit {
+    pub center: Vec3,
+    pub distance: f32,
+}
+
+impl Default for Orbit {
+    fn default() -> Self {
+        Self {
+            center: Vec3::ZERO,
+            distance: 15.0,
+        }
+    }
+}
+
+impl Orbit {
+    const MIN_DISTANCE: f32 = 0.5;
+    const ZOOM_PER_LINE: f32 = 0.1; // fraction of the distance zoomed per scrolled line
+}
+
 /// Grabs/ungrabs mouse cursor
 fn toggle_grab_cursor(window: &mut Window) {
     window.set_cursor_lock_mode(!window.cursor_locked());
@@ -62,8 +127,12 @@ fn player_move(
     time: Res<Time>,
     windows: Res<Windows>,
     settings: Res<MovementSettings>,
+    mode: Res<CameraMode>,
     mut query: Query<(&FlyCam, &mut Transform)>,
 ) {
+    if *mode != CameraMode::Fly {
+        return;
+    }
     let window = windows.get_primary().unwrap();
     for (_camera, mut transform) in query.iter_mut() {
         let mut velocity = Vec3::ZERO;
@@ -99,8 +168,12 @@ fn player_look(
     windows: Res<Windows>,
     mut state: ResMut<InputState>,
     motion: Res<Events<MouseMotion>>,
+    mode: Res<CameraMode>,
     mut query: Query<(&FlyCam, &mut Transform)>,
 ) {
+    if *mode != CameraMode::Fly {
+        return;
+    }
     let window = windows.get_primary().unwrap();
     for (_camera, mut transform) in query.iter_mut() {
         for ev in state.reader_motion.iter(&motion) {
@@ -121,9 +194,81 @@ fn player_look(
     }
 }
 
-fn cursor_grab(keys: Res<Input<KeyCode>>, mut windows: ResMut<Windows>) {
+/// Rotates the orbit camera while the right mouse button is held
+fn orbit_look(
+    settings: Res<MovementSettings>,
+    windows: Res<Windows>,
+    buttons: Res<Input<MouseButton>>,
+    mode: Res<CameraMode>,
+    mut state: ResMut<InputState>,
+    mut motion: EventReader<MouseMotion>,
+) {
+    if *mode != CameraMode::Orbit {
+        return;
+    }
+
+    let window = windows.get_primary().unwrap();
+    let window_scale = window.height().min(window.width());
+    for ev in motion.iter() {
+        if buttons.pressed(MouseButton::Right) {
+            state.pitch -= (settings.sensitivity * ev.delta.y * window_scale).to_radians();
+            state.yaw -= (settings.sensitivity * ev.delta.x * window_scale).to_radians();
+        }
+    }
+    state.pitch = state.pitch.clamp(-1.54, 1.54);
+}
+
+/// Zooms the orbit camera on scroll
+fn orbit_zoom(
+    mode: Res<CameraMode>,
+    mut orbit: ResMut<Orbit>,
+    mut scroll: EventReader<MouseWheel>,
+) {
+    if *mode != CameraMode::Orbit {
+        return;
+    }
+
+    for ev in scroll.iter() {
+        orbit.distance *= 1.0 - Orbit::ZOOM_PER_LINE * ev.y.clamp(-3.0, 3.0);
+    }
+    orbit.distance = orbit.distance.max(Orbit::MIN_DISTANCE);
+}
+
+/// Places the orbit camera on its sphere around the center
+fn orbit_follow(
+    mode: Res<CameraMode>,
+    state: Res<InputState>,
+    orbit: Res<Orbit>,
+    mut query: Query<(&FlyCam, &mut Transform)>,
+) {
+    if *mode != CameraMode::Orbit {
+        return;
+    }
+
+    for (_camera, mut transform) in query.iter_mut() {
+        transform.rotation = state.rotation();
+        transform.translation = orbit.center + transform.rotation * Vec3::Z * orbit.distance;
+    }
+}
+
+fn cursor_grab(
+    keys: Res<Input<KeyCode>>,
+    mut mode: ResMut<CameraMode>,
+    mut windows: ResMut<Windows>,
+) {
     let window = windows.get_primary_mut().unwrap();
-    if keys.just_pressed(KeyCode::Escape) {
+    if keys.just_pressed(KeyCode::O) {
+        *mode = match *mode {
+            CameraMode::Fly => CameraMode::Orbit,
+            CameraMode::Orbit => CameraMode::Fly,
+        };
+    }
+    if *mode == CameraMode::Orbit {
+        // the orbit camera is dragged with the mouse, it needs a free cursor
+        if window.cursor_locked() {
+            toggle_grab_cursor(window);
+        }
+    } else if keys.just_pressed(KeyCode::Escape) {
         toggle_grab_cursor(window);
     }
 }
@@ -134,10 +279,15 @@ impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.init_resource::<InputState>()
             .init_resource::<MovementSettings>()
+            .init_resource::<CameraMode>()
+            .init_resource::<Orbit>()
             .add_startup_system(setup_player.system())
             .add_startup_system(initial_grab_cursor.system())
             .add_system(player_move.system())
             .add_system(player_look.system())
+            .add_system(orbit_look.system().label("orbit input"))
+            .add_system(orbit_zoom.system().label("orbit input"))
+            .add_system(orbit_follow.system().after("orbit input"))
             .add_system(cursor_grab.system());
     }
 }
@@ -148,9 +298,14 @@ impl Plugin for NoCameraPlayerPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.init_resource::<InputState>()
             .init_resource::<MovementSettings>()
+            .init_resource::<CameraMode>()
+            .init_resource::<Orbit>()
             .add_startup_system(initial_grab_cursor.system())
             .add_system(player_move.system())
             .add_system(player_look.system())
+            .add_system(orbit_look.system().label("orbit input"))
+            .add_system(orbit_zoom.system().label("orbit input"))
+            .add_system(orbit_follow.system().after("orbit input"))
             .add_system(cursor_grab.system());
     }
 }
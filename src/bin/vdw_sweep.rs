@@ -0,0 +1,55 @@
+// Batch runner for parameter sweeps
+// Runs the simulations of a sweep file without rendering, see vdw_core::state::sweep for the format,
+// and writes the frames of each run and a summary of all of them to the output directory
+extern crate clap;
+
+use clap::{App as Cli, Arg};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use vdw_core::state::sweep::{self, SweepSpec};
+use vdw_core::state::SimulationPrototype;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let matches = Cli::new("Van Der Waals Interaction sweep")
+        .arg(
+            Arg::new("spec")
+                .value_name("FILE")
+                .required(true)
+                .about("TOML file listing the temperatures, densities and seeds to run"),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .value_name("DIR")
+                .takes_value(true)
+                .default_value("sweep")
+                .about("Directory the results files and summary.csv are written to"),
+        )
+        .get_matches();
+
+    let path = Path::new(matches.value_of("spec").unwrap());
+    let spec = SweepSpec::from_file(path)?;
+    // the base config is found next to the sweep file
+    let base = match &spec.config {
+        Some(config) => {
+            let dir = path.parent().unwrap_or_else(|| Path::new(""));
+            SimulationPrototype::from_config_file(dir.join(config))?
+        }
+        None => SimulationPrototype::new(),
+    };
+
+    println!("Running {} simulations", spec.points().len());
+    let report = sweep::run(&spec, &base)?;
+    println!("{}", report);
+
+    let output = Path::new(matches.value_of("output").unwrap());
+    fs::create_dir_all(output)?;
+    for run in report.runs.iter() {
+        run.export_csv(output.join(run.point.file_name()))?;
+    }
+    report.export_summary(output.join("summary.csv"))?;
+    println!("Results written to {}", output.display());
+
+    Ok(())
+}
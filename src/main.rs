@@ -3,45 +3,377 @@ extern crate bevy;
 extern crate clap;
 extern crate itertools;
 extern crate ndarray;
-extern crate rand;
-extern crate rand_distr;
-extern crate rayon;
 extern crate ringbuffer as rb;
 
 mod bevy_flycam;
-mod ring_buffer;
-mod state;
+mod plugin;
 
 use bevy::prelude::*;
 use bevy_egui::EguiPlugin;
 use bevy_flycam::NoCameraPlayerPlugin;
+use clap::{App as Cli, Arg};
+use state::experiment::{self, IsothermSweep, Quench};
+use state::logger::{ObservableLogger, Verbosity};
+use state::percolation;
+use state::preset::Preset;
+use state::replica;
+use state::sensitivity::{self, ScanParameter};
 use state::state_generator::Initialize;
+use std::error::Error;
+use vdw_core::state;
 
-fn main() -> Result<(), state::error::InvalidParamError> {
-    let vdw_simulation = state::SimulationPrototype::new()
+#[cfg(target_arch = "wasm32")]
+compile_error!(
+    "wasm32 isn't supported yet, bevy 0.5 needs the bevy_webgl2 plugin to render in a browser"
+);
+
+// Setup used without a config file or preset
+fn default_prototype() -> state::SimulationPrototype {
+    state::SimulationPrototype::new()
         .set_bound_x(15.0)
         .set_bound_y(15.0)
         .set_bound_z(15.0)
         .set_dt(0.001)
         .set_steps_per_frame(20)
         .initialize_spherical_cloud(2000, 1.0, 1.4)
-        .compile()?;
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::new("Van Der Waals Interaction")
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .value_name("FILE")
+                .takes_value(true)
+                .about("Read the simulation setup from a TOML file"),
+        )
+        .arg(
+            Arg::new("preset")
+                .long("preset")
+                .takes_value(true)
+                .possible_values(&Preset::NAMES)
+                .conflicts_with("config")
+                .about(
+                    "Start from one of the curated setups, also available in the Presets window",
+                ),
+        )
+        .arg(
+            Arg::new("scan")
+                .long("scan")
+                .value_name("PARAM")
+                .takes_value(true)
+                .possible_values(&ScanParameter::NAMES)
+                .about("Run a sensitivity scan over PARAM without rendering"),
+        )
+        .arg(
+            Arg::new("from")
+                .long("from")
+                .takes_value(true)
+                .requires("scan")
+                .about("Smallest value of the scanned parameter"),
+        )
+        .arg(
+            Arg::new("to")
+                .long("to")
+                .takes_value(true)
+                .requires("scan")
+                .about("Largest value of the scanned parameter"),
+        )
+        .arg(
+            Arg::new("samples")
+                .long("samples")
+                .takes_value(true)
+                .default_value("5")
+                .about("Number of values of the scanned parameter"),
+        )
+        .arg(
+            Arg::new("percolation")
+                .long("percolation")
+                .conflicts_with("scan")
+                .about("Measure the percolation probability without rendering"),
+        )
+        .arg(
+            Arg::new("densities")
+                .long("densities")
+                .takes_value(true)
+                .use_delimiter(true)
+                .default_value("0.5,1.0,2.0")
+                .about("Comma separated particle densities of the percolation scan"),
+        )
+        .arg(
+            Arg::new("temperatures")
+                .long("temperatures")
+                .takes_value(true)
+                .use_delimiter(true)
+                .default_value("0.5,1.0")
+                .about("Comma separated temperatures of the percolation scan"),
+        )
+        .arg(
+            Arg::new("particles")
+                .long("particles")
+                .takes_value(true)
+                .default_value("500")
+                .about("Number of particles in each run of the percolation scan"),
+        )
+        .arg(
+            Arg::new("runs")
+                .long("runs")
+                .takes_value(true)
+                .default_value("3")
+                .about("Number of runs per density and temperature"),
+        )
+        .arg(
+            Arg::new("duration")
+                .long("duration")
+                .takes_value(true)
+                .default_value("1.0")
+                .about("Simulated time of each run of the scan, after the quench, of the replicas or of sampling each isotherm point"),
+        )
+        .arg(
+            Arg::new("quench")
+                .long("quench")
+                .value_names(&["T_HIGH", "T_LOW"])
+                .number_of_values(2)
+                .use_delimiter(true)
+                .conflicts_with_all(&["scan", "percolation"])
+                .about("Equilibrate at T_HIGH, quench to T_LOW and report when a nucleus forms, without rendering"),
+        )
+        .arg(
+            Arg::new("equilibration")
+                .long("equilibration")
+                .takes_value(true)
+                .default_value("1.0")
+                .about("Simulated time at T_HIGH before the quench or at each volume of the isotherm"),
+        )
+        .arg(
+            Arg::new("nucleus")
+                .long("nucleus")
+                .takes_value(true)
+                .default_value("50")
+                .about("Size of the largest cluster that counts as a nucleus"),
+        )
+        .arg(
+            Arg::new("report")
+                .long("report")
+                .value_name("FILE")
+                .takes_value(true)
+                .requires("quench")
+                .about("Also write the quench report to FILE"),
+        )
+        .arg(
+            Arg::new("replicas")
+                .long("replicas")
+                .value_name("TEMPERATURES")
+                .takes_value(true)
+                .use_delimiter(true)
+                .conflicts_with_all(&["scan", "percolation", "quench"])
+                .about("Run one replica per comma separated temperature and swap configurations between neighbors"),
+        )
+        .arg(
+            Arg::new("isotherm")
+                .long("isotherm")
+                .value_name("TEMP")
+                .takes_value(true)
+                .conflicts_with_all(&["scan", "percolation", "quench", "replicas"])
+                .about("Compress the box step by step at TEMP and report the pressure at each volume, without rendering"),
+        )
+        .arg(
+            Arg::new("final-volume")
+                .long("final-volume")
+                .takes_value(true)
+                .default_value("0.3")
+                .about("Volume the isotherm ends at, as a fraction of the starting volume"),
+        )
+        .arg(
+            Arg::new("points")
+                .long("points")
+                .takes_value(true)
+                .default_value("10")
+                .about("Number of volumes along the isotherm"),
+        )
+        .arg(
+            Arg::new("isotherm-csv")
+                .long("isotherm-csv")
+                .value_name("FILE")
+                .takes_value(true)
+                .requires("isotherm")
+                .about("Also write the isotherm points to a CSV file"),
+        )
+        .arg(
+            Arg::new("show-replica")
+                .long("show-replica")
+                .value_name("INDEX")
+                .takes_value(true)
+                .default_value("0")
+                .about("Replica shown, counted from the coldest"),
+        )
+        .arg(
+            Arg::new("replica-report")
+                .long("replica-report")
+                .requires("replicas")
+                .about("Run the replicas for the duration without rendering and report the swap acceptance"),
+        )
+        .arg(
+            Arg::new("log")
+                .long("log")
+                .value_name("FILE")
+                .takes_value(true)
+                .about("Append the observables of every frame to a CSV file"),
+        )
+        .arg(
+            Arg::new("log-flush")
+                .long("log-flush")
+                .value_name("FRAMES")
+                .takes_value(true)
+                .default_value("60")
+                .about("Frames between flushes of the log file"),
+        )
+        .arg(
+            Arg::new("verbosity")
+                .long("verbosity")
+                .takes_value(true)
+                .possible_values(&Verbosity::NAMES)
+                .default_value("normal")
+                .about("How much the simulation prints to the console"),
+        );
+    #[cfg(feature = "remote")]
+    let cli = cli.arg(
+        Arg::new("remote")
+            .long("remote")
+            .value_name("ADDR")
+            .takes_value(true)
+            .about("Accept JSON commands over a WebSocket on ADDR, e.g. 127.0.0.1:9001"),
+    );
+    let matches = cli.get_matches();
+
+    let preset = matches
+        .value_of("preset")
+        .map(|name| Preset::from_name(name).unwrap());
+    let prototype = match (matches.value_of("config"), preset) {
+        (Some(path), _) => state::SimulationPrototype::from_config_file(path)?,
+        (None, Some(preset)) => preset.prototype(),
+        (None, None) => default_prototype(),
+    };
+
+    // Run a headless scan instead of the visual simulation
+    if let Some(name) = matches.value_of("scan") {
+        let parameter = ScanParameter::from_name(name).unwrap();
+        let values = sensitivity::linspace(
+            matches.value_of_t_or_exit("from"),
+            matches.value_of_t_or_exit("to"),
+            matches.value_of_t_or_exit("samples"),
+        );
+        let report = sensitivity::scan(
+            &prototype,
+            parameter,
+            &values,
+            matches.value_of_t_or_exit("duration"),
+        )?;
+        println!("{}", report);
+        return Ok(());
+    }
+    if matches.is_present("percolation") {
+        let n: usize = matches.value_of_t_or_exit("particles");
+        let make_prototype = |density: f32, temp: f32| {
+            // cubic box holding n particles at the given density
+            let side = (n as f32 / density).cbrt();
+            state::SimulationPrototype::new()
+                .set_bound_x(side)
+                .set_bound_y(side)
+                .set_bound_z(side)
+                .set_dt(0.001)
+                .set_steps_per_frame(20)
+                .set_target_temp(temp)
+                .set_inject_rate(0.1)
+                .initialize_uniform(n, state::physics::velocity_spread(temp))
+        };
+        let report = percolation::scan(
+            make_prototype,
+            &matches.values_of_t_or_exit::<f32>("densities"),
+            &matches.values_of_t_or_exit::<f32>("temperatures"),
+            matches.value_of_t_or_exit("runs"),
+            matches.value_of_t_or_exit("duration"),
+        )?;
+        println!("{}", report);
+        return Ok(());
+    }
+    if let Some(temperatures) = matches.values_of("quench") {
+        let temperatures: Vec<f32> = temperatures.map(|t| t.parse()).collect::<Result<_, _>>()?;
+        let mut quench = Quench::new(temperatures[0], temperatures[1]);
+        quench.equilibration = matches.value_of_t_or_exit("equilibration");
+        quench.duration = matches.value_of_t_or_exit("duration");
+        quench.nucleus = matches.value_of_t_or_exit("nucleus");
+        let report = experiment::run(&prototype, quench)?;
+        println!("{}", report);
+        if let Some(path) = matches.value_of("report") {
+            std::fs::write(path, report.to_string())?;
+        }
+        return Ok(());
+    }
+    if matches.is_present("isotherm") {
+        let mut sweep = IsothermSweep::new(matches.value_of_t_or_exit("isotherm"));
+        sweep.final_volume = matches.value_of_t_or_exit("final-volume");
+        sweep.points = matches.value_of_t_or_exit("points");
+        sweep.equilibration = matches.value_of_t_or_exit("equilibration");
+        sweep.sampling = matches.value_of_t_or_exit("duration");
+        let report = experiment::run(&prototype, sweep)?;
+        println!("{}", report);
+        if let Some(path) = matches.value_of("isotherm-csv") {
+            experiment::export_isotherm(&report.points, path)?;
+        }
+        return Ok(());
+    }
+    let replicas: Option<Vec<f32>> = matches
+        .values_of("replicas")
+        .map(|temperatures| temperatures.map(|t| t.parse()).collect::<Result<_, _>>())
+        .transpose()?;
+    if let (Some(temperatures), true) = (&replicas, matches.is_present("replica-report")) {
+        let report = replica::run(
+            &prototype,
+            temperatures,
+            matches.value_of_t_or_exit("duration"),
+        )?;
+        println!("{}", report);
+        return Ok(());
+    }
+
+    let verbosity = Verbosity::from_name(matches.value_of("verbosity").unwrap()).unwrap();
+    let logger = match matches.value_of("log") {
+        Some(path) => ObservableLogger::new(verbosity)
+            .with_file(path, matches.value_of_t_or_exit("log-flush"))?,
+        None => ObservableLogger::new(verbosity),
+    };
+
+    // a setup given on the command line keeps its own slider values
+    let from_command_line = matches.is_present("config") || matches.is_present("preset");
+    let mut plugin =
+        plugin::VDWPlugin::new(prototype, logger)?.with_saved_sliders(!from_command_line);
+    if let Some(temperatures) = &replicas {
+        plugin = plugin.with_replicas(temperatures, matches.value_of_t_or_exit("show-replica"))?;
+    }
 
-    App::build()
+    // antialiasing and vsync from the Graphics window of the last run
+    let (msaa, vsync) = (plugin.msaa(), plugin.vsync());
+    let mut app = App::build();
+    app.insert_resource(msaa)
         .add_plugins(DefaultPlugins)
         .add_plugin(NoCameraPlayerPlugin)
-        .add_plugin(vdw_simulation)
+        .add_plugin(plugin)
         .add_plugin(EguiPlugin)
-        // Set antialiasing to use 4 samples
-        // .insert_resource(Msaa { samples: 2 })
         // Set WindowDescriptor Resource to change title and size
         .insert_resource(WindowDescriptor {
             title: "Van Der Waals Interaction".to_string(),
             width: 800.,
             height: 800.,
+            vsync,
             ..Default::default()
-        })
-        .run();
+        });
+    #[cfg(feature = "remote")]
+    if let Some(addr) = matches.value_of("remote") {
+        app.insert_resource(plugin::remote::RemoteServer::bind(addr)?)
+            .add_system(plugin::remote::serve_requests.system());
+    }
+    app.run();
 
     Ok(())
 }
@@ -0,0 +1,323 @@
+// Bevy front end of the simulation
+// Renders the latest state and draws the gui, the simulation itself runs on a worker thread
+mod capture;
+mod colormap;
+mod export;
+mod heat_gun;
+mod keybindings;
+mod presentation;
+#[cfg(feature = "remote")]
+pub mod remote;
+mod render_systems;
+mod settings;
+mod sim_systems;
+mod ui_systems;
+
+use bevy::prelude::*;
+use std::sync::Mutex;
+use vdw_core::ring_buffer::RingBuffer;
+use vdw_core::state::particle::*;
+use vdw_core::state::probe::*;
+use vdw_core::state::sim_space::*;
+use vdw_core::state::*;
+
+pub struct VDWPlugin {
+    prototype: SimulationPrototype,
+    state: SimulationState,
+    replicas: Option<replica::ReplicaExchange>, // the state shown is one of the replicas
+    // build only gets &self, the logger is handed over to the worker the one time it runs
+    logger: Mutex<Option<logger::ObservableLogger>>,
+    restore_sliders: bool, // whether the saved slider values replace the ones of the prototype
+    saved: Option<settings::Settings>, // settings of the last run
+}
+
+impl VDWPlugin {
+    pub fn new(
+        prototype: SimulationPrototype,
+        logger: logger::ObservableLogger,
+    ) -> Result<Self, error::InvalidParamError> {
+        let simulation = prototype.compile()?;
+        for warning in simulation.warnings() {
+            eprintln!("Warning: {}", warning);
+        }
+        if let Some(report) = simulation.minimization() {
+            println!("{}", report);
+        }
+        Ok(Self {
+            state: simulation.into_state(),
+            prototype,
+            replicas: None,
+            logger: Mutex::new(Some(logger)),
+            restore_sliders: true,
+            saved: settings::Settings::load(),
+        })
+    }
+
+    // Antialiasing of the saved graphics settings
+    // bevy reads it while the render plugin is added, so it's inserted ahead of the default plugins
+    pub fn msaa(&self) -> Msaa {
+        let samples = self.saved.as_ref().map_or(1, |s| s.graphics.msaa_samples);
+        Msaa {
+            samples: render_systems::Graphics::valid_msaa_samples(samples),
+        }
+    }
+
+    pub fn vsync(&self) -> bool {
+        self.saved.as_ref().map_or(true, |s| s.graphics.vsync)
+    }
+
+    // Whether the slider values saved by the last run are restored, see settings.rs
+    pub fn with_saved_sliders(mut self, restore: bool) -> Self {
+        self.restore_sliders = restore;
+        self
+    }
+
+    // Run replicas of the prototype at the temperatures and show the replica at index selected
+    pub fn with_replicas(
+        mut self,
+        temperatures: &[f32],
+        selected: usize,
+    ) -> Result<Self, error::InvalidParamError> {
+        let mut exchange = replica::ReplicaExchange::new(&self.prototype, temperatures)?;
+        exchange.selected = selected.min(exchange.replicas.len() - 1);
+        self.state = exchange.get_selected().clone();
+        self.replicas = Some(exchange);
+        Ok(self)
+    }
+
+    // internal helper function
+    fn spawn_worker(&self) -> worker::SimulationWorker {
+        let logger = self
+            .logger
+            .lock()
+            .unwrap()
+            .take()
+            .expect("the plugin is only built once");
+        match &self.replicas {
+            Some(exchange) => worker::SimulationWorker::spawn_replicas(exchange.clone(), logger),
+            None => worker::SimulationWorker::spawn(self.state.clone(), logger),
+        }
+    }
+}
+
+// Setup the running simulation was compiled from, resetting compiles it again
+// Picking a preset replaces it
+pub struct InitialSetup {
+    pub prototype: SimulationPrototype,
+}
+
+impl Plugin for VDWPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(self.state.clone())
+            .insert_resource(InitialSetup {
+                prototype: self.prototype.clone(),
+            })
+            .insert_resource(self.spawn_worker())
+            .init_resource::<render_systems::ColorMode>()
+            .init_resource::<units::UnitSystem>()
+            .init_resource::<render_systems::CameraFocus>()
+            .init_resource::<render_systems::RenderDecimation>()
+            .init_resource::<render_systems::LevelOfDetail>()
+            .init_resource::<render_systems::Graphics>()
+            .init_resource::<render_systems::BoxView>()
+            .init_resource::<keybindings::KeyBindings>()
+            .init_resource::<keybindings::Rebinding>()
+            .init_resource::<keybindings::UiVisibility>()
+            .init_resource::<ui_systems::RestartFailure>()
+            .init_resource::<presentation::Presentation>()
+            .init_resource::<heat_gun::HeatGun>()
+            .init_resource::<capture::Capture>()
+            .init_resource::<field::FieldView>()
+            .init_resource::<field::FlowView>()
+            .init_resource::<profile::ProfileView>()
+            .init_resource::<interface::InterfaceView>()
+            .init_resource::<network::NetworkView>()
+            .init_resource::<cluster::ClusterView>()
+            .init_resource::<order::OrderView>()
+            .init_resource::<neighbor_check::NeighborCheck>()
+            .init_resource::<rewind::RewindBuffer>()
+            .init_resource::<watchdog::Watchdog>()
+            .init_resource::<trail::TrailView>()
+            .init_resource::<sim_systems::AppTimings>()
+            .insert_resource(settings::SettingsStore::new(
+                self.saved.clone(),
+                self.restore_sliders,
+            ))
+            .add_startup_system_to_stage(
+                StartupStage::PreStartup,
+                settings::restore_settings.system(),
+            )
+            .add_startup_system(render_systems::setup_bounding_box.system())
+            .add_startup_system(render_systems::setup_particles.system())
+            .add_startup_system(render_systems::setup_network.system())
+            .add_startup_system(render_systems::setup_field_slice.system())
+            .add_startup_system(render_systems::setup_flow.system())
+            .add_startup_system(render_systems::setup_trails.system())
+            .add_startup_system(render_systems::setup_camera.system())
+            .add_system(sim_systems::receive_snapshot.system().label("simulation"))
+            .add_system(sim_systems::update_fields.system().after("simulation"))
+            .add_system(sim_systems::update_profile.system().after("simulation"))
+            .add_system(sim_systems::update_interface.system().after("simulation"))
+            .add_system(
+                sim_systems::update_flow
+                    .system()
+                    .label("flow")
+                    .after("simulation"),
+            )
+            .add_system(sim_systems::update_clusters.system().after("simulation"))
+            .add_system(
+                sim_systems::update_order
+                    .system()
+                    .label("order")
+                    .after("simulation"),
+            )
+            .add_system(sim_systems::check_neighbors.system().after("simulation"))
+            .add_system(sim_systems::record_rewind.system().after("simulation"))
+            .add_system(
+                sim_systems::watch_divergence
+                    .system()
+                    .label("watchdog")
+                    .after("simulation"),
+            )
+            // shown even with the gui hidden
+            .add_system(ui_systems::divergence_dialog.system().after("watchdog"))
+            .add_system(ui_systems::restart_failure_dialog.system())
+            .add_system(
+                sim_systems::update_trails
+                    .system()
+                    .label("trails")
+                    .after("simulation"),
+            )
+            .add_system(
+                sim_systems::update_network
+                    .system()
+                    .label("network")
+                    .after("simulation"),
+            )
+            .add_system(
+                render_systems::update_particles_renders
+                    .system()
+                    .label("particle_renders")
+                    .after("order"),
+            )
+            .add_system(
+                capture::capture_frames
+                    .system()
+                    .after("particle_renders")
+                    .after("hotkeys")
+                    .after("ui"),
+            )
+            .add_system(
+                render_systems::update_bounding_box_renders
+                    .system()
+                    .after("simulation"),
+            )
+            .add_system(
+                render_systems::update_bounding_face_renders
+                    .system()
+                    .after("simulation"),
+            )
+            .add_system(
+                render_systems::update_floor_grid_renders
+                    .system()
+                    .after("simulation"),
+            )
+            .add_system(
+                render_systems::update_obstacle_renders
+                    .system()
+                    .after("simulation"),
+            )
+            .add_system(
+                render_systems::update_region_renders
+                    .system()
+                    .after("simulation"),
+            )
+            .add_system(
+                render_systems::update_field_slice_renders
+                    .system()
+                    .after("simulation"),
+            )
+            .add_system(render_systems::update_flow_renders.system().after("flow"))
+            .add_system(
+                render_systems::update_trail_renders
+                    .system()
+                    .after("trails"),
+            )
+            .add_system(
+                render_systems::update_network_renders
+                    .system()
+                    .after("network"),
+            )
+            .add_system(
+                render_systems::update_camera_focus
+                    .system()
+                    .after("simulation"),
+            )
+            .add_system(
+                render_systems::frame_box_camera
+                    .system()
+                    .after("simulation"),
+            )
+            .add_system(sim_systems::start_ui_timing.system().before("ui"))
+            .add_system(sim_systems::finish_ui_timing.system().after("ui"))
+            .add_system(keybindings::hotkeys.system().label("hotkeys").before("ui"))
+            .add_system(presentation::present.system().after("hotkeys"))
+            .add_system(heat_gun::heat_gun.system().before("ui"))
+            // the gui hotkey hides every window
+            .add_system_set(
+                SystemSet::new()
+                    .label("ui")
+                    .with_run_criteria(keybindings::ui_shown.system())
+                    .with_system(ui_systems::param_sliders.system())
+                    .with_system(ui_systems::display_settings.system())
+                    .with_system(ui_systems::render_settings.system())
+                    .with_system(ui_systems::graphics_settings.system())
+                    .with_system(ui_systems::simulation_info.system())
+                    .with_system(ui_systems::probe_list.system())
+                    .with_system(ui_systems::region_probes.system())
+                    .with_system(ui_systems::particle_table.system())
+                    .with_system(ui_systems::group_tools.system())
+                    .with_system(ui_systems::crystal_seed.system())
+                    .with_system(ui_systems::field_view.system())
+                    .with_system(ui_systems::flow_view.system())
+                    .with_system(ui_systems::profile_view.system())
+                    .with_system(ui_systems::interface_view.system())
+                    .with_system(ui_systems::trail_settings.system())
+                    .with_system(ui_systems::network_info.system())
+                    .with_system(ui_systems::external_fields.system())
+                    .with_system(ui_systems::cluster_info.system())
+                    .with_system(ui_systems::order_info.system())
+                    .with_system(ui_systems::neighbor_check_info.system())
+                    .with_system(ui_systems::fluctuation_info.system())
+                    .with_system(ui_systems::viscosity_info.system())
+                    .with_system(ui_systems::conductivity_info.system())
+                    .with_system(ui_systems::rewind_controls.system())
+                    .with_system(ui_systems::preset_menu.system())
+                    .with_system(ui_systems::console_window.system())
+                    .with_system(ui_systems::schedule_info.system())
+                    .with_system(ui_systems::annealing_controls.system())
+                    .with_system(ui_systems::collision_info.system())
+                    .with_system(ui_systems::kinetic_theory.system())
+                    .with_system(ui_systems::pressure_volume.system())
+                    .with_system(ui_systems::isotherm_sweep.system())
+                    .with_system(ui_systems::timing_info.system())
+                    .with_system(ui_systems::species_visuals.system())
+                    .with_system(ui_systems::stats_overlay.system())
+                    .with_system(ui_systems::axis_gizmo.system())
+                    .with_system(keybindings::keybinding_editor.system())
+                    .with_system(heat_gun::heat_gun_settings.system())
+                    .with_system(capture::capture_settings.system()),
+            )
+            .add_system(render_systems::apply_graphics.system())
+            .add_system_to_stage(CoreStage::Last, settings::save_settings.system())
+            .add_stage_after(
+                bevy::render::RenderStage::Draw,
+                render_systems::DRAW_INSTANCES,
+                SystemStage::parallel(),
+            )
+            .add_system_to_stage(
+                render_systems::DRAW_INSTANCES,
+                render_systems::draw_particle_instances.system(),
+            );
+    }
+}
@@ -0,0 +1,337 @@
+// Screenshots and frame recording
+// bevy 0.5 has no way to read back the rendered frame, so captures are drawn on the cpu from the
+// particle instances the renderer was handed: each drawn particle as a lit sphere in its color,
+// seen through the camera, on the clear color. The box, the overlays and the gui are left out
+// Screenshots go to screenshot_<n>.png in the capture directory, taking the first unused n
+// Recordings write frame_<n>.png every frame, numbered from 0, to assemble into a video,
+// e.g. with ffmpeg -i frame_%06d.png; encoding every frame slows the app down while recording
+use super::render_systems::ParticleInstances;
+use super::*;
+use crate::bevy_flycam::FlyCam;
+use bevy::render::camera::PerspectiveProjection;
+use bevy_egui::{egui, EguiContext};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Capture {
+    pub directory: String, // relative to the working directory unless absolute
+    pub width: u32,
+    pub height: u32,
+    #[serde(skip)]
+    pub screenshot: bool, // requested, taken once the particles of the frame are updated
+    #[serde(skip)]
+    pub recording: bool,
+    #[serde(skip)]
+    frame: usize, // number of the next frame of the recording
+    #[serde(skip)]
+    pub status: String, // the last capture saved, or why it failed
+}
+
+impl Default for Capture {
+    fn default() -> Self {
+        Self {
+            directory: "captures".to_string(),
+            width: 1280,
+            height: 720,
+            screenshot: false,
+            recording: false,
+            frame: 0,
+            status: String::new(),
+        }
+    }
+}
+
+impl Capture {
+    pub const MAX_SIZE: u32 = 4096;
+
+    // The settings kept between runs, without the captures in progress
+    pub fn saved(&self) -> Self {
+        Self {
+            directory: self.directory.clone(),
+            width: self.width.clamp(1, Self::MAX_SIZE),
+            height: self.height.clamp(1, Self::MAX_SIZE),
+            ..Self::default()
+        }
+    }
+
+    // Start a new recording numbered from 0, or stop the running one
+    pub fn toggle_recording(&mut self) {
+        self.recording = !self.recording;
+        self.frame = 0;
+    }
+}
+
+// Image of a capture, rows of rgb bytes from the top
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+// Camera a capture is seen through
+#[derive(Clone, Copy)]
+pub struct View {
+    pub camera: Mat4, // camera to world
+    pub fov: f32,     // vertical, in radians
+    pub near: f32,
+}
+
+// Ambient part of the light of the particle shader, see shaders/particle.frag
+const AMBIENT: f32 = 0.3;
+
+// Draw the particles of the instance buffers as the particle shader lights them
+// background and the instance colors are linear, the image is sRGB
+pub fn draw(instances: &[&[Vec4]], view: View, width: u32, height: u32, background: Vec3) -> Image {
+    let (columns, rows) = (width as usize, height as usize);
+    let mut color = vec![background; columns * rows];
+    let mut depth = vec![f32::INFINITY; columns * rows];
+    let world_to_camera = view.camera.inverse();
+    // the fixed light of the particle shader
+    let light = Vec3::new(0.4, 1.0, 0.6).normalize();
+    // pixels per unit length at unit distance in front of the camera
+    let focal = 0.5 * height as f32 / (0.5 * view.fov).tan();
+
+    // three entries per particle: position and radius, orientation, then the color
+    for particle in instances.iter().flat_map(|buffer| buffer.chunks_exact(3)) {
+        let (placement, tint) = (particle[0], particle[2].truncate());
+        let center = world_to_camera.transform_point3(placement.truncate());
+        let (distance, radius) = (-center.z, placement.w);
+        if distance <= view.near || radius <= 0.0 {
+            continue;
+        }
+        let scale = focal / distance;
+        let screen = Vec2::new(
+            0.5 * width as f32 + scale * center.x,
+            0.5 * height as f32 - scale * center.y,
+        );
+        let drawn_radius = scale * radius;
+        let lo = (screen - Vec2::splat(drawn_radius)).max(Vec2::ZERO);
+        let hi = (screen + Vec2::splat(drawn_radius)).min(Vec2::new(width as f32, height as f32));
+        for y in lo.y as usize..(hi.y.ceil() as usize).min(rows) {
+            for x in lo.x as usize..(hi.x.ceil() as usize).min(columns) {
+                let offset = (Vec2::new(x as f32 + 0.5, y as f32 + 0.5) - screen) / drawn_radius;
+                let inside = 1.0 - offset.length_squared();
+                if inside < 0.0 {
+                    continue;
+                }
+                // the sphere surface facing the camera
+                let normal = Vec3::new(offset.x, -offset.y, inside.sqrt());
+                let surface = distance - radius * normal.z;
+                let pixel = y * columns + x;
+                if surface >= depth[pixel] {
+                    continue;
+                }
+                depth[pixel] = surface;
+                let diffuse = view.camera.transform_vector3(normal).dot(light).max(0.0);
+                color[pixel] = tint * (AMBIENT + (1.0 - AMBIENT) * diffuse);
+            }
+        }
+    }
+
+    let pixels = color
+        .iter()
+        .flat_map(|c| {
+            let [r, g, b, _] = Color::rgb_linear(c.x, c.y, c.z).as_rgba_f32();
+            vec![r, g, b]
+        })
+        .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+        .collect();
+    Image {
+        width,
+        height,
+        pixels,
+    }
+}
+
+pub fn save_png(image: &Image, path: &Path) -> io::Result<()> {
+    let file = io::BufWriter::new(fs::File::create(path)?);
+    let mut encoder = png::Encoder::new(file, image.width, image.height);
+    encoder.set_color(png::ColorType::RGB);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&image.pixels)?;
+    Ok(())
+}
+
+// First screenshot_<n>.png not in dir yet
+// internal helper function
+fn next_screenshot(dir: &Path) -> PathBuf {
+    (0..)
+        .map(|n| dir.join(format!("screenshot_{:04}.png", n)))
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
+// Take the requested screenshot and the frame of the running recording
+pub fn capture_frames(
+    clear_color: Res<ClearColor>,
+    camera: Query<(&GlobalTransform, &PerspectiveProjection), With<FlyCam>>,
+    particle_renders: Query<&ParticleInstances>,
+    mut capture: ResMut<Capture>,
+) {
+    if !capture.screenshot && !capture.recording {
+        return;
+    }
+    let (transform, projection) = match camera.iter().next() {
+        Some(camera) => camera,
+        None => return,
+    };
+    let view = View {
+        camera: transform.compute_matrix(),
+        fov: projection.fov,
+        near: projection.near,
+    };
+    let buffers: Vec<&[Vec4]> = particle_renders
+        .iter()
+        .map(|renders| renders.instances.as_slice())
+        .collect();
+    let [r, g, b, _] = clear_color.0.as_linear_rgba_f32();
+    let image = draw(
+        &buffers,
+        view,
+        capture.width.clamp(1, Capture::MAX_SIZE),
+        capture.height.clamp(1, Capture::MAX_SIZE),
+        Vec3::new(r, g, b),
+    );
+
+    let dir = PathBuf::from(&capture.directory);
+    if let Err(e) = fs::create_dir_all(&dir) {
+        capture.status = format!("Capture failed: {}", e);
+        capture.screenshot = false;
+        capture.recording = false;
+        return;
+    }
+    if capture.screenshot {
+        capture.screenshot = false;
+        let path = next_screenshot(&dir);
+        capture.status = match save_png(&image, &path) {
+            Ok(()) => format!("Saved {}", path.display()),
+            Err(e) => format!("Screenshot failed: {}", e),
+        };
+    }
+    if capture.recording {
+        let path = dir.join(format!("frame_{:06}.png", capture.frame));
+        match save_png(&image, &path) {
+            Ok(()) => {
+                capture.frame += 1;
+                capture.status = format!("Recorded {} frames to {}", capture.frame, dir.display());
+            }
+            Err(e) => {
+                capture.recording = false;
+                capture.status = format!("Recording stopped: {}", e);
+            }
+        }
+    }
+}
+
+// Settings of the captures
+pub fn capture_settings(
+    egui_context: ResMut<EguiContext>,
+    bindings: Res<keybindings::KeyBindings>,
+    windows: Res<Windows>,
+    mut capture: ResMut<Capture>,
+) {
+    egui::Window::new("Capture").show(egui_context.ctx(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Directory");
+            ui.text_edit_singleline(&mut capture.directory);
+        });
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut capture.width)
+                    .clamp_range(1..=Capture::MAX_SIZE)
+                    .suffix(" px"),
+            );
+            ui.label("x");
+            ui.add(
+                egui::DragValue::new(&mut capture.height)
+                    .clamp_range(1..=Capture::MAX_SIZE)
+                    .suffix(" px"),
+            );
+            if let Some(window) = windows.get_primary() {
+                if ui.button("Window size").clicked() {
+                    capture.width = window.physical_width();
+                    capture.height = window.physical_height();
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            let screenshot = format!(
+                "Screenshot ({})",
+                bindings.label(keybindings::Action::Screenshot)
+            );
+            if ui.button(screenshot).clicked() {
+                capture.screenshot = true;
+            }
+            let record = if capture.recording { "Stop" } else { "Record" };
+            let record = format!(
+                "{} ({})",
+                record,
+                bindings.label(keybindings::Action::Record)
+            );
+            if ui.button(record).clicked() {
+                capture.toggle_recording();
+            }
+        });
+        ui.label("Only the particles are captured, drawn as spheres");
+        if !capture.status.is_empty() {
+            ui.label(&capture.status);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A particle of the given radius and linear color, unturned
+    // internal helper function
+    fn particle(pos: Vec3, radius: f32, color: Vec3) -> [Vec4; 3] {
+        [pos.extend(radius), Quat::IDENTITY.into(), color.extend(1.0)]
+    }
+
+    // internal helper function
+    fn pixel(image: &Image, x: u32, y: u32) -> [u8; 3] {
+        let i = 3 * (y * image.width + x) as usize;
+        [image.pixels[i], image.pixels[i + 1], image.pixels[i + 2]]
+    }
+
+    // Camera at the origin looking down -z
+    const VIEW: View = View {
+        camera: Mat4::IDENTITY,
+        fov: std::f32::consts::FRAC_PI_2,
+        near: 0.1,
+    };
+
+    #[test]
+    fn particles_are_drawn_where_the_camera_sees_them() {
+        let red = particle(Vec3::new(0.0, 0.0, -2.0), 0.5, Vec3::X);
+        let image = draw(&[&red[..]], VIEW, 40, 20, Vec3::ZERO);
+        assert_eq!(image.pixels.len(), 40 * 20 * 3);
+        // a quarter of the height across, in the middle
+        let center = pixel(&image, 20, 10);
+        assert!(center[0] > 100 && center[1] == 0 && center[2] == 0);
+        assert_eq!(pixel(&image, 20, 4), [0, 0, 0]);
+        assert_eq!(pixel(&image, 0, 0), [0, 0, 0]);
+
+        // behind the camera is out of sight
+        let behind = particle(Vec3::new(0.0, 0.0, 2.0), 0.5, Vec3::X);
+        let image = draw(&[&behind[..]], VIEW, 40, 20, Vec3::ZERO);
+        assert!(image.pixels.iter().all(|&c| c == 0));
+    }
+
+    #[test]
+    fn nearer_particles_hide_the_ones_behind() {
+        let far = particle(Vec3::new(0.0, 0.0, -4.0), 1.0, Vec3::X);
+        let near = particle(Vec3::new(0.0, 0.0, -2.0), 0.2, Vec3::Y);
+        // drawn in either order
+        for buffers in [[&far[..], &near[..]], [&near[..], &far[..]]].iter() {
+            let image = draw(buffers, VIEW, 40, 40, Vec3::ZERO);
+            let center = pixel(&image, 20, 20);
+            assert!(center[0] == 0 && center[1] > 100, "got {:?}", center);
+        }
+    }
+}
@@ -0,0 +1,48 @@
+// Colormaps used to color particles by a scalar field
+use bevy::prelude::Color;
+
+// Samples of the viridis colormap, evenly spaced from 0 to 1
+const VIRIDIS: [(f32, f32, f32); 9] = [
+    (0.267, 0.005, 0.329),
+    (0.283, 0.141, 0.458),
+    (0.254, 0.265, 0.530),
+    (0.207, 0.372, 0.553),
+    (0.128, 0.567, 0.551),
+    (0.135, 0.659, 0.518),
+    (0.267, 0.749, 0.441),
+    (0.478, 0.821, 0.319),
+    (0.993, 0.906, 0.144),
+];
+
+// Distinct colors for categorical values such as species
+const CATEGORICAL: [(f32, f32, f32); 8] = [
+    (1.0, 1.0, 1.0),
+    (0.0, 1.0, 1.0),
+    (1.0, 0.5, 0.0),
+    (0.8, 0.2, 0.8),
+    (0.3, 0.9, 0.3),
+    (1.0, 0.9, 0.2),
+    (0.9, 0.2, 0.2),
+    (0.3, 0.4, 1.0),
+];
+
+// Color at position t of the viridis colormap, t is clamped to [0, 1]
+pub fn viridis(t: f32) -> Color {
+    let t = t.clamp(0.0, 1.0) * (VIRIDIS.len() - 1) as f32;
+    let i = (t.floor() as usize).min(VIRIDIS.len() - 2);
+    let frac = t - i as f32;
+
+    let (r0, g0, b0) = VIRIDIS[i];
+    let (r1, g1, b1) = VIRIDIS[i + 1];
+    Color::rgb(
+        r0 + (r1 - r0) * frac,
+        g0 + (g1 - g0) * frac,
+        b0 + (b1 - b0) * frac,
+    )
+}
+
+// Color of category i, colors repeat after the palette runs out
+pub fn categorical(i: usize) -> Color {
+    let (r, g, b) = CATEGORICAL[i % CATEGORICAL.len()];
+    Color::rgb(r, g, b)
+}
@@ -0,0 +1,63 @@
+// Exports of the info windows
+// The history and isotherms go to csv files, the latest observables are copied to the clipboard as text
+use super::*;
+use bevy_egui::egui;
+use vdw_core::state::units::{Quantity, ReducedUnits, UnitSystem};
+
+pub const HISTORY_FILE: &str = "history.csv";
+pub const ISOTHERM_FILE: &str = "isotherm.csv";
+
+// Write the last window entries of the history, returns the message shown next to the button
+pub fn save_history(history: &History, window: usize) -> String {
+    match history.export_csv(HISTORY_FILE, window) {
+        Ok(()) => format!("Saved to {}", HISTORY_FILE),
+        Err(e) => format!("Export failed: {}", e),
+    }
+}
+
+// Write the points of an isotherm sweep, returns the message shown next to the button
+pub fn save_isotherm(points: &[experiment::IsothermPoint]) -> String {
+    match experiment::export_isotherm(points, ISOTHERM_FILE) {
+        Ok(()) => format!("Saved to {}", ISOTHERM_FILE),
+        Err(e) => format!("Export failed: {}", e),
+    }
+}
+
+// The latest observables, one per line, in the units the gui shows them in
+pub fn observables_text(state: &SimulationState, unit_system: UnitSystem) -> String {
+    let scale = ReducedUnits::of(state);
+    let show = |quantity, value| unit_system.format(&scale, quantity, value);
+    let lines = [
+        format!("steps: {}", state.steps),
+        format!("time: {}", show(Quantity::Time, state.time)),
+        format!("particles: {}", state.particles.len()),
+        format!(
+            "temperature: {}",
+            show(Quantity::Temperature, state.temperature())
+        ),
+        format!(
+            "pressure: {}",
+            show(Quantity::Pressure, state.pressure.get_pressure())
+        ),
+        format!(
+            "volume: {}",
+            show(Quantity::Volume, state.bound.get_volume())
+        ),
+        format!("kinetic: {}", show(Quantity::Energy, state.energy.kinetic)),
+        format!(
+            "potential: {}",
+            show(Quantity::Energy, state.energy.potential)
+        ),
+        format!(
+            "rotational: {}",
+            show(Quantity::Energy, state.energy.rotational)
+        ),
+        format!("total: {}", show(Quantity::Energy, state.energy.total())),
+    ];
+    lines.join("\n")
+}
+
+// bevy_egui hands the copied text to the system clipboard at the end of the frame
+pub fn copy_to_clipboard(ui: &egui::Ui, text: String) {
+    ui.ctx().output().copied_text = text;
+}
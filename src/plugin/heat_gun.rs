@@ -0,0 +1,161 @@
+// Heat gun, a brush for local heating
+// While its key is held, the particles within a ball around the cursor ray get random velocity kicks
+// The ball is centered on the first particle the ray hits, or on the point of the ray closest
+// to the box center when it misses them all
+// With a grabbed cursor the ray goes through the middle of the screen
+use super::*;
+use crate::bevy_flycam::FlyCam;
+use bevy::render::camera::Camera;
+use bevy_egui::{egui, EguiContext};
+
+pub struct HeatGun {
+    pub radius: f32,
+    pub rate: f32, // temperature added to the particles in the ball per second
+    pub last: Option<Target>, // where the last frame of heating went
+}
+
+impl Default for HeatGun {
+    fn default() -> Self {
+        Self {
+            radius: 0.5,
+            rate: 20.0,
+            last: None,
+        }
+    }
+}
+
+impl HeatGun {
+    pub const MAX_RADIUS: f32 = 3.0;
+    pub const MAX_RATE: f32 = 200.0;
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Target {
+    pub center: Vec3,
+    pub particle: Option<usize>, // the particle the ray hit
+}
+
+// Ray from the camera through the cursor, as origin and unit direction
+// internal helper function
+fn cursor_ray(
+    window: &Window,
+    camera: &Camera,
+    transform: &GlobalTransform,
+) -> Option<(Vec3, Vec3)> {
+    let size = Vec2::new(window.width(), window.height());
+    let cursor = if window.cursor_locked() {
+        size / 2.0
+    } else {
+        window.cursor_position()?
+    };
+    // screen to normalized device coordinates, inverting Camera::world_to_screen
+    let ndc = cursor / size * 2.0 - Vec2::ONE;
+    let ndc_to_world = transform.compute_matrix() * camera.projection_matrix.inverse();
+    let near = ndc_to_world.project_point3(ndc.extend(0.0));
+    let far = ndc_to_world.project_point3(ndc.extend(0.5));
+    let direction = (far - near).try_normalize()?;
+    Some((transform.translation, direction))
+}
+
+// Where a ray aims the heat gun
+// internal helper function
+fn aim(state: &SimulationState, origin: Vec3, direction: Vec3) -> Target {
+    let particle = state
+        .particles
+        .iter()
+        .enumerate()
+        .filter_map(|(i, p)| {
+            let to = p.get_pos() - origin;
+            let along = to.dot(direction);
+            let off = to.length_squared() - along * along;
+            if along > 0.0 && off <= p.get_radius().powi(2) {
+                Some((i, along))
+            } else {
+                None
+            }
+        })
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(i, _)| i);
+    let center = match particle {
+        Some(i) => state.particles[i].get_pos(),
+        None => {
+            let along = (state.bound.center() - origin).dot(direction).max(0.0);
+            origin + along * direction
+        }
+    };
+    Target { center, particle }
+}
+
+// Heat the particles around the cursor ray while the key is held
+#[allow(clippy::too_many_arguments)]
+pub fn heat_gun(
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<keybindings::KeyBindings>,
+    rebinding: Res<keybindings::Rebinding>,
+    egui_context: ResMut<EguiContext>,
+    windows: Res<Windows>,
+    camera: Query<(&Camera, &GlobalTransform), With<FlyCam>>,
+    mut state: ResMut<SimulationState>,
+    mut worker: ResMut<worker::SimulationWorker>,
+    mut gun: ResMut<HeatGun>,
+) {
+    gun.last = None;
+    if !keys.pressed(bindings.heat_gun)
+        || egui_context.ctx().wants_keyboard_input()
+        || rebinding.action.is_some()
+    {
+        return;
+    }
+    let (camera, transform) = match camera.iter().next() {
+        Some(camera) => camera,
+        None => return,
+    };
+    let window = match windows.get(camera.window) {
+        Some(window) => window,
+        None => return,
+    };
+    let (origin, direction) = match cursor_ray(window, camera, transform) {
+        Some(ray) => ray,
+        None => return,
+    };
+
+    let target = aim(&state, origin, direction);
+    let (radius, heat) = (gun.radius, gun.rate * time.delta_seconds());
+    // the same kicks on every copy of the state
+    let seed = time.seconds_since_startup().to_bits();
+    worker.edit(&mut state, move |state| {
+        state.heat_region(target.center, radius, heat, seed);
+    });
+    gun.last = Some(target);
+}
+
+// Settings of the heat gun
+pub fn heat_gun_settings(
+    egui_context: ResMut<EguiContext>,
+    bindings: Res<keybindings::KeyBindings>,
+    mut gun: ResMut<HeatGun>,
+) {
+    egui::Window::new("Heat Gun").show(egui_context.ctx(), |ui| {
+        ui.label(format!(
+            "Hold {} to heat the particles around the cursor",
+            bindings.label(keybindings::Action::HeatGun)
+        ));
+        ui.add(egui::Slider::new(&mut gun.radius, 0.05..=HeatGun::MAX_RADIUS).text("Radius"));
+        ui.add(egui::Slider::new(&mut gun.rate, 0.0..=HeatGun::MAX_RATE).text("Heating Rate"));
+        match gun.last {
+            Some(Target {
+                center,
+                particle: Some(i),
+            }) => ui.label(format!(
+                "Heating around particle {} at ({:.2}, {:.2}, {:.2})",
+                i, center.x, center.y, center.z
+            )),
+            Some(Target { center, .. }) => ui.label(format!(
+                "Heating around ({:.2}, {:.2}, {:.2})",
+                center.x, center.y, center.z
+            )),
+            None => ui.label("Idle"),
+        };
+    });
+}
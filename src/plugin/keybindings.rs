@@ -0,0 +1,299 @@
+// Hotkeys of the app
+// Each action is bound to one key, the bindings are edited in the Keys window and kept in the settings file
+// The camera keys of bevy_flycam (WASD, Space, LShift, Escape and O) are fixed and can't be bound
+// Hotkeys are ignored while a text field of the gui has the keyboard
+use super::*;
+use bevy::ecs::schedule::ShouldRun;
+use bevy_egui::{egui, EguiContext};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Action {
+    Pause,
+    Reset,
+    ToggleUi,
+    SpeedUp,
+    SpeedDown,
+    CameraReset,
+    Presentation,
+    HeatGun,
+    Screenshot,
+    Record,
+}
+
+impl Action {
+    pub const ALL: [Action; 10] = [
+        Action::Pause,
+        Action::Reset,
+        Action::ToggleUi,
+        Action::SpeedUp,
+        Action::SpeedDown,
+        Action::CameraReset,
+        Action::Presentation,
+        Action::HeatGun,
+        Action::Screenshot,
+        Action::Record,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Pause => "Pause",
+            Action::Reset => "Reset simulation",
+            Action::ToggleUi => "Toggle gui",
+            Action::SpeedUp => "Speed up",
+            Action::SpeedDown => "Slow down",
+            Action::CameraReset => "Reset view",
+            Action::Presentation => "Presentation mode",
+            Action::HeatGun => "Heat gun (hold)",
+            Action::Screenshot => "Screenshot",
+            Action::Record => "Record frames",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub pause: KeyCode,
+    pub reset: KeyCode,
+    pub toggle_ui: KeyCode,
+    pub speed_up: KeyCode,
+    pub speed_down: KeyCode,
+    pub camera_reset: KeyCode,
+    pub presentation: KeyCode,
+    pub heat_gun: KeyCode,
+    pub screenshot: KeyCode,
+    pub record: KeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            pause: KeyCode::P,
+            reset: KeyCode::F5,
+            toggle_ui: KeyCode::H,
+            speed_up: KeyCode::Equals,
+            speed_down: KeyCode::Minus,
+            camera_reset: KeyCode::R,
+            presentation: KeyCode::F10,
+            heat_gun: KeyCode::G,
+            screenshot: KeyCode::F12,
+            record: KeyCode::F9,
+        }
+    }
+}
+
+impl KeyBindings {
+    // keys bevy_flycam handles itself
+    pub const RESERVED: [KeyCode; 8] = [
+        KeyCode::W,
+        KeyCode::A,
+        KeyCode::S,
+        KeyCode::D,
+        KeyCode::Space,
+        KeyCode::LShift,
+        KeyCode::Escape,
+        KeyCode::O,
+    ];
+
+    pub fn key(&self, action: Action) -> KeyCode {
+        match action {
+            Action::Pause => self.pause,
+            Action::Reset => self.reset,
+            Action::ToggleUi => self.toggle_ui,
+            Action::SpeedUp => self.speed_up,
+            Action::SpeedDown => self.speed_down,
+            Action::CameraReset => self.camera_reset,
+            Action::Presentation => self.presentation,
+            Action::HeatGun => self.heat_gun,
+            Action::Screenshot => self.screenshot,
+            Action::Record => self.record,
+        }
+    }
+
+    // internal helper function
+    fn key_mut(&mut self, action: Action) -> &mut KeyCode {
+        match action {
+            Action::Pause => &mut self.pause,
+            Action::Reset => &mut self.reset,
+            Action::ToggleUi => &mut self.toggle_ui,
+            Action::SpeedUp => &mut self.speed_up,
+            Action::SpeedDown => &mut self.speed_down,
+            Action::CameraReset => &mut self.camera_reset,
+            Action::Presentation => &mut self.presentation,
+            Action::HeatGun => &mut self.heat_gun,
+            Action::Screenshot => &mut self.screenshot,
+            Action::Record => &mut self.record,
+        }
+    }
+
+    // Bind key to action, an action that had the key before swaps over to the old key of action
+    // Returns false for the reserved keys
+    pub fn bind(&mut self, action: Action, key: KeyCode) -> bool {
+        if Self::RESERVED.contains(&key) {
+            return false;
+        }
+        let old = self.key(action);
+        if let Some(&other) = Action::ALL
+            .iter()
+            .find(|&&a| a != action && self.key(a) == key)
+        {
+            *self.key_mut(other) = old;
+        }
+        *self.key_mut(action) = key;
+        true
+    }
+
+    // Key labels shown in the gui
+    pub fn label(&self, action: Action) -> String {
+        format!("{:?}", self.key(action))
+    }
+
+    // Whether the bindings were all distinct and none of them reserved, the saved ones may be edited by hand
+    pub fn is_valid(&self) -> bool {
+        Action::ALL.iter().enumerate().all(|(i, &a)| {
+            !Self::RESERVED.contains(&self.key(a))
+                && Action::ALL[i + 1..]
+                    .iter()
+                    .all(|&b| self.key(a) != self.key(b))
+        })
+    }
+}
+
+// Whether the gui windows are drawn, toggled by its hotkey
+pub struct UiVisibility {
+    pub shown: bool,
+}
+
+impl Default for UiVisibility {
+    fn default() -> Self {
+        Self { shown: true }
+    }
+}
+
+// Run criteria of the gui systems
+pub fn ui_shown(
+    visibility: Res<UiVisibility>,
+    presentation: Res<presentation::Presentation>,
+) -> ShouldRun {
+    if visibility.shown && !presentation.active {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+// Steps per frame, or milliseconds of stepping with a frame budget, change by this factor per key press
+const SPEED_FACTOR: f32 = 2.0;
+const MAX_STEPS_PER_FRAME: usize = 1000;
+const MAX_MS_PER_FRAME: f32 = 100.0;
+
+// Change the steps per frame by factor
+// internal helper function
+fn change_speed(state: &mut SimulationState, factor: f32) {
+    if state.frame_budget.enabled {
+        state.frame_budget.ms_per_frame =
+            (state.frame_budget.ms_per_frame * factor).min(MAX_MS_PER_FRAME);
+    } else {
+        let steps = (state.steps_per_frame as f32 * factor).round() as usize;
+        state.set_steps_per_frame(steps.clamp(1, MAX_STEPS_PER_FRAME));
+    }
+}
+
+// Run the actions of the pressed hotkeys
+#[allow(clippy::too_many_arguments)]
+pub fn hotkeys(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    rebinding: Res<Rebinding>,
+    egui_context: ResMut<EguiContext>,
+    setup: Res<InitialSetup>,
+    mut state: ResMut<SimulationState>,
+    mut worker: ResMut<worker::SimulationWorker>,
+    mut visibility: ResMut<UiVisibility>,
+    mut focus: ResMut<render_systems::CameraFocus>,
+    mut presentation: ResMut<presentation::Presentation>,
+    mut failure: ResMut<ui_systems::RestartFailure>,
+    mut capture: ResMut<capture::Capture>,
+) {
+    if egui_context.ctx().wants_keyboard_input() || rebinding.action.is_some() {
+        return;
+    }
+    for &action in Action::ALL.iter() {
+        if !keys.just_pressed(bindings.key(action)) {
+            continue;
+        }
+        match action {
+            Action::Pause => worker.edit(&mut state, |state| state.paused = !state.paused),
+            Action::Reset => {
+                if let Err(err) = ui_systems::restart(&setup.prototype, &mut state, &mut worker) {
+                    failure.report(format!("Reset failed: {}", err));
+                }
+            }
+            Action::ToggleUi => visibility.shown = !visibility.shown,
+            Action::SpeedUp => worker.edit(&mut state, |state| change_speed(state, SPEED_FACTOR)),
+            Action::SpeedDown => {
+                worker.edit(&mut state, |state| change_speed(state, 1.0 / SPEED_FACTOR))
+            }
+            Action::CameraReset => focus.frame_box = true,
+            Action::Presentation => presentation.active = !presentation.active,
+            Action::HeatGun => (), // held down, see heat_gun::heat_gun
+            Action::Screenshot => capture.screenshot = true,
+            Action::Record => capture.toggle_recording(),
+        }
+    }
+}
+
+// Action of the Keys window waiting for its new key
+#[derive(Default)]
+pub struct Rebinding {
+    pub action: Option<Action>,
+    pub status: String,
+}
+
+// Editor of the bindings, a clicked action takes the next key pressed, Escape cancels
+pub fn keybinding_editor(
+    egui_context: ResMut<EguiContext>,
+    keys: Res<Input<KeyCode>>,
+    mut bindings: ResMut<KeyBindings>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    if let Some(action) = rebinding.action {
+        if keys.just_pressed(KeyCode::Escape) {
+            rebinding.action = None;
+            rebinding.status.clear();
+        } else if let Some(&key) = keys.get_just_pressed().next() {
+            rebinding.action = None;
+            rebinding.status = if bindings.bind(action, key) {
+                String::new()
+            } else {
+                format!("{:?} is a camera key and can't be bound", key)
+            };
+        }
+    }
+
+    egui::Window::new("Keys").show(egui_context.ctx(), |ui| {
+        egui::Grid::new("key bindings").show(ui, |ui| {
+            for &action in Action::ALL.iter() {
+                ui.label(action.name());
+                let text = if rebinding.action == Some(action) {
+                    "Press a key...".to_string()
+                } else {
+                    bindings.label(action)
+                };
+                if ui.button(text).clicked() {
+                    rebinding.action = Some(action);
+                    rebinding.status.clear();
+                }
+                ui.end_row();
+            }
+        });
+        if !rebinding.status.is_empty() {
+            ui.label(&rebinding.status);
+        }
+        ui.label("Camera: WASD, Space, LShift, Escape grabs the mouse, O switches the mode");
+        if ui.button("Default keys").clicked() {
+            *bindings = KeyBindings::default();
+        }
+    });
+}
@@ -0,0 +1,72 @@
+// Presentation mode for demos and screen recordings
+// Hides every gui window and the stats overlay, and can orbit the camera slowly around the box
+// The Display window or the hotkey starts it, the hotkey ends it and brings the camera mode back
+use super::*;
+use crate::bevy_flycam::{CameraMode, InputState};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Presentation {
+    #[serde(skip)]
+    pub active: bool,
+    pub auto_orbit: bool,
+    pub orbit_speed: f32, // radians per second
+    #[serde(skip)]
+    entered: Option<CameraMode>, // camera mode before the presentation started
+}
+
+impl Default for Presentation {
+    fn default() -> Self {
+        Self {
+            active: false,
+            auto_orbit: true,
+            orbit_speed: 0.15,
+            entered: None,
+        }
+    }
+}
+
+impl Presentation {
+    pub const MAX_ORBIT_SPEED: f32 = 1.0;
+
+    // The settings kept between runs, without the running presentation
+    pub fn saved(&self) -> Self {
+        Self {
+            auto_orbit: self.auto_orbit,
+            orbit_speed: self.orbit_speed,
+            ..Self::default()
+        }
+    }
+}
+
+// Start and end the presentation, and turn the camera while it runs
+pub fn present(
+    time: Res<Time>,
+    mut presentation: ResMut<Presentation>,
+    mut mode: ResMut<CameraMode>,
+    mut focus: ResMut<render_systems::CameraFocus>,
+    mut input_state: ResMut<InputState>,
+) {
+    match (presentation.active, presentation.entered) {
+        (true, None) => {
+            presentation.entered = Some(*mode);
+            if presentation.auto_orbit {
+                // the orbit circles the box center, with the whole box in view
+                *mode = CameraMode::Orbit;
+                focus.selected = None;
+                focus.frame_box = true;
+            }
+        }
+        (true, Some(_)) => {
+            if presentation.auto_orbit {
+                input_state.turn(presentation.orbit_speed * time.delta_seconds());
+            }
+        }
+        (false, Some(entered)) => {
+            *mode = entered;
+            presentation.entered = None;
+        }
+        (false, None) => (),
+    }
+}
@@ -0,0 +1,453 @@
+// Control server, built with the remote feature
+// External scripts drive the running simulation with JSON messages over a WebSocket
+//
+// Requests, one per text message:
+//   {"command": "set temp 1.5"}   any console command, answered with the console output
+//   {"query": "observables"}      latest measurements of the simulation
+// Replies:
+//   {"ok": true, "log": [...]} or {"ok": true, "observables": {...}} or {"ok": false, "error": "..."}
+// Remote commands can't touch files, save and run are refused, and they add limited particles
+// Upgrades from web pages of other hosts are refused, so a page open in the browser can't drive it
+// A few connections are served at once, more are turned away until one closes
+use super::ui_systems;
+use super::*;
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum RemoteRequest {
+    Command(String),
+    Query(RemoteQuery),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RemoteQuery {
+    Observables,
+}
+
+#[derive(Serialize)]
+struct Observables {
+    steps: usize,
+    time: f32,
+    particles: usize,
+    temperature: f32,
+    pressure: f32,
+    volume: f32,
+    kinetic: f32,
+    potential: f32,
+    rotational: f32,
+    rotational_temperature: f32,
+    paused: bool,
+}
+
+impl Observables {
+    fn of(state: &SimulationState) -> Self {
+        Self {
+            steps: state.steps,
+            time: state.time,
+            particles: state.particles.len(),
+            temperature: state.temperature(),
+            pressure: state.pressure.get_pressure(),
+            volume: state.bound.get_volume(),
+            kinetic: state.energy.kinetic,
+            potential: state.energy.potential,
+            rotational: state.energy.rotational,
+            rotational_temperature: state.rotational_temperature(),
+            paused: state.paused,
+        }
+    }
+}
+
+// Most particles one remote command adds
+const MAX_REMOTE_PARTICLES: usize = 10_000;
+
+// The console command if a remote request may run it, or why not
+// internal helper function
+fn permitted(command: console::ConsoleCommand) -> Result<console::ConsoleCommand, String> {
+    use console::ConsoleCommand;
+    match command {
+        ConsoleCommand::Save(_) | ConsoleCommand::Run(_) => {
+            Err("remote commands can't read or write files".to_string())
+        }
+        ConsoleCommand::AddParticles { n, .. } if n > MAX_REMOTE_PARTICLES => Err(format!(
+            "remote commands add at most {} particles, got {}",
+            MAX_REMOTE_PARTICLES, n
+        )),
+        ConsoleCommand::Set(parameter, value) => {
+            parameter.check(value)?;
+            Ok(command)
+        }
+        command => Ok(command),
+    }
+}
+
+// Most connections served at the same time
+const MAX_CONNECTIONS: usize = 8;
+
+// Counts a connection as open until it is dropped
+struct Slot(Arc<AtomicUsize>);
+
+impl Slot {
+    // None when MAX_CONNECTIONS are already open
+    fn take(open: &Arc<AtomicUsize>) -> Option<Self> {
+        let mut count = open.load(Ordering::SeqCst);
+        loop {
+            if count >= MAX_CONNECTIONS {
+                return None;
+            }
+            match open.compare_exchange(count, count + 1, Ordering::SeqCst, Ordering::SeqCst) {
+                Ok(_) => return Some(Slot(Arc::clone(open))),
+                Err(current) => count = current,
+            }
+        }
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// A message received by a connection, answered by the serve_requests system
+struct Request {
+    text: String,
+    reply: Sender<String>,
+}
+
+// Receives the requests of all connections
+pub struct RemoteServer {
+    requests: Mutex<Receiver<Request>>,
+}
+
+impl RemoteServer {
+    // Listen on addr, every connection is served on its own thread
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        println!("remote control listening on {}", listener.local_addr()?);
+
+        let (sender, receiver) = mpsc::channel();
+        let open = Arc::new(AtomicUsize::new(0));
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let slot = match Slot::take(&open) {
+                    Some(slot) => slot,
+                    None => {
+                        // the client may be gone already
+                        let _ = write!(stream, "HTTP/1.1 503 Service Unavailable\r\n\r\n");
+                        continue;
+                    }
+                };
+                let sender = sender.clone();
+                thread::spawn(move || {
+                    if let Err(err) = serve_connection(stream, sender) {
+                        println!("remote connection closed: {}", err);
+                    }
+                    drop(slot);
+                });
+            }
+        });
+
+        Ok(Self {
+            requests: Mutex::new(receiver),
+        })
+    }
+}
+
+// System that answers the requests received since the last frame
+pub fn serve_requests(
+    server: Res<RemoteServer>,
+    mut state: ResMut<SimulationState>,
+    mut worker: ResMut<worker::SimulationWorker>,
+) {
+    for request in server.requests.lock().unwrap().try_iter() {
+        let reply = match serde_json::from_str(&request.text) {
+            Ok(RemoteRequest::Command(line)) => {
+                match console::ConsoleCommand::parse(&line).and_then(permitted) {
+                    Ok(command) => {
+                        let mut log = Vec::new();
+                        ui_systems::execute(command, &mut state, &mut worker, &mut log, false);
+                        serde_json::json!({ "ok": true, "log": log })
+                    }
+                    Err(err) => serde_json::json!({ "ok": false, "error": err }),
+                }
+            }
+            Ok(RemoteRequest::Query(RemoteQuery::Observables)) => {
+                serde_json::json!({ "ok": true, "observables": Observables::of(&state) })
+            }
+            Err(err) => serde_json::json!({ "ok": false, "error": err.to_string() }),
+        };
+        // the connection may be gone already
+        let _ = request.reply.send(reply.to_string());
+    }
+}
+
+/////////////////////////////////////////////////
+// Minimal WebSocket server side, text messages only
+//
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+const MAX_MESSAGE_LEN: u64 = 1 << 20;
+const MAX_HEADER_LINE_LEN: u64 = 8192;
+const MAX_HEADERS: usize = 64;
+
+// Upgrade the connection, then pass every text message on and send back its reply
+// internal helper function
+fn serve_connection(stream: TcpStream, requests: Sender<Request>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    handshake(&mut reader, &mut writer)?;
+
+    let mut message = Vec::new();
+    loop {
+        let (fin, opcode, payload) = read_frame(&mut reader)?;
+        match opcode {
+            OPCODE_TEXT | OPCODE_CONTINUATION => {
+                // the frames of a message are limited together, not just one by one
+                if (message.len() + payload.len()) as u64 > MAX_MESSAGE_LEN {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "message too long",
+                    ));
+                }
+                message.extend(payload);
+                if !fin {
+                    continue;
+                }
+                let text = String::from_utf8_lossy(&message).into_owned();
+                message.clear();
+
+                let (reply, answer) = mpsc::channel();
+                requests
+                    .send(Request { text, reply })
+                    .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "app closed"))?;
+                let answer = answer
+                    .recv()
+                    .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "app closed"))?;
+                write_frame(&mut writer, OPCODE_TEXT, answer.as_bytes())?;
+            }
+            OPCODE_PING => write_frame(&mut writer, OPCODE_PONG, &payload)?,
+            OPCODE_CLOSE => {
+                write_frame(&mut writer, OPCODE_CLOSE, &payload)?;
+                return Ok(());
+            }
+            _ => {} // binary messages and pongs are ignored
+        }
+    }
+}
+
+// Whether an Origin header names a page on this machine
+// Browsers send it with every WebSocket upgrade, scripts outside a browser usually leave it out
+// internal helper function
+fn is_local_origin(origin: &str) -> bool {
+    let host = match origin.split_once("://") {
+        Some((_, rest)) => rest.split('/').next().unwrap_or(""),
+        None => return false, // "null" of file pages and sandboxed frames
+    };
+    // drop the port, IPv6 hosts are in brackets
+    let host = if let Some(rest) = host.strip_prefix('[') {
+        rest.split(']').next().unwrap_or("")
+    } else {
+        host.split(':').next().unwrap_or("")
+    };
+    matches!(
+        host.to_ascii_lowercase().as_str(),
+        "localhost" | "127.0.0.1" | "::1"
+    )
+}
+
+// Answer the HTTP upgrade request of the client
+// Requests with the Origin of a page on another host are refused
+// internal helper function
+fn handshake(reader: &mut impl BufRead, writer: &mut impl Write) -> io::Result<()> {
+    let mut key = None;
+    let mut origin = None;
+    let mut lines = 0;
+    loop {
+        // the request line comes before the headers
+        lines += 1;
+        if lines > MAX_HEADERS + 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "too many headers",
+            ));
+        }
+        let mut line = String::new();
+        let len = reader
+            .by_ref()
+            .take(MAX_HEADER_LINE_LEN)
+            .read_line(&mut line)?;
+        if len == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "no handshake"));
+        }
+        if !line.ends_with('\n') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "handshake line too long",
+            ));
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("sec-websocket-key") {
+                key = Some(value.trim().to_string());
+            } else if name.trim().eq_ignore_ascii_case("origin") {
+                origin = Some(value.trim().to_string());
+            }
+        }
+    }
+    if let Some(origin) = origin.filter(|origin| !is_local_origin(origin)) {
+        write!(writer, "HTTP/1.1 403 Forbidden\r\n\r\n")?;
+        writer.flush()?;
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("refused the page at {}", origin),
+        ));
+    }
+    let key = key.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a websocket"))?;
+
+    let accept = base64::encode(sha1::Sha1::from(key + ACCEPT_GUID).digest().bytes());
+    write!(
+        writer,
+        "HTTP/1.1 101 Switching Protocols\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        accept
+    )?;
+    writer.flush()
+}
+
+// Read one frame and unmask its payload, returns (fin, opcode, payload)
+// internal helper function
+fn read_frame(reader: &mut impl Read) -> io::Result<(bool, u8, Vec<u8>)> {
+    let mut header = [0u8; 2];
+    reader.read_exact(&mut header)?;
+    let fin = header[0] & 0x80 != 0;
+    let opcode = header[0] & 0x0F;
+    let masked = header[1] & 0x80 != 0;
+
+    let len = match header[1] & 0x7F {
+        126 => {
+            let mut len = [0u8; 2];
+            reader.read_exact(&mut len)?;
+            u16::from_be_bytes(len) as u64
+        }
+        127 => {
+            let mut len = [0u8; 8];
+            reader.read_exact(&mut len)?;
+            u64::from_be_bytes(len)
+        }
+        len => len as u64,
+    };
+    if len > MAX_MESSAGE_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too long"));
+    }
+
+    let mut mask = [0u8; 4];
+    if masked {
+        reader.read_exact(&mut mask)?;
+    }
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload)?;
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+    Ok((fin, opcode, payload))
+}
+
+// Send a single unmasked frame, servers don't mask
+// internal helper function
+fn write_frame(writer: &mut impl Write, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        len if len < 126 => frame.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    writer.write_all(&frame)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // internal helper function
+    fn upgrade(request: &str) -> io::Result<Vec<u8>> {
+        let mut reply = Vec::new();
+        handshake(&mut request.as_bytes(), &mut reply)?;
+        Ok(reply)
+    }
+
+    // internal helper function
+    fn remote(line: &str) -> Result<console::ConsoleCommand, String> {
+        console::ConsoleCommand::parse(line).and_then(permitted)
+    }
+
+    #[test]
+    fn remote_commands_stay_away_from_files() {
+        assert!(remote("save state.ron").is_err());
+        assert!(remote("run setup.txt").is_err());
+        assert!(remote("set temp 1.5").is_ok());
+        assert!(remote("toggle pause").is_ok());
+        assert!(remote("help").is_ok());
+    }
+
+    #[test]
+    fn remote_commands_are_limited() {
+        assert!(remote(&format!("add particles {} uniform", MAX_REMOTE_PARTICLES)).is_ok());
+        assert!(remote(&format!(
+            "add particles {} uniform",
+            MAX_REMOTE_PARTICLES + 1
+        ))
+        .is_err());
+        // values built without the parser are checked too
+        let unparsed = console::ConsoleCommand::Set(schedule::ScheduleParameter::Dt, f32::NAN);
+        assert!(permitted(unparsed).is_err());
+    }
+
+    #[test]
+    fn handshakes_are_limited() {
+        let request = "GET / HTTP/1.1\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n";
+        let reply = upgrade(request).expect("a valid upgrade");
+        assert!(String::from_utf8_lossy(&reply).contains("s3pPLMBiTxaQ9kYGzzhZRbK+xOo="));
+
+        let long = format!("GET / HTTP/1.1\r\nX-Pad: {}\r\n\r\n", "a".repeat(10_000));
+        assert!(upgrade(&long).is_err());
+        let many = format!("GET / HTTP/1.1\r\n{}\r\n", "X-Pad: a\r\n".repeat(100));
+        assert!(upgrade(&many).is_err());
+    }
+
+    #[test]
+    fn connections_beyond_the_limit_get_no_slot() {
+        let open = Arc::new(AtomicUsize::new(0));
+        let slots: Vec<Slot> = (0..MAX_CONNECTIONS)
+            .map(|_| Slot::take(&open).expect("a free slot"))
+            .collect();
+        assert!(Slot::take(&open).is_none());
+        drop(slots);
+        assert_eq!(open.load(Ordering::SeqCst), 0);
+        assert!(Slot::take(&open).is_some());
+    }
+}
@@ -0,0 +1,1350 @@
+// bevy systems that updates the render of the simulation
+use super::colormap;
+use super::*;
+use crate::bevy_flycam::{self, CameraMode, FlyCam, InputState, Orbit};
+use bevy::render::camera::PerspectiveProjection;
+use bevy::render::draw::RenderCommand;
+use bevy::render::pipeline::{PipelineDescriptor, PrimitiveTopology, RenderPipeline};
+use bevy::render::render_graph::{base, RenderGraph, RenderResourcesNode};
+use bevy::render::renderer::RenderResources;
+use bevy::render::shader::{ShaderStage, ShaderStages};
+use bevy::render::texture::{Extent3d, TextureDimension, TextureFormat};
+use itertools::iproduct;
+use serde::{Deserialize, Serialize};
+
+// Marker Component:
+pub struct IsBoundEdge;
+pub struct IsBoundFace(Face);
+pub struct IsNetwork;
+pub struct IsFieldSlice;
+pub struct IsFlow;
+pub struct IsTrail(usize); // fade level, 0 is the newest part of the trails
+pub struct IsObstacle;
+pub struct IsRegion;
+pub struct IsFloorGrid;
+
+// Scalar field used to color the particles
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorMode {
+    Neighbors,
+    Speed,
+    KineticEnergy,
+    PotentialEnergy,
+    Species,
+    Order,     // local q6
+    Alignment, // cosine between the dipole and the applied field
+}
+
+impl Default for ColorMode {
+    fn default() -> Self {
+        ColorMode::Neighbors
+    }
+}
+
+impl ColorMode {
+    pub const ALL: [ColorMode; 7] = [
+        ColorMode::Neighbors,
+        ColorMode::Speed,
+        ColorMode::KineticEnergy,
+        ColorMode::PotentialEnergy,
+        ColorMode::Species,
+        ColorMode::Order,
+        ColorMode::Alignment,
+    ];
+    const MAX_NEIGHBORS: f32 = 12.0; // neighbors of a close packed particle
+    const MAX_Q6: f32 = 0.575; // q6 of an fcc crystal
+
+    // Value of the field on a particle
+    // q6 is the order of the particle, it isn't kept on the particle itself
+    // axis is the unit direction the dipoles are measured against
+    pub fn value(self, particle: &Particle, q6: f32, axis: Vec3) -> f32 {
+        match self {
+            ColorMode::Neighbors => particle.neighbors as f32,
+            ColorMode::Speed => particle.get_vel().length(),
+            ColorMode::KineticEnergy => {
+                0.5 * particle.get_mass() * particle.get_vel().length_squared()
+            }
+            ColorMode::PotentialEnergy => particle.potential,
+            ColorMode::Species => particle.get_species() as f32,
+            ColorMode::Order => q6,
+            ColorMode::Alignment => {
+                let dipole = particle.world_dipole();
+                if dipole == Vec3::ZERO {
+                    0.0
+                } else {
+                    dipole.normalize().dot(axis)
+                }
+            }
+        }
+    }
+
+    // Range of values that is spread over the colormap
+    fn range(self, particles: &[Particle]) -> (f32, f32) {
+        match self {
+            ColorMode::Neighbors => (0.0, Self::MAX_NEIGHBORS),
+            ColorMode::Order => (0.0, Self::MAX_Q6),
+            ColorMode::Alignment => (-1.0, 1.0),
+            _ => particles
+                .iter()
+                .map(|particle| self.value(particle, 0.0, Vec3::Z))
+                .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), v| {
+                    (lo.min(v), hi.max(v))
+                }),
+        }
+    }
+}
+
+// Size and color of the particles of one species
+#[derive(Clone, Copy)]
+pub struct SpeciesStyle {
+    pub scale: f32,   // size relative to the particle radius of the Graphics settings
+    pub color: Color, // used when coloring by species
+}
+
+// Maps species to their style so the parts of a mixture can be told apart
+// Species beyond the last style reuse the styles from the start
+pub struct SpeciesVisuals {
+    styles: Vec<SpeciesStyle>,
+}
+
+impl SpeciesVisuals {
+    pub fn len(&self) -> usize {
+        self.styles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.styles.is_empty()
+    }
+
+    pub fn style(&self, species: usize) -> SpeciesStyle {
+        self.styles[species % self.styles.len()]
+    }
+
+    pub fn set_scale(&mut self, species: usize, scale: f32) {
+        self.styles[species].scale = scale;
+    }
+
+    pub fn set_color(&mut self, species: usize, color: Color) {
+        self.styles[species].color = color;
+    }
+}
+
+// Lighter rendering for huge particle counts, the physics still runs on every particle
+// Renders can be updated only every few frames, and only a random subset of the particles drawn
+pub struct RenderDecimation {
+    pub interval: usize, // frames between updates of the renders, 1 updates every frame
+    pub fraction: f32,   // share of the particles drawn
+    frames: usize,
+}
+
+impl Default for RenderDecimation {
+    fn default() -> Self {
+        Self {
+            interval: 1,
+            fraction: 1.0,
+            frames: 0,
+        }
+    }
+}
+
+impl RenderDecimation {
+    // Whether particle i is in the drawn subset
+    // The subset is picked by hashing the index so it doesn't flicker between frames
+    pub fn shows(&self, i: usize) -> bool {
+        if self.fraction >= 1.0 {
+            return true;
+        }
+        // splitmix64 finalizer, spreads consecutive indices over the whole range
+        let mut x = (i as u64).wrapping_add(0x9E37_79B9_7F4A_7C15);
+        x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        x ^= x >> 31;
+        ((x >> 40) as f32 / (1u64 << 24) as f32) < self.fraction
+    }
+}
+
+// Meshes of the particles get coarser with their distance from the camera
+// Particles closer than near use the finest sphere, those beyond far the coarsest
+pub struct LevelOfDetail {
+    pub enabled: bool, // everything is drawn with the coarsest sphere without it
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for LevelOfDetail {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            near: 2.0,
+            far: 6.0,
+        }
+    }
+}
+
+impl LevelOfDetail {
+    // number of levels, finest first, Graphics::subdivisions gives their spheres
+    pub const LEVELS: usize = 3;
+
+    // Level of a particle at a distance from the camera
+    pub fn level(&self, distance: f32) -> usize {
+        if !self.enabled || distance >= self.far {
+            Self::LEVELS - 1
+        } else if distance >= self.near {
+            1
+        } else {
+            0
+        }
+    }
+}
+
+// Quality of the rendering, set in the Graphics window
+// The sample count of the render passes is fixed when bevy builds its render graph,
+// so a change of msaa_samples only takes effect at the next start
+// Particles are drawn with half their σ as radius, so they touch where the pair potential
+// turns repulsive and the packing on screen is the physical one, unless a radius is set instead
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Graphics {
+    pub msaa_samples: u32,
+    pub vsync: bool,
+    pub sphere_detail: usize, // subdivisions of the icosphere of the finest level of detail
+    pub light_intensity: f32, // brightness relative to the default lighting
+    pub radius_from_sigma: bool,
+    pub particle_radius: f32, // radius of a particle of the default size without radius_from_sigma
+    pub particle_shape: ParticleShape,
+}
+
+impl Default for Graphics {
+    fn default() -> Self {
+        Self {
+            msaa_samples: 1,
+            vsync: true,
+            sphere_detail: 3,
+            light_intensity: 1.0,
+            radius_from_sigma: true,
+            particle_radius: 0.1,
+            particle_shape: ParticleShape::Sphere,
+        }
+    }
+}
+
+impl Graphics {
+    pub const MSAA_SAMPLES: [u32; 3] = [1, 2, 4];
+    pub const MAX_SPHERE_DETAIL: usize = 5;
+    pub const MAX_LIGHT_INTENSITY: f32 = 3.0;
+    pub const MAX_PARTICLE_RADIUS: f32 = 0.5;
+
+    // Drawn radius of a particle of the default size, sigma is the length scale of the potential
+    pub fn default_radius(&self, sigma: f32) -> f32 {
+        if self.radius_from_sigma {
+            0.5 * sigma
+        } else {
+            self.particle_radius
+        }
+    }
+
+    // Subdivisions of the icosphere of each level of detail, finest first
+    pub fn subdivisions(&self) -> [usize; LevelOfDetail::LEVELS] {
+        let fine = self.sphere_detail.min(Self::MAX_SPHERE_DETAIL);
+        [fine, fine / 2, 0]
+    }
+
+    // Sample count bevy accepts, the nearest supported one
+    pub fn valid_msaa_samples(samples: u32) -> u32 {
+        *Self::MSAA_SAMPLES
+            .iter()
+            .min_by_key(|&&s| (s as i64 - samples as i64).abs())
+            .unwrap()
+    }
+}
+
+// Mesh every particle is drawn with, turned by the orientation of the particle
+// Spheres look the same however they are turned, cubes show the orientations and their ordering
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParticleShape {
+    Sphere,
+    Cube, // of the volume of the sphere
+}
+
+impl Default for ParticleShape {
+    fn default() -> Self {
+        ParticleShape::Sphere
+    }
+}
+
+impl ParticleShape {
+    pub const ALL: [ParticleShape; 2] = [ParticleShape::Sphere, ParticleShape::Cube];
+    const CUBE_EDGE: f32 = 1.612; // (4/3 π)^(1/3), the cube of the volume of a unit sphere
+
+    // Mesh of unit radius, subdivisions only refine the sphere
+    pub fn mesh(self, subdivisions: usize) -> Mesh {
+        match self {
+            ParticleShape::Sphere => Mesh::from(shape::Icosphere {
+                radius: 1.0,
+                subdivisions,
+            }),
+            ParticleShape::Cube => Mesh::from(shape::Cube {
+                size: Self::CUBE_EDGE,
+            }),
+        }
+    }
+}
+
+// Parts of the box that are drawn, set in the Display window
+// The faces are translucent so the particles stay visible through them, each can be hidden
+// Faces of thermal walls are drawn warm and adhesive faces green, the others plain
+// The floor grid has lines one neighbor grid square apart, laid over the box as the force grid is
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BoxView {
+    pub edges: bool,
+    pub faces: [bool; 6], // indexed by Face::index
+    pub opacity: f32,
+    pub floor_grid: bool,
+    pub axis_gizmo: bool, // x, y and z arrows in the corner of the screen
+}
+
+impl Default for BoxView {
+    fn default() -> Self {
+        Self {
+            edges: true,
+            faces: [true; 6],
+            opacity: 0.08,
+            floor_grid: false,
+            axis_gizmo: true,
+        }
+    }
+}
+
+impl BoxView {
+    pub const MAX_OPACITY: f32 = 0.5;
+    const PLAIN_COLOR: [f32; 3] = [0.7, 0.8, 1.0];
+    const THERMAL_COLOR: [f32; 3] = [1.0, 0.45, 0.2];
+    const ADHESIVE_COLOR: [f32; 3] = [0.3, 0.9, 0.4];
+
+    // Color of a face of the box, an adhesive face of a thermal box is drawn adhesive
+    pub fn face_color(&self, bound: &Boundary, face: Face) -> Color {
+        let [r, g, b] = if bound.adhesion.map_or(false, |a| a.attracts(face)) {
+            Self::ADHESIVE_COLOR
+        } else if let WallModel::Thermal(_) = bound.wall_model {
+            Self::THERMAL_COLOR
+        } else {
+            Self::PLAIN_COLOR
+        };
+        Color::rgba(r, g, b, self.opacity)
+    }
+}
+
+// Apply changed graphics settings to the window, the particle meshes and the lights
+pub fn apply_graphics(
+    graphics: Res<Graphics>,
+    mut applied_meshes: Local<Option<(usize, ParticleShape)>>,
+    mut windows: ResMut<Windows>,
+    mut resized: EventWriter<bevy::window::WindowResized>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut particle_meshes: Query<(&ParticleInstances, &mut Handle<Mesh>)>,
+    mut lights: Query<&mut Light>,
+) {
+    if !graphics.is_changed() {
+        return;
+    }
+
+    if let Some(window) = windows.get_primary_mut() {
+        if window.vsync() != graphics.vsync {
+            window.set_vsync(graphics.vsync);
+            // the swap chain picks up vsync when it's created, it's recreated when the window is resized
+            resized.send(bevy::window::WindowResized {
+                id: window.id(),
+                width: window.width(),
+                height: window.height(),
+            });
+        }
+    }
+
+    let wanted = (graphics.sphere_detail, graphics.particle_shape);
+    if *applied_meshes != Some(wanted) {
+        let subdivisions = graphics.subdivisions();
+        for (renders, mut mesh) in particle_meshes.iter_mut() {
+            *mesh = meshes.add(graphics.particle_shape.mesh(subdivisions[renders.level]));
+        }
+        *applied_meshes = Some(wanted);
+    }
+
+    for mut light in lights.iter_mut() {
+        light.intensity = Light::default().intensity * graphics.light_intensity;
+    }
+}
+
+// Update the rendering of particles
+// Fills the instance buffers, one draw call per level of detail renders all of them
+#[allow(clippy::too_many_arguments)]
+pub fn update_particles_renders(
+    state: Res<SimulationState>,
+    species_visuals: Res<SpeciesVisuals>,
+    color_mode: Res<ColorMode>,
+    order_view: Res<order::OrderView>,
+    lod: Res<LevelOfDetail>,
+    graphics: Res<Graphics>,
+    mut decimation: ResMut<RenderDecimation>,
+    camera: Query<&Transform, With<FlyCam>>,
+    mut particle_renders: Query<(&mut ParticleInstances, &mut Visible)>,
+    mut app_timings: ResMut<sim_systems::AppTimings>,
+) {
+    decimation.frames += 1;
+    if decimation.frames < decimation.interval.max(1) {
+        return;
+    }
+    decimation.frames = 0;
+    let stopwatch = timing::Stopwatch::start();
+
+    let color_mode = *color_mode;
+    let (lo, hi) = color_mode.range(&state.particles);
+    let axis = state.dipolar.alignment_axis();
+    let eye = camera
+        .iter()
+        .next()
+        .map_or(Vec3::ZERO, |trans| trans.translation);
+
+    let group_colors = state.groups.colors(state.particles.len());
+    let default_radius = graphics.default_radius(state.get_potential().length_scale());
+    let mut levels = vec![Vec::new(); LevelOfDetail::LEVELS];
+    for (i, particle) in state.particles.iter().enumerate() {
+        if !decimation.shows(i) {
+            continue;
+        }
+
+        let color = if let Some([r, g, b]) = group_colors[i] {
+            Color::rgb_linear(r, g, b)
+        } else if particle.is_inert() {
+            ParticleInstances::INERT_COLOR
+        } else if color_mode == ColorMode::Species {
+            species_visuals.style(particle.get_species()).color
+        } else {
+            let t = if hi > lo {
+                let q6 = order_view.q6.get(i).copied().unwrap_or(0.0);
+                (color_mode.value(particle, q6, axis) - lo) / (hi - lo)
+            } else {
+                0.0
+            };
+            colormap::viridis(t.clamp(0.0, 1.0))
+        };
+        let pos = particle.get_pos();
+        // larger particles are drawn larger, as their σ grows with the radius
+        let radius = default_radius
+            * species_visuals.style(particle.get_species()).scale
+            * particle.get_radius()
+            / physics::DEFAULT_RADIUS;
+        let instances = &mut levels[lod.level((pos - eye).length())];
+        instances.push(pos.extend(radius));
+        instances.push(particle.get_orientation().into());
+        // the particle shader has a fixed light, brighter lighting scales the colors instead
+        let [r, g, b, a] = color.as_linear_rgba_f32();
+        let shade = graphics.light_intensity;
+        instances.push(Vec4::new(r * shade, g * shade, b * shade, a));
+    }
+
+    for (mut renders, mut visible) in particle_renders.iter_mut() {
+        renders.instances = std::mem::take(&mut levels[renders.level]);
+        // there's no empty buffer to bind
+        visible.is_visible = !renders.instances.is_empty();
+    }
+    app_timings
+        .timings
+        .record(timing::Phase::RenderSync, stopwatch.elapsed_ms());
+}
+
+// Stage of draw_particle_instances
+pub const DRAW_INSTANCES: &str = "draw_instances";
+
+// The pipeline draw system issues every draw with a single instance,
+// widen the draw of the particle mesh to one instance per particle
+pub fn draw_particle_instances(mut particle_renders: Query<(&mut Draw, &ParticleInstances)>) {
+    for (mut draw, renders) in particle_renders.iter_mut() {
+        let count = renders.count() as u32;
+        for command in draw.render_commands.iter_mut() {
+            if let RenderCommand::DrawIndexed { instances, .. } = command {
+                *instances = 0..count;
+            }
+        }
+    }
+}
+
+// Update the rendering of bounding box
+pub fn update_bounding_box_renders(
+    state: Res<SimulationState>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut bounding_box_renders: Query<(&mut Transform, &mut Handle<Mesh>), With<IsBoundEdge>>,
+) {
+    let lo = state.bound.lo_corner();
+    let size = state.bound.size();
+    let binary = [0.0, 1.0]; // generate the four corners of each axis
+    let conditions = [0, 1, 2]; // stands for x, y, z axis
+    let multipliers = iproduct!(conditions.iter(), binary.iter(), binary.iter());
+
+    let line_x = meshes.add(create_line_mesh(size.x, 0.0, 0.0));
+    let line_y = meshes.add(create_line_mesh(0.0, size.y, 0.0));
+    let line_z = meshes.add(create_line_mesh(0.0, 0.0, size.z));
+
+    for ((&cond, &mult1, &mult2), (mut trans, mut mesh)) in
+        multipliers.zip(bounding_box_renders.iter_mut())
+    {
+        // edges along the x axis
+        if cond == 0 {
+            *mesh = line_x.clone();
+            trans.translation = lo + Vec3::new(0.0, size.y * mult1, size.z * mult2);
+        }
+        // edges along the y axis
+        else if cond == 1 {
+            *mesh = line_y.clone();
+            trans.translation = lo + Vec3::new(size.x * mult1, 0.0, size.z * mult2);
+        }
+        // edges along the z axis
+        else {
+            *mesh = line_z.clone();
+            trans.translation = lo + Vec3::new(size.x * mult1, size.y * mult2, 0.0);
+        }
+    }
+}
+
+// Show the edges and faces picked in the box view, and fit the faces to the box
+// The faces are unit squares scaled to the box, placed at their centers so they are sorted by
+// their distance from the camera when blended
+pub fn update_bounding_face_renders(
+    state: Res<SimulationState>,
+    box_view: Res<BoxView>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut edge_renders: Query<&mut Visible, (With<IsBoundEdge>, Without<IsBoundFace>)>,
+    mut face_renders: Query<(
+        &IsBoundFace,
+        &mut Transform,
+        &mut Visible,
+        &Handle<StandardMaterial>,
+    )>,
+) {
+    for mut visible in edge_renders.iter_mut() {
+        visible.is_visible = box_view.edges;
+    }
+
+    let (lo, hi) = (state.bound.lo_corner(), state.bound.hi_corner());
+    let size = hi - lo;
+    for (IsBoundFace(face), mut trans, mut visible, material) in face_renders.iter_mut() {
+        let axis = face.axis();
+        let mut center = 0.5 * (lo + hi);
+        center[axis] = if face.is_hi() { hi[axis] } else { lo[axis] };
+        let mut scale = size;
+        scale[axis] = 1.0;
+        trans.translation = center;
+        trans.scale = scale;
+
+        visible.is_visible = box_view.faces[face.index()] && box_view.opacity > 0.0;
+        let color = box_view.face_color(&state.bound, *face);
+        // only touched when it changes, a changed material is uploaded again
+        if materials.get(material).map(|m| m.base_color) != Some(color) {
+            if let Some(material) = materials.get_mut(material) {
+                material.base_color = color;
+            }
+        }
+    }
+}
+// Redraw the floor grid whenever the box or the grid squares change
+// The floor is the face at the lower y, the camera is upright along y
+pub fn update_floor_grid_renders(
+    mut commands: Commands,
+    state: Res<SimulationState>,
+    box_view: Res<BoxView>,
+    mut drawn: Local<Option<(Vec3, Vec3, f32)>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    grid_renders: Query<Entity, With<IsFloorGrid>>,
+) {
+    // more lines than this along an axis are too dense to read, none are drawn then
+    const MAX_LINES: usize = 500;
+
+    let (lo, hi) = (state.bound.lo_corner(), state.bound.hi_corner());
+    let unit = state.get_grid_unit_size();
+    let wanted = Some((lo, hi, unit)).filter(|_| box_view.floor_grid);
+    if *drawn == wanted {
+        return;
+    }
+    for entity in grid_renders.iter() {
+        commands.entity(entity).despawn();
+    }
+    *drawn = wanted;
+    if wanted.is_none() || unit.is_nan() || unit <= 0.0 {
+        return;
+    }
+
+    // lines inside the box, its edges close the grid
+    let lines = |from: f32, to: f32| {
+        (1..)
+            .map(move |k| from + k as f32 * unit)
+            .take_while(move |&at| at < to)
+            .take(MAX_LINES + 1)
+    };
+    let mut segments = Vec::new();
+    if lines(lo.x, hi.x).count() <= MAX_LINES && lines(lo.z, hi.z).count() <= MAX_LINES {
+        for x in lines(lo.x, hi.x) {
+            segments.push((Vec3::new(x, lo.y, lo.z), Vec3::new(x, lo.y, hi.z)));
+        }
+        for z in lines(lo.z, hi.z) {
+            segments.push((Vec3::new(lo.x, lo.y, z), Vec3::new(hi.x, lo.y, z)));
+        }
+    }
+    let material = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.45, 0.45, 0.5),
+        unlit: true,
+        ..Default::default()
+    });
+    commands
+        .spawn()
+        .insert_bundle(PbrBundle {
+            mesh: meshes.add(create_line_list_mesh(&segments)),
+            material,
+            ..Default::default()
+        })
+        .insert(IsFloorGrid);
+}
+
+// Redraw the obstacles whenever they changed, e.g. after a reset
+pub fn update_obstacle_renders(
+    mut commands: Commands,
+    state: Res<SimulationState>,
+    mut drawn: Local<Vec<obstacle::Obstacle>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    obstacle_renders: Query<Entity, With<IsObstacle>>,
+) {
+    if *drawn == state.obstacles {
+        return;
+    }
+    for entity in obstacle_renders.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    // translucent so the particles behind and around them stay visible
+    let material = materials.add(StandardMaterial {
+        base_color: Color::rgba(0.6, 0.7, 0.9, 0.3),
+        unlit: false,
+        ..Default::default()
+    });
+    for obstacle in state.obstacles.iter() {
+        let mesh = match *obstacle {
+            obstacle::Obstacle::Sphere { radius, .. } => Mesh::from(shape::Icosphere {
+                radius,
+                subdivisions: 3,
+            }),
+            obstacle::Obstacle::Box { lo, hi } => {
+                let size = hi - lo;
+                Mesh::from(shape::Box::new(size.x, size.y, size.z))
+            }
+        };
+        commands
+            .spawn()
+            .insert_bundle(PbrBundle {
+                mesh: meshes.add(mesh),
+                material: material.clone(),
+                transform: Transform::from_translation(obstacle.center()),
+                visible: Visible {
+                    is_visible: true,
+                    is_transparent: true,
+                },
+                ..Default::default()
+            })
+            .insert(IsObstacle);
+    }
+    *drawn = state.obstacles.clone();
+}
+
+// Redraw the edges of the region probes whenever they are placed, moved or removed
+pub fn update_region_renders(
+    mut commands: Commands,
+    state: Res<SimulationState>,
+    mut drawn: Local<Vec<(Vec3, Vec3)>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    region_renders: Query<Entity, With<IsRegion>>,
+) {
+    let corners: Vec<(Vec3, Vec3)> = state
+        .regions
+        .iter()
+        .map(|region| (region.lo_corner(), region.hi_corner()))
+        .collect();
+    if *drawn == corners {
+        return;
+    }
+    for entity in region_renders.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let material = materials.add(StandardMaterial {
+        base_color: Color::rgb(1.0, 0.8, 0.2),
+        unlit: true,
+        ..Default::default()
+    });
+    for &(lo, hi) in corners.iter() {
+        // the twelve edges, four along each axis
+        let size = hi - lo;
+        let binary = [0.0, 1.0];
+        let segments: Vec<(Vec3, Vec3)> = iproduct!(0..3, binary.iter(), binary.iter())
+            .map(|(axis, &a, &b)| {
+                let (edge, offset) = match axis {
+                    0 => (Vec3::X * size.x, Vec3::new(0.0, a * size.y, b * size.z)),
+                    1 => (Vec3::Y * size.y, Vec3::new(a * size.x, 0.0, b * size.z)),
+                    _ => (Vec3::Z * size.z, Vec3::new(a * size.x, b * size.y, 0.0)),
+                };
+                (lo + offset, lo + offset + edge)
+            })
+            .collect();
+        commands
+            .spawn()
+            .insert_bundle(PbrBundle {
+                mesh: meshes.add(create_line_list_mesh(&segments)),
+                material: material.clone(),
+                ..Default::default()
+            })
+            .insert(IsRegion);
+    }
+    *drawn = corners;
+}
+
+//////////////////////////////////////////
+pub fn setup_bounding_box(
+    state: Res<SimulationState>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let bound = state.bound;
+    let lo = bound.lo_corner();
+    let size = bound.size();
+
+    // Draw bounding Box
+    let multipliers = [(0.0, 0.0), (0.0, 1.0), (1.0, 0.0), (1.0, 1.0)];
+    let white_mat_unlit = materials.add(StandardMaterial {
+        base_color: Color::WHITE,
+        unlit: true,
+        ..Default::default()
+    });
+
+    let line_x = meshes.add(create_line_mesh(size.x, 0.0, 0.0));
+    for &(mult1, mult2) in multipliers.iter() {
+        commands
+            .spawn()
+            .insert_bundle(PbrBundle {
+                mesh: line_x.clone(),
+                material: white_mat_unlit.clone(),
+                transform: Transform::from_translation(
+                    lo + Vec3::new(0.0, size.y * mult1, size.z * mult2),
+                ),
+                ..Default::default()
+            })
+            .insert(IsBoundEdge);
+    }
+    let line_y = meshes.add(create_line_mesh(0.0, size.y, 0.0));
+    for &(mult1, mult2) in multipliers.iter() {
+        commands
+            .spawn()
+            .insert_bundle(PbrBundle {
+                mesh: line_y.clone(),
+                material: white_mat_unlit.clone(),
+                transform: Transform::from_translation(
+                    lo + Vec3::new(size.x * mult1, 0.0, size.z * mult2),
+                ),
+                ..Default::default()
+            })
+            .insert(IsBoundEdge);
+    }
+    let line_z = meshes.add(create_line_mesh(0.0, 0.0, size.z));
+    for &(mult1, mult2) in multipliers.iter() {
+        commands
+            .spawn()
+            .insert_bundle(PbrBundle {
+                mesh: line_z.clone(),
+                material: white_mat_unlit.clone(),
+                transform: Transform::from_translation(
+                    lo + Vec3::new(size.x * mult1, size.y * mult2, 0.0),
+                ),
+                ..Default::default()
+            })
+            .insert(IsBoundEdge);
+    }
+
+    // Faces, each with its own material so they can be colored apart
+    // update_bounding_face_renders places and colors them
+    let squares: Vec<_> = (0..3)
+        .map(|axis| meshes.add(create_square_mesh(axis)))
+        .collect();
+    for &face in Face::ALL.iter() {
+        commands
+            .spawn()
+            .insert_bundle(PbrBundle {
+                mesh: squares[face.axis()].clone(),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::NONE,
+                    unlit: false, // lit so the faces shade apart by their direction
+                    ..Default::default()
+                }),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: true,
+                },
+                ..Default::default()
+            })
+            .insert(IsBoundFace(face));
+    }
+
+    // Add Lights
+    commands.spawn().insert_bundle(LightBundle {
+        transform: Transform::from_translation(bound.lo_corner()),
+        ..Default::default()
+    });
+
+    commands.spawn().insert_bundle(LightBundle {
+        transform: Transform::from_translation(bound.hi_corner()),
+        ..Default::default()
+    });
+}
+
+// Helper function for draw bounding box
+fn create_line_mesh(x: f32, y: f32, z: f32) -> Mesh {
+    let mut mesh = Mesh::new(PrimitiveTopology::LineStrip);
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, vec![[0.0, 0.0, 0.0], [x, y, z]]);
+    mesh.set_attribute(
+        Mesh::ATTRIBUTE_NORMAL,
+        vec![[0.0, 0.0, 1.0], [0.0, 0.0, 1.0]],
+    );
+    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0, 0.0], [x, y, z]]);
+    mesh
+}
+
+// Unit square centered at the origin, perpendicular to the axis
+// Both sides are drawn, the box is seen from inside as well as from outside
+// internal helper function
+fn create_square_mesh(axis: usize) -> Mesh {
+    let axes = [Vec3::X, Vec3::Y, Vec3::Z];
+    let (u, v) = (axes[(axis + 1) % 3], axes[(axis + 2) % 3]);
+    let normal = axes[axis];
+    let corners = [-u - v, u - v, u + v, -u + v];
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+    for &side in [normal, -normal].iter() {
+        for corner in corners.iter() {
+            positions.push((0.5 * *corner).into());
+            normals.push(side.into());
+        }
+    }
+    // counterclockwise seen from the side the normal points to
+    let indices = vec![0, 1, 2, 0, 2, 3, 4, 6, 5, 4, 7, 6];
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; positions.len()]);
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.set_indices(Some(bevy::render::mesh::Indices::U32(indices)));
+    mesh
+}
+
+////////////////////////////////////////////
+// The particles are rendered by instancing sphere meshes with a custom pipeline, one entity per level of detail
+// The instance buffer holds three entries per particle: position and radius, orientation, then the linear color
+#[derive(RenderResources, Default)]
+pub struct ParticleInstances {
+    #[render_resources(buffer)]
+    pub instances: Vec<Vec4>,
+    #[render_resources(ignore)]
+    pub level: usize, // level of detail, 0 is the finest
+}
+
+impl ParticleInstances {
+    const INERT_COLOR: Color = Color::rgb(0.6, 0.6, 0.6); // inert particles keep this color in every mode
+    const NODE: &'static str = "particle_instances";
+    const VERTEX_SHADER: &'static str = include_str!("shaders/particle.vert");
+    const FRAGMENT_SHADER: &'static str = include_str!("shaders/particle.frag");
+
+    pub fn count(&self) -> usize {
+        self.instances.len() / 3
+    }
+}
+
+pub fn setup_particles(
+    state: Res<SimulationState>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut pipelines: ResMut<Assets<PipelineDescriptor>>,
+    mut shaders: ResMut<Assets<Shader>>,
+    mut render_graph: ResMut<RenderGraph>,
+    graphics: Res<Graphics>,
+) {
+    // one style for each species present, colored by the categorical colormap
+    let n_species = state
+        .particles
+        .iter()
+        .map(|particle| particle.get_species() + 1)
+        .max()
+        .unwrap_or(1);
+    let styles: Vec<_> = (0..n_species)
+        .map(|i| SpeciesStyle {
+            scale: 1.0,
+            color: colormap::categorical(i),
+        })
+        .collect();
+    commands.insert_resource(SpeciesVisuals { styles });
+
+    // the instance buffer is written by its own node before the main pass
+    render_graph.add_system_node(
+        ParticleInstances::NODE,
+        RenderResourcesNode::<ParticleInstances>::new(false),
+    );
+    render_graph
+        .add_node_edge(ParticleInstances::NODE, base::node::MAIN_PASS)
+        .unwrap();
+
+    let pipeline = pipelines.add(PipelineDescriptor::default_config(ShaderStages {
+        vertex: shaders.add(Shader::from_glsl(
+            ShaderStage::Vertex,
+            ParticleInstances::VERTEX_SHADER,
+        )),
+        fragment: Some(shaders.add(Shader::from_glsl(
+            ShaderStage::Fragment,
+            ParticleInstances::FRAGMENT_SHADER,
+        ))),
+    }));
+
+    // unit shapes, the shader turns them and scales them by the radius of each particle
+    for (level, &subdivisions) in graphics.subdivisions().iter().enumerate() {
+        commands
+            .spawn()
+            .insert_bundle(MeshBundle {
+                mesh: meshes.add(graphics.particle_shape.mesh(subdivisions)),
+                render_pipelines: RenderPipelines::from_pipelines(vec![RenderPipeline::new(
+                    pipeline.clone(),
+                )]),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: false,
+                },
+                ..Default::default()
+            })
+            .insert(ParticleInstances {
+                instances: Vec::new(),
+                level,
+            });
+    }
+}
+
+////////////////////////////////////////////////////////////
+pub fn setup_camera(
+    mut commands: Commands,
+    state: Res<SimulationState>,
+    mut input_state: ResMut<InputState>,
+) {
+    let bound = state.bound;
+
+    // Initialize Camera
+    let camera_position = Vec3::new(5.0, 3.0, -5.0);
+    let camera_trans = Transform::from_translation(camera_position)
+        .looking_at(bound.center() - camera_position, Vec3::Y);
+    let (axis, angle) = camera_trans.rotation.to_axis_angle();
+    input_state.reset_axis_angle(axis, angle);
+    commands
+        .spawn()
+        .insert_bundle(PerspectiveCameraBundle {
+            transform: camera_trans,
+            ..Default::default()
+        })
+        .insert(FlyCam);
+}
+
+// Request to move the camera to a particle, cleared once the camera got there
+// The selected particle is the center of the orbit camera, the box center is used without one
+// frame_box asks for the whole boundary in view, the camera reset hotkey sets it
+pub struct CameraFocus {
+    pub particle: Option<usize>,
+    pub distance: f32, // distance the camera is placed from the particle
+    pub selected: Option<usize>,
+    pub frame_box: bool,
+}
+
+impl Default for CameraFocus {
+    fn default() -> Self {
+        Self {
+            particle: None,
+            distance: 2.0,
+            selected: None,
+            frame_box: false,
+        }
+    }
+}
+
+// Distance at which a camera with the given projection sees the whole box
+// The bounding sphere of the box has to fit in the narrower of the two fields of view
+fn framing_distance(bound: &Boundary, projection: &PerspectiveProjection) -> f32 {
+    let radius = 0.5 * bound.size().length();
+    let half_fov_y = 0.5 * projection.fov;
+    let half_fov_x = (half_fov_y.tan() * projection.aspect_ratio).atan();
+    radius / half_fov_y.min(half_fov_x).sin()
+}
+
+pub fn frame_box_camera(
+    state: Res<SimulationState>,
+    mode: Res<CameraMode>,
+    mut focus: ResMut<CameraFocus>,
+    mut orbit: ResMut<Orbit>,
+    mut input_state: ResMut<InputState>,
+    mut query: Query<(&mut Transform, &PerspectiveProjection), With<FlyCam>>,
+) {
+    if !focus.frame_box {
+        return;
+    }
+    focus.frame_box = false;
+
+    let center = state.bound.center();
+    for (mut transform, projection) in query.iter_mut() {
+        let distance = framing_distance(&state.bound, projection);
+        if *mode == CameraMode::Orbit {
+            // the orbit camera places itself around its center
+            focus.selected = None;
+            orbit.distance = distance;
+        } else {
+            bevy_flycam::focus_on(&mut transform, &mut input_state, center, distance);
+        }
+    }
+}
+
+pub fn update_camera_focus(
+    state: Res<SimulationState>,
+    mode: Res<CameraMode>,
+    mut focus: ResMut<CameraFocus>,
+    mut orbit: ResMut<Orbit>,
+    mut input_state: ResMut<InputState>,
+    mut query: Query<&mut Transform, With<FlyCam>>,
+) {
+    let particle_pos = |pid: Option<usize>| {
+        pid.filter(|&pid| pid < state.particles.len())
+            .map(|pid| state.particles[pid].get_pos())
+    };
+    orbit.center = particle_pos(focus.selected).unwrap_or_else(|| state.bound.center());
+
+    let target = match particle_pos(focus.particle.take()) {
+        Some(pos) => pos,
+        None => return,
+    };
+    if *mode == CameraMode::Orbit {
+        orbit.distance = focus.distance;
+        return;
+    }
+    for mut transform in query.iter_mut() {
+        bevy_flycam::focus_on(&mut transform, &mut input_state, target, focus.distance);
+    }
+}
+
+////////////////////////////////////////////////////////////
+pub fn setup_network(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    // All network edges are drawn by one line mesh, hidden until the analysis is enabled
+    commands
+        .spawn()
+        .insert_bundle(PbrBundle {
+            mesh: meshes.add(create_line_list_mesh(&[])),
+            material: materials.add(StandardMaterial {
+                base_color: Color::YELLOW,
+                unlit: true,
+                ..Default::default()
+            }),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: false,
+            },
+            ..Default::default()
+        })
+        .insert(IsNetwork);
+}
+
+// Texture shown on the field slice, replaced whenever the slice changes
+pub struct FieldSliceTexture(Handle<Texture>);
+
+pub fn setup_field_slice(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut textures: ResMut<Assets<Texture>>,
+) {
+    let texture = textures.add(Texture::default());
+    let material = materials.add(StandardMaterial {
+        base_color: Color::rgba(1.0, 1.0, 1.0, 0.7),
+        base_color_texture: Some(texture.clone()),
+        unlit: true,
+        ..Default::default()
+    });
+
+    // back faces are culled, the flipped quad is the same square wound the other way
+    for &flip in [false, true].iter() {
+        commands
+            .spawn()
+            .insert_bundle(PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::Quad {
+                    size: Vec2::ONE,
+                    flip,
+                })),
+                material: material.clone(),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: true,
+                },
+                ..Default::default()
+            })
+            .insert(IsFieldSlice);
+    }
+    commands.insert_resource(FieldSliceTexture(texture));
+}
+
+// Update the slice of the local field drawn inside the box
+pub fn update_field_slice_renders(
+    state: Res<SimulationState>,
+    field_view: Res<field::FieldView>,
+    slice_texture: Res<FieldSliceTexture>,
+    mut textures: ResMut<Assets<Texture>>,
+    mut slice_renders: Query<(&mut Transform, &mut Visible), With<IsFieldSlice>>,
+) {
+    let field = match field_view.field() {
+        Some(field) if field_view.enabled && field_view.in_scene => field,
+        _ => {
+            for (_, mut visible) in slice_renders.iter_mut() {
+                visible.is_visible = false;
+            }
+            return;
+        }
+    };
+
+    let slice = field::slice(&field, field_view.axis, field_view.slice);
+    if let Some(texture) = textures.get_mut(&slice_texture.0) {
+        *texture = create_slice_texture(&slice);
+    }
+
+    // the two remaining axes in the order of the slice, and the slice normal
+    let (a, b) = match field_view.axis {
+        field::FieldAxis::X => (Vec3::Y, Vec3::Z),
+        field::FieldAxis::Y => (Vec3::X, Vec3::Z),
+        field::FieldAxis::Z => (Vec3::X, Vec3::Y),
+    };
+    let axis = field_view.axis.index();
+    let resolution = field.len_of(ndarray::Axis(axis));
+    let index = field_view.slice.min(resolution - 1);
+
+    let size = state.bound.size();
+    let mut center = state.bound.center();
+    center[axis] =
+        state.bound.lo_corner()[axis] + (index as f32 + 0.5) * size[axis] / resolution as f32;
+
+    for (mut trans, mut visible) in slice_renders.iter_mut() {
+        visible.is_visible = true;
+        trans.translation = center;
+        trans.rotation = Quat::from_rotation_mat3(&Mat3::from_cols(a, b, a.cross(b)));
+        trans.scale = Vec3::new(size.dot(a), size.dot(b), 1.0);
+    }
+}
+
+// Helper function for drawing a slice as an image, one texel per cell
+// The first index of the slice runs along the texture width, the second upwards
+fn create_slice_texture(slice: &ndarray::Array2<f32>) -> Texture {
+    let (width, height) = slice.dim();
+    let (lo, hi) = field::slice_range(slice);
+
+    let mut data = vec![0; width * height * 4];
+    for ((i, j), &value) in slice.indexed_iter() {
+        let t = if hi > lo {
+            (value - lo) / (hi - lo)
+        } else {
+            0.0
+        };
+        let color = colormap::viridis(t);
+        let texel = ((height - 1 - j) * width + i) * 4;
+        data[texel..texel + 4].copy_from_slice(&[
+            (color.r() * 255.0) as u8,
+            (color.g() * 255.0) as u8,
+            (color.b() * 255.0) as u8,
+            255,
+        ]);
+    }
+
+    Texture::new(
+        Extent3d::new(width as u32, height as u32, 1),
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+pub fn setup_flow(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    // All arrows are drawn by one line mesh, hidden until the flow view is enabled
+    commands
+        .spawn()
+        .insert_bundle(PbrBundle {
+            mesh: meshes.add(create_line_list_mesh(&[])),
+            material: materials.add(StandardMaterial {
+                base_color: Color::CYAN,
+                unlit: true,
+                ..Default::default()
+            }),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: false,
+            },
+            ..Default::default()
+        })
+        .insert(IsFlow);
+}
+
+// Update the rendering of the velocity arrows
+pub fn update_flow_renders(
+    flow_view: Res<field::FlowView>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut flow_renders: Query<(&mut Handle<Mesh>, &mut Visible), With<IsFlow>>,
+) {
+    for (mut mesh, mut visible) in flow_renders.iter_mut() {
+        visible.is_visible = flow_view.enabled;
+        if !flow_view.enabled {
+            continue;
+        }
+
+        let segments: Vec<_> = flow_view
+            .arrows()
+            .into_iter()
+            .flat_map(|(center, vel)| arrow_segments(center, vel * flow_view.arrow_scale))
+            .collect();
+        *mesh = meshes.add(create_line_list_mesh(&segments));
+    }
+}
+
+// Helper function for drawing an arrow centered on a point
+// A shaft along the vector and two short strokes for the head
+fn arrow_segments(center: Vec3, vector: Vec3) -> Vec<(Vec3, Vec3)> {
+    let length = vector.length();
+    if length == 0.0 {
+        return Vec::new();
+    }
+
+    let tail = center - vector / 2.0;
+    let tip = center + vector / 2.0;
+    let dir = vector / length;
+    // any direction perpendicular to the arrow spans the head
+    let side = if dir.x.abs() < 0.9 { Vec3::X } else { Vec3::Y };
+    let side = dir.cross(side).normalize() * length * 0.15;
+    let back = tip - dir * length * 0.3;
+
+    vec![(tail, tip), (tip, back + side), (tip, back - side)]
+}
+
+// Trails fade out in steps, each step is a line mesh with its own brightness
+const TRAIL_FADE_LEVELS: usize = 4;
+
+pub fn setup_trails(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for level in 0..TRAIL_FADE_LEVELS {
+        let brightness = 1.0 - level as f32 / TRAIL_FADE_LEVELS as f32;
+        commands
+            .spawn()
+            .insert_bundle(PbrBundle {
+                mesh: meshes.add(create_line_list_mesh(&[])),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::rgb(brightness, brightness * 0.8, brightness * 0.4),
+                    unlit: true,
+                    ..Default::default()
+                }),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: false,
+                },
+                ..Default::default()
+            })
+            .insert(IsTrail(level));
+    }
+}
+
+// Update the rendering of the particle trails
+pub fn update_trail_renders(
+    trail_view: Res<trail::TrailView>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut trail_renders: Query<(&mut Handle<Mesh>, &mut Visible, &IsTrail)>,
+) {
+    let segments = if trail_view.enabled {
+        trail_view.segments()
+    } else {
+        Vec::new()
+    };
+
+    for (mut mesh, mut visible, &IsTrail(level)) in trail_renders.iter_mut() {
+        visible.is_visible = trail_view.enabled;
+        if !trail_view.enabled {
+            continue;
+        }
+
+        let level_segments: Vec<_> = segments
+            .iter()
+            .filter(|&&(_, _, age)| {
+                let segment_level = (age * TRAIL_FADE_LEVELS as f32) as usize;
+                segment_level.min(TRAIL_FADE_LEVELS - 1) == level
+            })
+            .map(|&(a, b, _)| (a, b))
+            .collect();
+        *mesh = meshes.add(create_line_list_mesh(&level_segments));
+    }
+}
+
+// Update the rendering of the bond network
+pub fn update_network_renders(
+    state: Res<SimulationState>,
+    network_view: Res<network::NetworkView>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut network_renders: Query<(&mut Handle<Mesh>, &mut Visible), With<IsNetwork>>,
+) {
+    for (mut mesh, mut visible) in network_renders.iter_mut() {
+        visible.is_visible = network_view.enabled;
+        if !network_view.enabled {
+            continue;
+        }
+
+        let segments: Vec<_> = network_view
+            .network
+            .get_edges()
+            .iter()
+            .filter(|&&(i, j)| i < state.particles.len() && j < state.particles.len())
+            .map(|&(i, j)| (state.particles[i].get_pos(), state.particles[j].get_pos()))
+            .collect();
+        *mesh = meshes.add(create_line_list_mesh(&segments));
+    }
+}
+
+// Helper function for drawing many separate line segments in one mesh
+fn create_line_list_mesh(segments: &[(Vec3, Vec3)]) -> Mesh {
+    // a mesh without vertices can't be drawn, fall back to a degenerate segment
+    let positions: Vec<[f32; 3]> = if segments.is_empty() {
+        vec![[0.0, 0.0, 0.0], [0.0, 0.0, 0.0]]
+    } else {
+        segments
+            .iter()
+            .flat_map(|(a, b)| vec![[a.x, a.y, a.z], [b.x, b.y, b.z]])
+            .collect()
+    };
+
+    let mut mesh = Mesh::new(PrimitiveTopology::LineList);
+    mesh.set_attribute(
+        Mesh::ATTRIBUTE_NORMAL,
+        vec![[0.0, 0.0, 1.0]; positions.len()],
+    );
+    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; positions.len()]);
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh
+}
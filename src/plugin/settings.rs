@@ -0,0 +1,356 @@
+// Settings kept between runs
+// The gui adjustable settings are written to settings.toml in the platform config directory
+// when they change and on exit, and read back at startup
+// The slider values of the simulation are only restored for the default setup,
+// a config file or preset given on the command line decides them itself
+// There is no filesystem to keep them on in the browser
+use super::capture::Capture;
+use super::keybindings::KeyBindings;
+use super::presentation::Presentation;
+use super::render_systems::{BoxView, ColorMode, Graphics, LevelOfDetail, RenderDecimation};
+use super::*;
+use crate::bevy_flycam::{CameraMode, MovementSettings, Orbit};
+use bevy::app::AppExit;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use vdw_core::state::units::UnitSystem;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(default)]
+pub struct Settings {
+    pub simulation: SimulationSettings,
+    pub display: DisplaySettings,
+    pub camera: CameraSettings,
+    pub rendering: RenderingSettings,
+    pub graphics: Graphics,
+    pub box_view: BoxView,
+    pub keys: KeyBindings,
+    pub presentation: Presentation,
+    pub capture: Capture,
+}
+
+// Values of the sliders
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(default)]
+pub struct SimulationSettings {
+    pub target_temp: Option<f32>,
+    pub inject_rate: Option<f32>,
+    pub dt: Option<f32>,
+    pub steps_per_frame: Option<usize>,
+    pub frame_budget_ms: Option<f32>, // steps_per_frame is used without it
+    pub pressure_sampling_period: Option<f32>,
+    pub remove_drift: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(default)]
+pub struct DisplaySettings {
+    pub color_mode: ColorMode,
+    pub units: UnitSystem,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct CameraSettings {
+    pub mode: CameraMode,
+    pub sensitivity: f32,
+    pub speed: f32,
+    pub orbit_distance: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        let movement = MovementSettings::default();
+        Self {
+            mode: CameraMode::default(),
+            sensitivity: movement.sensitivity,
+            speed: movement.speed,
+            orbit_distance: Orbit::default().distance,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct RenderingSettings {
+    pub update_interval: usize,
+    pub drawn_fraction: f32,
+    pub level_of_detail: bool,
+    pub fine_within: f32,
+    pub coarse_beyond: f32,
+}
+
+impl Default for RenderingSettings {
+    fn default() -> Self {
+        let decimation = RenderDecimation::default();
+        let lod = LevelOfDetail::default();
+        Self {
+            update_interval: decimation.interval,
+            drawn_fraction: decimation.fraction,
+            level_of_detail: lod.enabled,
+            fine_within: lod.near,
+            coarse_beyond: lod.far,
+        }
+    }
+}
+
+impl Settings {
+    pub const FILE: &'static str = "settings.toml";
+
+    // File the settings are kept in, None where there is no config directory
+    pub fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("van_der_waals").join(Self::FILE))
+    }
+
+    // Saved settings, None if there are none or they can't be read
+    pub fn load() -> Option<Self> {
+        let path = Self::path()?;
+        let text = fs::read_to_string(&path).ok()?;
+        match toml::from_str(&text) {
+            Ok(settings) => Some(settings),
+            Err(e) => {
+                eprintln!("Ignoring the settings in {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text =
+            toml::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, text)
+    }
+}
+
+// Platform config directory
+// internal helper function
+fn config_dir() -> Option<PathBuf> {
+    let var = |name| std::env::var_os(name).map(PathBuf::from);
+    if cfg!(target_os = "windows") {
+        var("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        var("XDG_CONFIG_HOME").or_else(|| var("HOME").map(|home| home.join(".config")))
+    }
+}
+
+// Settings read at startup and the ones last written
+pub struct SettingsStore {
+    pub loaded: Option<Settings>,
+    pub restore_simulation: bool, // whether the slider values are restored
+    saved: Option<Settings>,
+    last_change: Option<f64>, // seconds since startup the settings last changed unsaved
+}
+
+impl SettingsStore {
+    // Changed settings are written once they have been left alone this long
+    const SAVE_DELAY: f64 = 2.0;
+
+    pub fn new(loaded: Option<Settings>, restore_simulation: bool) -> Self {
+        Self {
+            saved: loaded.clone(),
+            loaded,
+            restore_simulation,
+            last_change: None,
+        }
+    }
+}
+
+// Settings read off the running app
+// internal helper function
+#[allow(clippy::too_many_arguments)]
+fn capture(
+    state: &SimulationState,
+    color_mode: ColorMode,
+    units: UnitSystem,
+    camera_mode: CameraMode,
+    movement: &MovementSettings,
+    orbit: &Orbit,
+    decimation: &RenderDecimation,
+    lod: &LevelOfDetail,
+    graphics: &Graphics,
+    box_view: &BoxView,
+    keys: &KeyBindings,
+    presentation: &Presentation,
+    capture: &Capture,
+) -> Settings {
+    Settings {
+        simulation: SimulationSettings {
+            target_temp: Some(state.target_temp),
+            inject_rate: Some(state.inject_rate),
+            dt: Some(state.dt),
+            steps_per_frame: Some(state.steps_per_frame),
+            frame_budget_ms: Some(state.frame_budget.ms_per_frame)
+                .filter(|_| state.frame_budget.enabled),
+            pressure_sampling_period: Some(state.pressure_sampling_period),
+            remove_drift: Some(state.remove_drift),
+        },
+        display: DisplaySettings { color_mode, units },
+        camera: CameraSettings {
+            mode: camera_mode,
+            sensitivity: movement.sensitivity,
+            speed: movement.speed,
+            orbit_distance: orbit.distance,
+        },
+        rendering: RenderingSettings {
+            update_interval: decimation.interval,
+            drawn_fraction: decimation.fraction,
+            level_of_detail: lod.enabled,
+            fine_within: lod.near,
+            coarse_beyond: lod.far,
+        },
+        graphics: graphics.clone(),
+        box_view: box_view.clone(),
+        keys: keys.clone(),
+        presentation: presentation.saved(),
+        capture: capture.saved(),
+    }
+}
+
+// Startup system that applies the saved settings
+#[allow(clippy::too_many_arguments)]
+pub fn restore_settings(
+    store: Res<SettingsStore>,
+    mut state: ResMut<SimulationState>,
+    mut worker: ResMut<worker::SimulationWorker>,
+    mut color_mode: ResMut<ColorMode>,
+    mut units: ResMut<UnitSystem>,
+    mut camera_mode: ResMut<CameraMode>,
+    mut movement: ResMut<MovementSettings>,
+    mut orbit: ResMut<Orbit>,
+    mut decimation: ResMut<RenderDecimation>,
+    mut lod: ResMut<LevelOfDetail>,
+    mut graphics: ResMut<Graphics>,
+    mut box_view: ResMut<BoxView>,
+    mut keys: ResMut<KeyBindings>,
+    mut presentation: ResMut<Presentation>,
+    mut captures: ResMut<Capture>,
+) {
+    let settings = match &store.loaded {
+        Some(settings) => settings.clone(),
+        None => return,
+    };
+
+    *color_mode = settings.display.color_mode;
+    *units = settings.display.units;
+    *camera_mode = settings.camera.mode;
+    movement.sensitivity = settings.camera.sensitivity;
+    movement.speed = settings.camera.speed;
+    orbit.distance = settings.camera.orbit_distance;
+    decimation.interval = settings.rendering.update_interval.max(1);
+    decimation.fraction = settings.rendering.drawn_fraction.clamp(0.01, 1.0);
+    lod.enabled = settings.rendering.level_of_detail;
+    lod.near = settings.rendering.fine_within;
+    lod.far = settings.rendering.coarse_beyond.max(lod.near);
+    *graphics = settings.graphics.clone();
+    graphics.msaa_samples = Graphics::valid_msaa_samples(graphics.msaa_samples);
+    graphics.sphere_detail = graphics.sphere_detail.min(Graphics::MAX_SPHERE_DETAIL);
+    graphics.light_intensity = graphics
+        .light_intensity
+        .clamp(0.0, Graphics::MAX_LIGHT_INTENSITY);
+    graphics.particle_radius = graphics
+        .particle_radius
+        .clamp(0.01, Graphics::MAX_PARTICLE_RADIUS);
+    *box_view = settings.box_view.clone();
+    box_view.opacity = box_view.opacity.clamp(0.0, BoxView::MAX_OPACITY);
+    // bindings edited into clashes by hand fall back to the defaults
+    if settings.keys.is_valid() {
+        *keys = settings.keys.clone();
+    }
+    presentation.auto_orbit = settings.presentation.auto_orbit;
+    presentation.orbit_speed = settings.presentation.orbit_speed.clamp(
+        -Presentation::MAX_ORBIT_SPEED,
+        Presentation::MAX_ORBIT_SPEED,
+    );
+    *captures = settings.capture.saved();
+
+    if store.restore_simulation {
+        let sliders = settings.simulation;
+        worker.edit(&mut state, move |state| {
+            if let Some(temp) = sliders.target_temp {
+                state.target_temp = temp.max(0.0);
+            }
+            if let Some(rate) = sliders.inject_rate {
+                state.inject_rate = rate.max(0.0);
+            }
+            // the setters ignore invalid values
+            if let Some(dt) = sliders.dt {
+                state.set_dt(dt);
+            }
+            if let Some(period) = sliders.pressure_sampling_period {
+                state.set_pressure_sampling_period(period);
+            }
+            if let Some(steps) = sliders.steps_per_frame {
+                state.set_steps_per_frame(steps);
+            }
+            state.frame_budget.enabled = sliders.frame_budget_ms.is_some();
+            if let Some(ms) = sliders.frame_budget_ms {
+                state.frame_budget.ms_per_frame = ms;
+            }
+            if let Some(remove_drift) = sliders.remove_drift {
+                state.remove_drift = remove_drift;
+            }
+        });
+    }
+}
+
+// System that writes the settings once they stop changing, and on exit
+#[allow(clippy::too_many_arguments)]
+pub fn save_settings(
+    mut store: ResMut<SettingsStore>,
+    time: Res<Time>,
+    mut exit: EventReader<AppExit>,
+    state: Res<SimulationState>,
+    color_mode: Res<ColorMode>,
+    units: Res<UnitSystem>,
+    camera_mode: Res<CameraMode>,
+    movement: Res<MovementSettings>,
+    orbit: Res<Orbit>,
+    decimation: Res<RenderDecimation>,
+    lod: Res<LevelOfDetail>,
+    graphics: Res<Graphics>,
+    box_view: Res<BoxView>,
+    keys: Res<KeyBindings>,
+    presentation: Res<Presentation>,
+    captures: Res<Capture>,
+) {
+    let exiting = exit.iter().next().is_some();
+    let current = capture(
+        &state,
+        *color_mode,
+        *units,
+        *camera_mode,
+        &movement,
+        &orbit,
+        &decimation,
+        &lod,
+        &graphics,
+        &box_view,
+        &keys,
+        &presentation,
+        &captures,
+    );
+    if store.saved.as_ref() == Some(&current) {
+        store.last_change = None;
+        return;
+    }
+
+    let now = time.seconds_since_startup();
+    let changed_at = *store.last_change.get_or_insert(now);
+    if exiting || now - changed_at >= SettingsStore::SAVE_DELAY {
+        if let Err(e) = current.save() {
+            eprintln!("Could not save the settings: {}", e);
+        }
+        // not retried until the settings change again
+        store.saved = Some(current);
+        store.last_change = None;
+    }
+}
@@ -0,0 +1,15 @@
+#version 450
+
+layout(location = 0) in vec3 v_Normal;
+layout(location = 1) in vec4 v_Color;
+
+layout(location = 0) out vec4 o_Target;
+
+// fixed light from above, the ambient part keeps the far side of the spheres visible
+const vec3 LIGHT_DIRECTION = vec3(0.4, 1.0, 0.6);
+const float AMBIENT = 0.3;
+
+void main() {
+    float diffuse = max(dot(normalize(v_Normal), normalize(LIGHT_DIRECTION)), 0.0);
+    o_Target = vec4(v_Color.rgb * (AMBIENT + (1.0 - AMBIENT) * diffuse), v_Color.a);
+}
@@ -0,0 +1,30 @@
+#version 450
+
+layout(location = 0) in vec3 Vertex_Position;
+layout(location = 1) in vec3 Vertex_Normal;
+
+layout(location = 0) out vec3 v_Normal;
+layout(location = 1) out vec4 v_Color;
+
+layout(set = 0, binding = 0) uniform CameraViewProj {
+    mat4 ViewProj;
+};
+
+// three entries per particle: position and radius, orientation, then the color
+layout(set = 1, binding = 0) buffer ParticleInstances_instances {
+    vec4[] Instances;
+};
+
+// rotate v by the unit quaternion q
+vec3 turn(vec4 q, vec3 v) {
+    return v + 2.0 * cross(q.xyz, cross(q.xyz, v) + q.w * v);
+}
+
+void main() {
+    vec4 placement = Instances[3 * gl_InstanceIndex];
+    vec4 orientation = Instances[3 * gl_InstanceIndex + 1];
+    v_Color = Instances[3 * gl_InstanceIndex + 2];
+    v_Normal = turn(orientation, Vertex_Normal);
+    vec3 offset = turn(orientation, Vertex_Position);
+    gl_Position = ViewProj * vec4(placement.xyz + placement.w * offset, 1.0);
+}
@@ -0,0 +1,129 @@
+// bevy systems that follow the simulation
+use super::*;
+use bevy::prelude::*;
+
+// Timings of the phases the app runs itself, the simulation times its own phases
+// The gui is timed from before its first window to after its last one
+#[derive(Default)]
+pub struct AppTimings {
+    pub timings: timing::Timings,
+    ui_clock: Option<timing::Stopwatch>,
+}
+
+// System that replaces the state with the latest frame from the simulation thread
+pub fn receive_snapshot(
+    mut state: ResMut<SimulationState>,
+    mut worker: ResMut<worker::SimulationWorker>,
+    mut app_timings: ResMut<AppTimings>,
+) {
+    let stopwatch = timing::Stopwatch::start();
+    if let Some(snapshot) = worker.take_snapshot() {
+        *state = snapshot;
+    }
+    app_timings
+        .timings
+        .record(timing::Phase::RenderSync, stopwatch.elapsed_ms());
+}
+
+// System that runs before the gui windows
+pub fn start_ui_timing(mut app_timings: ResMut<AppTimings>) {
+    app_timings.ui_clock = Some(timing::Stopwatch::start());
+}
+
+// System that runs after the gui windows and closes the frame of the app phases
+pub fn finish_ui_timing(mut app_timings: ResMut<AppTimings>) {
+    if let Some(clock) = app_timings.ui_clock.take() {
+        app_timings
+            .timings
+            .record(timing::Phase::Ui, clock.elapsed_ms());
+    }
+    app_timings
+        .timings
+        .finish_frame(&[timing::Phase::RenderSync, timing::Phase::Ui]);
+}
+
+// System that rebuilds the local density and temperature fields
+pub fn update_fields(state: Res<SimulationState>, mut field_view: ResMut<field::FieldView>) {
+    field_view.update(&state.particles, state.bound);
+}
+
+// System that rebuilds the coarse grained velocity field
+pub fn update_flow(state: Res<SimulationState>, mut flow_view: ResMut<field::FlowView>) {
+    flow_view.update(&state.particles, state.bound);
+}
+
+// System that bins the particles into the slabs of the profile
+pub fn update_profile(state: Res<SimulationState>, mut profile_view: ResMut<profile::ProfileView>) {
+    profile_view.update(&state);
+}
+
+// System that averages the profile and the stress of the interfaces and fits them
+pub fn update_interface(
+    state: Res<SimulationState>,
+    mut interface_view: ResMut<interface::InterfaceView>,
+) {
+    interface_view.update(&state);
+}
+
+// System that rebuilds the bond network and checks whether it percolates
+pub fn update_network(state: Res<SimulationState>, mut network_view: ResMut<network::NetworkView>) {
+    if !network_view.enabled {
+        return;
+    }
+
+    let network = state.energy_network(network_view.threshold);
+    let positions: Vec<Vec3> = state.particles.iter().map(|p| p.get_pos()).collect();
+    network_view.percolation =
+        network.percolating_axes(&positions, state.bound, state.get_grid_range());
+    network_view.network = network;
+}
+
+// System that finds clusters of neighboring particles
+pub fn update_clusters(
+    state: Res<SimulationState>,
+    mut cluster_view: ResMut<cluster::ClusterView>,
+) {
+    if cluster_view.is_due() {
+        cluster_view.stats = cluster::ClusterStats::from_network(&state.neighbor_network());
+    }
+}
+
+// System that computes the bond orientational order of the particles
+// Also runs while particles are colored by it
+pub fn update_order(
+    state: Res<SimulationState>,
+    color_mode: Res<render_systems::ColorMode>,
+    mut order_view: ResMut<order::OrderView>,
+) {
+    let needed = *color_mode == render_systems::ColorMode::Order;
+    order_view.update(&state, needed);
+}
+
+// System that compares the neighbor grid against a brute force sum
+pub fn check_neighbors(
+    state: Res<SimulationState>,
+    mut neighbor_check: ResMut<neighbor_check::NeighborCheck>,
+) {
+    neighbor_check.update(&state);
+}
+
+// System that extends the trails of the traced particles
+pub fn update_trails(state: Res<SimulationState>, mut trail_view: ResMut<trail::TrailView>) {
+    trail_view.update(&state);
+}
+
+// System that pauses the simulation once it diverges
+pub fn watch_divergence(
+    mut state: ResMut<SimulationState>,
+    mut worker: ResMut<worker::SimulationWorker>,
+    mut watchdog: ResMut<watchdog::Watchdog>,
+) {
+    if watchdog.update(&state) {
+        worker.edit(&mut state, |state| state.paused = true);
+    }
+}
+
+// System that keeps snapshots of the state to rewind to
+pub fn record_rewind(state: Res<SimulationState>, mut rewind: ResMut<rewind::RewindBuffer>) {
+    rewind.update(&state);
+}
@@ -0,0 +1,2793 @@
+// Contains bevy systems that draws the gui
+
+use super::colormap;
+use super::export;
+use super::external_field::{Gravity, HarmonicTrap, OscillatingField};
+use super::render_systems::{
+    ColorMode, Graphics, LevelOfDetail, ParticleShape, RenderDecimation, SpeciesVisuals,
+};
+use super::*;
+use crate::bevy_flycam::{CameraMode, FlyCam};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use egui::plot::{Curve, Plot, Value};
+
+// Parameters adjusted by the sliders
+// Edited as a copy and sent to the simulation thread when changed
+#[derive(Clone, PartialEq)]
+struct SliderParams {
+    pressure_pinned: bool,
+    pinned_at: f32,
+    bound_rate: f32,
+    face_rates: FaceRates,
+    wall_model: WallModel,
+    shear_rate: f32, // of the Lees-Edwards images, 0 for none
+    adhesion: Option<WallAdhesion>,
+    dipolar: dipole::Dipolar,
+    potential: physics::PotentialModel,
+    neighbor_rule: physics::NeighborRule,
+    integrator: integrator::Integrator,
+    target_temp: f32,
+    inject_rate: f32,
+    dt: f32,
+    pressure_sampling_period: f32,
+    frame_budget: bool,
+    ms_per_frame: f32,
+    steps_per_frame: usize,
+    remove_drift: bool,
+}
+
+impl SliderParams {
+    fn read(state: &SimulationState) -> Self {
+        Self {
+            pressure_pinned: state.pressure_pinned.is_pinned,
+            pinned_at: state.pressure_pinned.at_value,
+            bound_rate: state.bound_rate,
+            face_rates: state.face_rates,
+            wall_model: state.bound.wall_model,
+            shear_rate: state.bound.lees_edwards.map_or(0.0, |shear| shear.rate),
+            adhesion: state.bound.adhesion,
+            dipolar: state.dipolar,
+            potential: state.get_potential(),
+            neighbor_rule: state.get_neighbor_rule(),
+            integrator: state.integrator,
+            target_temp: state.target_temp,
+            inject_rate: state.inject_rate,
+            dt: state.dt,
+            pressure_sampling_period: state.pressure_sampling_period,
+            frame_budget: state.frame_budget.enabled,
+            ms_per_frame: state.frame_budget.ms_per_frame,
+            steps_per_frame: state.steps_per_frame,
+            remove_drift: state.remove_drift,
+        }
+    }
+
+    fn write(&self, state: &mut SimulationState) {
+        state.pressure_pinned.is_pinned = self.pressure_pinned;
+        state.pressure_pinned.at_value = self.pinned_at;
+        state.bound_rate = self.bound_rate;
+        state.face_rates = self.face_rates;
+        state.bound.wall_model = self.wall_model;
+        // the images keep sliding on from where they are
+        state.bound.lees_edwards = if self.shear_rate != 0.0 {
+            Some(LeesEdwards {
+                rate: self.shear_rate,
+                ..state.bound.lees_edwards.unwrap_or_default()
+            })
+        } else {
+            None
+        };
+        state.bound.adhesion = self.adhesion;
+        state.dipolar = self.dipolar;
+        state.set_potential(self.potential);
+        state.set_neighbor_rule(self.neighbor_rule);
+        state.integrator = self.integrator;
+        state.target_temp = self.target_temp;
+        state.inject_rate = self.inject_rate;
+        // dt needs to go through its setter to keep pressure consistent
+        state.set_dt(self.dt);
+        state.set_pressure_sampling_period(self.pressure_sampling_period);
+        state.frame_budget.enabled = self.frame_budget;
+        state.frame_budget.ms_per_frame = self.ms_per_frame;
+        state.set_steps_per_frame(self.steps_per_frame);
+        state.remove_drift = self.remove_drift;
+    }
+}
+
+pub fn param_sliders(
+    egui_context: ResMut<EguiContext>,
+    mut state: ResMut<SimulationState>,
+    mut worker: ResMut<worker::SimulationWorker>,
+) {
+    let current = SliderParams::read(&state);
+    let mut params = current.clone();
+
+    egui::Window::new("Sliders").show(egui_context.ctx(), |ui| {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut params.pressure_pinned, "Pin pressure at: ");
+            ui.add(egui::widgets::DragValue::new(&mut params.pinned_at).speed(0.02));
+        });
+        ui.add(egui::Slider::new(&mut params.bound_rate, -0.2..=0.2).text("Boundary"));
+        ui.collapsing("Wall Rates", |ui| {
+            let lo = params.face_rates.lo.as_mut().iter_mut();
+            let hi = params.face_rates.hi.as_mut().iter_mut();
+            for ((axis, lo), hi) in ["x", "y", "z"].iter().zip(lo).zip(hi) {
+                ui.add(egui::Slider::new(lo, -0.2..=0.2).text(format!("-{}", axis)));
+                ui.add(egui::Slider::new(hi, -0.2..=0.2).text(format!("+{}", axis)));
+            }
+        });
+        egui::ComboBox::from_label("Walls")
+            .selected_text(params.wall_model.name())
+            .show_ui(ui, |ui| {
+                for &model in WallModel::ALL.iter() {
+                    let selected = model.name() == params.wall_model.name();
+                    if ui.selectable_label(selected, model.name()).clicked() && !selected {
+                        params.wall_model = model;
+                    }
+                }
+            });
+        if let WallModel::Thermal(temp) = &mut params.wall_model {
+            ui.add(
+                egui::Slider::new(temp, 0.0..=3.0)
+                    .text("Wall Temperature")
+                    .clamp_to_range(true),
+            );
+        }
+        if params.wall_model.is_periodic() {
+            ui.add(egui::Slider::new(&mut params.shear_rate, -2.0..=2.0).text("Shear Rate"));
+        }
+        ui.collapsing("Wall Adhesion", |ui| {
+            let before = params
+                .adhesion
+                .unwrap_or_else(|| WallAdhesion::new(0.0, &[]));
+            let mut adhesion = before;
+            ui.add(egui::Slider::new(&mut adhesion.strength, 0.0..=2.0).text("Strength"));
+            ui.horizontal_wrapped(|ui| {
+                for face in Face::ALL.iter() {
+                    ui.checkbox(&mut adhesion.faces[face.index()], face.name());
+                }
+            });
+            if adhesion != before {
+                params.adhesion = Some(adhesion);
+            }
+        });
+        egui::ComboBox::from_label("Potential")
+            .selected_text(params.potential.name())
+            .show_ui(ui, |ui| {
+                for &model in physics::PotentialModel::ALL.iter() {
+                    let selected = model.name() == params.potential.name();
+                    if ui.selectable_label(selected, model.name()).clicked() && !selected {
+                        params.potential = model;
+                    }
+                }
+            });
+        match &mut params.potential {
+            physics::PotentialModel::LennardJones => {}
+            physics::PotentialModel::Morse { depth, width, r0 } => {
+                ui.add(egui::Slider::new(depth, 0.05..=2.0).text("Well Depth"));
+                ui.add(egui::Slider::new(width, 5.0..=80.0).text("Well Width"));
+                ui.add(egui::Slider::new(r0, 0.05..=0.5).text("Well Position"));
+            }
+            physics::PotentialModel::SoftSphere { epsilon, n } => {
+                ui.add(egui::Slider::new(epsilon, 0.05..=2.0).text("Strength"));
+                ui.add(egui::Slider::new(n, 1..=24).text("Exponent"));
+            }
+        }
+        ui.collapsing("Dipoles", |ui| {
+            let dipolar = &mut params.dipolar;
+            ui.add(egui::Slider::new(&mut dipolar.strength, 0.0..=2.0).text("Strength"));
+            for (axis, field) in ["x", "y", "z"].iter().zip(dipolar.field.iter_mut()) {
+                ui.add(egui::Slider::new(field, -2.0..=2.0).text(format!("Field {}", axis)));
+            }
+        });
+        ui.collapsing("Neighbors", |ui| {
+            let rule = &mut params.neighbor_rule;
+            egui::ComboBox::from_label("Metric")
+                .selected_text(rule.metric.name())
+                .show_ui(ui, |ui| {
+                    for &metric in physics::NeighborMetric::ALL.iter() {
+                        ui.selectable_value(&mut rule.metric, metric, metric.name());
+                    }
+                });
+            ui.add(egui::Slider::new(&mut rule.distance, 0.05..=1.0).text("Distance"));
+            if rule.metric == physics::NeighborMetric::Nearest {
+                ui.add(egui::Slider::new(&mut rule.nearest, 1..=26).text("Nearest"));
+            }
+        });
+        egui::ComboBox::from_label("Integrator")
+            .selected_text(params.integrator.name())
+            .show_ui(ui, |ui| {
+                for &integrator in integrator::Integrator::ALL.iter() {
+                    let selected = integrator.name() == params.integrator.name();
+                    if ui.selectable_label(selected, integrator.name()).clicked() && !selected {
+                        params.integrator = integrator;
+                    }
+                }
+            });
+        match &mut params.integrator {
+            integrator::Integrator::Verlet => {}
+            integrator::Integrator::Langevin { gamma } => {
+                ui.add(
+                    egui::Slider::new(gamma, 0.0..=10.0)
+                        .text("Friction")
+                        .clamp_to_range(true),
+                );
+            }
+            integrator::Integrator::Brownian { gamma } => {
+                ui.add(
+                    egui::Slider::new(gamma, 1.0..=100.0)
+                        .text("Friction")
+                        .clamp_to_range(true),
+                );
+            }
+        }
+        ui.add(
+            egui::Slider::new(&mut params.target_temp, 0.0..=3.0)
+                .text("Target Temperature")
+                .clamp_to_range(true),
+        );
+        ui.add(
+            egui::Slider::new(&mut params.inject_rate, 0.0..=0.5)
+                .text("Injection Rate")
+                .clamp_to_range(true),
+        );
+        ui.add(
+            egui::Slider::new(&mut params.dt, 0.0001..=0.005)
+                .text("dt")
+                .clamp_to_range(true),
+        );
+        ui.add(
+            egui::Slider::new(&mut params.pressure_sampling_period, 0.5..=20.0)
+                .text("Pressure Window")
+                .clamp_to_range(true),
+        );
+
+        ui.checkbox(&mut params.frame_budget, "Time budget per frame");
+        if params.frame_budget {
+            ui.add(
+                egui::Slider::new(&mut params.ms_per_frame, 1.0..=50.0)
+                    .text("ms per Frame")
+                    .clamp_to_range(true),
+            );
+            ui.label(format!("Steps per Frame: {}", state.last_frame_steps));
+        } else {
+            ui.add(
+                egui::Slider::new(&mut params.steps_per_frame, 1..=100)
+                    .text("Steps per Frame")
+                    .clamp_to_range(true),
+            );
+        }
+
+        ui.checkbox(&mut params.remove_drift, "Remove momentum drift");
+    });
+
+    if params != current {
+        worker.edit(&mut state, move |state| params.write(state));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn display_settings(
+    egui_context: ResMut<EguiContext>,
+    mut color_mode: ResMut<ColorMode>,
+    mut camera_mode: ResMut<CameraMode>,
+    mut focus: ResMut<render_systems::CameraFocus>,
+    mut unit_system: ResMut<units::UnitSystem>,
+    state: Res<SimulationState>,
+    bindings: Res<keybindings::KeyBindings>,
+    mut presentation: ResMut<presentation::Presentation>,
+    mut box_view: ResMut<render_systems::BoxView>,
+) {
+    egui::Window::new("Display").show(egui_context.ctx(), |ui| {
+        egui::ComboBox::from_label("Color by")
+            .selected_text(format!("{:?}", *color_mode))
+            .show_ui(ui, |ui| {
+                for &mode in ColorMode::ALL.iter() {
+                    ui.selectable_value(&mut *color_mode, mode, format!("{:?}", mode));
+                }
+            });
+        egui::ComboBox::from_label("Camera (O)")
+            .selected_text(format!("{:?}", *camera_mode))
+            .show_ui(ui, |ui| {
+                for &mode in CameraMode::ALL.iter() {
+                    ui.selectable_value(&mut *camera_mode, mode, format!("{:?}", mode));
+                }
+            });
+        egui::ComboBox::from_label("Units")
+            .selected_text(unit_system.name())
+            .show_ui(ui, |ui| {
+                for &system in units::UnitSystem::ALL.iter() {
+                    ui.selectable_value(&mut *unit_system, system, system.name());
+                }
+            });
+        if *unit_system != units::UnitSystem::Simulation {
+            let scale = units::ReducedUnits::of(&state);
+            ui.label(format!(
+                "ε = {:.4}, σ = {:.4}, m = {:.4}, τ = {:.4} in simulation units",
+                scale.epsilon,
+                scale.sigma,
+                scale.mass,
+                scale.tau()
+            ));
+        }
+        let reset_label = format!(
+            "Reset view ({})",
+            bindings.label(keybindings::Action::CameraReset)
+        );
+        if ui.button(reset_label).clicked() {
+            focus.frame_box = true;
+        }
+        if *camera_mode == CameraMode::Orbit {
+            match focus.selected {
+                Some(pid) => {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Orbiting particle {}", pid));
+                        if ui.button("Orbit box").clicked() {
+                            focus.selected = None;
+                        }
+                    });
+                }
+                None => {
+                    ui.label("Orbiting the box center, pick a particle in the table to orbit it");
+                }
+            }
+        }
+        ui.collapsing("Box", |ui| {
+            ui.checkbox(&mut box_view.edges, "Edges");
+            ui.horizontal(|ui| {
+                ui.label("Faces");
+                for (shown, name) in box_view.faces.iter_mut().zip(Face::NAMES.iter()) {
+                    ui.checkbox(shown, *name);
+                }
+            });
+            ui.add(
+                egui::Slider::new(
+                    &mut box_view.opacity,
+                    0.0..=render_systems::BoxView::MAX_OPACITY,
+                )
+                .text("Face opacity"),
+            );
+            ui.label("Thermal walls are drawn orange, adhesive faces green");
+            ui.checkbox(
+                &mut box_view.floor_grid,
+                format!(
+                    "Floor grid, one line per grid square of {:.3}",
+                    state.get_grid_unit_size()
+                ),
+            );
+            ui.checkbox(&mut box_view.axis_gizmo, "Axis gizmo");
+        });
+        ui.collapsing("Presentation", |ui| {
+            ui.checkbox(&mut presentation.auto_orbit, "Orbit the box");
+            ui.add(
+                egui::Slider::new(
+                    &mut presentation.orbit_speed,
+                    -presentation::Presentation::MAX_ORBIT_SPEED
+                        ..=presentation::Presentation::MAX_ORBIT_SPEED,
+                )
+                .text("Orbit speed (rad/s)"),
+            );
+            let key = bindings.label(keybindings::Action::Presentation);
+            if ui.button(format!("Present ({})", key)).clicked() {
+                presentation.active = true;
+            }
+            ui.label(format!("Hides the gui, {} brings it back", key));
+        });
+    });
+}
+
+// Settings that keep large systems fast to render
+pub fn render_settings(
+    egui_context: ResMut<EguiContext>,
+    mut decimation: ResMut<RenderDecimation>,
+    mut lod: ResMut<LevelOfDetail>,
+    state: Res<SimulationState>,
+) {
+    egui::Window::new("Rendering").show(egui_context.ctx(), |ui| {
+        ui.collapsing("Decimation", |ui| {
+            ui.add(
+                egui::Slider::new(&mut decimation.interval, 1..=30)
+                    .text("Update particles every N frames"),
+            );
+            ui.add(egui::Slider::new(&mut decimation.fraction, 0.01..=1.0).text("Drawn fraction"));
+            ui.label(format!(
+                "Drawing about {} of {} particles",
+                (decimation.fraction * state.particles.len() as f32).round(),
+                state.particles.len()
+            ));
+        });
+        ui.collapsing("Level of Detail", |ui| {
+            ui.checkbox(&mut lod.enabled, "Coarser spheres far from the camera");
+            ui.add(egui::Slider::new(&mut lod.near, 0.1..=20.0).text("Fine within"));
+            ui.add(egui::Slider::new(&mut lod.far, 0.1..=50.0).text("Coarse beyond"));
+            lod.far = lod.far.max(lod.near);
+        });
+    });
+}
+
+pub fn graphics_settings(
+    egui_context: ResMut<EguiContext>,
+    mut graphics: ResMut<Graphics>,
+    msaa: Res<Msaa>,
+    state: Res<SimulationState>,
+) {
+    egui::Window::new("Graphics").show(egui_context.ctx(), |ui| {
+        egui::ComboBox::from_label("Antialiasing")
+            .selected_text(format!("{}x MSAA", graphics.msaa_samples))
+            .show_ui(ui, |ui| {
+                for &samples in Graphics::MSAA_SAMPLES.iter() {
+                    ui.selectable_value(
+                        &mut graphics.msaa_samples,
+                        samples,
+                        format!("{}x MSAA", samples),
+                    );
+                }
+            });
+        if graphics.msaa_samples != msaa.samples {
+            ui.label(format!(
+                "Running with {}x, the new setting applies after a restart",
+                msaa.samples
+            ));
+        }
+        ui.checkbox(&mut graphics.vsync, "Vsync");
+        egui::ComboBox::from_label("Particle shape")
+            .selected_text(format!("{:?}", graphics.particle_shape))
+            .show_ui(ui, |ui| {
+                for &shape in ParticleShape::ALL.iter() {
+                    ui.selectable_value(
+                        &mut graphics.particle_shape,
+                        shape,
+                        format!("{:?}", shape),
+                    );
+                }
+            });
+        ui.add(
+            egui::Slider::new(&mut graphics.sphere_detail, 0..=Graphics::MAX_SPHERE_DETAIL)
+                .text("Sphere subdivisions"),
+        );
+        ui.add(
+            egui::Slider::new(
+                &mut graphics.light_intensity,
+                0.1..=Graphics::MAX_LIGHT_INTENSITY,
+            )
+            .text("Light intensity"),
+        );
+
+        let half_sigma = 0.5 * state.get_potential().length_scale();
+        let mut from_sigma = graphics.radius_from_sigma;
+        ui.checkbox(&mut from_sigma, "Particle radius from σ");
+        if from_sigma != graphics.radius_from_sigma {
+            // the override starts from the linked radius so the particles don't jump
+            graphics.particle_radius = half_sigma.min(Graphics::MAX_PARTICLE_RADIUS);
+            graphics.radius_from_sigma = from_sigma;
+        }
+        if graphics.radius_from_sigma {
+            ui.label(format!("Particle radius σ/2 = {:.4}", half_sigma));
+        } else {
+            ui.add(
+                egui::Slider::new(
+                    &mut graphics.particle_radius,
+                    0.01..=Graphics::MAX_PARTICLE_RADIUS,
+                )
+                .text("Particle radius"),
+            );
+        }
+    });
+}
+
+// Inputs of the history plots that are not part of the simulation state
+pub struct HistoryPlotSettings {
+    window: usize, // number of most recent entries to plot
+    export_status: String,
+}
+
+impl Default for HistoryPlotSettings {
+    fn default() -> Self {
+        Self {
+            window: 500,
+            export_status: String::new(),
+        }
+    }
+}
+
+// Plot the last window entries of a history channel against simulated time
+fn history_curve<'a>(
+    history: &History,
+    window: usize,
+    values: impl Iterator<Item = &'a f32>,
+    name: &str,
+) -> Curve {
+    let skip = history.len().saturating_sub(window);
+    Curve::from_values_iter(
+        history
+            .time
+            .iter()
+            .zip(values)
+            .skip(skip)
+            .map(|(&t, &v)| Value::new(t, v)),
+    )
+    .name(name)
+}
+
+pub fn simulation_info(
+    egui_context: ResMut<EguiContext>,
+    state: Res<SimulationState>,
+    unit_system: Res<units::UnitSystem>,
+    mut settings: Local<HistoryPlotSettings>,
+) {
+    let total_energy = state.energy.total();
+    let scale = units::ReducedUnits::of(&state);
+    let show = |quantity, value| unit_system.format(&scale, quantity, value);
+
+    let pressure_val = state.pressure.get_pressure();
+    let volume = state.bound.get_volume();
+
+    let history = &state.history;
+    let window = settings.window;
+    let kinetic: Vec<f32> = history.energy.iter().map(|e| e.kinetic).collect();
+    let potential: Vec<f32> = history.energy.iter().map(|e| e.potential).collect();
+    let total: Vec<f32> = history.energy.iter().map(|e| e.total()).collect();
+
+    egui::Window::new("Pressure/Volume/Temperature").show(egui_context.ctx(), |ui| {
+        ui.label(format!(
+            "PV/nkT: {:.5}",
+            pressure_val * volume / physics::kt(state.energy.kinetic)
+        ));
+        ui.label(format!(
+            "P: {}",
+            show(units::Quantity::Pressure, pressure_val)
+        ));
+        ui.collapsing("Wall Pressures", |ui| {
+            egui::Grid::new("wall pressures").show(ui, |ui| {
+                for axis in 0..3 {
+                    for &hi in [false, true].iter() {
+                        let face = Face::of(axis, hi);
+                        ui.label(format!(
+                            "{}: {}",
+                            face.name(),
+                            show(units::Quantity::Pressure, state.face_pressure(face))
+                        ));
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+        ui.label(format!("V: {}", show(units::Quantity::Volume, volume)));
+        ui.label(format!(
+            "T: {}",
+            show(units::Quantity::Temperature, state.temperature())
+        ));
+        ui.add(
+            Plot::new("Pressure")
+                .curve(history_curve(history, window, history.pressure.iter(), "P"))
+                .height(120.0),
+        );
+        ui.add(
+            Plot::new("Temperature")
+                .curve(history_curve(
+                    history,
+                    window,
+                    history.temperature.iter(),
+                    "T",
+                ))
+                .height(120.0),
+        );
+        export_row(ui, &state, *unit_system, &mut settings);
+    });
+
+    egui::Window::new("Energy").show(egui_context.ctx(), |ui| {
+        ui.label(format!(
+            "KE: {}",
+            show(units::Quantity::Energy, state.energy.kinetic)
+        ));
+        ui.label(format!(
+            "PE: {}",
+            show(units::Quantity::Energy, state.energy.potential)
+        ));
+        ui.label(format!(
+            "Rotational KE: {}",
+            show(units::Quantity::Energy, state.energy.rotational)
+        ));
+        ui.label(format!(
+            "Rotational Temperature: {}",
+            show(units::Quantity::Temperature, state.rotational_temperature())
+        ));
+        ui.label(format!(
+            "Total Energy: {}",
+            show(units::Quantity::Energy, total_energy)
+        ));
+        ui.add(
+            Plot::new("Energy")
+                .curve(history_curve(history, window, kinetic.iter(), "KE"))
+                .curve(history_curve(history, window, potential.iter(), "PE"))
+                .curve(history_curve(history, window, total.iter(), "Total")),
+        );
+        export_row(ui, &state, *unit_system, &mut settings);
+    });
+
+    egui::Window::new("History").show(egui_context.ctx(), |ui| {
+        let capacity = history.capacity().max(2);
+        ui.add(egui::Slider::new(&mut settings.window, 2..=capacity).text("Window Length"));
+        export_row(ui, &state, *unit_system, &mut settings);
+    });
+}
+
+// Buttons of the info windows saving the history and copying the latest values
+// internal helper function
+fn export_row(
+    ui: &mut egui::Ui,
+    state: &SimulationState,
+    unit_system: units::UnitSystem,
+    settings: &mut HistoryPlotSettings,
+) {
+    ui.horizontal(|ui| {
+        if ui.button("Export History").clicked() {
+            settings.export_status = export::save_history(&state.history, settings.window);
+        }
+        if ui.button("Copy Values").clicked() {
+            export::copy_to_clipboard(ui, export::observables_text(state, unit_system));
+            settings.export_status = "Copied to the clipboard".to_string();
+        }
+        ui.label(&settings.export_status);
+    });
+}
+
+// Columns the particle table can be sorted by
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ParticleColumn {
+    Index,
+    Speed,
+    Kinetic,
+    Neighbors,
+}
+
+impl ParticleColumn {
+    pub const ALL: [ParticleColumn; 4] = [
+        ParticleColumn::Index,
+        ParticleColumn::Speed,
+        ParticleColumn::Kinetic,
+        ParticleColumn::Neighbors,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            ParticleColumn::Index => "#",
+            ParticleColumn::Speed => "|v|",
+            ParticleColumn::Kinetic => "KE",
+            ParticleColumn::Neighbors => "Neighbors",
+        }
+    }
+
+    fn format(self, value: f32) -> String {
+        match self {
+            ParticleColumn::Index | ParticleColumn::Neighbors => format!("{:.0}", value),
+            _ => format!("{:.3}", value),
+        }
+    }
+
+    fn key(self, pid: usize, particle: &particle::Particle) -> f32 {
+        let vel = particle.get_vel();
+        match self {
+            ParticleColumn::Index => pid as f32,
+            ParticleColumn::Speed => vel.length(),
+            ParticleColumn::Kinetic => 0.5 * particle.get_mass() * vel.length_squared(),
+            ParticleColumn::Neighbors => particle.neighbors as f32,
+        }
+    }
+}
+
+// Inputs of the particle table that are not part of the simulation state
+pub struct ParticleTable {
+    page: usize,
+    sort_by: ParticleColumn,
+    descending: bool,
+    search: usize,           // particle to jump to
+    selected: Option<usize>, // highlighted row
+}
+
+impl Default for ParticleTable {
+    fn default() -> Self {
+        Self {
+            page: 0,
+            sort_by: ParticleColumn::Index,
+            descending: false,
+            search: 0,
+            selected: None,
+        }
+    }
+}
+
+impl ParticleTable {
+    const PAGE_SIZE: usize = 20;
+}
+
+pub fn particle_table(
+    egui_context: ResMut<EguiContext>,
+    state: Res<SimulationState>,
+    mut focus: ResMut<render_systems::CameraFocus>,
+    mut table: Local<ParticleTable>,
+) {
+    egui::Window::new("Particles").show(egui_context.ctx(), |ui| {
+        let n = state.particles.len();
+        if n == 0 {
+            ui.label("No particles");
+            return;
+        }
+
+        // rows in display order
+        let mut order: Vec<(usize, f32)> = state
+            .particles
+            .iter()
+            .enumerate()
+            .map(|(pid, particle)| (pid, table.sort_by.key(pid, particle)))
+            .collect();
+        order.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+        if table.descending {
+            order.reverse();
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Particle:");
+            ui.add(egui::DragValue::new(&mut table.search).clamp_range(0..=n - 1));
+            if ui.button("Go").clicked() {
+                let pid = table.search;
+                let row = order.iter().position(|&(i, _)| i == pid).unwrap_or(0);
+                table.page = row / ParticleTable::PAGE_SIZE;
+                table.selected = Some(pid);
+                focus.particle = Some(pid);
+                focus.selected = Some(pid);
+            }
+        });
+
+        let pages = (n + ParticleTable::PAGE_SIZE - 1) / ParticleTable::PAGE_SIZE;
+        table.page = table.page.min(pages - 1);
+        ui.horizontal(|ui| {
+            if ui.button("<").clicked() && table.page > 0 {
+                table.page -= 1;
+            }
+            ui.label(format!("Page {} of {}", table.page + 1, pages));
+            if ui.button(">").clicked() && table.page + 1 < pages {
+                table.page += 1;
+            }
+        });
+
+        egui::Grid::new("particle table")
+            .striped(true)
+            .show(ui, |ui| {
+                // clicking a header sorts by it, clicking it again flips the order
+                for &column in ParticleColumn::ALL.iter() {
+                    let arrow = match (column == table.sort_by, table.descending) {
+                        (false, _) => "",
+                        (true, false) => " ^",
+                        (true, true) => " v",
+                    };
+                    if ui.button(format!("{}{}", column.title(), arrow)).clicked() {
+                        if table.sort_by == column {
+                            table.descending = !table.descending;
+                        } else {
+                            table.sort_by = column;
+                            table.descending = false;
+                        }
+                    }
+                }
+                ui.label("Position");
+                ui.end_row();
+
+                let start = table.page * ParticleTable::PAGE_SIZE;
+                for &(pid, _) in order.iter().skip(start).take(ParticleTable::PAGE_SIZE) {
+                    let particle = &state.particles[pid];
+                    let selected = table.selected == Some(pid);
+                    if ui.selectable_label(selected, pid.to_string()).clicked() {
+                        table.selected = Some(pid);
+                        focus.particle = Some(pid);
+                        focus.selected = Some(pid);
+                    }
+                    for &column in ParticleColumn::ALL.iter().skip(1) {
+                        ui.label(column.format(column.key(pid, particle)));
+                    }
+                    let pos = particle.get_pos();
+                    ui.label(format!("({:.2}, {:.2}, {:.2})", pos.x, pos.y, pos.z));
+                    ui.end_row();
+                }
+            });
+    });
+}
+
+// Inputs of the probe window that are not part of the simulation state
+#[derive(Default)]
+pub struct ProbeEditor {
+    particle: usize,
+    point: Vec3,
+    export_status: String,
+}
+
+pub fn probe_list(
+    egui_context: ResMut<EguiContext>,
+    mut state: ResMut<SimulationState>,
+    mut worker: ResMut<worker::SimulationWorker>,
+    mut editor: Local<ProbeEditor>,
+) {
+    egui::Window::new("Probes").show(egui_context.ctx(), |ui| {
+        let mut removed = None;
+        let mut renamed = Vec::new(); // (index, name, quantity) of edited probes
+
+        for (i, probe) in state.probes.iter().enumerate() {
+            ui.group(|ui| {
+                ui.horizontal(|ui| {
+                    let mut name = probe.name.clone();
+                    let mut quantity = probe.get_quantity();
+                    ui.text_edit_singleline(&mut name);
+                    egui::ComboBox::from_id_source(("probe quantity", i))
+                        .selected_text(format!("{:?}", quantity))
+                        .show_ui(ui, |ui| {
+                            for &option in ProbeQuantity::ALL.iter() {
+                                ui.selectable_value(&mut quantity, option, format!("{:?}", option));
+                            }
+                        });
+                    if name != probe.name || quantity != probe.get_quantity() {
+                        renamed.push((i, name, quantity));
+                    }
+                    if ui.button("Remove").clicked() {
+                        removed = Some(i);
+                    }
+                });
+
+                let target = match probe.target {
+                    ProbeTarget::Particle(pid) => format!("particle {}", pid),
+                    ProbeTarget::Point(pos) => {
+                        format!("point ({:.2}, {:.2}, {:.2})", pos.x, pos.y, pos.z)
+                    }
+                };
+                let latest = probe.get_history().peak().copied().unwrap_or(0.0);
+                ui.label(format!("{}: {:.5}", target, latest));
+
+                let curve = Curve::from_values_iter(
+                    probe
+                        .get_history()
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &v)| Value::new(i as f64, v)),
+                );
+                ui.add(
+                    Plot::new(format!("probe plot {}", i))
+                        .curve(curve)
+                        .height(80.0),
+                );
+            });
+        }
+        for (i, name, quantity) in renamed {
+            worker.edit(&mut state, move |state| {
+                if let Some(probe) = state.probes.get_mut(i) {
+                    probe.name = name.clone();
+                    probe.set_quantity(quantity);
+                }
+            });
+        }
+        if let Some(i) = removed {
+            worker.edit(&mut state, move |state| {
+                if i < state.probes.len() {
+                    state.probes.remove(i);
+                }
+            });
+        }
+
+        // Add new probes
+        let n = state.particles.len();
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::widgets::DragValue::new(&mut editor.particle)
+                    .clamp_range(0..=n.saturating_sub(1)),
+            );
+            if ui.button("Attach to particle").clicked() && editor.particle < n {
+                let name = format!("Probe {}", state.probes.len());
+                let target = ProbeTarget::Particle(editor.particle);
+                worker.edit(&mut state, move |state| {
+                    state.add_probe(name.clone(), target, ProbeQuantity::Speed)
+                });
+            }
+        });
+        ui.horizontal(|ui| {
+            let lo = state.bound.lo_corner();
+            let hi = state.bound.hi_corner();
+            ui.add(
+                egui::widgets::DragValue::new(&mut editor.point.x)
+                    .clamp_range(lo.x..=hi.x)
+                    .speed(0.05),
+            );
+            ui.add(
+                egui::widgets::DragValue::new(&mut editor.point.y)
+                    .clamp_range(lo.y..=hi.y)
+                    .speed(0.05),
+            );
+            ui.add(
+                egui::widgets::DragValue::new(&mut editor.point.z)
+                    .clamp_range(lo.z..=hi.z)
+                    .speed(0.05),
+            );
+            if ui.button("Place at point").clicked() {
+                let name = format!("Probe {}", state.probes.len());
+                let target = ProbeTarget::Point(editor.point);
+                worker.edit(&mut state, move |state| {
+                    state.add_probe(name.clone(), target, ProbeQuantity::LocalDensity)
+                });
+            }
+        });
+
+        // Export all probe histories
+        ui.horizontal(|ui| {
+            if ui.button("Export").clicked() {
+                editor.export_status = match probe::export_csv(&state.probes, "probes.csv") {
+                    Ok(()) => "Saved to probes.csv".to_string(),
+                    Err(e) => format!("Export failed: {}", e),
+                };
+            }
+            ui.label(&editor.export_status);
+        });
+    });
+}
+
+// Inputs of the region probe window
+pub struct RegionEditor {
+    lo: Vec3, // corners of the next region
+    hi: Vec3,
+    plotted: usize, // index into RegionEditor::PLOTS
+}
+
+impl Default for RegionEditor {
+    fn default() -> Self {
+        Self {
+            lo: Vec3::ZERO,
+            hi: Vec3::ONE,
+            plotted: 0,
+        }
+    }
+}
+
+impl RegionEditor {
+    const PLOTS: [&'static str; 3] = ["Temperature", "Density", "Pressure tensor"];
+}
+
+// Box-shaped probes of the local temperature, density and pressure tensor, each with its own plot
+pub fn region_probes(
+    egui_context: ResMut<EguiContext>,
+    mut state: ResMut<SimulationState>,
+    mut worker: ResMut<worker::SimulationWorker>,
+    mut editor: Local<RegionEditor>,
+) {
+    egui::Window::new("Regions").show(egui_context.ctx(), |ui| {
+        egui::ComboBox::from_label("Plot")
+            .selected_text(RegionEditor::PLOTS[editor.plotted])
+            .show_ui(ui, |ui| {
+                for (i, name) in RegionEditor::PLOTS.iter().enumerate() {
+                    ui.selectable_value(&mut editor.plotted, i, *name);
+                }
+            });
+
+        let mut removed = None;
+        for (i, region) in state.regions.iter().enumerate() {
+            ui.group(|ui| {
+                ui.horizontal(|ui| {
+                    let (lo, hi) = (region.lo_corner(), region.hi_corner());
+                    ui.label(format!(
+                        "{}: ({:.2}, {:.2}, {:.2}) to ({:.2}, {:.2}, {:.2})",
+                        region.name, lo.x, lo.y, lo.z, hi.x, hi.y, hi.z
+                    ));
+                    if ui.button("Remove").clicked() {
+                        removed = Some(i);
+                    }
+                });
+                let latest = region.get_history().peak().copied().unwrap_or_default();
+                let p = latest.pressure;
+                ui.label(format!(
+                    "{} particles, T: {:.4}, density: {:.4}, P: {:.4}",
+                    latest.particles,
+                    latest.temperature,
+                    latest.density,
+                    latest.scalar_pressure()
+                ));
+                ui.label(format!(
+                    "Pxx: {:.4}, Pyy: {:.4}, Pzz: {:.4}, Pxy: {:.4}, Pxz: {:.4}, Pyz: {:.4}",
+                    p.x_axis.x, p.y_axis.y, p.z_axis.z, p.y_axis.x, p.z_axis.x, p.z_axis.y
+                ));
+
+                let history = region.get_history();
+                let series = |value: fn(&region::RegionSample) -> f32| {
+                    Curve::from_values_iter(
+                        history
+                            .iter()
+                            .enumerate()
+                            .map(move |(k, sample)| Value::new(k as f64, value(sample))),
+                    )
+                };
+                let plot = Plot::new(format!("region plot {}", i)).height(80.0);
+                let plot = match editor.plotted {
+                    0 => plot.curve(series(|s| s.temperature)),
+                    1 => plot.curve(series(|s| s.density)),
+                    _ => plot
+                        .curve(series(|s| s.pressure.x_axis.x).name("xx"))
+                        .curve(series(|s| s.pressure.y_axis.y).name("yy"))
+                        .curve(series(|s| s.pressure.z_axis.z).name("zz")),
+                };
+                ui.add(plot);
+            });
+        }
+        if let Some(i) = removed {
+            worker.edit(&mut state, move |state| {
+                if i < state.regions.len() {
+                    state.regions.remove(i);
+                }
+            });
+        }
+
+        // Place new regions
+        vec3_drags(ui, "Lower corner", &mut editor.lo, 0.05);
+        vec3_drags(ui, "Upper corner", &mut editor.hi, 0.05);
+        ui.horizontal(|ui| {
+            if ui.button("Place region").clicked() {
+                let name = format!("Region {}", state.regions.len());
+                let (lo, hi) = (editor.lo, editor.hi);
+                worker.edit(&mut state, move |state| {
+                    state.add_region_probe(name.clone(), lo, hi)
+                });
+            }
+            if ui.button("Whole box").clicked() {
+                editor.lo = state.bound.lo_corner();
+                editor.hi = state.bound.hi_corner();
+            }
+        });
+    });
+}
+
+// Density, temperature and velocity profiles along an axis
+pub fn profile_view(
+    egui_context: ResMut<EguiContext>,
+    mut profile_view: ResMut<profile::ProfileView>,
+    mut export_status: Local<String>,
+) {
+    egui::Window::new("Profiles").show(egui_context.ctx(), |ui| {
+        ui.checkbox(&mut profile_view.enabled, "Enabled");
+        egui::ComboBox::from_label("Axis")
+            .selected_text(format!("{:?}", profile_view.axis))
+            .show_ui(ui, |ui| {
+                for &axis in field::FieldAxis::ALL.iter() {
+                    ui.selectable_value(&mut profile_view.axis, axis, format!("{:?}", axis));
+                }
+            });
+        ui.add(
+            egui::Slider::new(&mut profile_view.slabs, 2..=profile::ProfileView::MAX_SLABS)
+                .text("Slabs"),
+        );
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut profile_view.average, "Average over frames");
+            if ui.button("Clear").clicked() {
+                profile_view.clear();
+            }
+            ui.label(format!("{} frames", profile_view.frames()));
+        });
+
+        let profile = match &profile_view.profile {
+            Some(profile) => profile,
+            None => return,
+        };
+        let series = |values: Vec<f32>| {
+            Curve::from_values_iter(
+                values
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, v)| Value::new(profile.slab_center(i) as f64, v)),
+            )
+        };
+        ui.label("Density");
+        ui.add(
+            Plot::new("density profile")
+                .curve(series(profile.density.clone()))
+                .height(100.0),
+        );
+        ui.label("Temperature");
+        ui.add(
+            Plot::new("temperature profile")
+                .curve(series(profile.temperature.clone()))
+                .height(100.0),
+        );
+        ui.label("Velocity");
+        let component = |k: usize| series(profile.velocity.iter().map(|v| v[k]).collect());
+        ui.add(
+            Plot::new("velocity profile")
+                .curve(component(0).name("vx"))
+                .curve(component(1).name("vy"))
+                .curve(component(2).name("vz"))
+                .height(100.0),
+        );
+
+        ui.horizontal(|ui| {
+            if ui.button("Export").clicked() {
+                *export_status = match profile.export_csv("profile.csv") {
+                    Ok(()) => "Saved to profile.csv".to_string(),
+                    Err(e) => format!("Export failed: {}", e),
+                };
+            }
+            ui.label(&*export_status);
+        });
+    });
+}
+
+// Liquid-vapor interfaces found in the density profile and their surface tension
+pub fn interface_view(
+    egui_context: ResMut<EguiContext>,
+    mut interface_view: ResMut<interface::InterfaceView>,
+) {
+    egui::Window::new("Interface").show(egui_context.ctx(), |ui| {
+        ui.checkbox(&mut interface_view.enabled, "Enabled");
+        let view = &mut *interface_view;
+        egui::ComboBox::from_label("Normal")
+            .selected_text(format!("{:?}", view.profile.axis))
+            .show_ui(ui, |ui| {
+                for &axis in field::FieldAxis::ALL.iter() {
+                    ui.selectable_value(&mut view.profile.axis, axis, format!("{:?}", axis));
+                }
+            });
+        ui.add(
+            egui::Slider::new(&mut view.profile.slabs, 4..=profile::ProfileView::MAX_SLABS)
+                .text("Slabs"),
+        );
+        ui.horizontal(|ui| {
+            if ui.button("Clear").clicked() {
+                view.clear();
+            }
+            ui.label(format!("averaged over {} frames", view.profile.frames()));
+        });
+
+        let (normal, tangential) = interface::normal_and_tangential(view.stress, view.profile.axis);
+        ui.label(format!(
+            "Normal pressure: {:.5}, tangential pressure: {:.5}",
+            normal, tangential
+        ));
+        let fit = match &view.fit {
+            Some(fit) => fit,
+            None => {
+                ui.label("No interface, the profile has no distinct liquid and vapor");
+                return;
+            }
+        };
+        ui.label(format!(
+            "Liquid density: {:.4}, vapor density: {:.4}",
+            fit.liquid_density, fit.vapor_density
+        ));
+        for found in fit.interfaces.iter() {
+            ui.label(format!(
+                "Interface at {:.3}, width {:.3}, density {} along the axis",
+                found.position,
+                found.width,
+                if found.rising { "rising" } else { "falling" }
+            ));
+        }
+        match view.surface_tension {
+            Some(tension) => ui.label(format!("Surface tension: {:.5}", tension)),
+            None => ui.label("Surface tension: no interfaces"),
+        };
+
+        // the averaged profile and the fitted tanh of each interface
+        if let Some(profile) = &view.profile.profile {
+            let measured = Curve::from_values_iter(
+                (0..profile.slabs())
+                    .map(|i| Value::new(profile.slab_center(i) as f64, profile.density[i])),
+            );
+            let mid = 0.5 * (fit.liquid_density + fit.vapor_density);
+            let half = 0.5 * (fit.liquid_density - fit.vapor_density);
+            let fitted = Curve::from_values_iter((0..profile.slabs()).map(|i| {
+                let z = profile.slab_center(i);
+                // the nearest interface decides
+                let model = fit
+                    .interfaces
+                    .iter()
+                    .min_by(|a, b| {
+                        let (da, db) = ((a.position - z).abs(), (b.position - z).abs());
+                        da.partial_cmp(&db).unwrap_or(std::cmp::Ordering::Equal)
+                    })
+                    .map_or(mid, |found| {
+                        let sign = if found.rising { 1.0 } else { -1.0 };
+                        mid + sign * half * ((z - found.position) / found.width).tanh()
+                    });
+                Value::new(z as f64, model)
+            }))
+            .color(egui::Color32::RED);
+            ui.add(
+                Plot::new("interface profile")
+                    .curve(measured.name("density"))
+                    .curve(fitted.name("fit"))
+                    .height(120.0),
+            );
+        }
+    });
+}
+
+pub fn field_view(
+    egui_context: ResMut<EguiContext>,
+    mut field_view: ResMut<field::FieldView>,
+    mut export_status: Local<String>,
+) {
+    egui::Window::new("Fields").show(egui_context.ctx(), |ui| {
+        ui.checkbox(&mut field_view.enabled, "Enabled");
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("Field")
+                .selected_text(format!("{:?}", field_view.kind))
+                .show_ui(ui, |ui| {
+                    for &kind in field::FieldKind::ALL.iter() {
+                        ui.selectable_value(&mut field_view.kind, kind, format!("{:?}", kind));
+                    }
+                });
+            egui::ComboBox::from_label("Slice axis")
+                .selected_text(format!("{:?}", field_view.axis))
+                .show_ui(ui, |ui| {
+                    for &axis in field::FieldAxis::ALL.iter() {
+                        ui.selectable_value(&mut field_view.axis, axis, format!("{:?}", axis));
+                    }
+                });
+        });
+        ui.add(egui::Slider::new(&mut field_view.resolution, 2..=30).text("Resolution"));
+        let last_slice = field_view.resolution - 1;
+        ui.add(egui::Slider::new(&mut field_view.slice, 0..=last_slice).text("Slice"));
+        ui.checkbox(&mut field_view.in_scene, "Show slice in box");
+        ui.add(egui::Slider::new(&mut field_view.interval, 1..=100).text("Update Interval"));
+
+        let field = match field_view.field() {
+            Some(field) => field,
+            None => return,
+        };
+
+        // Draw the slice as a grid of colored cells
+        let slice = field::slice(&field, field_view.axis, field_view.slice);
+        let (lo, hi) = field::slice_range(&slice);
+        let (rows, cols) = slice.dim();
+        let size = 200.0;
+        let (response, painter) = ui.allocate_painter(egui::vec2(size, size), egui::Sense::hover());
+        let cell = egui::vec2(size / rows as f32, size / cols as f32);
+        for ((i, j), &value) in slice.indexed_iter() {
+            let t = if hi > lo {
+                (value - lo) / (hi - lo)
+            } else {
+                0.0
+            };
+            let color = colormap::viridis(t);
+            let min = response.rect.left_bottom()
+                + egui::vec2(i as f32 * cell.x, -((j + 1) as f32) * cell.y);
+            painter.rect_filled(
+                egui::Rect::from_min_size(min, cell),
+                0.0,
+                egui::Color32::from_rgb(
+                    (color.r() * 255.0) as u8,
+                    (color.g() * 255.0) as u8,
+                    (color.b() * 255.0) as u8,
+                ),
+            );
+        }
+        ui.label(format!("Range: {:.5} to {:.5}", lo, hi));
+
+        ui.horizontal(|ui| {
+            if ui.button("Export").clicked() {
+                let name = format!("{:?}", field_view.kind).to_lowercase();
+                let path = format!("{}_field.csv", name);
+                *export_status = match field::export_csv(&field, &name, &path) {
+                    Ok(()) => format!("Saved to {}", path),
+                    Err(e) => format!("Export failed: {}", e),
+                };
+            }
+            ui.label(&*export_status);
+        });
+    });
+}
+
+pub fn flow_view(egui_context: ResMut<EguiContext>, mut flow_view: ResMut<field::FlowView>) {
+    egui::Window::new("Flow").show(egui_context.ctx(), |ui| {
+        ui.checkbox(&mut flow_view.enabled, "Show velocity arrows");
+        ui.add(egui::Slider::new(&mut flow_view.resolution, 2..=20).text("Resolution"));
+        ui.add(egui::Slider::new(&mut flow_view.interval, 1..=100).text("Update Interval"));
+        ui.add(
+            egui::Slider::new(&mut flow_view.arrow_scale, 0.1..=10.0)
+                .logarithmic(true)
+                .text("Arrow Scale"),
+        );
+        if flow_view.enabled {
+            ui.label(format!(
+                "Largest cell velocity: {:.5}",
+                flow_view.max_speed()
+            ));
+        }
+    });
+}
+
+pub fn trail_settings(egui_context: ResMut<EguiContext>, mut trail_view: ResMut<trail::TrailView>) {
+    egui::Window::new("Trails").show(egui_context.ctx(), |ui| {
+        ui.checkbox(&mut trail_view.enabled, "Show trails");
+        ui.add(egui::Slider::new(&mut trail_view.length, 2..=1000).text("Length (frames)"));
+        ui.add(egui::Slider::new(&mut trail_view.every, 1..=500).text("Trace every n-th"));
+        if trail_view.enabled {
+            ui.label(format!("Traced particles: {}", trail_view.traced_count()));
+        }
+    });
+}
+
+pub fn network_info(
+    egui_context: ResMut<EguiContext>,
+    mut network_view: ResMut<network::NetworkView>,
+) {
+    egui::Window::new("Bond Network").show(egui_context.ctx(), |ui| {
+        ui.checkbox(&mut network_view.enabled, "Enabled");
+        ui.add(
+            egui::Slider::new(&mut network_view.threshold, -0.6..=0.0)
+                .text("Bond Energy Threshold"),
+        );
+        if !network_view.enabled {
+            return;
+        }
+
+        let components = network_view.network.components();
+        let largest = components.first().map_or(0, |c| c.len());
+        ui.label(format!("Bonds: {}", network_view.network.get_edges().len()));
+        ui.label(format!("Clusters: {}", components.len()));
+        ui.label(format!("Largest cluster: {}", largest));
+
+        let [x, y, z] = network_view.percolation;
+        ui.label(format!("Percolates along x: {}, y: {}, z: {}", x, y, z));
+    });
+}
+
+pub fn external_fields(
+    egui_context: ResMut<EguiContext>,
+    mut state: ResMut<SimulationState>,
+    mut worker: ResMut<worker::SimulationWorker>,
+) {
+    // fields are edited as a copy, the whole set is sent when anything changed
+    let mut fields = state.external_fields.clone();
+    let mut changed = false;
+
+    egui::Window::new("External Fields").show(egui_context.ctx(), |ui| {
+        let mut removed = None;
+        for (i, field) in fields.iter_mut().enumerate() {
+            ui.group(|ui| {
+                ui.horizontal(|ui| {
+                    ui.label(field.name());
+                    if ui.button("Remove").clicked() {
+                        removed = Some(i);
+                    }
+                });
+                for (name, value) in field.parameters() {
+                    ui.horizontal(|ui| {
+                        changed |= ui
+                            .add(egui::widgets::DragValue::new(value).speed(0.02))
+                            .changed();
+                        ui.label(name);
+                    });
+                }
+            });
+        }
+        if let Some(i) = removed {
+            fields.remove(i);
+            changed = true;
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Add Gravity").clicked() {
+                let field = Gravity::new(Vec3::new(0.0, -1.0, 0.0));
+                fields.push(Box::new(field));
+                changed = true;
+            }
+            if ui.button("Add Trap").clicked() {
+                let field = HarmonicTrap::new(state.bound.center(), 1.0);
+                fields.push(Box::new(field));
+                changed = true;
+            }
+            if ui.button("Add Oscillating").clicked() {
+                let field = OscillatingField::new(Vec3::new(0.0, 1.0, 0.0), 0.5, 0.0);
+                fields.push(Box::new(field));
+                changed = true;
+            }
+        });
+    });
+
+    if changed {
+        worker.edit(&mut state, move |state| {
+            state.external_fields = fields.clone()
+        });
+    }
+}
+
+pub fn cluster_info(
+    egui_context: ResMut<EguiContext>,
+    mut cluster_view: ResMut<cluster::ClusterView>,
+) {
+    egui::Window::new("Clusters").show(egui_context.ctx(), |ui| {
+        ui.checkbox(&mut cluster_view.enabled, "Enabled");
+        ui.add(egui::Slider::new(&mut cluster_view.interval, 1..=100).text("Update Interval"));
+        if !cluster_view.enabled {
+            return;
+        }
+
+        let stats = &cluster_view.stats;
+        ui.label(format!("Clusters: {}", stats.clusters));
+        ui.label(format!("Largest cluster: {}", stats.largest));
+        ui.label(format!(
+            "Largest cluster fraction: {:.3}",
+            stats.largest_fraction
+        ));
+
+        // one bar per cluster size, drawn as the outline of a step curve
+        let bars = stats.distribution.iter().flat_map(|&(size, count)| {
+            let (size, count) = (size as f64, count as f64);
+            vec![
+                Value::new(size - 0.4, 0.0),
+                Value::new(size - 0.4, count),
+                Value::new(size + 0.4, count),
+                Value::new(size + 0.4, 0.0),
+            ]
+        });
+        ui.label("Number of clusters by size");
+        ui.add(
+            Plot::new("Cluster Sizes")
+                .curve(Curve::from_values_iter(bars).name("Clusters"))
+                .include_y(0.0)
+                .view_aspect(2.0),
+        );
+    });
+}
+
+pub fn order_info(egui_context: ResMut<EguiContext>, mut order_view: ResMut<order::OrderView>) {
+    egui::Window::new("Order").show(egui_context.ctx(), |ui| {
+        ui.checkbox(&mut order_view.enabled, "Enabled");
+        ui.add(egui::Slider::new(&mut order_view.interval, 1..=100).text("Update Interval"));
+        if !order_view.enabled {
+            return;
+        }
+
+        ui.label(format!("Mean q6: {:.4}", order_view.mean));
+        ui.label(format!(
+            "Crystalline fraction (q6 > {}): {:.3}",
+            order::CRYSTALLINE_Q6,
+            order_view.crystalline_fraction
+        ));
+
+        let curve = |values: &RingBuffer<f32>, name: &str| {
+            Curve::from_values_iter(
+                order_view
+                    .time
+                    .iter()
+                    .zip(values.iter())
+                    .map(|(&t, &v)| Value::new(t, v)),
+            )
+            .name(name)
+        };
+        ui.add(
+            Plot::new("Order")
+                .curve(curve(&order_view.mean_history, "Mean q6"))
+                .curve(curve(&order_view.crystalline_history, "Crystalline"))
+                .include_y(0.0)
+                .height(120.0),
+        );
+    });
+}
+
+pub fn neighbor_check_info(
+    egui_context: ResMut<EguiContext>,
+    mut neighbor_check: ResMut<neighbor_check::NeighborCheck>,
+) {
+    egui::Window::new("Neighbor Check").show(egui_context.ctx(), |ui| {
+        ui.checkbox(&mut neighbor_check.enabled, "Enabled");
+        ui.add(egui::Slider::new(&mut neighbor_check.interval, 1..=300).text("Check Interval"));
+        ui.add(egui::Slider::new(&mut neighbor_check.sample_size, 1..=500).text("Sample Size"));
+        if !neighbor_check.enabled || neighbor_check.checks == 0 {
+            return;
+        }
+
+        let report = neighbor_check.latest;
+        ui.label(format!(
+            "Checks: {} ({} failed)",
+            neighbor_check.checks, neighbor_check.failures
+        ));
+        ui.label(format!("Sampled particles: {}", report.sampled));
+        ui.label(format!(
+            "Max force deviation: {:.3e} (relative {:.3e})",
+            report.max_force_deviation, report.max_relative_deviation
+        ));
+        ui.label(format!(
+            "Max potential deviation: {:.3e}",
+            report.max_potential_deviation
+        ));
+        ui.label(format!("Missed pairs: {}", report.missed_pairs));
+        if let Some(pid) = report.worst_particle {
+            ui.label(format!("Worst particle: {}", pid));
+        }
+        if report.is_consistent() {
+            ui.label("Grid matches the brute force sum");
+        } else {
+            ui.colored_label(
+                egui::Color32::RED,
+                "Grid disagrees with the brute force sum",
+            );
+        }
+        if let Some((time, failure)) = neighbor_check.last_failure {
+            ui.colored_label(
+                egui::Color32::RED,
+                format!(
+                    "Last failure at t = {:.4}: force deviation {:.3e} at particle {:?}, {} missed pairs",
+                    time,
+                    failure.max_force_deviation,
+                    failure.worst_particle,
+                    failure.missed_pairs
+                ),
+            );
+        }
+    });
+}
+
+pub fn fluctuation_info(
+    egui_context: ResMut<EguiContext>,
+    mut state: ResMut<SimulationState>,
+    mut worker: ResMut<worker::SimulationWorker>,
+) {
+    egui::Window::new("Fluctuations").show(egui_context.ctx(), |ui| {
+        let observables =
+            observables::Observables::from_history(&state.history, state.particles.len());
+        let row = |ui: &mut egui::Ui, name: &str, estimate: observables::Estimate| {
+            ui.label(format!(
+                "{}: {:.5} ± {:.5}",
+                name, estimate.value, estimate.error
+            ));
+        };
+
+        ui.label(format!("Samples: {}", observables.samples));
+        row(ui, "Total Energy", observables.total_energy);
+        row(ui, "Temperature", observables.temperature);
+        row(ui, "Pressure", observables.pressure);
+        row(ui, "Volume", observables.volume);
+        ui.separator();
+        row(ui, "Heat capacity per particle", observables.heat_capacity);
+        row(ui, "Compressibility", observables.compressibility);
+        ui.label(
+            "Heat capacity needs the thermostat on, compressibility needs the pressure pinned",
+        );
+
+        if ui.button("Reset Averages").clicked() {
+            worker.edit(&mut state, |state| state.history.reset_moments());
+        }
+    });
+}
+
+// Inputs of the viscosity and conductivity windows that are not part of the simulation state
+pub struct TransportSettings {
+    enabled: bool,  // the analysis is expensive, only run it when asked
+    max_lag: usize, // number of steps the autocorrelation is integrated over
+}
+
+impl Default for TransportSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_lag: 500,
+        }
+    }
+}
+
+pub fn viscosity_info(
+    egui_context: ResMut<EguiContext>,
+    state: Res<SimulationState>,
+    mut settings: Local<TransportSettings>,
+) {
+    egui::Window::new("Viscosity").show(egui_context.ctx(), |ui| {
+        let stress = &state.stress;
+        ui.label(format!("Pressure from stress: {:.5}", stress.pressure()));
+        ui.label(format!("Stress samples: {}", stress.len()));
+        ui.checkbox(&mut settings.enabled, "Green-Kubo analysis");
+        let capacity = stress.capacity().max(2);
+        ui.add(egui::Slider::new(&mut settings.max_lag, 2..=capacity).text("Max Lag (steps)"));
+        if !settings.enabled {
+            return;
+        }
+
+        let kt = physics::kt(state.temperature());
+        let viscosity = stress::ShearViscosity::from_stress(
+            stress,
+            kt,
+            state.bound.get_volume(),
+            settings.max_lag,
+        );
+        ui.label(format!("Shear viscosity: {:.5}", viscosity.viscosity));
+
+        let dt = viscosity.sample_dt as f64;
+        let curve = |values: &[f32], name: &str| {
+            Curve::from_values_iter(
+                values
+                    .iter()
+                    .enumerate()
+                    .map(|(lag, &v)| Value::new(lag as f64 * dt, v)),
+            )
+            .name(name)
+        };
+        ui.label("Stress autocorrelation");
+        ui.add(
+            Plot::new("Stress Autocorrelation")
+                .curve(curve(&viscosity.autocorrelation, "<Pxy(0) Pxy(t)>"))
+                .height(100.0),
+        );
+        ui.label("Running integral");
+        ui.add(
+            Plot::new("Viscosity Integral")
+                .curve(curve(&viscosity.running_integral, "Viscosity"))
+                .include_y(0.0)
+                .height(100.0),
+        );
+        ui.label("The integral should level off before the max lag, samples need a steady state");
+    });
+}
+
+pub fn conductivity_info(
+    egui_context: ResMut<EguiContext>,
+    state: Res<SimulationState>,
+    mut settings: Local<TransportSettings>,
+) {
+    egui::Window::new("Thermal Conductivity").show(egui_context.ctx(), |ui| {
+        let flux = &state.heat_flux;
+        let current = flux.latest;
+        ui.label(format!(
+            "Heat current: ({:.4}, {:.4}, {:.4})",
+            current.x, current.y, current.z
+        ));
+        ui.label(format!("Heat current samples: {}", flux.len()));
+        ui.checkbox(&mut settings.enabled, "Green-Kubo analysis");
+        let capacity = flux.capacity().max(2);
+        ui.add(egui::Slider::new(&mut settings.max_lag, 2..=capacity).text("Max Lag (steps)"));
+        if !settings.enabled {
+            return;
+        }
+
+        let kt = physics::kt(state.temperature());
+        let conductivity = heat_flux::ThermalConductivity::from_flux(
+            flux,
+            kt,
+            state.bound.get_volume(),
+            settings.max_lag,
+        );
+        ui.label(format!(
+            "Thermal conductivity: {:.5}",
+            conductivity.conductivity
+        ));
+
+        let dt = conductivity.sample_dt as f64;
+        let curve = |values: &[f32], name: &str| {
+            Curve::from_values_iter(
+                values
+                    .iter()
+                    .enumerate()
+                    .map(|(lag, &v)| Value::new(lag as f64 * dt, v)),
+            )
+            .name(name)
+        };
+        ui.label("Heat current autocorrelation");
+        ui.add(
+            Plot::new("Heat Current Autocorrelation")
+                .curve(curve(&conductivity.autocorrelation, "<Jx(0) Jx(t)>"))
+                .height(100.0),
+        );
+        ui.label("Running integral");
+        ui.add(
+            Plot::new("Conductivity Integral")
+                .curve(curve(&conductivity.running_integral, "Conductivity"))
+                .include_y(0.0)
+                .height(100.0),
+        );
+        ui.label("Bonds, charges and walls don't enter the heat current, only the pair forces do");
+    });
+}
+
+pub fn rewind_controls(
+    egui_context: ResMut<EguiContext>,
+    mut state: ResMut<SimulationState>,
+    mut worker: ResMut<worker::SimulationWorker>,
+    mut rewind: ResMut<rewind::RewindBuffer>,
+) {
+    egui::Window::new("Rewind").show(egui_context.ctx(), |ui| {
+        ui.checkbox(&mut rewind.enabled, "Keep snapshots");
+        ui.add(egui::Slider::new(&mut rewind.interval, 1..=300).text("Frames per Snapshot"));
+        ui.add(egui::Slider::new(&mut rewind.budget_mb, 10.0..=2000.0).text("Memory Budget (MB)"));
+        ui.label(format!(
+            "Snapshots: {} of {}",
+            rewind.len(),
+            rewind.capacity(&state)
+        ));
+        if rewind.is_empty() {
+            return;
+        }
+
+        let last = rewind.len() - 1;
+        ui.add(egui::Slider::new(&mut rewind.selected, 0..=last).text("Snapshot"));
+        let selected = rewind.selected;
+        ui.label(format!(
+            "Time: {:.3} (now {:.3})",
+            rewind.time_of(selected).unwrap_or(0.0),
+            state.time
+        ));
+
+        if ui.button("Rewind").clicked() {
+            if let Some(snapshot) = rewind.rewind(selected) {
+                worker.edit(&mut state, move |state| *state = snapshot.clone());
+            }
+        }
+    });
+}
+
+// Dialog shown while the watchdog holds a divergence
+pub fn divergence_dialog(
+    egui_context: ResMut<EguiContext>,
+    mut state: ResMut<SimulationState>,
+    mut worker: ResMut<worker::SimulationWorker>,
+    mut watchdog: ResMut<watchdog::Watchdog>,
+) {
+    let divergence = match &watchdog.divergence {
+        Some(divergence) => divergence.clone(),
+        None => return,
+    };
+    egui::Window::new("Simulation Diverged").show(egui_context.ctx(), |ui| {
+        ui.colored_label(
+            egui::Color32::RED,
+            format!(
+                "{} particles diverged at step {} (t = {:.3}), the simulation is paused",
+                divergence.particles.len(),
+                divergence.steps,
+                divergence.time
+            ),
+        );
+        if divergence.non_finite {
+            ui.label("Some of their positions or velocities are NaN or infinite");
+        }
+        const LISTED: usize = 10;
+        let listed: Vec<String> = divergence
+            .particles
+            .iter()
+            .take(LISTED)
+            .map(|i| i.to_string())
+            .collect();
+        let more = if divergence.particles.len() > LISTED {
+            ", ..."
+        } else {
+            ""
+        };
+        ui.label(format!("Particles: {}{}", listed.join(", "), more));
+        ui.label(divergence.cause.describe());
+        ui.label(format!("dt = {}", state.dt));
+
+        ui.separator();
+        match watchdog.last_good().map(|good| good.time) {
+            Some(time) => {
+                let mut rewind = None;
+                if ui
+                    .button(format!("Rewind to t = {:.3}, paused", time))
+                    .clicked()
+                {
+                    rewind = Some(false);
+                }
+                if ui.button("Rewind and go on with half the dt").clicked() {
+                    rewind = Some(true);
+                }
+                // the same dt would diverge the same way, so a plain rewind stays paused
+                if let Some(halve) = rewind {
+                    if let Some(good) = watchdog.rewind() {
+                        worker.edit(&mut state, move |state| {
+                            *state = good.clone();
+                            state.paused = !halve;
+                            if halve {
+                                let dt = state.dt / 2.0;
+                                state.set_dt(dt);
+                            }
+                        });
+                    }
+                }
+            }
+            None => {
+                ui.label("No good state to rewind to");
+            }
+        }
+        if ui.button("Dismiss").clicked() {
+            watchdog.dismiss();
+        }
+        ui.checkbox(&mut watchdog.enabled, "Watch for divergence");
+    });
+}
+
+// Error of the latest reset or preset that didn't compile, shown until it's dismissed
+#[derive(Default)]
+pub struct RestartFailure {
+    pub message: Option<String>,
+}
+
+impl RestartFailure {
+    // Show the error in the gui and print it for the terminal
+    pub fn report(&mut self, message: String) {
+        eprintln!("{}", message);
+        self.message = Some(message);
+    }
+}
+
+// Dialog with the error of a failed restart, shown even with the gui hidden
+pub fn restart_failure_dialog(
+    egui_context: ResMut<EguiContext>,
+    mut failure: ResMut<RestartFailure>,
+) {
+    let message = match &failure.message {
+        Some(message) => message.clone(),
+        None => return,
+    };
+    egui::Window::new("Restart Failed").show(egui_context.ctx(), |ui| {
+        ui.colored_label(
+            egui::Color32::RED,
+            "The setup didn't compile, the simulation goes on as before",
+        );
+        for line in message.lines() {
+            ui.label(line);
+        }
+        if ui.button("Dismiss").clicked() {
+            failure.message = None;
+        }
+    });
+}
+
+// Replace the running simulation with a freshly compiled one
+pub fn restart(
+    prototype: &SimulationPrototype,
+    state: &mut SimulationState,
+    worker: &mut worker::SimulationWorker,
+) -> Result<(), error::InvalidParamError> {
+    let simulation = prototype.compile()?;
+    for warning in simulation.warnings() {
+        eprintln!("Warning: {}", warning);
+    }
+    if let Some(report) = simulation.minimization() {
+        println!("{}", report);
+    }
+    let fresh = simulation.into_state();
+    worker.edit(state, move |state| *state = fresh.clone());
+    Ok(())
+}
+
+// Start over from the initial setup, or from a preset that becomes the new initial setup
+pub fn preset_menu(
+    egui_context: ResMut<EguiContext>,
+    mut state: ResMut<SimulationState>,
+    mut worker: ResMut<worker::SimulationWorker>,
+    mut setup: ResMut<InitialSetup>,
+    mut focus: ResMut<render_systems::CameraFocus>,
+    mut failure: ResMut<RestartFailure>,
+) {
+    egui::Window::new("Presets").show(egui_context.ctx(), |ui| {
+        if ui.button("Reset simulation").clicked() {
+            if let Err(err) = restart(&setup.prototype, &mut state, &mut worker) {
+                failure.report(format!("Reset failed: {}", err));
+            }
+        }
+        ui.separator();
+
+        for preset in preset::Preset::ALL.iter() {
+            let params = preset.params();
+            ui.horizontal(|ui| {
+                if ui.button(preset.name()).clicked() {
+                    let prototype = preset.prototype();
+                    match restart(&prototype, &mut state, &mut worker) {
+                        Ok(()) => {
+                            setup.prototype = prototype;
+                            // the box size changes with the preset
+                            focus.selected = None;
+                            focus.frame_box = true;
+                        }
+                        Err(err) => failure.report(format!(
+                            "Preset {} failed to compile: {}",
+                            preset.name(),
+                            err
+                        )),
+                    }
+                }
+                ui.label(format!(
+                    "{} particles, density {}, temperature {}",
+                    params.particles, params.density, params.temperature
+                ));
+            });
+            ui.label(preset.description());
+        }
+    });
+}
+
+// Input line and output of the console window
+#[derive(Default)]
+pub struct Console {
+    input: String,
+    log: Vec<String>, // echoed commands and their results, oldest first
+}
+
+impl Console {
+    const LOG_LINES: usize = 200; // older lines are dropped
+}
+
+// Carry out a console command, messages for the user are pushed to log
+// Edits of the state go through the worker like the ones made by the other windows
+pub fn execute(
+    command: console::ConsoleCommand,
+    state: &mut SimulationState,
+    worker: &mut worker::SimulationWorker,
+    log: &mut Vec<String>,
+    in_macro: bool,
+) {
+    use console::ConsoleCommand;
+    match command {
+        ConsoleCommand::Set(parameter, value) => match parameter.check(value) {
+            Ok(()) => worker.edit(state, move |state| {
+                // checked above, apply can't refuse it
+                let _ = parameter.apply(state, value);
+            }),
+            Err(err) => log.push(err),
+        },
+        ConsoleCommand::AddParticles { n, placement } => {
+            let particles = placement.particles(n, state);
+            let added = particles.len();
+            worker.edit(state, move |state| {
+                state.add_particles(particles.clone());
+            });
+            log.push(format!(
+                "generated {} particles, crowded ones are skipped",
+                added
+            ));
+        }
+        ConsoleCommand::Toggle(flag) => {
+            worker.edit(state, move |state| flag.apply(state));
+        }
+        ConsoleCommand::Save(path) => {
+            log.push(match snapshot::Snapshot::of(state).save(&path) {
+                Ok(()) => format!("saved {}", path),
+                Err(err) => format!("saving {} failed: {}", path, err),
+            });
+        }
+        ConsoleCommand::Run(_) if in_macro => log.push("macros can't run other macros".to_string()),
+        ConsoleCommand::Run(path) => match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                let lines = text
+                    .lines()
+                    .map(|line| line.split('#').next().unwrap_or("").trim())
+                    .filter(|line| !line.is_empty());
+                for line in lines {
+                    log.push(format!("> {}", line));
+                    match ConsoleCommand::parse(line) {
+                        Ok(command) => execute(command, state, worker, log, true),
+                        Err(err) => log.push(err),
+                    }
+                }
+            }
+            Err(err) => log.push(format!("reading {} failed: {}", path, err)),
+        },
+        ConsoleCommand::Help => log.extend(console::HELP.iter().map(|line| line.to_string())),
+    }
+}
+
+pub fn console_window(
+    egui_context: ResMut<EguiContext>,
+    mut state: ResMut<SimulationState>,
+    mut worker: ResMut<worker::SimulationWorker>,
+    mut console: Local<Console>,
+) {
+    egui::Window::new("Console").show(egui_context.ctx(), |ui| {
+        egui::ScrollArea::from_max_height(150.0).show(ui, |ui| {
+            for line in console.log.iter() {
+                ui.monospace(line);
+            }
+        });
+        if state.paused {
+            ui.label("Paused");
+        }
+
+        let response = ui.text_edit_singleline(&mut console.input);
+        let entered = response.lost_focus() && ui.input().key_pressed(egui::Key::Enter);
+        if entered || ui.button("Enter").clicked() {
+            let line = std::mem::take(&mut console.input);
+            let Console { log, .. } = &mut *console;
+            log.push(format!("> {}", line));
+            match console::ConsoleCommand::parse(&line) {
+                Ok(command) => execute(command, &mut state, &mut worker, log, false),
+                Err(err) => log.push(err),
+            }
+            response.request_focus();
+        }
+
+        let excess = console.log.len().saturating_sub(Console::LOG_LINES);
+        console.log.drain(..excess);
+    });
+}
+
+pub fn schedule_info(egui_context: ResMut<EguiContext>, state: Res<SimulationState>) {
+    if state.schedule.is_empty() {
+        return;
+    }
+
+    egui::Window::new("Schedule").show(egui_context.ctx(), |ui| {
+        ui.label(format!("Step: {}", state.steps));
+        for event in state.schedule.events() {
+            let action = match event.action {
+                schedule::EventAction::Set(value) => {
+                    format!(
+                        "at step {} set {:?} to {}",
+                        event.step, event.parameter, value
+                    )
+                }
+                schedule::EventAction::Ramp { from, to, steps } => format!(
+                    "from step {} ramp {:?} from {} to {} over {} steps",
+                    event.step, event.parameter, from, to, steps
+                ),
+            };
+            let status = if state.steps > event.end_step() {
+                "done"
+            } else if state.steps >= event.step {
+                "running"
+            } else {
+                "pending"
+            };
+            ui.label(format!("[{}] {}", status, action));
+        }
+    });
+}
+
+// Ramp the thermostat target over simulated time instead of by hand
+pub fn annealing_controls(
+    egui_context: ResMut<EguiContext>,
+    mut state: ResMut<SimulationState>,
+    mut worker: ResMut<worker::SimulationWorker>,
+) {
+    let current = state.annealer.clone();
+    let mut annealer = current.clone();
+
+    egui::Window::new("Annealing").show(egui_context.ctx(), |ui| {
+        ui.add(egui::Slider::new(&mut annealer.start_temp, 0.0..=5.0).text("Start Temperature"));
+        ui.add(egui::Slider::new(&mut annealer.end_temp, 0.0..=5.0).text("End Temperature"));
+        ui.add(
+            egui::Slider::new(&mut annealer.duration, 1.0..=1000.0)
+                .logarithmic(true)
+                .text("Ramp Duration"),
+        );
+        egui::ComboBox::from_label("At the End")
+            .selected_text(annealer.mode.name())
+            .show_ui(ui, |ui| {
+                for &mode in anneal::AnnealMode::ALL.iter() {
+                    ui.selectable_value(&mut annealer.mode, mode, mode.name());
+                }
+            });
+
+        ui.horizontal(|ui| {
+            if ui.button("Start").clicked() {
+                annealer.start(state.time);
+            }
+            if annealer.running && ui.button("Stop").clicked() {
+                annealer.running = false;
+            }
+        });
+        if annealer.running {
+            ui.label(format!(
+                "Target: {:.4} ({:.0}%)",
+                annealer.target_at(state.time),
+                100.0 * annealer.progress(state.time)
+            ));
+            if state.inject_rate <= 0.0 {
+                ui.label("The thermostat is off, raise the inject rate to follow the target");
+            }
+        }
+    });
+
+    if annealer != current {
+        worker.edit(&mut state, move |state| state.annealer = annealer.clone());
+    }
+}
+
+// Wall hits and pair collisions, with the mean free path they imply
+pub fn collision_info(
+    egui_context: ResMut<EguiContext>,
+    state: Res<SimulationState>,
+    unit_system: Res<units::UnitSystem>,
+) {
+    let scale = units::ReducedUnits::of(&state);
+    let show = |quantity, value| unit_system.format(&scale, quantity, value);
+    let collisions = &state.collisions;
+    let n = state.particles.len();
+
+    egui::Window::new("Collisions").show(egui_context.ctx(), |ui| {
+        if !collisions.is_measured() {
+            ui.label(format!(
+                "Counting over {}...",
+                show(units::Quantity::Time, collisions.period)
+            ));
+            return;
+        }
+        // rates are shown as the time between events, which converts like any other time
+        let between = |rate: f32| {
+            if rate > 0.0 {
+                show(units::Quantity::Time, 1.0 / rate)
+            } else {
+                "none".to_string()
+            }
+        };
+        ui.label(format!(
+            "Time between wall hits: {}",
+            between(collisions.wall_rate())
+        ));
+        ui.label(format!(
+            "Time between collisions: {}",
+            between(collisions.pair_rate())
+        ));
+        ui.label(format!(
+            "Time between collisions of a particle: {}",
+            between(collisions.frequency(n))
+        ));
+        match collisions.mean_free_path(state.mean_speed(), n) {
+            Some(path) => ui.label(format!(
+                "Mean free path: {}",
+                show(units::Quantity::Length, path)
+            )),
+            None => ui.label("Mean free path: no collisions yet"),
+        };
+    });
+}
+
+// Measured pressure and mean free path next to the ideal gas and the fitted van der Waals gas
+pub fn kinetic_theory(
+    egui_context: ResMut<EguiContext>,
+    state: Res<SimulationState>,
+    unit_system: Res<units::UnitSystem>,
+) {
+    use units::Quantity;
+    let scale = units::ReducedUnits::of(&state);
+    let show = |quantity, value| unit_system.format(&scale, quantity, value);
+    let show_some = |quantity, value: Option<f32>| {
+        value.map_or("not enough data".to_string(), |v| show(quantity, v))
+    };
+    let comparison = kinetic::Comparison::of(&state);
+
+    egui::Window::new("Kinetic Theory").show(egui_context.ctx(), |ui| {
+        ui.label(format!(
+            "Density: {}",
+            show(Quantity::Density, comparison.density)
+        ));
+        ui.label(format!(
+            "Temperature: {}",
+            show(Quantity::Temperature, comparison.temperature)
+        ));
+        ui.separator();
+
+        ui.label(format!(
+            "Pressure: {}",
+            show(Quantity::Pressure, comparison.pressure)
+        ));
+        ui.label(format!(
+            "Ideal gas: {}",
+            show(Quantity::Pressure, comparison.ideal_pressure)
+        ));
+        ui.label(format!(
+            "Van der Waals: {}",
+            show_some(Quantity::Pressure, comparison.van_der_waals_pressure())
+        ));
+        match comparison.van_der_waals {
+            // a is an energy times a volume, shown in simulation units
+            Some(fit) => ui.label(format!(
+                "Fitted a: {:.5}, b: {}",
+                fit.a,
+                show(Quantity::Volume, fit.b)
+            )),
+            None => ui.label("Vary the volume or temperature to fit a and b"),
+        };
+        ui.separator();
+
+        ui.label(format!(
+            "Mean free path: {}",
+            show_some(Quantity::Length, comparison.mean_free_path)
+        ));
+        ui.label(format!(
+            "Ideal gas: {}",
+            show(Quantity::Length, comparison.ideal_mean_free_path)
+        ));
+        ui.label(format!(
+            "Van der Waals: {}",
+            show_some(Quantity::Length, comparison.van_der_waals_mean_free_path())
+        ));
+    });
+}
+
+// Inputs of the Groups window for tagging a new group
+pub struct GroupTool {
+    name: String,
+    selection: usize, // index into GroupTool::SELECTIONS
+    first: usize,     // range of indices
+    last: usize,
+    lo: Vec3, // corners of the region
+    hi: Vec3,
+    species: usize,
+    kick: Vec3, // velocity added by the kick buttons
+}
+
+impl Default for GroupTool {
+    fn default() -> Self {
+        Self {
+            name: "group".to_string(),
+            selection: 1,
+            first: 0,
+            last: 0,
+            lo: Vec3::ZERO,
+            hi: Vec3::ONE,
+            species: 0,
+            kick: Vec3::new(0.0, 1.0, 0.0),
+        }
+    }
+}
+
+impl GroupTool {
+    const SELECTIONS: [&'static str; 3] = ["Indices", "Region", "Species"];
+
+    // internal helper function
+    fn selection(&self) -> group::Selection {
+        match self.selection {
+            0 => group::Selection::Indices((self.first..=self.last).collect()),
+            1 => group::Selection::Region {
+                lo: self.lo,
+                hi: self.hi,
+            },
+            _ => group::Selection::Species(self.species),
+        }
+    }
+}
+
+// Edit a vector with one drag value per component
+// internal helper function
+fn vec3_drags(ui: &mut egui::Ui, label: &str, v: &mut Vec3, speed: f32) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        ui.add(egui::DragValue::new(&mut v.x).speed(speed).prefix("x: "));
+        ui.add(egui::DragValue::new(&mut v.y).speed(speed).prefix("y: "));
+        ui.add(egui::DragValue::new(&mut v.z).speed(speed).prefix("z: "));
+    });
+}
+
+// Change of the state queued while the windows are drawn, handed to the worker afterwards
+type Edit = Box<dyn Fn(&mut SimulationState) + Send + Sync>;
+
+// Tag particles into groups and work on the groups
+pub fn group_tools(
+    egui_context: ResMut<EguiContext>,
+    mut state: ResMut<SimulationState>,
+    mut worker: ResMut<worker::SimulationWorker>,
+    mut tool: Local<GroupTool>,
+) {
+    egui::Window::new("Groups").show(egui_context.ctx(), |ui| {
+        let n = state.particles.len();
+        ui.horizontal(|ui| {
+            ui.label("Name:");
+            ui.text_edit_singleline(&mut tool.name);
+        });
+        egui::ComboBox::from_label("Select by")
+            .selected_text(GroupTool::SELECTIONS[tool.selection])
+            .show_ui(ui, |ui| {
+                for (i, name) in GroupTool::SELECTIONS.iter().enumerate() {
+                    ui.selectable_value(&mut tool.selection, i, *name);
+                }
+            });
+        match tool.selection {
+            0 => {
+                let max = n.max(1) - 1;
+                ui.horizontal(|ui| {
+                    ui.label("From");
+                    ui.add(egui::DragValue::new(&mut tool.first).clamp_range(0..=max));
+                    ui.label("to");
+                    ui.add(egui::DragValue::new(&mut tool.last).clamp_range(0..=max));
+                });
+            }
+            1 => {
+                if ui.button("Whole box").clicked() {
+                    tool.lo = state.bound.lo_corner();
+                    tool.hi = state.bound.hi_corner();
+                }
+                vec3_drags(ui, "From", &mut tool.lo, 0.05);
+                vec3_drags(ui, "To", &mut tool.hi, 0.05);
+            }
+            _ => {
+                ui.add(egui::DragValue::new(&mut tool.species).prefix("Species: "));
+            }
+        }
+        let selection = tool.selection();
+        ui.label(format!(
+            "{} particles selected",
+            selection.select(&state.particles).len()
+        ));
+        if ui.button("Tag").clicked() {
+            let name = tool.name.clone();
+            worker.edit(&mut state, move |state| {
+                state.add_group(name.clone(), &selection);
+            });
+        }
+        ui.separator();
+
+        vec3_drags(ui, "Kick", &mut tool.kick, 0.05);
+        let kick = tool.kick;
+        let mut edits: Vec<Edit> = Vec::new();
+        egui::Grid::new("groups").show(ui, |ui| {
+            for (i, group) in state.groups.iter().enumerate() {
+                ui.label(format!("{} ({})", group.name, group.get_members().len()));
+                let mut frozen = group.frozen;
+                if ui.checkbox(&mut frozen, "Frozen").changed() {
+                    edits.push(Box::new(move |state| state.freeze_group(i, frozen)));
+                }
+                let mut colored = group.color.is_some();
+                let mut color = group.color.unwrap_or([1.0, 0.2, 0.2]);
+                let toggled = ui.checkbox(&mut colored, "Color").changed();
+                let picked = colored && ui.color_edit_button_rgb(&mut color).changed();
+                if toggled || picked {
+                    let color = Some(color).filter(|_| colored);
+                    edits.push(Box::new(move |state| {
+                        if let Some(group) = state.groups.get_mut(i) {
+                            group.color = color;
+                        }
+                    }));
+                }
+                if ui.button("Kick").clicked() {
+                    edits.push(Box::new(move |state| state.kick_group(i, kick)));
+                }
+                if ui.button("Delete particles").clicked() {
+                    edits.push(Box::new(move |state| {
+                        state.delete_group(i);
+                    }));
+                }
+                if ui.button("Untag").clicked() {
+                    edits.push(Box::new(move |state| {
+                        state.groups.remove(i);
+                    }));
+                }
+                ui.end_row();
+            }
+        });
+        for edit in edits {
+            worker.edit(&mut state, edit);
+        }
+    });
+}
+
+// Seed of the crystal seed window, centered on the box until it's moved
+#[derive(Default)]
+pub struct SeedTool {
+    seed: seed::Seed,
+    placed: bool,
+    status: String,
+}
+
+// Insert fcc crystallites into the fluid to nucleate on
+pub fn crystal_seed(
+    egui_context: ResMut<EguiContext>,
+    mut state: ResMut<SimulationState>,
+    mut worker: ResMut<worker::SimulationWorker>,
+    focus: Res<render_systems::CameraFocus>,
+    mut tool: Local<SeedTool>,
+) {
+    if !tool.placed {
+        tool.seed.center = state.bound.center();
+    }
+    egui::Window::new("Crystal Seed").show(egui_context.ctx(), |ui| {
+        let mut center = tool.seed.center;
+        vec3_drags(ui, "Center", &mut center, 0.05);
+        ui.horizontal(|ui| {
+            if ui.button("Box center").clicked() {
+                center = state.bound.center();
+            }
+            let selected = focus
+                .selected
+                .and_then(|i| state.particles.get(i))
+                .map(|p| p.get_pos());
+            if let Some(pos) = selected {
+                if ui.button("Selected particle").clicked() {
+                    center = pos;
+                }
+            }
+        });
+        if center != tool.seed.center {
+            tool.seed.center = center;
+            tool.placed = true;
+        }
+        ui.add(egui::Slider::new(&mut tool.seed.radius, 0.0..=2.0).text("Radius"));
+        ui.add(egui::Slider::new(&mut tool.seed.spacing, 0.1..=0.3).text("Spacing"));
+        ui.checkbox(&mut tool.seed.frozen, "Frozen");
+        let sites = tool.seed.sites().len();
+        if ui.button(format!("Insert {} particles", sites)).clicked() {
+            let particles = tool.seed.particles(&state);
+            worker.edit(&mut state, move |state| {
+                state.insert_seed(particles.clone());
+            });
+            tool.status = "inserted into the Groups window as seed".to_string();
+        }
+        if !tool.status.is_empty() {
+            ui.label(&tool.status);
+        }
+    });
+}
+
+// Inputs of the pressure-volume window
+pub struct PressureVolumeSettings {
+    window: usize, // number of most recent history entries plotted
+}
+
+impl Default for PressureVolumeSettings {
+    fn default() -> Self {
+        Self { window: 1000 }
+    }
+}
+
+impl PressureVolumeSettings {
+    // the trajectory is split into this many pieces, colored from old to new
+    const SEGMENTS: usize = 8;
+}
+
+// Pressure against volume from the history, while the box is compressed or expanded
+pub fn pressure_volume(
+    egui_context: ResMut<EguiContext>,
+    state: Res<SimulationState>,
+    mut settings: Local<PressureVolumeSettings>,
+) {
+    let history = &state.history;
+    egui::Window::new("Pressure-Volume").show(egui_context.ctx(), |ui| {
+        let capacity = history.capacity().max(2);
+        ui.add(egui::Slider::new(&mut settings.window, 2..=capacity).text("Window Length"));
+        let points = kinetic::pressure_volume(history, settings.window);
+
+        let mut plot = Plot::new("P(V)").view_aspect(1.5);
+        let segment = (points.len() / PressureVolumeSettings::SEGMENTS).max(1);
+        let segments = (points.len() + segment - 1) / segment;
+        for k in 0..segments {
+            // pieces overlap by a point so the trajectory stays connected
+            let piece = &points[k * segment..((k + 1) * segment + 1).min(points.len())];
+            let t = if segments > 1 {
+                k as f32 / (segments - 1) as f32
+            } else {
+                1.0
+            };
+            let color = colormap::viridis(t);
+            plot = plot.curve(
+                Curve::from_values_iter(piece.iter().map(|&(v, p)| Value::new(v, p))).color(
+                    egui::Color32::from_rgb(
+                        (color.r() * 255.0) as u8,
+                        (color.g() * 255.0) as u8,
+                        (color.b() * 255.0) as u8,
+                    ),
+                ),
+            );
+        }
+
+        let fit = kinetic::PressureVolume::fit(&points);
+        if let Some(fit) = fit {
+            let lo = points.iter().map(|&(_, p)| p).fold(f32::INFINITY, f32::min);
+            let hi = points
+                .iter()
+                .map(|&(_, p)| p)
+                .fold(f32::NEG_INFINITY, f32::max);
+            plot = plot.curve(
+                Curve::from_values(vec![
+                    Value::new(fit.volume(lo), lo),
+                    Value::new(fit.volume(hi), hi),
+                ])
+                .color(egui::Color32::RED)
+                .name("Linear fit"),
+            );
+        }
+        ui.add(plot);
+        ui.label("Volume across, pressure up; older points are darker");
+
+        match fit {
+            // 1 over a pressure, shown in simulation units
+            Some(fit) => {
+                ui.label(format!(
+                    "Compressibility: {:.5}, correlation {:.3}",
+                    fit.compressibility(),
+                    fit.correlation
+                ));
+            }
+            None => {
+                ui.label("Compress or expand the box to measure the compressibility");
+            }
+        }
+    });
+}
+
+// Settings of the next isotherm sweep
+pub struct IsothermSettings {
+    sweep: experiment::IsothermSweep,
+    export_status: String,
+}
+
+impl Default for IsothermSettings {
+    fn default() -> Self {
+        Self {
+            sweep: experiment::IsothermSweep::new(1.0),
+            export_status: String::new(),
+        }
+    }
+}
+
+// Compress the box step by step and plot the pressure against the volume
+pub fn isotherm_sweep(
+    egui_context: ResMut<EguiContext>,
+    mut state: ResMut<SimulationState>,
+    mut worker: ResMut<worker::SimulationWorker>,
+    mut settings: Local<IsothermSettings>,
+) {
+    egui::Window::new("Isotherm").show(egui_context.ctx(), |ui| {
+        let sweep = &mut settings.sweep;
+        ui.add(egui::Slider::new(&mut sweep.temperature, 0.01..=5.0).text("Temperature"));
+        ui.add(
+            egui::Slider::new(&mut sweep.final_volume, 0.05..=1.0).text("Final Volume Fraction"),
+        );
+        ui.add(egui::Slider::new(&mut sweep.points, 2..=40).text("Points"));
+        ui.add(egui::Slider::new(&mut sweep.equilibration, 0.1..=20.0).text("Equilibration Time"));
+        ui.add(egui::Slider::new(&mut sweep.sampling, 0.1..=20.0).text("Sampling Time"));
+        ui.add(egui::Slider::new(&mut sweep.rate, 0.01..=1.0).text("Compression Rate"));
+
+        ui.horizontal(|ui| {
+            if ui.button("Start").clicked() {
+                let sweep = settings.sweep.clone();
+                worker.edit(&mut state, move |state| {
+                    let mut sweep = sweep.clone();
+                    experiment::Experiment::start(&mut sweep, state);
+                    state.isotherm = Some(sweep);
+                });
+            }
+            let running = state
+                .isotherm
+                .as_ref()
+                .map_or(false, |sweep| !sweep.is_done());
+            if running && ui.button("Stop").clicked() {
+                worker.edit(&mut state, |state| {
+                    state.isotherm = None;
+                    state.bound_rate = 0.0;
+                });
+            }
+        });
+
+        let sweep = match &state.isotherm {
+            Some(sweep) => sweep,
+            None => return,
+        };
+        ui.label(sweep.status());
+        let points = sweep.results();
+        let coexistence = coexistence::Coexistence::of(points);
+        let mut plot = Plot::new("Isotherm")
+            .curve(
+                Curve::from_values_iter(points.iter().map(|p| Value::new(p.volume, p.pressure)))
+                    .name("P(V)"),
+            )
+            .height(160.0);
+        match coexistence {
+            Some(coexistence) => {
+                // the flat line of the Maxwell construction, between the volumes of the pure liquid and gas
+                let n = points[0].density * points[0].volume;
+                plot = plot.curve(
+                    Curve::from_values_iter(
+                        [coexistence.liquid_density, coexistence.gas_density]
+                            .iter()
+                            .map(|density| Value::new(n / density, coexistence.pressure)),
+                    )
+                    .name("Maxwell"),
+                );
+                ui.label(format!("Coexistence pressure: {:.5}", coexistence.pressure));
+                ui.label(format!(
+                    "Liquid density: {:.5}, gas density: {:.5}",
+                    coexistence.liquid_density, coexistence.gas_density
+                ));
+            }
+            None => {
+                ui.label("No van der Waals loop yet");
+            }
+        }
+        ui.add(plot);
+        ui.horizontal(|ui| {
+            if ui
+                .add(egui::Button::new("Export Isotherm").enabled(!points.is_empty()))
+                .clicked()
+            {
+                settings.export_status = export::save_isotherm(points);
+            }
+            ui.label(&settings.export_status);
+        });
+    });
+}
+
+// Rolling breakdown of where the time of a frame goes
+pub fn timing_info(
+    egui_context: ResMut<EguiContext>,
+    mut state: ResMut<SimulationState>,
+    mut worker: ResMut<worker::SimulationWorker>,
+    mut app_timings: ResMut<sim_systems::AppTimings>,
+) {
+    egui::Window::new("Timings").show(egui_context.ctx(), |ui| {
+        let mut enabled = state.timings.enabled;
+        ui.checkbox(&mut enabled, "Enabled");
+        if enabled != state.timings.enabled {
+            worker.edit(&mut state, move |state| {
+                state.timings.enabled = enabled;
+                state.timings.clear();
+            });
+            app_timings.timings.enabled = enabled;
+            app_timings.timings.clear();
+        }
+
+        // the simulation phases come with the snapshot, the others are timed here
+        let source = |phase: timing::Phase| {
+            if timing::Phase::SIMULATION.contains(&phase) {
+                &state.timings
+            } else {
+                &app_timings.timings
+            }
+        };
+        let total: f32 = timing::Phase::ALL
+            .iter()
+            .map(|&phase| source(phase).mean_ms(phase))
+            .sum();
+        ui.label(format!(
+            "Mean over the last {} frames: {:.2} ms",
+            timing::Timings::WINDOW,
+            total
+        ));
+        for &phase in timing::Phase::ALL.iter() {
+            let ms = source(phase).mean_ms(phase);
+            let share = if total > 0.0 { 100.0 * ms / total } else { 0.0 };
+            ui.label(format!("{}: {:.3} ms ({:.0}%)", phase.name(), ms, share));
+        }
+
+        let plot = timing::Phase::ALL
+            .iter()
+            .fold(Plot::new("Timings"), |plot, &phase| {
+                plot.curve(
+                    Curve::from_values_iter(
+                        source(phase)
+                            .frames(phase)
+                            .enumerate()
+                            .map(|(i, &ms)| Value::new(i as f64, ms)),
+                    )
+                    .name(phase.name()),
+                )
+            })
+            .height(120.0);
+        ui.add(plot);
+    });
+}
+
+pub fn species_visuals(egui_context: ResMut<EguiContext>, mut visuals: ResMut<SpeciesVisuals>) {
+    egui::Window::new("Species").show(egui_context.ctx(), |ui| {
+        for species in 0..visuals.len() {
+            let style = visuals.style(species);
+            ui.horizontal(|ui| {
+                ui.label(format!("Species {}", species));
+
+                let mut rgb = [style.color.r(), style.color.g(), style.color.b()];
+                if ui.color_edit_button_rgb(&mut rgb).changed() {
+                    let [r, g, b] = rgb;
+                    visuals.set_color(species, Color::rgb(r, g, b));
+                }
+
+                let mut scale = style.scale;
+                ui.add(egui::Slider::new(&mut scale, 0.2..=3.0).text("Size"));
+                if (scale - style.scale).abs() > f32::EPSILON {
+                    visuals.set_scale(species, scale);
+                }
+            });
+        }
+    });
+}
+
+// Rates shown by the stats overlay, measured over windows of wall clock time
+#[derive(Default)]
+pub struct OverlayRates {
+    window_start: f64,     // wall clock time at the start of the current window
+    frames: usize,         // frames rendered in the current window
+    steps_at_start: usize, // simulation steps done when the window started
+    fps: f32,
+    steps_per_second: f32,
+}
+
+impl OverlayRates {
+    const WINDOW: f64 = 0.5; // seconds between updates of the rates
+}
+
+pub fn stats_overlay(
+    egui_context: ResMut<EguiContext>,
+    time: Res<Time>,
+    state: Res<SimulationState>,
+    unit_system: Res<units::UnitSystem>,
+    mut rates: Local<OverlayRates>,
+) {
+    let scale = units::ReducedUnits::of(&state);
+    let show = |value| unit_system.format(&scale, units::Quantity::Time, value);
+
+    let now = time.seconds_since_startup();
+    rates.frames += 1;
+    let elapsed = now - rates.window_start;
+    if elapsed >= OverlayRates::WINDOW {
+        rates.fps = (rates.frames as f64 / elapsed) as f32;
+        // steps go back when rewinding
+        let steps = state.steps.saturating_sub(rates.steps_at_start);
+        rates.steps_per_second = (steps as f64 / elapsed) as f32;
+
+        rates.window_start = now;
+        rates.frames = 0;
+        rates.steps_at_start = state.steps;
+    }
+
+    egui::Area::new("stats overlay")
+        .anchor(egui::Align2::RIGHT_TOP, [-10.0, 10.0])
+        .show(egui_context.ctx(), |ui| {
+            ui.label(format!("FPS: {:.1}", rates.fps));
+            ui.label(format!("Steps/s: {:.0}", rates.steps_per_second));
+            ui.label(format!("N: {}", state.particles.len()));
+            ui.label(format!("dt: {}", show(state.dt)));
+            ui.label(format!("Simulated time: {}", show(state.time)));
+            ui.label(format!("Wall clock: {:.1} s", now));
+        });
+}
+
+// x, y and z arrows in the lower left corner, turned with the camera
+// Arrows pointing away from the camera are dimmed, with the floor grid its spacing is shown too
+pub fn axis_gizmo(
+    egui_context: ResMut<EguiContext>,
+    box_view: Res<render_systems::BoxView>,
+    state: Res<SimulationState>,
+    camera: Query<&Transform, With<FlyCam>>,
+) {
+    const LENGTH: f32 = 40.0; // of an arrow pointing across the screen, in points
+    const MARGIN: f32 = 60.0;
+
+    let rotation = match camera.iter().next() {
+        Some(trans) if box_view.axis_gizmo => trans.rotation,
+        _ => return,
+    };
+    let ctx = egui_context.ctx();
+    let screen = ctx.input().screen_rect();
+    let painter = egui::Painter::new(
+        ctx.clone(),
+        egui::LayerId::new(egui::Order::Background, egui::Id::new("axis gizmo")),
+        screen,
+    );
+    let origin = egui::pos2(screen.left() + MARGIN, screen.bottom() - MARGIN);
+
+    let axes = [
+        ("X", Vec3::X, egui::Color32::from_rgb(230, 80, 80)),
+        ("Y", Vec3::Y, egui::Color32::from_rgb(80, 200, 80)),
+        ("Z", Vec3::Z, egui::Color32::from_rgb(90, 140, 240)),
+    ];
+    // the farthest first so the nearer arrows are drawn over it
+    let mut in_view: Vec<(&str, Vec3, egui::Color32)> = axes
+        .iter()
+        .map(|&(label, axis, color)| (label, rotation.inverse() * axis, color))
+        .collect();
+    in_view.sort_by(|a, b| {
+        a.1.z
+            .partial_cmp(&b.1.z)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    for &(label, dir, color) in in_view.iter() {
+        // the camera looks along -z, screen y points down
+        let color = if dir.z > 0.0 {
+            color.linear_multiply(0.4)
+        } else {
+            color
+        };
+        let arrow = egui::vec2(dir.x, -dir.y) * LENGTH;
+        painter.arrow(origin, arrow, egui::Stroke::new(2.0, color));
+        painter.text(
+            origin + arrow * 1.25,
+            egui::Align2::CENTER_CENTER,
+            label,
+            egui::TextStyle::Body,
+            color,
+        );
+    }
+    if box_view.floor_grid {
+        painter.text(
+            origin + egui::vec2(0.0, MARGIN - 10.0),
+            egui::Align2::CENTER_BOTTOM,
+            format!("grid {:.3}", state.get_grid_unit_size()),
+            egui::TextStyle::Small,
+            egui::Color32::GRAY,
+        );
+    }
+}
@@ -0,0 +1,6 @@
+// Simulation core
+// Particles, forces, integration and analysis, independent of any renderer
+// Vectors are glam types, the same ones bevy re-exports
+mod par;
+pub mod ring_buffer;
+pub mod state;
@@ -0,0 +1,145 @@
+// Parallel iteration through rayon with the parallel feature, the default
+// Without it the same calls run on plain iterators, for a single threaded build
+#[cfg(feature = "parallel")]
+pub use rayon::prelude::*;
+
+#[cfg(not(feature = "parallel"))]
+pub use sequential::*;
+
+#[cfg(not(feature = "parallel"))]
+mod sequential {
+    // Collections rayon iterates over by value, here they give plain iterators
+    pub trait IntoParallelIterator {
+        type Item;
+        type Iter: Iterator<Item = Self::Item>;
+        fn into_par_iter(self) -> Self::Iter;
+    }
+
+    impl<T> IntoParallelIterator for Vec<T> {
+        type Item = T;
+        type Iter = std::vec::IntoIter<T>;
+        fn into_par_iter(self) -> Self::Iter {
+            self.into_iter()
+        }
+    }
+
+    impl<'a, T> IntoParallelIterator for &'a Vec<T> {
+        type Item = &'a T;
+        type Iter = std::slice::Iter<'a, T>;
+        fn into_par_iter(self) -> Self::Iter {
+            self.iter()
+        }
+    }
+
+    impl<'a, T> IntoParallelIterator for &'a mut Vec<T> {
+        type Item = &'a mut T;
+        type Iter = std::slice::IterMut<'a, T>;
+        fn into_par_iter(self) -> Self::Iter {
+            self.iter_mut()
+        }
+    }
+
+    impl<'a, T> IntoParallelIterator for &'a [T] {
+        type Item = &'a T;
+        type Iter = std::slice::Iter<'a, T>;
+        fn into_par_iter(self) -> Self::Iter {
+            self.iter()
+        }
+    }
+
+    impl<'a, T> IntoParallelIterator for &'a mut [T] {
+        type Item = &'a mut T;
+        type Iter = std::slice::IterMut<'a, T>;
+        fn into_par_iter(self) -> Self::Iter {
+            self.iter_mut()
+        }
+    }
+
+    // Tuples of collections are zipped, like rayon's MultiZip
+    pub struct MultiZip<T>(T);
+
+    macro_rules! multizip {
+        ($($T:ident $t:ident),+) => {
+            impl<$($T: IntoParallelIterator),+> IntoParallelIterator for ($($T,)+) {
+                type Item = ($($T::Item,)+);
+                type Iter = MultiZip<($($T::Iter,)+)>;
+                fn into_par_iter(self) -> Self::Iter {
+                    let ($($t,)+) = self;
+                    MultiZip(($($t.into_par_iter(),)+))
+                }
+            }
+
+            impl<$($T: Iterator),+> Iterator for MultiZip<($($T,)+)> {
+                type Item = ($($T::Item,)+);
+                fn next(&mut self) -> Option<Self::Item> {
+                    let ($($t,)+) = &mut self.0;
+                    Some(($($t.next()?,)+))
+                }
+            }
+        };
+    }
+
+    multizip!(A a, B b);
+    multizip!(A a, B b, C c);
+    multizip!(A a, B b, C c, D d);
+    multizip!(A a, B b, C c, D d, E e);
+
+    pub trait IntoParallelRefIterator<'a> {
+        type Iter: Iterator;
+        fn par_iter(&'a self) -> Self::Iter;
+    }
+
+    impl<'a, I: 'a + ?Sized> IntoParallelRefIterator<'a> for I
+    where
+        &'a I: IntoIterator,
+    {
+        type Iter = <&'a I as IntoIterator>::IntoIter;
+        fn par_iter(&'a self) -> Self::Iter {
+            self.into_iter()
+        }
+    }
+
+    pub trait IntoParallelRefMutIterator<'a> {
+        type Iter: Iterator;
+        fn par_iter_mut(&'a mut self) -> Self::Iter;
+    }
+
+    impl<'a, I: 'a + ?Sized> IntoParallelRefMutIterator<'a> for I
+    where
+        &'a mut I: IntoIterator,
+    {
+        type Iter = <&'a mut I as IntoIterator>::IntoIter;
+        fn par_iter_mut(&'a mut self) -> Self::Iter {
+            self.into_iter()
+        }
+    }
+
+    // Methods of rayon's parallel iterators that plain iterators don't have
+    pub trait ParallelIterator: Iterator + Sized {
+        // Reuses the allocation of target
+        fn collect_into_vec(self, target: &mut Vec<Self::Item>) {
+            target.clear();
+            target.extend(self);
+        }
+
+        fn flat_map_iter<U, F>(self, f: F) -> std::iter::FlatMap<Self, U, F>
+        where
+            U: IntoIterator,
+            F: FnMut(Self::Item) -> U,
+        {
+            self.flat_map(f)
+        }
+    }
+
+    impl<I: Iterator> ParallelIterator for I {}
+
+    pub trait ParallelExtend<T> {
+        fn par_extend<I: IntoIterator<Item = T>>(&mut self, iter: I);
+    }
+
+    impl<T> ParallelExtend<T> for Vec<T> {
+        fn par_extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+            self.extend(iter);
+        }
+    }
+}
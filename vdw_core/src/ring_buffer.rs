@@ -0,0 +1,94 @@
+use std::collections::vec_deque::{Drain, Iter, IterMut};
+use std::collections::VecDeque;
+use std::iter::Skip;
+
+#[derive(Clone)]
+pub struct RingBuffer<T> {
+    data: VecDeque<T>,
+    capacity: usize,
+}
+
+impl<T> RingBuffer<T> {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            data: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    // Push onto the ring buffer, overwrite the oldest entry when at capacity
+    // Return the overwritten value
+    pub fn push(&mut self, val: T) -> Option<T> {
+        self.data.push_back(val);
+        // >= so a buffer resized to zero capacity stays empty
+        if self.data.len() >= self.capacity {
+            self.data.pop_front()
+        } else {
+            None
+        }
+    }
+
+    // Change the capacity, the oldest entries are dropped if they no longer fit
+    pub fn resize(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        // same as push, the buffer holds one less entry than its capacity
+        while !self.data.is_empty() && self.data.len() >= capacity {
+            self.data.pop_front();
+        }
+    }
+
+    // Pop the newest value
+    pub fn pop(&mut self) -> Option<T> {
+        self.data.pop_back()
+    }
+
+    // Peak on the newest value
+    pub fn peak(&self) -> Option<&T> {
+        self.data.back()
+    }
+
+    pub fn peak_mut(&mut self) -> Option<&mut T> {
+        self.data.back_mut()
+    }
+
+    // Iterator implementation
+    // old to new
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.data.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        self.data.iter_mut()
+    }
+
+    // The newest k entries, old to new
+    pub fn last_n(&self, k: usize) -> Skip<Iter<'_, T>> {
+        self.data.iter().skip(self.data.len().saturating_sub(k))
+    }
+
+    // Remove all entries, old to new
+    // The capacity stays the same
+    pub fn drain(&mut self) -> Drain<'_, T> {
+        self.data.drain(..)
+    }
+
+    // Getters
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl<T: Clone> RingBuffer<T> {
+    // Copy the entries out, old to new
+    pub fn to_vec(&self) -> Vec<T> {
+        self.data.iter().cloned().collect()
+    }
+}
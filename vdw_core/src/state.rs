@@ -0,0 +1,2736 @@
+pub mod anneal;
+pub mod bond;
+pub mod cluster;
+pub mod coexistence;
+pub mod collision;
+pub mod config;
+pub mod console;
+pub mod dipole;
+pub mod electrostatics;
+pub mod error;
+pub mod experiment;
+pub mod external_field;
+pub mod field;
+pub mod group;
+pub mod heat_flux;
+pub mod integrator;
+pub mod interface;
+pub mod kinetic;
+pub mod logger;
+pub mod minimize;
+pub mod neighbor_check;
+pub mod network;
+pub mod observables;
+pub mod obstacle;
+pub mod order;
+pub mod particle;
+pub mod percolation;
+pub mod physics;
+pub mod preset;
+pub mod probe;
+pub mod profile;
+pub mod region;
+pub mod replica;
+pub mod rewind;
+pub mod rigid_cluster;
+pub mod schedule;
+pub mod seed;
+pub mod sensitivity;
+pub mod sim_space;
+pub mod snapshot;
+pub mod state_generator;
+pub mod stress;
+pub mod sweep;
+pub mod timing;
+pub mod trail;
+pub mod units;
+pub mod watchdog;
+pub mod worker;
+
+use crate::par::*;
+use bond::Bond;
+use error::*;
+use external_field::ExternalField;
+use glam::{Mat3, Vec3};
+use particle::*;
+use probe::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::StandardNormal;
+use rigid_cluster::RigidCluster;
+use sim_space::*;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::ring_buffer::RingBuffer;
+
+/////////////////////////////////////////////////
+// Contains all simulation initial conditions
+// Need to be compiled into a State to be useable
+//
+#[derive(Clone)]
+pub struct SimulationPrototype {
+    bound: Boundary,       // location of the 6 walls of the box
+    face_rates: FaceRates, // speeds at which the walls move outwards
+
+    grid_unit_size: f32,        // how big a grid point is
+    grid_reach: usize,          // particle interaction cutoff
+    cutoff_radius: Option<f32>, // replaces the cutoff of the grid, which then picks its own squares
+    switch_start: Option<f32>,  // interactions are smoothly switched off beyond this distance
+    potential: physics::PotentialModel,
+    neighbor_rule: physics::NeighborRule, // which particles count as neighbors
+    coulomb: electrostatics::Coulomb, // acts between charged particles within the interaction range
+    dipolar: dipole::Dipolar, // acts between particles with dipoles within the interaction range
+    integrator: integrator::Integrator,
+    seed: Option<u64>, // seed of the random numbers used while stepping, random if not set
+    dt: f32,           // time step
+    steps_per_frame: usize,
+    frame_budget: Option<f32>, // milliseconds of stepping per frame, overrides steps_per_frame
+    pressure_sampling_period: f32, // pressure is averaged over this period of time
+    history_capacity: usize,   // number of frames kept in history
+    external_fields: Vec<Box<dyn ExternalField>>, // act on all particles
+    target_temp: f32,          // temperature the thermostat drives the system towards
+    inject_rate: f32,          // strength of the thermostat
+    remove_drift: bool,        // periodically cancel the net momentum and angular momentum
+    particles: Vec<Particle>,
+    inert_fraction: f32, // share of the particles placed by initializers that only repel
+    mass_distribution: Option<state_generator::Distribution>, // masses of the particles placed by initializers
+    radius_distribution: Option<state_generator::Distribution>, // radii of the particles placed by initializers
+    bonds: Vec<Bond>, // harmonic springs between pairs of particles
+    probes: Vec<Probe>,
+    obstacles: Vec<obstacle::Obstacle>, // immovable geometry inside the box
+    rigid_clusters: Vec<Vec<usize>>,    // indices of particles that move as one rigid body
+    minimization: Option<(usize, f32)>, // max iterations and force tolerance of the relaxation at compile time
+    schedule: schedule::Schedule,       // timed parameter changes
+}
+
+impl Default for SimulationPrototype {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimulationPrototype {
+    const MAX_PACKING: f32 = 0.74; // volume fraction of close packed spheres
+    const MIN_CUTOFF: f32 = 2.0; // in units of the length scale of the potential, for attractive ones
+    const MIN_BOND_STRETCH: f32 = 0.8; // shortest starting length of a bond, in units of its rest length
+    const MEMORY_WARNING: usize = 1 << 30; // bytes
+
+    // Create a new StatePrototype with default settings
+    // Parameters can be changed using builders
+    pub fn new() -> Self {
+        Self {
+            bound: Boundary::new(),
+            face_rates: FaceRates::default(),
+
+            grid_unit_size: 1.0,
+            grid_reach: 1,
+            cutoff_radius: None,
+            switch_start: None,
+            potential: physics::PotentialModel::default(),
+            neighbor_rule: physics::NeighborRule::default(),
+            coulomb: electrostatics::Coulomb::default(),
+            dipolar: dipole::Dipolar::default(),
+            integrator: integrator::Integrator::default(),
+            seed: None,
+            dt: 0.001,
+            steps_per_frame: 20,
+            frame_budget: None,
+            pressure_sampling_period: VDWSimulation::PRESSURE_SAMPLING_PERIOD,
+            history_capacity: VDWSimulation::HISTORY_CAPACITY,
+            external_fields: Vec::new(),
+            target_temp: 0.0,
+            inject_rate: 0.0,
+            remove_drift: false,
+            particles: Vec::new(),
+            inert_fraction: 0.0,
+            mass_distribution: None,
+            radius_distribution: None,
+            bonds: Vec::new(),
+            probes: Vec::new(),
+            obstacles: Vec::new(),
+            rigid_clusters: Vec::new(),
+            minimization: None,
+            schedule: schedule::Schedule::default(),
+        }
+    }
+
+    ///////////////////////////
+    // Getters
+    //
+    pub fn get_bound(&self) -> Boundary {
+        self.bound
+    }
+
+    pub fn get_obstacles(&self) -> &[obstacle::Obstacle] {
+        &self.obstacles
+    }
+
+    pub fn get_minimization(&self) -> Option<(usize, f32)> {
+        self.minimization
+    }
+
+    /////////////////////////////
+    // Builders
+    // Use these after initialization to specify relevant attributes
+    //
+
+    //
+    // Builders for Boundary
+    //
+
+    // Bound
+    pub fn set_bound_x(mut self, val: f32) -> Self {
+        self.bound.set_length(0, val);
+        self
+    }
+
+    pub fn set_bound_y(mut self, val: f32) -> Self {
+        self.bound.set_length(1, val);
+        self
+    }
+    pub fn set_bound_z(mut self, val: f32) -> Self {
+        self.bound.set_length(2, val);
+        self
+    }
+    // Place the box anywhere, the lengths set above are replaced
+    pub fn set_bound_corners(mut self, lo: Vec3, hi: Vec3) -> Self {
+        self.bound.set_corners(lo, hi);
+        self
+    }
+    // Keep each wall moving outwards at its own rate, negative rates move it inwards
+    pub fn set_face_rates(mut self, lo: Vec3, hi: Vec3) -> Self {
+        self.face_rates = FaceRates { lo, hi };
+        self
+    }
+    pub fn set_wall_model(mut self, wall_model: WallModel) -> Self {
+        self.bound.wall_model = wall_model;
+        self
+    }
+    // Make some faces attract the particles, see WallAdhesion
+    pub fn set_wall_adhesion(mut self, adhesion: WallAdhesion) -> Self {
+        self.bound.adhesion = Some(adhesion);
+        self
+    }
+    // Slide the periodic images above and below the box along x at rate times its height,
+    // shearing the particles into the flow rate * (y - center y) along x, see LeesEdwards
+    // Thermostats act on the full velocities, so strong thermostats slow the flow down
+    pub fn set_lees_edwards(mut self, rate: f32) -> Self {
+        self.bound.lees_edwards = Some(LeesEdwards::new(rate));
+        self
+    }
+
+    //
+    // Builder for Grid
+    //
+    pub fn set_grid_unit_size(mut self, unit_size: f32) -> Self {
+        self.grid_unit_size = unit_size;
+        self
+    }
+
+    pub fn set_grid_reach(mut self, reach: usize) -> Self {
+        self.grid_reach = reach;
+        self
+    }
+
+    // Cut interactions off at radius, the grid squares are sized to match
+    // Overrides grid_unit_size and grid_reach
+    pub fn set_cutoff_radius(mut self, radius: f32) -> Self {
+        self.cutoff_radius = Some(radius);
+        self
+    }
+
+    // Grid the compiled simulation sorts the particles into
+    // internal helper function
+    fn grid(&self) -> Grid {
+        match self.cutoff_radius {
+            Some(radius) => Grid::with_cutoff(radius),
+            None => Grid::new(self.grid_unit_size, self.grid_reach),
+        }
+    }
+
+    // Switch interactions off smoothly from r_switch up to the cutoff instead of abruptly at it
+    pub fn set_switch_start(mut self, r_switch: f32) -> Self {
+        self.switch_start = Some(r_switch);
+        self
+    }
+
+    // Pair potential between particles, Lennard-Jones by default
+    pub fn set_potential(mut self, potential: physics::PotentialModel) -> Self {
+        self.potential = potential;
+        self
+    }
+
+    // Which particles count as neighbors for the neighbor counts and the cluster analysis
+    // Neighbors are only looked for within the interaction range
+    pub fn set_neighbor_rule(mut self, neighbor_rule: physics::NeighborRule) -> Self {
+        self.neighbor_rule = neighbor_rule;
+        self
+    }
+
+    // Electrostatics between charged particles, particles carry no charge by default
+    pub fn set_coulomb(mut self, coulomb: electrostatics::Coulomb) -> Self {
+        self.coulomb = coulomb;
+        self
+    }
+
+    // Interactions between particles with dipoles and the applied field they align with,
+    // particles carry no dipole by default
+    pub fn set_dipolar(mut self, dipolar: dipole::Dipolar) -> Self {
+        self.dipolar = dipolar;
+        self
+    }
+
+    //
+    // Builder for other values
+    //
+    pub fn set_integrator(mut self, integrator: integrator::Integrator) -> Self {
+        self.integrator = integrator;
+        self
+    }
+
+    // Makes stochastic runs reproducible
+    pub fn set_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn set_dt(mut self, dt: f32) -> Self {
+        self.dt = dt;
+        self
+    }
+
+    pub fn set_steps_per_frame(mut self, spf: usize) -> Self {
+        self.steps_per_frame = spf;
+        self
+    }
+
+    // Run as many steps as fit in ms milliseconds each frame instead of a fixed number
+    pub fn set_frame_budget(mut self, ms: f32) -> Self {
+        self.frame_budget = Some(ms);
+        self
+    }
+
+    // Average the wall impulses over sampling_period units of time to measure pressure
+    pub fn set_pressure_sampling_period(mut self, sampling_period: f32) -> Self {
+        self.pressure_sampling_period = sampling_period;
+        self
+    }
+
+    // Number of frames of energy, pressure and probe measurements that are kept
+    pub fn set_history_capacity(mut self, capacity: usize) -> Self {
+        self.history_capacity = capacity;
+        self
+    }
+
+    //
+    // Builders for external fields
+    //
+    pub fn add_external_field(mut self, field: Box<dyn ExternalField>) -> Self {
+        self.external_fields.push(field);
+        self
+    }
+
+    // Shorthand for a constant acceleration on all particles
+    pub fn set_ext_a(self, ext_a: Vec3) -> Self {
+        self.add_external_field(Box::new(external_field::Gravity::new(ext_a)))
+    }
+
+    //
+    // Builders for the thermostat
+    //
+    pub fn set_target_temp(mut self, temp: f32) -> Self {
+        self.target_temp = temp;
+        self
+    }
+
+    pub fn set_inject_rate(mut self, rate: f32) -> Self {
+        self.inject_rate = rate;
+        self
+    }
+
+    pub fn set_remove_drift(mut self, remove_drift: bool) -> Self {
+        self.remove_drift = remove_drift;
+        self
+    }
+
+    pub fn set_particles(mut self, particles: Vec<Particle>) -> Self {
+        self.particles = particles;
+        self
+    }
+
+    // Charge the current particles by cycling through the pattern, [1.0, -1.0] alternates the sign
+    pub fn set_charges(mut self, pattern: &[f32]) -> Self {
+        if pattern.is_empty() {
+            return self;
+        }
+        self.particles = self
+            .particles
+            .into_iter()
+            .zip(pattern.iter().cycle())
+            .map(|(particle, &charge)| particle.set_charge(charge))
+            .collect();
+        self
+    }
+
+    // Dipoles along the body axes of the current particles, cycling through the pattern like set_charges
+    // The particles keep the orientations they have, the initializers turn them at random
+    pub fn set_dipoles(mut self, pattern: &[Vec3]) -> Self {
+        if pattern.is_empty() {
+            return self;
+        }
+        self.particles = self
+            .particles
+            .into_iter()
+            .zip(pattern.iter().cycle())
+            .map(|(particle, &mu)| particle.set_dipole(mu.x, mu.y, mu.z))
+            .collect();
+        self
+    }
+
+    // Well depths of the current particles relative to the potential, cycling through the pattern like set_charges
+    // Unlike pairs mix their depths with the geometric mean
+    pub fn set_well_depths(mut self, pattern: &[f32]) -> Self {
+        if pattern.is_empty() {
+            return self;
+        }
+        self.particles = self
+            .particles
+            .into_iter()
+            .zip(pattern.iter().cycle())
+            .map(|(particle, &epsilon)| particle.set_epsilon(epsilon))
+            .collect();
+        self
+    }
+
+    // Radii of the current particles, cycling through the pattern like set_charges
+    // Pairs sit at the bottom of the well when they touch, at the sum of their radii
+    pub fn set_radii(mut self, pattern: &[f32]) -> Self {
+        if pattern.is_empty() {
+            return self;
+        }
+        self.particles = self
+            .particles
+            .into_iter()
+            .zip(pattern.iter().cycle())
+            .map(|(particle, &radius)| particle.set_radius(radius))
+            .collect();
+        self
+    }
+
+    // Fix the current particles inside the box with corners lo and hi in place, for walls and seeds
+    // They still exert their forces on the rest
+    pub fn set_fixed_region(mut self, lo: Vec3, hi: Vec3) -> Self {
+        let (lo, hi) = (lo.min(hi), lo.max(hi));
+        self.particles = self
+            .particles
+            .into_iter()
+            .map(|particle| {
+                let pos = particle.get_pos();
+                if pos.cmpge(lo).all() && pos.cmple(hi).all() {
+                    particle.set_fixed(true)
+                } else {
+                    particle
+                }
+            })
+            .collect();
+        self
+    }
+
+    //
+    // Builders for Bonds
+    //
+    pub fn set_bonds(mut self, bonds: Vec<Bond>) -> Self {
+        self.bonds = bonds;
+        self
+    }
+
+    // Connect particle i and particle j with a spring of constant k and rest length r0
+    pub fn add_bond(mut self, i: usize, j: usize, k: f32, r0: f32) -> Self {
+        self.bonds.push(Bond::new(i, j, k, r0));
+        self
+    }
+
+    //
+    // Builder for Obstacles
+    // Add obstacles before initializing, initializers leave out the particles placed inside them
+    //
+    pub fn add_obstacle(mut self, obstacle: obstacle::Obstacle) -> Self {
+        self.obstacles.push(obstacle);
+        self
+    }
+
+    // Fill the current box with the obstacles of a porous layout, set the box first
+    pub fn add_pores(mut self, layout: obstacle::PoreLayout) -> Self {
+        self.obstacles.extend(layout.obstacles(&self.bound));
+        self
+    }
+
+    // Confine the particles to a slit channel of the given width across the middle of the y axis
+    pub fn initialize_channel(self, width: f32) -> Self {
+        self.add_pores(obstacle::PoreLayout::Slit { width })
+    }
+
+    //
+    // Builder for Rigid Clusters
+    // The members keep their arrangement at compile time
+    //
+    pub fn add_rigid_cluster(mut self, members: Vec<usize>) -> Self {
+        self.rigid_clusters.push(members);
+        self
+    }
+
+    //
+    // Builder for energy minimization
+    // Relax the particles before dynamics begin, see SimulationState::minimize_energy
+    // Set before initializing so overlapping particles are kept and pushed apart instead of deleted
+    //
+    pub fn set_minimization(mut self, max_iters: usize, tolerance: f32) -> Self {
+        self.minimization = Some((max_iters, tolerance));
+        self
+    }
+
+    //
+    // Builder for the schedule
+    // Events run in the order they are added
+    //
+    pub fn add_event(mut self, event: schedule::Event) -> Self {
+        self.schedule.push(event);
+        self
+    }
+
+    //
+    // Builder for Probes
+    //
+    pub fn add_probe(mut self, name: &str, target: ProbeTarget, quantity: ProbeQuantity) -> Self {
+        self.probes.push(Probe::new(
+            name.to_string(),
+            target,
+            quantity,
+            self.history_capacity,
+        ));
+        self
+    }
+
+    ////////////////
+    // Compilation
+    // Check for consistency and create a VDWSimulation
+    // Every problem found names the offending values and how to fix them
+    // Setups that are valid but likely not what was meant compile with warnings, see warnings
+    //
+    pub fn compile(&self) -> Result<VDWSimulation, InvalidParamError> {
+        let mut errors = Vec::new();
+        let mut error = |kind: ErrorKind, message: String| errors.push(Problem::new(kind, message));
+
+        let lengths: [f32; 3] = self.bound.size().into();
+        for (axis, &length) in ["x", "y", "z"].iter().zip(lengths.iter()) {
+            if length.is_nan() || length < Boundary::MIN_LEN {
+                error(
+                    ErrorKind::Bound,
+                    format!(
+                        "bound.{} = {:?} < MIN_LEN {:?}, make the box at least {:?} long along {}",
+                        axis,
+                        length,
+                        Boundary::MIN_LEN,
+                        Boundary::MIN_LEN,
+                        axis
+                    ),
+                );
+            }
+        }
+        if let Some(adhesion) = self.bound.adhesion.filter(|adhesion| !adhesion.is_valid()) {
+            error(
+                ErrorKind::Adhesion,
+                format!(
+                    "bound.adhesion has strength = {:?}, sigma = {:?}, cutoff = {:?}, \
+                     it needs strength >= 0, sigma > 0 and a cutoff beyond sigma",
+                    adhesion.strength, adhesion.sigma, adhesion.cutoff
+                ),
+            );
+        }
+        if let Some(radius) = self.cutoff_radius {
+            if radius.is_nan() || radius <= 0.0 {
+                error(
+                    ErrorKind::Cutoff,
+                    format!(
+                        "cutoff_radius = {:?} <= 0, interactions need a positive range",
+                        radius
+                    ),
+                );
+            }
+        } else if self.grid_unit_size < 0.0 {
+            error(
+                ErrorKind::UnitSize,
+                format!(
+                    "grid_unit_size = {:?} < 0, grid squares need a positive size",
+                    self.grid_unit_size
+                ),
+            );
+        }
+        if self.cutoff_radius.is_none() && self.grid_reach < 1 {
+            error(
+                ErrorKind::Reach,
+                "grid_reach = 0, interactions need to reach at least 1 grid square".to_string(),
+            );
+        }
+        let range = self.grid().get_range();
+        let shortest = lengths.iter().copied().fold(f32::INFINITY, f32::min);
+        if self.bound.wall_model.is_periodic() && range >= 0.5 * shortest {
+            error(
+                ErrorKind::Bound,
+                format!(
+                    "interaction range {:?} >= half of the shortest side {:?} of a periodic box,                      particles would meet more than one image of each other, use a cutoff_radius                      below {:?} or a larger box",
+                    range,
+                    shortest,
+                    0.5 * shortest
+                ),
+            );
+        }
+        if let Some(shear) = self.bound.lees_edwards {
+            if !self.bound.wall_model.is_periodic() {
+                error(
+                    ErrorKind::Bound,
+                    "lees_edwards needs wall_model = Periodic, the images only slide past \
+                     periodic faces"
+                        .to_string(),
+                );
+            }
+            if !shear.rate.is_finite() {
+                error(
+                    ErrorKind::Bound,
+                    format!(
+                        "lees_edwards rate = {:?}, use a finite shear rate",
+                        shear.rate
+                    ),
+                );
+            }
+            if let integrator::Integrator::Brownian { .. } = self.integrator {
+                error(
+                    ErrorKind::Bound,
+                    "lees_edwards with the Brownian integrator, overdamped particles have no \
+                     velocity to carry the flow, use Verlet or Langevin"
+                        .to_string(),
+                );
+            }
+        }
+        if self.bound.wall_model.is_periodic() && self.bound.adhesion.is_some() {
+            error(
+                ErrorKind::Bound,
+                "bound.adhesion on a periodic box, faces that wrap around have no wall to \
+                 adhere to, remove the adhesion or use another wall_model"
+                    .to_string(),
+            );
+        }
+        if let Some(r_switch) = self
+            .switch_start
+            .filter(|&r_switch| r_switch <= 0.0 || r_switch >= range)
+        {
+            error(
+                ErrorKind::Switch,
+                format!(
+                    "switch_start = {:?} is not between 0 and the interaction range {:?}, \
+                     start switching inside the range or leave it out",
+                    r_switch, range
+                ),
+            );
+        }
+        if !self.potential.is_valid() {
+            error(
+                ErrorKind::Potential,
+                format!(
+                    "potential = {:?}, its parameters must be above 0",
+                    self.potential
+                ),
+            );
+        }
+        if !self.neighbor_rule.is_valid() {
+            error(
+                ErrorKind::Neighbors,
+                format!(
+                    "neighbors distance = {:?}, nearest = {}, both must be above 0",
+                    self.neighbor_rule.distance, self.neighbor_rule.nearest
+                ),
+            );
+        }
+        if self.neighbor_rule.distance > range {
+            error(
+                ErrorKind::Neighbors,
+                format!(
+                    "neighbors distance = {:?} > interaction range {:?}, pairs beyond the range are \
+                     never found, use a distance up to {:?} or a larger cutoff_radius",
+                    self.neighbor_rule.distance, range, range
+                ),
+            );
+        }
+        if !self.coulomb.is_valid() {
+            error(
+                ErrorKind::Coulomb,
+                format!(
+                    "electrostatics strength = {:?}, damping = {:?}, both must be 0 or more",
+                    self.coulomb.strength, self.coulomb.damping
+                ),
+            );
+        }
+        if !self.dipolar.is_valid() {
+            error(
+                ErrorKind::Dipole,
+                format!(
+                    "dipoles strength = {:?}, field = {:?}, the strength must be 0 or more and \
+                     both finite",
+                    self.dipolar.strength, self.dipolar.field
+                ),
+            );
+        }
+        if let Some((i, p, count)) =
+            first_offender(&self.particles, |p| !p.get_dipole().is_finite())
+        {
+            error(
+                ErrorKind::Dipole,
+                format!(
+                    "particles[{}] has dipole = {}{}, it must be finite",
+                    i,
+                    p.get_dipole(),
+                    and_more(count)
+                ),
+            );
+        }
+        if !self.integrator.is_valid() {
+            error(
+                ErrorKind::Integrator,
+                format!(
+                    "integrator = {:?}, Langevin needs gamma >= 0 and Brownian gamma > 0",
+                    self.integrator
+                ),
+            );
+        }
+        if self.dt.is_nan() || self.dt <= 0.0 {
+            error(
+                ErrorKind::Dt,
+                format!(
+                    "dt = {:?} <= 0, use a small positive time step such as 0.001",
+                    self.dt
+                ),
+            );
+        }
+        if self.target_temp < 0.0 {
+            error(
+                ErrorKind::TargTemp,
+                format!(
+                    "target_temp = {:?} < 0, temperatures are 0 or more",
+                    self.target_temp
+                ),
+            );
+        }
+        if self.inject_rate < 0.0 {
+            error(
+                ErrorKind::InjectRate,
+                format!(
+                    "inject_rate = {:?} < 0, use 0 to turn the thermostat off",
+                    self.inject_rate
+                ),
+            );
+        }
+        if let Some(gain) = self.thermostat_gain().filter(|&gain| gain >= 2.0) {
+            error(
+                ErrorKind::InjectRate,
+                format!(
+                    "inject_rate = {:?} with target_temp = {:?} and frames of {} steps of dt = {:?} \
+                     gives a thermostat gain of {:.2} >= 2, the temperature swings further from the \
+                     target every frame; use inject_rate < {:.3}",
+                    self.inject_rate,
+                    self.target_temp,
+                    self.steps_per_frame,
+                    self.dt,
+                    gain,
+                    self.inject_rate_for_gain(2.0)
+                ),
+            );
+        }
+        if self.steps_per_frame == 0 {
+            error(
+                ErrorKind::StepsPerFrame,
+                "steps_per_frame = 0, step at least once per frame".to_string(),
+            );
+        }
+        if let Some(ms) = self.frame_budget.filter(|&ms| ms <= 0.0) {
+            error(
+                ErrorKind::FrameBudget,
+                format!(
+                    "frame_budget = {:?} ms <= 0, give the milliseconds of stepping per frame \
+                     or leave it out",
+                    ms
+                ),
+            );
+        }
+        if self.pressure_sampling_period <= 0.0 {
+            error(
+                ErrorKind::PressureSampling,
+                format!(
+                    "pressure_sampling_period = {:?} <= 0, average the pressure over a positive time",
+                    self.pressure_sampling_period
+                ),
+            );
+        }
+        // the history ring buffers need room for at least one entry
+        if self.history_capacity < 2 {
+            error(
+                ErrorKind::HistoryCapacity,
+                format!(
+                    "history_capacity = {} < 2, keep at least 2 frames",
+                    self.history_capacity
+                ),
+            );
+        }
+
+        let distributions = [
+            ("mass_distribution", self.mass_distribution),
+            ("radius_distribution", self.radius_distribution),
+        ];
+        for (name, distribution) in distributions.iter() {
+            if let Some(distribution) = distribution.filter(|distribution| !distribution.is_valid())
+            {
+                error(
+                    ErrorKind::Particle,
+                    format!(
+                        "{} = {:?}, the values it gives must be above 0",
+                        name, distribution
+                    ),
+                );
+            }
+        }
+        if let Some((i, p, count)) = first_offender(&self.particles, |p| {
+            !self.bound.contains_position(p.get_pos())
+        }) {
+            error(
+                ErrorKind::Particle,
+                format!(
+                    "particles[{}] at {} is outside the box from {} to {}{}, \
+                     move it inside or enlarge the box",
+                    i,
+                    p.get_pos(),
+                    self.bound.lo_corner(),
+                    self.bound.hi_corner(),
+                    and_more(count)
+                ),
+            );
+        }
+        let scalars = [
+            ("mass", Particle::get_mass as fn(&Particle) -> f32, false),
+            ("radius", Particle::get_radius, false),
+            ("epsilon", Particle::get_epsilon, true),
+        ];
+        for &(name, value, zero_allowed) in scalars.iter() {
+            let invalid = |p: &Particle| {
+                let x = value(p);
+                x.is_nan() || x < 0.0 || (x == 0.0 && !zero_allowed)
+            };
+            if let Some((i, p, count)) = first_offender(&self.particles, invalid) {
+                error(
+                    ErrorKind::Particle,
+                    format!(
+                        "particles[{}] has {} = {:?}{}, it must be {} 0",
+                        i,
+                        name,
+                        value(p),
+                        and_more(count),
+                        if zero_allowed { "at least" } else { "above" }
+                    ),
+                );
+            }
+        }
+        let bad_inertia = |p: &Particle| {
+            let inertia = p.get_inertia();
+            !inertia.is_finite() || inertia.min_element() <= 0.0
+        };
+        if let Some((i, p, count)) = first_offender(&self.particles, bad_inertia) {
+            error(
+                ErrorKind::Particle,
+                format!(
+                    "particles[{}] has inertia = {}{}, its principal moments must be above 0",
+                    i,
+                    p.get_inertia(),
+                    and_more(count)
+                ),
+            );
+        }
+        for (k, obstacle) in self.obstacles.iter().enumerate() {
+            if !obstacle.is_valid() {
+                error(
+                    ErrorKind::Obstacle,
+                    format!(
+                        "obstacles[{}] = {:?}, spheres need a radius above 0 and boxes lo below hi",
+                        k, obstacle
+                    ),
+                );
+            } else if let Some((i, p, count)) =
+                first_offender(&self.particles, |p| obstacle.contains(p.get_pos()))
+            {
+                error(
+                    ErrorKind::Obstacle,
+                    format!(
+                        "obstacles[{}] covers particles[{}] at {}{}, move the obstacle or the particles",
+                        k,
+                        i,
+                        p.get_pos(),
+                        and_more(count)
+                    ),
+                );
+            }
+        }
+
+        let n = self.particles.len();
+        if let Some((k, bond, count)) = first_offender(&self.bonds, |bond| !bond.is_valid(n)) {
+            error(
+                ErrorKind::Bond,
+                format!(
+                    "bonds[{}] joins particles {} and {} with k = {:?}, r0 = {:?}{}, it needs two \
+                     different particles below {} and k, r0 of 0 or more",
+                    k,
+                    bond.i,
+                    bond.j,
+                    bond.k,
+                    bond.r0,
+                    and_more(count),
+                    n
+                ),
+            );
+        }
+        // coincident particles have no well defined pair force
+        let coincide = |bond: &Bond| {
+            bond.is_valid(n) && self.particles[bond.i].get_pos() == self.particles[bond.j].get_pos()
+        };
+        let compressed = |bond: &Bond| {
+            bond.is_valid(n)
+                && !coincide(bond)
+                && self.bond_length(bond) < Self::MIN_BOND_STRETCH * bond.r0
+        };
+        if let Some((k, bond, count)) = first_offender(&self.bonds, compressed) {
+            error(
+                ErrorKind::Bond,
+                format!(
+                    "bonds[{}] starts at length {:.4}, below {:.0}% of its rest length {:?}{}, the \
+                     spring and the pair repulsion throw the particles apart on the first step; \
+                     use fewer or shorter chains or a larger box",
+                    k,
+                    self.bond_length(bond),
+                    100.0 * Self::MIN_BOND_STRETCH,
+                    bond.r0,
+                    and_more(count)
+                ),
+            );
+        }
+        if let Some((k, bond, count)) = first_offender(&self.bonds, coincide) {
+            error(
+                ErrorKind::Bond,
+                format!(
+                    "bonds[{}] joins particles {} and {} at the same position {}{}, move them apart",
+                    k,
+                    bond.i,
+                    bond.j,
+                    self.particles[bond.i].get_pos(),
+                    and_more(count)
+                ),
+            );
+        }
+        for (k, probe) in self.probes.iter().enumerate() {
+            match probe.target {
+                ProbeTarget::Particle(i) if i >= n => error(
+                    ErrorKind::Probe,
+                    format!(
+                        "probes[{}] follows particles[{}] but there are {} particles",
+                        k, i, n
+                    ),
+                ),
+                ProbeTarget::Point(pos) if !self.bound.contains_position(pos) => error(
+                    ErrorKind::Probe,
+                    format!("probes[{}] at {} is outside the box", k, pos),
+                ),
+                _ => (),
+            }
+        }
+        for (k, event) in self.schedule.events().iter().enumerate() {
+            if let Err(message) = event.check() {
+                error(
+                    ErrorKind::Schedule,
+                    format!("events[{}] at step {}: {}", k, event.step, message),
+                );
+            }
+        }
+
+        // every particle can belong to at most one cluster and clusters need at least 2 members
+        let mut clustered = vec![false; n];
+        for (c, members) in self.rigid_clusters.iter().enumerate() {
+            if members.len() < 2 {
+                error(
+                    ErrorKind::RigidCluster,
+                    format!(
+                        "rigid_clusters[{}] has {} members, clusters need at least 2",
+                        c,
+                        members.len()
+                    ),
+                );
+            }
+            for &i in members.iter() {
+                if i >= n {
+                    error(
+                        ErrorKind::RigidCluster,
+                        format!(
+                            "rigid_clusters[{}] has particles[{}] but there are {} particles",
+                            c, i, n
+                        ),
+                    );
+                } else if clustered[i] {
+                    error(
+                        ErrorKind::RigidCluster,
+                        format!(
+                            "particles[{}] of rigid_clusters[{}] is already in another cluster, \
+                             particles can belong to one cluster only",
+                            i, c
+                        ),
+                    );
+                } else {
+                    clustered[i] = true;
+                }
+            }
+        }
+
+        // Confirm errors and return
+        if !errors.is_empty() {
+            Err(InvalidParamError::new(errors))
+        } else {
+            Ok(VDWSimulation::new(self))
+        }
+    }
+
+    // Current length of a valid bond
+    // internal helper function
+    fn bond_length(&self, bond: &Bond) -> f32 {
+        (self.particles[bond.i].get_pos() - self.particles[bond.j].get_pos()).length()
+    }
+
+    // Bottom of the pair well of the two particles of a valid bond, scaled with their sizes
+    // internal helper function
+    fn well_position(&self, bond: &Bond) -> Option<f32> {
+        let (scale, _) = self.particles[bond.i]
+            .get_site()
+            .mix(&self.particles[bond.j].get_site());
+        self.potential.well_position().map(|r| r * scale)
+    }
+
+    // Setups that compile but are likely not what was meant
+    pub fn warnings(&self) -> Vec<Problem> {
+        let mut warnings = Vec::new();
+        let mut warn =
+            |kind: ErrorKind, message: String| warnings.push(Problem::new(kind, message));
+
+        if let Some(gain) = self
+            .thermostat_gain()
+            .filter(|&gain| (1.0..2.0).contains(&gain))
+        {
+            warn(
+                ErrorKind::InjectRate,
+                format!(
+                    "inject_rate = {:?} with target_temp = {:?} gives a thermostat gain of {:.2} > 1, \
+                     the temperature overshoots the target every frame; inject_rate < {:.3} \
+                     approaches it smoothly",
+                    self.inject_rate,
+                    self.target_temp,
+                    gain,
+                    self.inject_rate_for_gain(1.0)
+                ),
+            );
+        }
+        let (range, sigma) = (self.grid().get_range(), self.potential.length_scale());
+        let attractive = !matches!(self.potential, physics::PotentialModel::SoftSphere { .. });
+        if attractive && range > 0.0 && range < Self::MIN_CUTOFF * sigma {
+            warn(
+                ErrorKind::Cutoff,
+                format!(
+                    "the interaction range {:?} is only {:.2} sigma of the potential \
+                     (sigma = {:.4}), most of the attraction is cut off; cutoff_radius = {:.3} \
+                     (2.5 sigma) keeps the well",
+                    range,
+                    range / sigma,
+                    sigma,
+                    2.5 * sigma
+                ),
+            );
+        }
+        if self.integrator.is_stochastic() && self.inject_rate > 0.0 {
+            warn(
+                ErrorKind::InjectRate,
+                format!(
+                    "inject_rate = {:?} is ignored by the {} integrator, its bath keeps the \
+                     temperature at target_temp",
+                    self.inject_rate,
+                    self.integrator.name()
+                ),
+            );
+        }
+        // bonded pairs keep their pair interaction, see bond.rs
+        let n = self.particles.len();
+        let inside_well = |bond: &Bond| {
+            bond.is_valid(n) && bond.r0 > 0.0 && bond.r0 < self.well_position(bond).unwrap_or(0.0)
+        };
+        if let Some((k, bond, count)) = first_offender(&self.bonds, inside_well) {
+            warn(
+                ErrorKind::Bond,
+                format!(
+                    "bonds[{}] has a rest length {:?}{} inside the pair repulsion, which bottoms \
+                     out at {:.4}; the spring and the repulsion of the bonded pair pull against \
+                     each other",
+                    k,
+                    bond.r0,
+                    and_more(count),
+                    self.well_position(bond).unwrap_or(0.0)
+                ),
+            );
+        }
+        if self.particles.is_empty() {
+            warn(
+                ErrorKind::Particle,
+                "there are no particles, add some with an initializer".to_string(),
+            );
+        }
+        let particle_volume: f32 = self
+            .particles
+            .iter()
+            .map(|p| 4.0 / 3.0 * std::f32::consts::PI * p.get_radius().powi(3))
+            .sum();
+        let packing = particle_volume / self.bound.get_volume();
+        if packing > Self::MAX_PACKING {
+            warn(
+                ErrorKind::Particle,
+                format!(
+                    "the particles fill {:.0}% of the box, more than the {:.0}% of close packing, \
+                     they overlap and will fly apart; use fewer particles or a larger box",
+                    100.0 * packing,
+                    100.0 * Self::MAX_PACKING
+                ),
+            );
+        }
+        let memory = self.estimated_memory();
+        if memory > Self::MEMORY_WARNING {
+            warn(
+                ErrorKind::Particle,
+                format!(
+                    "{} particles take up about {} MiB, expect slow frames; use fewer particles{}",
+                    self.particles.len(),
+                    memory >> 20,
+                    if self.grid_squares() * std::mem::size_of::<Vec<usize>>() > memory / 2 {
+                        " or larger grid squares"
+                    } else {
+                        ""
+                    }
+                ),
+            );
+        }
+        warnings
+    }
+
+    // Approximate number of bytes the compiled state takes up, see SimulationState::memory_size
+    // The buffers of the force calculation and the grid covering the box are included
+    pub fn estimated_memory(&self) -> usize {
+        use std::mem::size_of;
+        let n = self.particles.len();
+        let history_entry = 3 * size_of::<f32>() + size_of::<Energy>();
+        let pressure_samples = (self.pressure_sampling_period / self.dt).max(0.0) as usize;
+        // particle, grid square and raw grid square, contacts, virial, acceleration and potential
+        let per_particle = size_of::<Particle>()
+            + 6 * size_of::<usize>()
+            + size_of::<usize>()
+            + size_of::<Mat3>()
+            + size_of::<Vec3>()
+            + size_of::<f32>();
+        size_of::<SimulationState>()
+            + n * per_particle
+            + self.bonds.len() * size_of::<Bond>()
+            + self.grid_squares() * size_of::<Vec<usize>>()
+            + 7 * pressure_samples * size_of::<f32>()
+            + self.history_capacity * history_entry
+            + self.probes.len() * self.history_capacity * size_of::<f32>()
+            + 6 * VDWSimulation::STRESS_HISTORY_CAPACITY * size_of::<f32>()
+    }
+
+    // Number of grid squares covering the box, see Grid::dim_in
+    // internal helper function
+    fn grid_squares(&self) -> usize {
+        let (x, y, z) = self.grid().dim_in(&self.bound);
+        x.saturating_mul(y).saturating_mul(z)
+    }
+
+    // Close to the target a frame of s steps scales the deviation of the temperature by about
+    // 1 - gain, gain = 2 target_temp inject_rate s dt, as the velocities scale by 1 + amount dt every
+    // step while the amount is only updated once a frame, see recalculate_kinetic_energy
+    // Above a gain of 1 the correction overshoots, from 2 on the swings grow
+    // None without a heat injecting thermostat
+    // internal helper function
+    fn thermostat_gain(&self) -> Option<f32> {
+        let active = !self.integrator.is_stochastic()
+            && self.inject_rate > 0.0
+            && self.target_temp > 0.0
+            && self.dt > 0.0;
+        if active {
+            Some(2.0 * self.target_temp * self.inject_rate * self.frame_time())
+        } else {
+            None
+        }
+    }
+
+    // Inject rate that gives the thermostat a gain
+    // internal helper function
+    fn inject_rate_for_gain(&self, gain: f32) -> f32 {
+        gain / (2.0 * self.target_temp * self.frame_time())
+    }
+
+    // Simulated time of a frame, with a frame budget the steps of a frame vary
+    // internal helper function
+    fn frame_time(&self) -> f32 {
+        self.steps_per_frame.max(1) as f32 * self.dt
+    }
+}
+
+// First item that fails a check, with its index and the number of items that fail it
+// internal helper function
+fn first_offender<T>(items: &[T], invalid: impl Fn(&T) -> bool) -> Option<(usize, &T, usize)> {
+    let mut offenders = items.iter().enumerate().filter(|(_, item)| invalid(item));
+    let (i, first) = offenders.next()?;
+    Some((i, first, 1 + offenders.count()))
+}
+
+// Note on the other items failing the same check
+// internal helper function
+fn and_more(count: usize) -> String {
+    if count > 1 {
+        format!(" (and {} more)", count - 1)
+    } else {
+        String::new()
+    }
+}
+
+/////////////////////////////
+// State component wrappers
+#[derive(Clone, Copy, Default)]
+pub struct Energy {
+    pub kinetic: f32,
+    pub potential: f32,
+    pub rotational: f32, // of the free particles turning on their own, rigid clusters turn in kinetic
+}
+
+impl Energy {
+    pub fn total(&self) -> f32 {
+        self.kinetic + self.potential + self.rotational
+    }
+}
+
+// a struct to keep pressure stablized at a certain value
+// done by shrinking or expanding the boundary
+#[derive(Clone)]
+pub struct PressurePinned {
+    pub previous_state: bool, // To reset bound_rate when toggle
+    pub is_pinned: bool,
+    pub at_value: f32,
+}
+// Buffers of the force calculation, kept between steps so their allocations are reused
+// Only scratch space, clones of the state start with empty ones
+#[derive(Default)]
+struct ForceWorkspace {
+    positions: Vec<Vec3>,
+    sites: Vec<physics::InteractionSite>,
+    charges: Vec<f32>,
+    dipoles: Vec<Vec3>,               // in the world frame
+    is_free: Vec<bool>, // particles that aren't members of a rigid cluster, found once per step
+    wall_impulses: Vec<FaceImpulses>, // impulse of each particle bouncing off a hard wall
+    grid: GridWorkspace, // also holds the forces, potential energies and neighbors between particles
+    pairs: Vec<(usize, usize)>, // pairs within range, for the charges and the dipoles
+    bound_forces: Vec<Vec3>,
+    obstacle_forces: Vec<Vec3>,
+    adhesion_forces: Vec<Vec3>,
+    adhesion_energies: Vec<f32>,
+    bond_forces: Vec<Vec3>,
+    bond_energies: Vec<f32>,
+    coulomb_forces: Vec<Vec3>,
+    coulomb_energies: Vec<f32>,
+    dipole_forces: Vec<Vec3>,
+    dipole_energies: Vec<f32>,
+    torques: Vec<Vec3>,       // on each particle, only the dipoles exert any
+    accelerations: Vec<Vec3>, // sum of all of the above per unit mass, with the external fields
+    potentials: Vec<f32>,     // potential energy of each particle, summed like the accelerations
+}
+
+impl Clone for ForceWorkspace {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+// Run as many steps per frame as fit in a time budget
+#[derive(Clone)]
+pub struct FrameBudget {
+    pub enabled: bool,
+    pub ms_per_frame: f32,
+}
+
+// Process instantaneous impulse data to return pressure
+#[derive(Clone)]
+pub struct Pressure {
+    data: RingBuffer<f32>,
+    sum_cache: f32,
+    dt: f32, // time per data point
+}
+impl Pressure {
+    // Create ring buffer with capacity, all entries initialized to zero
+    pub fn new(capacity: usize, dt: f32) -> Self {
+        Self {
+            data: RingBuffer::with_capacity(capacity),
+            sum_cache: 0.0,
+            dt,
+        }
+    }
+
+    // Create a ring buffer that averages over sampling_period units of time
+    // dt is the time between two samples
+    pub fn with_sampling_period(sampling_period: f32, dt: f32) -> Self {
+        Self::new(Self::sampling_capacity(sampling_period, dt), dt)
+    }
+
+    // One pressure for each face of the box
+    pub fn for_faces(sampling_period: f32, dt: f32) -> [Self; 6] {
+        [(); 6].map(|_| Self::with_sampling_period(sampling_period, dt))
+    }
+
+    // Average over a different period of time, the newest samples are kept
+    pub fn set_sampling_period(&mut self, sampling_period: f32) {
+        self.data
+            .resize(Self::sampling_capacity(sampling_period, self.dt));
+        self.sum_cache = self.data.iter().sum();
+    }
+
+    // Number of samples taken over sampling_period
+    fn sampling_capacity(sampling_period: f32, dt: f32) -> usize {
+        // at least two entries are needed for the ring buffer to hold any data
+        ((sampling_period / dt) as usize).max(2)
+    }
+
+    pub fn push_sample(&mut self, value: f32) {
+        self.sum_cache -= self.data.push(value).unwrap_or(0.0);
+        self.sum_cache += value;
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.data.capacity()
+    }
+
+    // Calulate the average impulse based on sampled values
+    pub fn get_pressure(&self) -> f32 {
+        self.sum_cache / self.data.len() as f32 / self.dt
+    }
+}
+
+// Store the previous entries of energy and pressure
+#[derive(Clone)]
+pub struct History {
+    pub time: RingBuffer<f32>, // simulated time of each entry
+    pub energy: RingBuffer<Energy>,
+    pub pressure: RingBuffer<f32>,
+    pub temperature: RingBuffer<f32>,
+    pub volume: RingBuffer<f32>,
+    pub moments: observables::HistoryMoments, // running first and second moments of every entry
+}
+impl History {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            time: RingBuffer::with_capacity(capacity),
+            energy: RingBuffer::with_capacity(capacity),
+            pressure: RingBuffer::with_capacity(capacity),
+            temperature: RingBuffer::with_capacity(capacity),
+            volume: RingBuffer::with_capacity(capacity),
+            moments: observables::HistoryMoments::default(),
+        }
+    }
+
+    // Forget the accumulated moments, used to discard the equilibration period
+    pub fn reset_moments(&mut self) {
+        self.moments = observables::HistoryMoments::default();
+    }
+
+    pub fn len(&self) -> usize {
+        self.time.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.time.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.time.capacity()
+    }
+
+    // Write the last window entries to a csv file
+    pub fn export_csv(&self, path: impl AsRef<Path>, window: usize) -> io::Result<()> {
+        let mut file = File::create(path)?;
+
+        writeln!(
+            file,
+            "time,kinetic,potential,total,temperature,pressure,volume"
+        )?;
+        let rows = self
+            .time
+            .last_n(window)
+            .zip(self.energy.last_n(window))
+            .zip(self.temperature.last_n(window))
+            .zip(self.pressure.last_n(window))
+            .zip(self.volume.last_n(window));
+        for ((((t, e), temp), p), v) in rows {
+            writeln!(
+                file,
+                "{},{},{},{},{},{},{}",
+                t,
+                e.kinetic,
+                e.potential,
+                e.total(),
+                temp,
+                p,
+                v
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+//////////////////////////////////////////////////////////////
+// State contains all simulation parameters and particle data
+// Can only be created by compiling a StatePrototype
+//
+#[derive(Clone)]
+pub struct SimulationState {
+    // Simulated entities
+    pub particles: Vec<Particle>,
+    pub bonds: Vec<Bond>,
+    rigid_clusters: Vec<RigidCluster>,
+    pub bound: Boundary,                    // location of the 6 walls of the box
+    pub obstacles: Vec<obstacle::Obstacle>, // immovable, kept out like the walls
+    pub groups: group::Groups,              // named sets of particles, frozen groups don't move
+    grid: Grid,
+
+    // Simulation dynamic quantities
+    pub bound_rate: f32, // rate at which all sides grow, moves the walls at the higher corner
+    pub face_rates: FaceRates, // rates at which each wall moves outwards, on top of bound_rate
+    pub target_temp: f32,
+    pub inject_rate: f32,
+    heat_injection_ammount: f32,     // private cache
+    rotation_injection_ammount: f32, // private cache, heat_injection_ammount of the rotations
+    workspace: ForceWorkspace,       // scratch buffers of the force calculation
+    pub pressure_pinned: PressurePinned,
+    pub remove_drift: bool,
+    frames_since_drift_removal: usize,
+    pub paused: bool, // the worker stops stepping while set, edits still apply
+
+    // Simulation constants
+    // dt and steps_per_frame should be changed through their setters
+    pub dt: f32,
+    pub steps_per_frame: usize,
+    pub frame_budget: FrameBudget,
+    pub pressure_sampling_period: f32, // pressure is averaged over this period of time
+    pub external_fields: Vec<Box<dyn ExternalField>>, // act on all particles
+    pub coulomb: electrostatics::Coulomb, // acts between charged particles
+    pub dipolar: dipole::Dipolar,      // acts between particles with dipoles
+    pub integrator: integrator::Integrator,
+    rng: StdRng,                      // random numbers of the stochastic integrators
+    pub schedule: schedule::Schedule, // timed parameter changes
+    pub annealer: anneal::Annealer,   // ramps the thermostat target while running
+    pub isotherm: Option<experiment::IsothermSweep>, // sweep observed after every frame, kept once done
+
+    // Simulation measurements
+    pub steps: usize,            // number of times step is called
+    pub last_frame_steps: usize, // number of steps executed in the latest frame
+    pub time: f32,               // simulated time, dt can change so this is not steps * dt
+    pub energy: Energy,
+    pub pressure: Pressure,
+    pub face_pressures: [Pressure; 6], // pressure on each face, indexed by Face::index
+    pub impulse_accumultor: FaceImpulses, // cache for impulse, used to calculate pressure
+    pub history: History,              // history of energy and pressure
+    pub probes: Vec<Probe>,            // measurements attached to particles or points
+    pub regions: Vec<region::RegionProbe>, // box-shaped probes of temperature, density and pressure
+    pub stress: stress::StressHistory, // stress tensor of the latest steps
+    pub heat_flux: heat_flux::HeatFluxHistory, // heat current of the latest steps
+    pub collisions: collision::CollisionStats, // wall hits and pair collisions per unit of time
+    pub timings: timing::Timings, // wall clock time of the simulation phases of the last frames
+}
+
+impl SimulationState {
+    const MIN_SEPARATION: f32 = 0.15; // closest an added particle may get to another, as in the initializers
+
+    // Advance one animation frame
+    // Multiple simulation steps are executed in one animation frame
+    pub fn advance_frame(&mut self) {
+        for _i in 0..self.steps_per_frame {
+            self.step();
+            self.commit_pressure();
+        }
+        self.last_frame_steps = self.steps_per_frame;
+        self.finish_frame();
+    }
+
+    // Advance one animation frame, stepping until the budget is used up
+    // At least one step is always executed
+    pub fn advance_frame_within(&mut self, budget: Duration) {
+        let start = Instant::now();
+        let mut steps = 0;
+        while steps == 0 || start.elapsed() < budget {
+            self.step();
+            self.commit_pressure();
+            steps += 1;
+        }
+        self.last_frame_steps = steps;
+        self.finish_frame();
+    }
+
+    // Measurements taken once per frame
+    // internal helper function
+    fn finish_frame(&mut self) {
+        let stopwatch = timing::Stopwatch::start();
+        // the thermostat doesn't conserve momentum, so the cloud slowly drifts without this
+        self.frames_since_drift_removal += 1;
+        if self.remove_drift
+            && self.frames_since_drift_removal >= VDWSimulation::DRIFT_REMOVAL_INTERVAL
+        {
+            self.remove_momentum_drift();
+            self.frames_since_drift_removal = 0;
+        }
+
+        self.recalculate_kinetic_energy();
+        // the force calculation counts neighbors by distance, the nearest ones need the whole network
+        if self.grid.get_neighbor_rule().metric == physics::NeighborMetric::Nearest {
+            let degrees = self.neighbor_network().degrees();
+            (&mut self.particles, degrees)
+                .into_par_iter()
+                .for_each(|(particle, nei)| particle.neighbors = nei);
+        }
+        self.record_history();
+
+        if let Some(mut sweep) = self.isotherm.take() {
+            experiment::Experiment::observe(&mut sweep, self);
+            self.isotherm = Some(sweep);
+        }
+
+        self.timings
+            .record(timing::Phase::Integration, stopwatch.elapsed_ms());
+        self.timings.finish_frame(&timing::Phase::SIMULATION);
+    }
+
+    // Subtract the center of mass velocity and the rigid rotation of the whole system
+    // Leaves the system with zero net momentum and angular momentum about its center of mass
+    // Rigid clusters only have the velocity of their center of mass corrected
+    pub fn remove_momentum_drift(&mut self) {
+        let total_mass: f32 = self.particles.iter().map(|p| p.get_mass()).sum();
+        if self.particles.len() < 2 || total_mass <= 0.0 {
+            return;
+        }
+
+        let com_pos = self
+            .particles
+            .iter()
+            .fold(Vec3::ZERO, |acc, p| acc + p.get_mass() * p.get_pos())
+            / total_mass;
+        let com_vel = self
+            .particles
+            .iter()
+            .fold(Vec3::ZERO, |acc, p| acc + p.get_mass() * p.get_vel())
+            / total_mass;
+
+        // angular momentum and moment of inertia about the center of mass
+        let mut ang_momentum = Vec3::ZERO;
+        let mut inertia = Mat3::ZERO;
+        for particle in self.particles.iter() {
+            let r = particle.get_pos() - com_pos;
+            let v = particle.get_vel() - com_vel;
+            let m = particle.get_mass();
+            ang_momentum += m * r.cross(v);
+            inertia = inertia
+                + (Mat3::from_diagonal(Vec3::splat(r.length_squared()))
+                    - Mat3::from_cols(r * r.x, r * r.y, r * r.z))
+                    * m;
+        }
+
+        // a line of particles can't be rotated about its own axis
+        let ang_vel = if inertia.determinant().abs() > f32::EPSILON {
+            inertia.inverse() * ang_momentum
+        } else {
+            Vec3::ZERO
+        };
+        let drift = |pos: Vec3| com_vel + ang_vel.cross(pos - com_pos);
+
+        let is_free = self.free_particles();
+        (&mut self.particles, is_free)
+            .into_par_iter()
+            .filter(|(_, is_free)| *is_free)
+            .for_each(|(particle, _)| {
+                let pos = particle.get_pos();
+                particle.move_to(pos, particle.get_vel() - drift(pos));
+            });
+
+        let particles = &mut self.particles;
+        self.rigid_clusters.iter_mut().for_each(|cluster| {
+            cluster.shift_vel(-drift(cluster.get_pos()));
+            cluster.apply(particles);
+        });
+    }
+
+    // Multiply every velocity by factor, angular velocities and rigid clusters included
+    pub fn scale_velocities(&mut self, factor: f32) {
+        for particle in self.particles.iter_mut() {
+            let pos = particle.get_pos();
+            particle.move_to(pos, factor * particle.get_vel());
+            particle.turn_to(particle.get_orientation(), factor * particle.get_ang_vel());
+        }
+        let particles = &mut self.particles;
+        self.rigid_clusters.iter_mut().for_each(|cluster| {
+            cluster.scale_vel(factor);
+            cluster.apply(particles);
+        });
+        self.recalculate_kinetic_energy();
+    }
+
+    // Trade configurations with another state, used by replica exchange
+    // Both keep their thermostat, history and probes,
+    // the velocities are rescaled from the temperature of the old state to that of the new one
+    pub fn exchange_configuration(&mut self, other: &mut SimulationState) {
+        std::mem::swap(&mut self.particles, &mut other.particles);
+        std::mem::swap(&mut self.bonds, &mut other.bonds);
+        std::mem::swap(&mut self.rigid_clusters, &mut other.rigid_clusters);
+        std::mem::swap(&mut self.bound, &mut other.bound);
+        std::mem::swap(&mut self.energy, &mut other.energy);
+
+        let ratio = self.target_temp / other.target_temp;
+        if ratio.is_finite() && ratio > 0.0 {
+            self.scale_velocities(ratio.sqrt());
+            other.scale_velocities(1.0 / ratio.sqrt());
+        }
+    }
+
+    // Execute one time step
+    // Inertial integrators use leapfrog, the overdamped one moves particles along the forces
+    pub fn step(&mut self) {
+        // the force calculation times itself, the rest of the step counts as integration
+        let stopwatch = timing::Stopwatch::start();
+        let forces_before = self.force_ms();
+        self.steps += 1;
+        self.run_schedule();
+        self.time += self.dt;
+        // a running annealer overrides temperatures set by the schedule
+        if let Some(target) = self.annealer.advance(self.time) {
+            self.target_temp = target;
+        }
+        let mut is_free = std::mem::take(&mut self.workspace.is_free);
+        self.mark_free_particles(&mut is_free);
+        self.workspace.is_free = is_free;
+
+        let (pot_energy, impulse, virial) = match self.integrator {
+            integrator::Integrator::Brownian { gamma } => self.step_overdamped(gamma),
+            _ => self.step_inertial(),
+        };
+
+        // adjust boundary size, slide the images of a sheared box along
+        self.bound.expand(self.bound_rate, self.dt);
+        self.bound.advance_shear(self.dt);
+        self.bound.expand_faces(self.face_rates, self.dt);
+
+        // record potential energy
+        self.energy.potential = pot_energy;
+
+        // record stress, kinetic part and virial of the interactions
+        // the kinetic part of a sheared box is taken relative to the flow
+        let bound = self.bound;
+        let kinetic_stress = self.particles.iter().fold(Mat3::ZERO, |acc, particle| {
+            let vel = particle.get_vel() - bound.streaming_velocity(particle.get_pos());
+            acc + physics::outer_product(vel * particle.get_mass(), vel)
+        });
+        let volume = self.bound.get_volume();
+        let stress = (kinetic_stress + virial) * (1.0 / volume);
+        self.stress.push(stress, self.dt);
+
+        // periodic faces take no impulse, they are loaded with the normal stress instead
+        let impulse = if self.bound.wall_model.is_periodic() {
+            Face::ALL
+                .iter()
+                .fold(FaceImpulses::default(), |mut acc, &face| {
+                    let normal_stress = stress.col(face.axis())[face.axis()];
+                    acc.add(face, normal_stress * self.bound.face_area(face) * self.dt);
+                    acc
+                })
+        } else {
+            impulse
+        };
+
+        // record heat current, energy carried along by the particles and passed on by the pair forces
+        let current = heat_flux::heat_current(&self.particles, self.workspace.grid.virials());
+        self.heat_flux.push(current, self.dt);
+
+        // accumulate impulse
+        self.impulse_accumultor += impulse;
+        self.collisions.finish_step(self.dt);
+
+        let forces_ms = self.force_ms() - forces_before;
+        self.timings.record(
+            timing::Phase::Integration,
+            stopwatch.elapsed_ms() - forces_ms,
+        );
+    }
+
+    // Milliseconds of force calculations in the frame so far
+    // internal helper function
+    fn force_ms(&self) -> f32 {
+        self.timings.current_ms(timing::Phase::Grid)
+            + self.timings.current_ms(timing::Phase::Forces)
+    }
+
+    // Leapfrog step of positions and velocities, shared by Verlet and Langevin
+    // Return (potential energy, impulse on each wall, virial)
+    // internal helper function
+    fn step_inertial(&mut self) -> (f32, FaceImpulses, Mat3) {
+        let dt = self.dt;
+
+        // step position
+        self.particles
+            .par_iter_mut()
+            .for_each(|particle| particle.step_pos(dt, 0.5));
+        self.step_rigid_clusters_pos(dt, 0.5);
+        let wall_impulse = self.reflect_from_walls();
+
+        // calculate accelerations and step velocity
+        let (impulse, virial) = self.calculate_particle_acceleration();
+        let pot_energy = self.workspace.potentials.iter().sum();
+        let accelerations = &self.workspace.accelerations;
+        (&mut self.particles, accelerations)
+            .into_par_iter()
+            .for_each(|(particle, &acc)| particle.step_vel(acc, dt, 1.0));
+        // the members of rigid clusters are turned by their cluster, their torques are left out
+        (&mut self.particles, &self.workspace.torques)
+            .into_par_iter()
+            .for_each(|(particle, &torque)| particle.step_ang_vel(torque, dt, 1.0));
+        let particles = &self.particles;
+        self.rigid_clusters
+            .iter_mut()
+            .for_each(|cluster| cluster.step_vel(particles, accelerations, dt, 1.0));
+
+        // inject/drain heat into/from system
+        // stochastic integrators exchange heat with their bath instead
+        if let integrator::Integrator::Langevin { gamma } = self.integrator {
+            self.apply_langevin_bath(gamma, dt);
+        } else {
+            let heat_injection_ammount = self.heat_injection_ammount;
+            let rotation_injection_ammount = self.rotation_injection_ammount;
+            self.particles.par_iter_mut().for_each(|particle| {
+                particle.heat(dt, heat_injection_ammount);
+                particle.heat_rotation(dt, rotation_injection_ammount);
+            });
+            self.rigid_clusters
+                .iter_mut()
+                .for_each(|cluster| cluster.heat(dt, heat_injection_ammount));
+        }
+
+        self.save_particle_measurements();
+
+        // step position again
+        self.particles
+            .par_iter_mut()
+            .for_each(|particle| particle.step_pos(dt, 0.5));
+        self.step_rigid_clusters_pos(dt, 0.5);
+        let wall_impulse = wall_impulse + self.reflect_from_walls();
+
+        (pot_energy, impulse + wall_impulse, virial)
+    }
+
+    // Overdamped Brownian step, particles drift along the force with mobility 1 / (m gamma)
+    // and diffuse at the target temperature, velocities are not integrated and stay zero
+    // Rigid clusters don't move in this mode
+    // Return (potential energy, impulse on each wall, virial)
+    // internal helper function
+    fn step_overdamped(&mut self, gamma: f32) -> (f32, FaceImpulses, Mat3) {
+        let dt = self.dt;
+        let (impulse, virial) = self.calculate_particle_acceleration();
+        let pot_energy = self.workspace.potentials.iter().sum();
+
+        let kt = physics::kt(self.target_temp);
+        let rng = &mut self.rng;
+        for ((particle, &acc), _) in self
+            .particles
+            .iter_mut()
+            .zip(self.workspace.accelerations.iter())
+            .zip(self.workspace.is_free.iter())
+            .filter(|(_, &is_free)| is_free)
+        {
+            let sigma = (2.0 * kt * dt / (particle.get_mass() * gamma)).sqrt();
+            let noise = Vec3::new(
+                rng.sample(StandardNormal),
+                rng.sample(StandardNormal),
+                rng.sample(StandardNormal),
+            );
+            let pos = particle.get_pos() + acc / gamma * dt + sigma * noise;
+            particle.move_to(pos, Vec3::ZERO);
+        }
+        self.save_particle_measurements();
+
+        // with no velocity the walls only mirror positions back inside, they take no impulse
+        let wall_impulse = self.reflect_from_walls();
+
+        (pot_energy, impulse + wall_impulse, virial)
+    }
+
+    // Per particle results of the force calculation
+    // used for rendering particles with different colors
+    // internal helper function
+    fn save_particle_measurements(&mut self) {
+        // save number of neighbors and potential energy of each particle
+        (
+            &mut self.particles,
+            self.workspace.grid.neighbors(),
+            &self.workspace.potentials,
+        )
+            .into_par_iter()
+            .for_each(|(particle, &nei, &pot)| {
+                particle.neighbors = nei;
+                particle.potential = pot;
+            });
+    }
+
+    // Bounce particles and rigid clusters that crossed a hard wall
+    // Members of rigid clusters are moved by their cluster and skipped here
+    // Free particles also bounce off the obstacles, rigid clusters pass through them
+    // Return the impulse delivered to the walls of the box
+    // internal helper function
+    fn reflect_from_walls(&mut self) -> FaceImpulses {
+        if !self.bound.wall_model.is_hard() {
+            return FaceImpulses::default();
+        }
+
+        let bound = self.bound;
+        let obstacles = &self.obstacles;
+        (&mut self.particles, &self.workspace.is_free)
+            .into_par_iter()
+            .map(|(particle, &is_free)| {
+                if !is_free {
+                    return FaceImpulses::default();
+                }
+                let mut pos = particle.get_pos();
+                let mut vel = particle.get_vel();
+                for obstacle in obstacles.iter() {
+                    obstacle.reflect(&mut pos, &mut vel);
+                }
+                let impulse = bound.reflect(&mut pos, &mut vel, particle.get_mass());
+                particle.move_to(pos, vel);
+                impulse
+            })
+            .collect_into_vec(&mut self.workspace.wall_impulses);
+
+        let particles = &mut self.particles;
+        let cluster_impulses = self.rigid_clusters.iter_mut().map(|cluster| {
+            let impulse = cluster.reflect(&bound);
+            cluster.apply(particles);
+            impulse
+        });
+
+        // every body that took an impulse bounced
+        let impulses = self
+            .workspace
+            .wall_impulses
+            .iter()
+            .copied()
+            .chain(cluster_impulses);
+        let (hits, impulse) =
+            impulses.fold((0, FaceImpulses::default()), |(hits, total), impulse| {
+                let hit = (impulse.total() > 0.0) as usize;
+                (hits + hit, total + impulse)
+            });
+        self.collisions.record_wall_hits(hits);
+        impulse
+    }
+
+    // Exact friction and random kick of the Langevin equation over dt, at the target temperature
+    // Members of rigid clusters are moved by their cluster and skipped here
+    // internal helper function
+    fn apply_langevin_bath(&mut self, gamma: f32, dt: f32) {
+        let decay = (-gamma * dt).exp();
+        let variance = (1.0 - decay * decay) * physics::kt(self.target_temp);
+
+        let rng = &mut self.rng;
+        for (particle, _) in self
+            .particles
+            .iter_mut()
+            .zip(self.workspace.is_free.iter())
+            .filter(|(_, &is_free)| is_free)
+        {
+            let sigma = (variance / particle.get_mass()).sqrt();
+            let kick = Vec3::new(
+                rng.sample(StandardNormal),
+                rng.sample(StandardNormal),
+                rng.sample(StandardNormal),
+            );
+            let vel = decay * particle.get_vel() + sigma * kick;
+            particle.move_to(particle.get_pos(), vel);
+
+            // the rotations take their kicks along the body axes, scaled by the moments
+            let kick = Vec3::new(
+                rng.sample(StandardNormal),
+                rng.sample(StandardNormal),
+                rng.sample(StandardNormal),
+            );
+            let rotation = particle.get_orientation();
+            let inertia = particle.get_inertia();
+            let sigma = Vec3::new(
+                (variance / inertia.x).sqrt(),
+                (variance / inertia.y).sqrt(),
+                (variance / inertia.z).sqrt(),
+            );
+            let body = decay * (rotation.inverse() * particle.get_ang_vel()) + sigma * kick;
+            particle.turn_to(rotation, rotation * body);
+        }
+    }
+
+    // Apply the events of the schedule that are active at this step
+    // internal helper function
+    fn run_schedule(&mut self) {
+        if self.schedule.is_empty() {
+            return;
+        }
+        let schedule = std::mem::take(&mut self.schedule);
+        schedule.apply(self);
+        self.schedule = schedule;
+    }
+
+    // Reach of the neighbor grid, pairs further apart than this don't interact
+    pub fn get_grid_range(&self) -> f32 {
+        self.grid.get_range()
+    }
+
+    pub fn get_potential(&self) -> physics::PotentialModel {
+        self.grid.get_potential()
+    }
+
+    // Side of the squares of the neighbor grid
+    pub fn get_grid_unit_size(&self) -> f32 {
+        self.grid.get_unit_size()
+    }
+
+    // Invalid models are ignored
+    pub fn set_potential(&mut self, potential: physics::PotentialModel) {
+        if potential.is_valid() {
+            self.grid.set_potential(potential);
+        }
+    }
+
+    pub fn get_neighbor_rule(&self) -> physics::NeighborRule {
+        self.grid.get_neighbor_rule()
+    }
+
+    // Invalid rules are ignored, the distance is capped at the interaction range
+    pub fn set_neighbor_rule(&mut self, mut neighbor_rule: physics::NeighborRule) {
+        if neighbor_rule.is_valid() {
+            neighbor_rule.distance = neighbor_rule.distance.min(self.grid.get_range());
+            self.grid.set_neighbor_rule(neighbor_rule);
+        }
+    }
+
+    // Flags of the particles that aren't members of a rigid cluster
+    // internal helper function
+    fn free_particles(&self) -> Vec<bool> {
+        let mut is_free = Vec::new();
+        self.mark_free_particles(&mut is_free);
+        is_free
+    }
+
+    // Same as free_particles, writing the flags into a reused buffer
+    // internal helper function
+    fn mark_free_particles(&self, is_free: &mut Vec<bool>) {
+        is_free.clear();
+        is_free.resize(self.particles.len(), true);
+        for cluster in self.rigid_clusters.iter() {
+            for &i in cluster.get_members() {
+                is_free[i] = false;
+            }
+        }
+    }
+
+    // Step rigid clusters and move their members to match
+    // Overrides the free particle step of the members
+    // internal helper function
+    fn step_rigid_clusters_pos(&mut self, dt: f32, coeff: f32) {
+        let particles = &mut self.particles;
+        self.rigid_clusters.iter_mut().for_each(|cluster| {
+            cluster.step_pos(dt, coeff);
+            cluster.apply(particles);
+        });
+    }
+
+    // Calculate the acceleration and the potential energy of each particle into the workspace
+    // Return the impulse on the boundary and the virial of the forces between particles
+    // internal helper function
+    fn calculate_particle_acceleration(&mut self) -> (FaceImpulses, Mat3) {
+        // Collect particle positions into the reused buffers
+        let mut workspace = std::mem::take(&mut self.workspace);
+        workspace.positions.clear();
+        workspace
+            .positions
+            .extend(self.particles.iter().map(|particle| particle.get_pos()));
+        workspace.sites.clear();
+        workspace
+            .sites
+            .extend(self.particles.iter().map(|particle| particle.get_site()));
+        let particle_pos = &workspace.positions;
+
+        // Calculate forces
+        let bound_push = self
+            .bound
+            .calculate_force_into(particle_pos, &mut workspace.bound_forces);
+        obstacle::calculate_force_into(
+            &self.obstacles,
+            self.bound.wall_model,
+            particle_pos,
+            &mut workspace.obstacle_forces,
+        );
+        let adhesion_push = self.bound.calculate_adhesion_into(
+            particle_pos,
+            &mut workspace.adhesion_forces,
+            &mut workspace.adhesion_energies,
+        );
+        // time has already been advanced, fields are sampled at the middle of the step
+        let field_time = self.time - 0.5 * self.dt;
+        // the box may have moved since the last step
+        workspace.grid.anchor_to(self.bound);
+        let grid_virial =
+            self.grid
+                .calculate_force_with(particle_pos, &workspace.sites, &mut workspace.grid);
+        if !self.bound.wall_model.is_hard() {
+            self.collisions
+                .record_wall_contacts(workspace.bound_forces.iter().map(|&f| f != Vec3::ZERO));
+        }
+        self.collisions.record_contacts(workspace.grid.contacts());
+        let (grid_ms, forces_ms) = workspace.grid.timing();
+        self.timings.record(timing::Phase::Grid, grid_ms);
+        self.timings.record(timing::Phase::Forces, forces_ms);
+        bond::calculate_force_into(
+            &self.bonds,
+            particle_pos,
+            &self.bound,
+            &mut workspace.bond_forces,
+            &mut workspace.bond_energies,
+        );
+        let coulomb_virial = self.calculate_coulomb_force(&mut workspace);
+        let dipole_virial = self.calculate_dipole_force(&mut workspace);
+        let particle_pos = &workspace.positions;
+        let virial = grid_virial
+            + bond::calculate_virial(&self.bonds, particle_pos, &self.bound)
+            + coulomb_virial
+            + dipole_virial;
+
+        // Sum up accelerations
+        (
+            &self.particles,
+            (&workspace.bound_forces, &workspace.adhesion_forces),
+            (workspace.grid.forces(), &workspace.obstacle_forces),
+            (
+                &workspace.bond_forces,
+                &workspace.coulomb_forces,
+                &workspace.dipole_forces,
+            ),
+        )
+            .into_par_iter()
+            // @param bnd_f: force on particle by the bounding box
+            // @param adh_f: force on particle by the adhering walls
+            // @param grd_f: force on particle by other particles as calculated through the grid
+            // @param obs_f: force on particle by the obstacles
+            // @param bnd_s: force on particle by the springs attached to it
+            // @param elc_f: force on particle by the charges around it
+            // @param dip_f: force on particle by the dipoles around it
+            .map(
+                |(particle, (&bnd_f, &adh_f), (&grd_f, &obs_f), (&bnd_s, &elc_f, &dip_f))| {
+                    (bnd_f + adh_f + grd_f + obs_f + bnd_s + elc_f + dip_f) / particle.get_mass()
+                        + external_field::total_acceleration(
+                            &self.external_fields,
+                            particle.get_pos(),
+                            field_time,
+                        )
+                },
+            )
+            .collect_into_vec(&mut workspace.accelerations);
+
+        // calculate impulse and potential energy
+        (
+            workspace.grid.potentials(),
+            (&workspace.bond_energies, &workspace.coulomb_energies),
+            (&workspace.adhesion_energies, &workspace.dipole_energies),
+        )
+            .into_par_iter()
+            .map(|(grd_e, (bnd_e, elc_e), (adh_e, dip_e))| grd_e + bnd_e + elc_e + adh_e + dip_e)
+            .collect_into_vec(&mut workspace.potentials);
+        // attracting walls pull inwards, lowering the pressure
+        let impulse = (bound_push + adhesion_push).scale(self.dt);
+        self.workspace = workspace;
+
+        (impulse, virial)
+    }
+
+    // Mean speed of the particles, zero without any
+    pub fn mean_speed(&self) -> f32 {
+        let total: f32 = self.particles.iter().map(|p| p.get_vel().length()).sum();
+        total / self.particles.len().max(1) as f32
+    }
+
+    // Pair interaction sites of the particles, in particle order
+    pub fn interaction_sites(&self) -> Vec<physics::InteractionSite> {
+        self.particles.iter().map(|p| p.get_site()).collect()
+    }
+
+    // Electrostatic forces at the positions of the workspace, cut off at the interaction range
+    // The forces and potential energies are written into the workspace, all zero when no particle
+    // is charged, the virial is returned
+    // The pairs are found on the grid of the workspace
+    // internal helper function
+    fn calculate_coulomb_force(&self, workspace: &mut ForceWorkspace) -> Mat3 {
+        let n = workspace.positions.len();
+        if self.particles.iter().all(|p| p.get_charge() == 0.0) {
+            workspace.coulomb_forces.clear();
+            workspace.coulomb_forces.resize(n, Vec3::ZERO);
+            workspace.coulomb_energies.clear();
+            workspace.coulomb_energies.resize(n, 0.0);
+            return Mat3::ZERO;
+        }
+        workspace.charges.clear();
+        workspace
+            .charges
+            .extend(self.particles.iter().map(|p| p.get_charge()));
+
+        self.grid.find_pairs_with(
+            &workspace.positions,
+            &mut workspace.grid,
+            &mut workspace.pairs,
+        );
+        electrostatics::calculate_force_into(
+            &self.coulomb,
+            &workspace.positions,
+            &self.bound,
+            &workspace.charges,
+            &workspace.pairs,
+            self.grid.get_range(),
+            &mut workspace.coulomb_forces,
+            &mut workspace.coulomb_energies,
+        )
+    }
+
+    // Dipolar forces and torques at the positions of the workspace, cut off at the interaction range
+    // The forces, torques and potential energies are written into the workspace, all zero when no
+    // particle has a dipole, the virial is returned
+    // The pairs are found on the grid of the workspace
+    // internal helper function
+    fn calculate_dipole_force(&self, workspace: &mut ForceWorkspace) -> Mat3 {
+        let n = workspace.positions.len();
+        if self.particles.iter().all(|p| p.get_dipole() == Vec3::ZERO) {
+            workspace.dipole_forces.clear();
+            workspace.dipole_forces.resize(n, Vec3::ZERO);
+            workspace.dipole_energies.clear();
+            workspace.dipole_energies.resize(n, 0.0);
+            workspace.torques.clear();
+            workspace.torques.resize(n, Vec3::ZERO);
+            return Mat3::ZERO;
+        }
+        workspace.dipoles.clear();
+        workspace
+            .dipoles
+            .extend(self.particles.iter().map(|p| p.world_dipole()));
+
+        self.grid.find_pairs_with(
+            &workspace.positions,
+            &mut workspace.grid,
+            &mut workspace.pairs,
+        );
+        dipole::calculate_force_into(
+            &self.dipolar,
+            &workspace.positions,
+            &self.bound,
+            &workspace.dipoles,
+            &workspace.pairs,
+            self.grid.get_range(),
+            &mut workspace.dipole_forces,
+            &mut workspace.torques,
+            &mut workspace.dipole_energies,
+        )
+    }
+
+    // Kinetic energy is cached in a variable, this function updates that cache
+    // The rotational energy is cached along with it
+    pub fn recalculate_kinetic_energy(&mut self) {
+        self.energy.kinetic = self
+            .particles
+            .iter_mut()
+            .map(|particle| 0.5 * particle.get_mass() * particle.get_vel().length_squared())
+            .sum();
+        let is_free = self.free_particles();
+        self.energy.rotational = self
+            .particles
+            .iter()
+            .zip(is_free.iter())
+            .filter(|(_, &is_free)| is_free)
+            .map(|(particle, _)| particle.rotational_energy())
+            .sum();
+
+        // update heat injection per time step, the thermostat pulls both temperatures to the target
+        self.heat_injection_ammount = (self.target_temp - self.temperature()) * self.inject_rate;
+        self.rotation_injection_ammount =
+            (self.target_temp - self.rotational_temperature()) * self.inject_rate;
+    }
+
+    // Kinetic energy per particle that can move, fixed particles are always at rest
+    pub fn temperature(&self) -> f32 {
+        let mobile = self.particles.iter().filter(|p| !p.is_fixed()).count();
+        if mobile == 0 {
+            0.0
+        } else {
+            self.energy.kinetic / mobile as f32
+        }
+    }
+
+    // Rotational energy per particle that turns on its own, like temperature for the rotations
+    // Members of rigid clusters turn with their cluster and fixed particles don't turn, neither
+    // is counted
+    pub fn rotational_temperature(&self) -> f32 {
+        let is_free = self.free_particles();
+        let turning = self
+            .particles
+            .iter()
+            .zip(is_free.iter())
+            .filter(|(p, &is_free)| is_free && !p.is_fixed())
+            .count();
+        if turning == 0 {
+            0.0
+        } else {
+            self.energy.rotational / turning as f32
+        }
+    }
+
+    // Commit the impulse value accumulated since the last commit, one sample per step
+    // Every face gets its own sample, the total is spread over the whole surface
+    // Reset the value
+    pub fn commit_pressure(&mut self) {
+        let impulses = self.impulse_accumultor;
+        let pressure_value = impulses.total() / self.bound.get_surface_area();
+        self.pressure.push_sample(pressure_value);
+        for (face, pressure) in Face::ALL.iter().zip(self.face_pressures.iter_mut()) {
+            pressure.push_sample(impulses.get(*face) / self.bound.face_area(*face));
+        }
+        self.impulse_accumultor = FaceImpulses::default();
+    }
+
+    // Pressure on one face, averaged over the same period as the total
+    pub fn face_pressure(&self, face: Face) -> f32 {
+        self.face_pressures[face.index()].get_pressure()
+    }
+
+    // Save current energy and pressure to history
+    pub fn record_history(&mut self) {
+        let pressure = self.pressure.get_pressure();
+        let temperature = self.temperature();
+        self.history.time.push(self.time);
+        self.history.energy.push(self.energy);
+        self.history.pressure.push(pressure);
+        self.history.temperature.push(temperature);
+        self.history.volume.push(self.bound.get_volume());
+
+        let moments = &mut self.history.moments;
+        moments.total_energy.push(self.energy.total());
+        moments.temperature.push(temperature);
+        moments.pressure.push(pressure);
+        moments.volume.push(self.bound.get_volume());
+
+        let particles = &self.particles;
+        self.probes
+            .iter_mut()
+            .for_each(|probe| probe.record(particles));
+        region::record(&mut self.regions, particles, self.workspace.grid.virials());
+    }
+
+    // Network of particles connected to their neighbors, see physics::NeighborRule
+    // With the nearest metric a pair is connected when either particle picks the other
+    pub fn neighbor_network(&self) -> network::Network {
+        let particle_pos: Vec<Vec3> = self
+            .particles
+            .iter()
+            .map(|particle| particle.get_pos())
+            .collect();
+        let sites = self.interaction_sites();
+        let rule = self.grid.get_neighbor_rule();
+
+        let close: Vec<(usize, usize, f32)> = self
+            .grid
+            .find_pairs(&particle_pos)
+            .into_par_iter()
+            .filter_map(|(i, j)| {
+                let r_norm_sqr = (particle_pos[i] - particle_pos[j]).length_squared();
+                let (scale, _) = sites[i].mix(&sites[j]);
+                // compared at the size of default particles, so the nearest are picked by relative distance
+                if rule.within(r_norm_sqr, scale) {
+                    Some((i, j, r_norm_sqr / scale.powi(2)))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let edges = match rule.metric {
+            physics::NeighborMetric::Distance => {
+                close.into_iter().map(|(i, j, _)| (i, j)).collect()
+            }
+            physics::NeighborMetric::Nearest => {
+                let mut candidates = vec![Vec::new(); self.particles.len()];
+                for &(i, j, r) in close.iter() {
+                    candidates[i].push((r, j));
+                    candidates[j].push((r, i));
+                }
+                let mut edges: Vec<(usize, usize)> = candidates
+                    .iter_mut()
+                    .enumerate()
+                    .flat_map(|(i, candidates)| {
+                        candidates.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+                        candidates
+                            .iter()
+                            .take(rule.nearest)
+                            .map(move |&(_, j)| (i.min(j), i.max(j)))
+                    })
+                    .collect();
+                edges.sort_unstable();
+                edges.dedup();
+                edges
+            }
+        };
+
+        network::Network::from_edges(self.particles.len(), edges)
+    }
+
+    // Check whether the largest cluster of neighbors spans the box along any axis
+    pub fn neighbor_network_percolates(&self) -> bool {
+        let positions: Vec<Vec3> = self.particles.iter().map(|p| p.get_pos()).collect();
+        self.neighbor_network().largest_percolates(
+            &positions,
+            self.bound,
+            self.grid.get_neighbor_rule().distance,
+        )
+    }
+
+    // Network of particles bonded by springs or by an interaction energy below threshold
+    pub fn energy_network(&self, threshold: f32) -> network::Network {
+        let particle_pos: Vec<Vec3> = self
+            .particles
+            .iter()
+            .map(|particle| particle.get_pos())
+            .collect();
+        let range = self.grid.get_range();
+        let switch_start = self.grid.get_switch_start();
+        let potential_model = self.grid.get_potential();
+        let sites = self.interaction_sites();
+
+        let mut edges: Vec<_> = self
+            .grid
+            .find_pairs(&particle_pos)
+            .into_par_iter()
+            .filter(|&(i, j)| {
+                // each particle of the pair holds half of the pair energy
+                let (_, potential) = potential_model.site_interaction(
+                    particle_pos[i],
+                    sites[i],
+                    particle_pos[j],
+                    sites[j],
+                    range,
+                    switch_start,
+                );
+                2.0 * potential < threshold
+            })
+            .collect();
+        edges.extend(self.bonds.iter().map(|bond| (bond.i, bond.j)));
+
+        network::Network::from_edges(self.particles.len(), edges)
+    }
+
+    // Change the time step
+    // Pressure samples taken with the old time step are discarded
+    pub fn set_dt(&mut self, dt: f32) {
+        if dt > 0.0 && (dt - self.dt).abs() > f32::EPSILON {
+            self.dt = dt;
+            self.reset_pressure();
+        }
+    }
+
+    // Change the period of time pressure is averaged over
+    // The newest samples are kept
+    pub fn set_pressure_sampling_period(&mut self, sampling_period: f32) {
+        if sampling_period > 0.0
+            && (sampling_period - self.pressure_sampling_period).abs() > f32::EPSILON
+        {
+            self.pressure_sampling_period = sampling_period;
+            self.pressure.set_sampling_period(sampling_period);
+            for pressure in self.face_pressures.iter_mut() {
+                pressure.set_sampling_period(sampling_period);
+            }
+        }
+    }
+
+    // Change the number of steps executed per animation frame
+    pub fn set_steps_per_frame(&mut self, steps_per_frame: usize) {
+        if steps_per_frame > 0 {
+            self.steps_per_frame = steps_per_frame;
+        }
+    }
+
+    // Rebuild the pressure buffer for the current time step
+    // internal helper function
+    fn reset_pressure(&mut self) {
+        self.pressure = Pressure::with_sampling_period(self.pressure_sampling_period, self.dt);
+        self.face_pressures = Pressure::for_faces(self.pressure_sampling_period, self.dt);
+        self.impulse_accumultor = FaceImpulses::default();
+    }
+
+    // Approximate number of bytes taken up by the state, used to budget snapshots
+    pub fn memory_size(&self) -> usize {
+        let history_entry = 3 * std::mem::size_of::<f32>() + std::mem::size_of::<Energy>();
+        std::mem::size_of::<Self>()
+            + self.particles.len() * std::mem::size_of::<Particle>()
+            + self.bonds.len() * std::mem::size_of::<Bond>()
+            + 7 * self.pressure.capacity() * std::mem::size_of::<f32>()
+            + self.history.capacity() * history_entry
+            + self.probes.len() * self.history.capacity() * std::mem::size_of::<f32>()
+            + self.regions.len()
+                * self.history.capacity()
+                * std::mem::size_of::<region::RegionSample>()
+            + 3 * self.stress.capacity() * std::mem::size_of::<f32>()
+            + 3 * self.heat_flux.capacity() * std::mem::size_of::<f32>()
+    }
+
+    // Place a new region probe, its history has the same length as the simulation history
+    pub fn add_region_probe(&mut self, name: String, lo: Vec3, hi: Vec3) {
+        self.regions.push(region::RegionProbe::new(
+            name,
+            lo,
+            hi,
+            self.history.capacity(),
+        ));
+    }
+
+    // Attach a new probe, its history has the same length as the simulation history
+    pub fn add_probe(&mut self, name: String, target: ProbeTarget, quantity: ProbeQuantity) {
+        self.probes
+            .push(Probe::new(name, target, quantity, self.history.capacity()));
+    }
+
+    // Add particles to the running simulation and return how many were added
+    // Particles outside the box or too close to an existing one are skipped, like the initializers prune them
+    pub fn add_particles(&mut self, particles: Vec<Particle>) -> usize {
+        let before = self.particles.len();
+        for particle in particles {
+            let pos = particle.get_pos();
+            let clear = self
+                .particles
+                .iter()
+                .all(|other| (other.get_pos() - pos).length() >= Self::MIN_SEPARATION);
+            if clear && self.bound.contains_position(pos) {
+                self.particles.push(particle);
+            }
+        }
+        self.particles.len() - before
+    }
+
+    // Insert the particles of a crystal seed, see seed::Seed::particles, and tag them as a group
+    // Particles already in the way of the seed are removed, seed particles outside the box
+    // or inside an obstacle are skipped
+    // Returns the index of the new group
+    pub fn insert_seed(&mut self, particles: Vec<Particle>) -> usize {
+        let bound = self.bound;
+        let obstacles = &self.obstacles;
+        let particles: Vec<Particle> = particles
+            .into_iter()
+            .filter(|p| {
+                let pos = p.get_pos();
+                bound.contains_position(pos) && !obstacles.iter().any(|o| o.contains(pos))
+            })
+            .collect();
+        let in_the_way: Vec<usize> = self
+            .particles
+            .iter()
+            .enumerate()
+            .filter(|(_, other)| {
+                particles
+                    .iter()
+                    .any(|p| (other.get_pos() - p.get_pos()).length() < Self::MIN_SEPARATION)
+            })
+            .map(|(i, _)| i)
+            .collect();
+        self.remove_particles(&in_the_way);
+
+        let before = self.particles.len();
+        let frozen = !particles.is_empty() && particles.iter().all(|p| p.is_fixed());
+        self.particles.extend(particles);
+        let group = self.groups.add(group::Group::new(
+            "seed".to_string(),
+            (before..self.particles.len()).collect(),
+        ));
+        if frozen {
+            self.freeze_group(group, true);
+        }
+        self.recalculate_kinetic_energy();
+        group
+    }
+
+    // Give the particles within radius of center random velocity kicks that heat them by heat,
+    // in units of temperature, the local heating of the heat gun
+    // seed decides the kicks, so every copy of the state heats the same way
+    // Fixed particles and members of rigid clusters are left alone
+    // Returns how many particles were kicked
+    pub fn heat_region(&mut self, center: Vec3, radius: f32, heat: f32, seed: u64) -> usize {
+        let positions: Vec<Vec3> = self.particles.iter().map(|p| p.get_pos()).collect();
+        let is_free = self.free_particles();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut kicked = 0;
+        for i in self.grid.find_within(&positions, center, radius) {
+            let particle = &mut self.particles[i];
+            if !is_free[i] || particle.is_fixed() {
+                continue;
+            }
+            let sigma = physics::velocity_spread(heat) / particle.get_mass().sqrt();
+            let kick = Vec3::new(
+                rng.sample(StandardNormal),
+                rng.sample(StandardNormal),
+                rng.sample(StandardNormal),
+            );
+            particle.move_to(particle.get_pos(), particle.get_vel() + sigma * kick);
+            kicked += 1;
+        }
+        self.recalculate_kinetic_energy();
+        kicked
+    }
+
+    // Tag the selected particles as a new group, returns its index
+    pub fn add_group(&mut self, name: String, selection: &group::Selection) -> usize {
+        let members = selection.select(&self.particles);
+        self.groups.add(group::Group::new(name, members))
+    }
+
+    // Fix the members of a group in place or release them
+    // Members of rigid clusters move with their cluster and are left alone
+    pub fn freeze_group(&mut self, index: usize, frozen: bool) {
+        let members = match self.groups.get_mut(index) {
+            Some(group) => {
+                group.frozen = frozen;
+                group.get_members().to_vec()
+            }
+            None => return,
+        };
+        // a particle released from one group stays fixed while another frozen group holds it
+        let held = self.groups.frozen_members();
+        let is_free = self.free_particles();
+        for i in members
+            .into_iter()
+            .filter(|&i| is_free.get(i) == Some(&true))
+        {
+            self.particles[i].fix(frozen || held.binary_search(&i).is_ok());
+        }
+        self.recalculate_kinetic_energy();
+    }
+
+    // Add dv to the velocity of every member of a group, fixed particles stay at rest
+    pub fn kick_group(&mut self, index: usize, dv: Vec3) {
+        let members = match self.groups.get(index) {
+            Some(group) => group.get_members().to_vec(),
+            None => return,
+        };
+        let is_free = self.free_particles();
+        for i in members
+            .into_iter()
+            .filter(|&i| is_free.get(i) == Some(&true))
+        {
+            let particle = &mut self.particles[i];
+            particle.move_to(particle.get_pos(), particle.get_vel() + dv);
+        }
+        self.recalculate_kinetic_energy();
+    }
+
+    // Delete the particles of a group and the group itself, returns how many particles were deleted
+    pub fn delete_group(&mut self, index: usize) -> usize {
+        match self.groups.remove(index) {
+            Some(group) => self.remove_particles(group.get_members()),
+            None => 0,
+        }
+    }
+
+    // Remove particles by index and return how many were removed
+    // Members of rigid clusters are kept, bonds to removed particles are dropped
+    // and probes that followed one stay where it was
+    pub fn remove_particles(&mut self, indices: &[usize]) -> usize {
+        let is_free = self.free_particles();
+        let mut removed: Vec<usize> = indices
+            .iter()
+            .copied()
+            .filter(|&i| is_free.get(i) == Some(&true))
+            .collect();
+        removed.sort_unstable();
+        removed.dedup();
+        if removed.is_empty() {
+            return 0;
+        }
+        let is_removed = |i: usize| removed.binary_search(&i).is_ok();
+        let renumber = |i: usize| i - removed.partition_point(|&r| r < i);
+
+        for probe in self.probes.iter_mut() {
+            if let ProbeTarget::Particle(i) = probe.target {
+                probe.target = if is_removed(i) {
+                    ProbeTarget::Point(self.particles[i].get_pos())
+                } else {
+                    ProbeTarget::Particle(renumber(i))
+                };
+            }
+        }
+        self.bonds
+            .retain(|bond| !is_removed(bond.i) && !is_removed(bond.j));
+        for bond in self.bonds.iter_mut() {
+            bond.i = renumber(bond.i);
+            bond.j = renumber(bond.j);
+        }
+        for cluster in self.rigid_clusters.iter_mut() {
+            cluster.renumber(&removed);
+        }
+        self.groups.renumber(&removed);
+
+        let mut index = 0;
+        self.particles.retain(|_| {
+            index += 1;
+            !is_removed(index - 1)
+        });
+        self.recalculate_kinetic_energy();
+        removed.len()
+    }
+}
+
+// Plugin
+pub struct VDWSimulation {
+    resources: SimulationState,
+    warnings: Vec<Problem>, // of the prototype it was compiled from
+    minimization: Option<minimize::MinimizationReport>, // none without a minimization
+}
+
+impl VDWSimulation {
+    // Defaults of the prototype
+    const PRESSURE_SAMPLING_PERIOD: f32 = 5.0; // Average impulses over this period of time
+    const HISTORY_CAPACITY: usize = 1000; // Number of frames kept in history
+    const DRIFT_REMOVAL_INTERVAL: usize = 10; // in animation frames
+    const STRESS_HISTORY_CAPACITY: usize = 5000; // Number of steps of stress and heat current kept for transport coefficients
+
+    // Make a new State
+    // This function is only used by StatePrototype's compile method
+    fn new(prototype: &SimulationPrototype) -> Self {
+        let dt = prototype.dt;
+        let steps_per_frame = prototype.steps_per_frame;
+
+        let mut resources = SimulationState {
+            particles: prototype.particles.clone(),
+            bonds: prototype.bonds.clone(),
+            rigid_clusters: prototype
+                .rigid_clusters
+                .iter()
+                .map(|members| RigidCluster::new(members.clone(), &prototype.particles))
+                .collect(),
+            bound: prototype.bound,
+            obstacles: prototype.obstacles.clone(),
+            groups: group::Groups::default(),
+            grid: prototype
+                .grid()
+                .with_switch(prototype.switch_start)
+                .with_potential(prototype.potential)
+                .with_neighbor_rule(prototype.neighbor_rule),
+            coulomb: prototype.coulomb,
+            dipolar: prototype.dipolar,
+            integrator: prototype.integrator,
+            rng: match prototype.seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            },
+
+            bound_rate: 0.0,
+            face_rates: prototype.face_rates,
+            target_temp: prototype.target_temp,
+            inject_rate: prototype.inject_rate,
+            remove_drift: prototype.remove_drift,
+            frames_since_drift_removal: 0,
+            paused: false,
+            heat_injection_ammount: 0.0,
+            rotation_injection_ammount: 0.0,
+            workspace: ForceWorkspace::default(),
+            pressure_pinned: PressurePinned {
+                previous_state: false,
+                is_pinned: false,
+                at_value: 0.5,
+            },
+
+            dt,
+            steps_per_frame,
+            frame_budget: FrameBudget {
+                enabled: prototype.frame_budget.is_some(),
+                ms_per_frame: prototype.frame_budget.unwrap_or(10.0),
+            },
+            pressure_sampling_period: prototype.pressure_sampling_period,
+            external_fields: prototype.external_fields.clone(),
+            schedule: prototype.schedule.clone(),
+            annealer: anneal::Annealer::default(),
+            isotherm: None,
+
+            steps: 0,
+            last_frame_steps: 0,
+            time: 0.0,
+            energy: Energy::default(),
+            pressure: Pressure::with_sampling_period(prototype.pressure_sampling_period, dt),
+            face_pressures: Pressure::for_faces(prototype.pressure_sampling_period, dt),
+            impulse_accumultor: FaceImpulses::default(),
+            history: History::with_capacity(prototype.history_capacity),
+            probes: prototype
+                .probes
+                .iter()
+                .cloned()
+                .map(|mut probe| {
+                    // probes added before the history capacity was set
+                    probe.resize_history(prototype.history_capacity);
+                    probe
+                })
+                .collect(),
+            regions: Vec::new(),
+            stress: stress::StressHistory::with_capacity(Self::STRESS_HISTORY_CAPACITY, dt),
+            heat_flux: heat_flux::HeatFluxHistory::with_capacity(Self::STRESS_HISTORY_CAPACITY, dt),
+            collisions: collision::CollisionStats::default(),
+            timings: timing::Timings::default(),
+        };
+
+        let minimization = prototype
+            .minimization
+            .map(|(max_iters, tolerance)| resources.minimize_energy(max_iters, tolerance));
+
+        Self {
+            resources,
+            warnings: prototype.warnings(),
+            minimization,
+        }
+    }
+
+    // Outcome of the minimization of the initial particles, see set_minimization
+    pub fn minimization(&self) -> Option<minimize::MinimizationReport> {
+        self.minimization
+    }
+
+    // Setups that compiled but are likely not what was meant, see SimulationPrototype::warnings
+    pub fn warnings(&self) -> &[Problem] {
+        &self.warnings
+    }
+
+    // Take the simulation state out of the plugin
+    // Used to run simulations without rendering
+    pub fn into_state(self) -> SimulationState {
+        self.resources
+    }
+}
@@ -0,0 +1,106 @@
+// Annealing controller
+// Drives the thermostat target from a start to an end temperature over a span of simulated time,
+// for slow cooling and heating runs without dragging the temperature slider
+// The thermostat still sets how fast the particles follow the target
+
+// What happens once the end temperature is reached
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AnnealMode {
+    Once,    // stay at the end temperature
+    Loop,    // jump back to the start temperature and ramp again
+    Reverse, // ramp back to the start temperature, then forth again
+}
+
+impl AnnealMode {
+    pub const ALL: [AnnealMode; 3] = [AnnealMode::Once, AnnealMode::Loop, AnnealMode::Reverse];
+    pub const NAMES: [&'static str; 3] = ["once", "loop", "reverse"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .position(|&n| n == name)
+            .map(|i| Self::ALL[i])
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnnealMode::Once => "Once",
+            AnnealMode::Loop => "Loop",
+            AnnealMode::Reverse => "Reverse",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Annealer {
+    pub running: bool,
+    pub start_temp: f32,
+    pub end_temp: f32,
+    pub duration: f32, // simulated time of one ramp
+    pub mode: AnnealMode,
+    started_at: f32, // simulated time the ramp began
+}
+
+impl Default for Annealer {
+    fn default() -> Self {
+        Self {
+            running: false,
+            start_temp: 1.0,
+            end_temp: 0.1,
+            duration: 100.0,
+            mode: AnnealMode::Once,
+            started_at: 0.0,
+        }
+    }
+}
+
+impl Annealer {
+    pub fn is_valid(&self) -> bool {
+        self.duration > 0.0 && self.start_temp >= 0.0 && self.end_temp >= 0.0
+    }
+
+    // Begin the ramp at the start temperature
+    pub fn start(&mut self, time: f32) {
+        self.running = true;
+        self.started_at = time;
+    }
+
+    // Position along the ramp at a simulated time, 0 at the start temperature and 1 at the end temperature
+    pub fn progress(&self, time: f32) -> f32 {
+        let ramps = ((time - self.started_at) / self.duration).max(0.0);
+        match self.mode {
+            AnnealMode::Once => ramps.min(1.0),
+            AnnealMode::Loop => ramps.fract(),
+            AnnealMode::Reverse => {
+                let phase = ramps % 2.0;
+                if phase <= 1.0 {
+                    phase
+                } else {
+                    2.0 - phase
+                }
+            }
+        }
+    }
+
+    pub fn target_at(&self, time: f32) -> f32 {
+        self.start_temp + (self.end_temp - self.start_temp) * self.progress(time)
+    }
+
+    // Only a ramp run once comes to an end
+    pub fn is_finished(&self, time: f32) -> bool {
+        self.mode == AnnealMode::Once && time - self.started_at >= self.duration
+    }
+
+    // Thermostat target for the current step, None when the controller isn't running
+    // A ramp run once stops after setting the end temperature
+    pub fn advance(&mut self, time: f32) -> Option<f32> {
+        if !self.running || !self.is_valid() {
+            return None;
+        }
+        let target = self.target_at(time);
+        if self.is_finished(time) {
+            self.running = false;
+        }
+        Some(target)
+    }
+}
@@ -0,0 +1,90 @@
+use super::physics;
+use super::sim_space::Boundary;
+use glam::{Mat3, Vec3};
+
+////////////////////////////////////////////////////////////
+// Bond is a harmonic spring connecting two particles
+// Used to build dimers and polymer-like chains
+// Bonded pairs keep their pair interaction as in bead-spring models, there is no exclusion, so a
+// rest length inside the pair repulsion fights it; compile warns about those
+// To be used internally by State
+//
+#[derive(Clone, Copy)]
+pub struct Bond {
+    pub i: usize, // index of the first particle
+    pub j: usize, // index of the second particle
+    pub k: f32,   // spring constant
+    pub r0: f32,  // rest length
+}
+
+impl Bond {
+    pub fn new(i: usize, j: usize, k: f32, r0: f32) -> Self {
+        Self { i, j, k, r0 }
+    }
+
+    // Check for a valid bond between n particles
+    pub fn is_valid(&self, n: usize) -> bool {
+        self.i < n && self.j < n && self.i != self.j && self.k >= 0.0 && self.r0 >= 0.0
+    }
+}
+
+// Calculate the spring forces of all bonds on the particles
+// Return (forces, potential energies)
+// The energy of each bond is split evenly between the two particles it connects
+// The bonds are stretched between the particles as they are, ignoring periodic faces
+pub fn calculate_force(bonds: &[Bond], particles: &[Vec3]) -> (Vec<Vec3>, Vec<f32>) {
+    let (mut forces, mut potential_energies) = (Vec::new(), Vec::new());
+    calculate_force_into(
+        bonds,
+        particles,
+        &Boundary::new(),
+        &mut forces,
+        &mut potential_energies,
+    );
+    (forces, potential_energies)
+}
+
+// Same as calculate_force, writing the forces and potential energies into reused buffers
+// Bonds reach across the periodic faces of bound to the nearest image of their far end
+pub fn calculate_force_into(
+    bonds: &[Bond],
+    particles: &[Vec3],
+    bound: &Boundary,
+    forces: &mut Vec<Vec3>,
+    potential_energies: &mut Vec<f32>,
+) {
+    forces.clear();
+    forces.resize(particles.len(), Vec3::ZERO);
+    potential_energies.clear();
+    potential_energies.resize(particles.len(), 0.0);
+
+    for bond in bonds {
+        let (pos_i, pos_j) = ends(bond, particles, bound);
+        let (force, potential) = physics::harmonic_interaction(pos_i, pos_j, bond.k, bond.r0);
+
+        // equal and opposite forces on both ends
+        forces[bond.i] += force;
+        forces[bond.j] -= force;
+        potential_energies[bond.i] += potential / 2.0;
+        potential_energies[bond.j] += potential / 2.0;
+    }
+}
+
+// Sum of r_ij (x) f_ij over all bonds
+pub fn calculate_virial(bonds: &[Bond], particles: &[Vec3], bound: &Boundary) -> Mat3 {
+    bonds.iter().fold(Mat3::ZERO, |acc, bond| {
+        let (pos_i, pos_j) = ends(bond, particles, bound);
+        let (force, _) = physics::harmonic_interaction(pos_i, pos_j, bond.k, bond.r0);
+        acc + physics::outer_product(pos_i - pos_j, force)
+    })
+}
+
+// Positions of the two ends of a bond, the second one at its nearest image
+// internal helper function
+fn ends(bond: &Bond, particles: &[Vec3], bound: &Boundary) -> (Vec3, Vec3) {
+    let pos_i = particles[bond.i];
+    (
+        pos_i,
+        pos_i - bound.minimum_image(pos_i - particles[bond.j]),
+    )
+}
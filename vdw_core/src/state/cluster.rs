@@ -0,0 +1,69 @@
+// Cluster analysis
+// Groups particles that are neighbors of each other into clusters
+// Used to watch the gas condense into droplets
+use super::network::Network;
+
+// Summary of the clusters of a network
+#[derive(Clone, Default)]
+pub struct ClusterStats {
+    pub distribution: Vec<(usize, usize)>, // (cluster size, number of clusters of that size), smallest first
+    pub clusters: usize,
+    pub largest: usize,
+    pub largest_fraction: f32, // fraction of all particles that are in the largest cluster
+}
+
+impl ClusterStats {
+    pub fn from_network(network: &Network) -> Self {
+        let components = network.components();
+        let particles: usize = components.iter().map(|c| c.len()).sum();
+
+        // components are sorted largest first
+        let mut distribution: Vec<(usize, usize)> = Vec::new();
+        for component in components.iter().rev() {
+            match distribution.last_mut() {
+                Some((size, count)) if *size == component.len() => *count += 1,
+                _ => distribution.push((component.len(), 1)),
+            }
+        }
+
+        let largest = components.first().map_or(0, |c| c.len());
+        Self {
+            distribution,
+            clusters: components.len(),
+            largest,
+            largest_fraction: largest as f32 / particles.max(1) as f32,
+        }
+    }
+}
+
+// Settings and latest result of the cluster analysis
+pub struct ClusterView {
+    pub enabled: bool,
+    pub interval: usize, // number of frames between updates
+    frames: usize,
+    pub stats: ClusterStats,
+}
+
+impl Default for ClusterView {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 10,
+            frames: 0,
+            stats: ClusterStats::default(),
+        }
+    }
+}
+
+impl ClusterView {
+    // Check whether the analysis is due this frame
+    // Counts frames, call once per frame
+    pub fn is_due(&mut self) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let due = self.frames % self.interval.max(1) == 0;
+        self.frames += 1;
+        due
+    }
+}
@@ -0,0 +1,164 @@
+// Liquid-gas coexistence from an isotherm
+// Below the critical temperature a van der Waals like isotherm has a loop: going from small to large volumes
+// the pressure falls, rises again between the two spinodals and then falls along the gas branch
+// The Maxwell construction replaces the loop by a flat line at the coexistence pressure,
+// drawn so that the areas the line cuts off above and below the isotherm are equal
+// The isotherm is interpolated linearly between the measured points, volumes are per particle
+use super::experiment::IsothermPoint;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Coexistence {
+    pub pressure: f32,
+    pub liquid_density: f32,
+    pub gas_density: f32,
+    pub temperature: f32, // mean measured temperature of the points
+}
+
+// Point of the isotherm as volume per particle
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    volume: f32,
+    pressure: f32,
+    error: f32,
+}
+
+impl Coexistence {
+    // bisections of the coexistence pressure
+    const ITERATIONS: usize = 60;
+    // a rise in pressure only counts as a loop when it is this many standard errors above the noise
+    const SIGNIFICANCE: f32 = 2.0;
+
+    // None when the isotherm has no loop, or doesn't reach far enough into both branches to close the construction
+    pub fn of(points: &[IsothermPoint]) -> Option<Self> {
+        let curve = curve(points);
+        let (spinodal_liquid, spinodal_gas) = find_loop(&curve)?;
+
+        // the flat line has to cross the liquid branch left of the loop and the gas branch right of it
+        let liquid_max = curve[..=spinodal_liquid]
+            .iter()
+            .map(|n| n.pressure)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let gas_min = curve[spinodal_gas..]
+            .iter()
+            .map(|n| n.pressure)
+            .fold(f32::INFINITY, f32::min);
+        let mut lo = curve[spinodal_liquid].pressure.max(gas_min);
+        let mut hi = curve[spinodal_gas].pressure.min(liquid_max);
+        if lo >= hi {
+            return None;
+        }
+
+        // the area between the isotherm and the line falls as the line is raised
+        let area = |pressure| {
+            let (v_liquid, v_gas) = crossings(&curve, spinodal_liquid, spinodal_gas, pressure)?;
+            Some((
+                integrate(&curve, v_liquid, v_gas, pressure),
+                v_liquid,
+                v_gas,
+            ))
+        };
+        let (area_lo, _, _) = area(lo)?;
+        let (area_hi, _, _) = area(hi)?;
+        if area_lo < 0.0 || area_hi > 0.0 {
+            return None;
+        }
+        for _ in 0..Self::ITERATIONS {
+            let mid = 0.5 * (lo + hi);
+            match area(mid) {
+                Some((a, _, _)) if a > 0.0 => lo = mid,
+                Some(_) => hi = mid,
+                None => return None,
+            }
+        }
+        let pressure = 0.5 * (lo + hi);
+        let (_, v_liquid, v_gas) = area(pressure)?;
+        let temperature = points.iter().map(|p| p.temperature).sum::<f32>() / points.len() as f32;
+        Some(Self {
+            pressure,
+            liquid_density: 1.0 / v_liquid,
+            gas_density: 1.0 / v_gas,
+            temperature,
+        })
+    }
+}
+
+// Points ordered by volume per particle
+// internal helper function
+fn curve(points: &[IsothermPoint]) -> Vec<Node> {
+    let mut curve: Vec<Node> = points
+        .iter()
+        .filter(|p| p.density > 0.0 && p.pressure.is_finite())
+        .map(|p| Node {
+            volume: 1.0 / p.density,
+            pressure: p.pressure,
+            error: p.pressure_error,
+        })
+        .collect();
+    curve.sort_by(|a, b| a.volume.partial_cmp(&b.volume).unwrap());
+    curve
+}
+
+// Indices of the liquid and the gas spinodal: the pair of points where the pressure rises most with the volume
+// None if no rise stands out of the errors of the points
+// internal helper function
+fn find_loop(curve: &[Node]) -> Option<(usize, usize)> {
+    let mut best = None;
+    let mut best_rise = 0.0;
+    for (i, a) in curve.iter().enumerate() {
+        for (j, b) in curve.iter().enumerate().skip(i + 1) {
+            let noise = Coexistence::SIGNIFICANCE * (a.error.powi(2) + b.error.powi(2)).sqrt();
+            let rise = b.pressure - a.pressure;
+            if rise > noise && rise > best_rise {
+                best_rise = rise;
+                best = Some((i, j));
+            }
+        }
+    }
+    best
+}
+
+// Volumes where the line at pressure meets the liquid and the gas branch, the crossings closest to the loop
+// internal helper function
+fn crossings(curve: &[Node], liquid: usize, gas: usize, pressure: f32) -> Option<(f32, f32)> {
+    let v_liquid = (1..=liquid)
+        .rev()
+        .find_map(|k| crossing(curve[k - 1], curve[k], pressure))?;
+    let v_gas = (gas + 1..curve.len()).find_map(|k| crossing(curve[k - 1], curve[k], pressure))?;
+    Some((v_liquid, v_gas))
+}
+
+// Volume where the segment from a to b passes pressure
+// internal helper function
+fn crossing(a: Node, b: Node, pressure: f32) -> Option<f32> {
+    let (lo, hi) = (a.pressure.min(b.pressure), a.pressure.max(b.pressure));
+    if pressure < lo || pressure > hi {
+        return None;
+    }
+    if hi - lo <= 0.0 {
+        return Some(a.volume);
+    }
+    let t = (pressure - a.pressure) / (b.pressure - a.pressure);
+    Some(a.volume + t * (b.volume - a.volume))
+}
+
+// Integral of the isotherm minus pressure from volume lo to hi
+// internal helper function
+fn integrate(curve: &[Node], lo: f32, hi: f32, pressure: f32) -> f32 {
+    let at = |a: Node, b: Node, v: f32| {
+        let t = (v - a.volume) / (b.volume - a.volume);
+        a.pressure + t * (b.pressure - a.pressure)
+    };
+    curve
+        .windows(2)
+        .filter(|w| w[1].volume > w[0].volume)
+        .map(|w| {
+            let (a, b) = (w[0], w[1]);
+            let v0 = a.volume.max(lo);
+            let v1 = b.volume.min(hi);
+            if v1 <= v0 {
+                return 0.0;
+            }
+            (0.5 * (at(a, b, v0) + at(a, b, v1)) - pressure) * (v1 - v0)
+        })
+        .sum()
+}
@@ -0,0 +1,113 @@
+// Collision statistics
+// Counts particles hitting the walls and pairs of particles running into each other per unit of simulated time
+// A pair collides when it gets closer than its collision diameter, where the pair energy crosses zero
+// Collisions are told apart from lasting contacts by comparing the contacts of each particle with the step before,
+// a particle that gains one contact while losing another in the same step goes uncounted
+// The rate of collisions per particle gives an estimate of the mean free path
+
+#[derive(Clone)]
+pub struct CollisionStats {
+    pub period: f32, // counts are turned into rates over this much simulated time
+    touching_wall: Vec<bool>, // soft walls: whether each particle was pushed by a wall the step before
+    contacts: Vec<usize>,     // contacts of each particle the step before
+    wall_hits: usize,
+    pair_hits: f32, // half of the contacts gained, every pair is seen from both of its particles
+    elapsed: f32,
+    wall_rate: f32,
+    pair_rate: f32,
+    measured: bool,
+}
+
+impl Default for CollisionStats {
+    fn default() -> Self {
+        Self {
+            period: 1.0,
+            touching_wall: Vec::new(),
+            contacts: Vec::new(),
+            wall_hits: 0,
+            pair_hits: 0.0,
+            elapsed: 0.0,
+            wall_rate: 0.0,
+            pair_rate: 0.0,
+            measured: false,
+        }
+    }
+}
+
+impl CollisionStats {
+    // Particles pushed by the soft walls this step, a hit is counted when the push starts
+    pub fn record_wall_contacts(&mut self, touching: impl Iterator<Item = bool>) {
+        let touching: Vec<bool> = touching.collect();
+        if touching.len() == self.touching_wall.len() {
+            self.wall_hits += touching
+                .iter()
+                .zip(self.touching_wall.iter())
+                .filter(|(&now, &before)| now && !before)
+                .count();
+        }
+        self.touching_wall = touching;
+    }
+
+    // Bounces off the hard walls, which are events already
+    pub fn record_wall_hits(&mut self, hits: usize) {
+        self.wall_hits += hits;
+    }
+
+    // Contacts of each particle this step, from the pair loop of the force calculation
+    // Particles added or removed since the step before reset the comparison
+    pub fn record_contacts(&mut self, contacts: &[usize]) {
+        if contacts.len() == self.contacts.len() {
+            let gained: usize = contacts
+                .iter()
+                .zip(self.contacts.iter())
+                .map(|(&now, &before)| now.saturating_sub(before))
+                .sum();
+            self.pair_hits += gained as f32 / 2.0;
+        }
+        self.contacts.clear();
+        self.contacts.extend_from_slice(contacts);
+    }
+
+    // Close the step, the rates are updated once a period has been counted
+    pub fn finish_step(&mut self, dt: f32) {
+        self.elapsed += dt;
+        if self.elapsed >= self.period {
+            self.wall_rate = self.wall_hits as f32 / self.elapsed;
+            self.pair_rate = self.pair_hits / self.elapsed;
+            self.measured = true;
+            self.wall_hits = 0;
+            self.pair_hits = 0.0;
+            self.elapsed = 0.0;
+        }
+    }
+
+    // Whether a whole period has been counted yet
+    pub fn is_measured(&self) -> bool {
+        self.measured
+    }
+
+    // Wall hits per unit of time
+    pub fn wall_rate(&self) -> f32 {
+        self.wall_rate
+    }
+
+    // Pair collisions per unit of time
+    pub fn pair_rate(&self) -> f32 {
+        self.pair_rate
+    }
+
+    // Collisions each particle takes part in per unit of time, two particles per collision
+    pub fn frequency(&self, n: usize) -> f32 {
+        2.0 * self.pair_rate / n.max(1) as f32
+    }
+
+    // Distance a particle moving at the mean speed covers between collisions, None before any collision
+    pub fn mean_free_path(&self, mean_speed: f32, n: usize) -> Option<f32> {
+        let frequency = self.frequency(n);
+        if frequency > 0.0 {
+            Some(mean_speed / frequency)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,528 @@
+// Simulation setups described by a TOML file
+// Every entry is optional, missing entries keep the defaults of SimulationPrototype::new
+//
+// Example:
+//   dt = 0.001
+//   steps_per_frame = 20
+//   frame_budget_ms = 10.0 # replaces steps_per_frame with a time budget
+//   pressure_sampling_period = 5.0 # wall impulses are averaged over this much time
+//   history_capacity = 1000 # frames kept for the plots
+//   remove_drift = true
+//   seed = 42 # of the random kicks of stochastic integrators, optional
+//   inert_fraction = 0.3 # share of the initialized particles that only repel, optional
+//   mass_distribution = { kind = "uniform", lo = 0.5, hi = 2.0 } # of the initialized particles, optional
+//   radius_distribution = { kind = "bimodal", a = 0.075, b = 0.1, fraction = 0.5 } # or normal with mean and std, optional
+//   integrator = { kind = "langevin", gamma = 1.0 } # or verlet, or brownian with gamma, bath temperature is the thermostat target
+//
+//   [boundary]
+//   x = 15.0
+//   y = 15.0
+//   z = 15.0
+//   lo = [0.0, 0.0, 0.0] # lower corner, optional
+//   face_rates_hi = [0.0, 0.1, 0.0] # walls moving outwards, optional, also face_rates_lo
+//   wall_model = "hard_reflect" # or "soft", or "periodic", or { thermal = 1.0 } for walls at that temperature
+//   shear_rate = 0.5 # Lees-Edwards shear flow along x with the gradient along y, periodic boxes only, optional
+//   adhesion = { strength = 0.6, faces = ["y_lo"] } # attracting faces, optional, also sigma and cutoff
+//
+//   [thermostat]
+//   target_temp = 1.0
+//   inject_rate = 0.1
+//
+//   [force_field]
+//   grid_unit_size = 1.0
+//   grid_reach = 1
+//   cutoff_radius = 0.35 # range of the interactions, replaces grid_unit_size and grid_reach, optional
+//   switch_start = 0.8 # smooth cutoff from here up to the range, optional
+//   potential = { kind = "morse", depth = 0.6, width = 40.0, r0 = 0.15 } # or lennard_jones, or soft_sphere with epsilon and n
+//   well_depths = [1.0, 0.5] # relative well depths cycled through by particle index, applied after the initializer, optional
+//
+//   [neighbors] # which particles count as neighbors for the coloring and the cluster analysis, every entry optional
+//   metric = "nearest" # or "distance", the default
+//   distance = 0.3 # for two default particles, at most the interaction range
+//   nearest = 12 # neighbors each particle picks by the nearest metric
+//
+//   [electrostatics] # charges cycle through the pattern by particle index, applied after the initializer
+//   charges = [1.0, -1.0]
+//   strength = 0.05 # optional, coupling of two unit charges at unit distance
+//   damping = 1.0 # optional, inverse screening length of the damped shifted force
+//
+//   [dipoles] # dipoles along the body axes cycle through the pattern by particle index, applied after the initializer
+//   moments = [[0.0, 0.0, 1.0]]
+//   strength = 0.01 # optional, coupling of two unit dipoles side by side at unit distance
+//   field = [0.0, 0.0, 5.0] # optional, uniform applied field the dipoles align with
+//
+//   [[external_field]]
+//   kind = "harmonic_trap" # centered on the box unless center is given
+//   k = 0.5
+//
+//   [[external_field]]
+//   kind = "oscillating"
+//   amplitude = [0.0, 0.0, 2.0]
+//   frequency = 0.2
+//
+//   [[event]]
+//   kind = "set" # at step 50000 set the thermostat target
+//   parameter = "target_temp" # or inject_rate, bound_rate, pinned_pressure, dt
+//   step = 50000
+//   value = 0.5
+//
+//   [[event]]
+//   kind = "ramp" # change linearly over the given number of steps
+//   parameter = "bound_rate"
+//   step = 60000
+//   steps = 10000
+//   from = 0.0
+//   to = -0.05
+//
+//   [[obstacle]] # immovable, kept out like the walls, initializers leave their inside empty
+//   kind = "sphere"
+//   center = [7.5, 7.5, 7.5]
+//   radius = 2.0
+//
+//   [[obstacle]]
+//   kind = "box"
+//   lo = [0.0, 0.0, 6.0]
+//   hi = [15.0, 3.0, 9.0]
+//
+//   [pores] # porous layout filling the box, added to the obstacles
+//   kind = "sphere_packing" # random spheres of one radius, stops early once no more fit
+//   porosity = 0.7
+//   radius = 1.0
+//   # or kind = "slit" with width, a channel between two plates across the y axis
+//
+//   [[fixed_region]] # the particles placed inside are fixed, they exert their forces but never move
+//   lo = [0.0, 0.0, 0.0]
+//   hi = [15.0, 1.0, 15.0]
+//
+//   [minimize] # relax overlapping particles before the run instead of deleting them
+//   max_iters = 1000
+//   tolerance = 1.0
+//
+//   [initializer]
+//   kind = "spherical_cloud"
+//   n = 2000
+//   sigma = 1.0
+//   temp = 1.4
+use super::dipole::Dipolar;
+use super::electrostatics::Coulomb;
+use super::error::ConfigError;
+use super::external_field::{Gravity, HarmonicTrap, OscillatingField};
+use super::integrator::Integrator;
+use super::obstacle::{Obstacle, PoreLayout};
+use super::physics::{NeighborMetric, NeighborRule, PotentialModel};
+use super::schedule::{Event, ScheduleParameter};
+use super::sim_space::{Face, WallAdhesion, WallModel};
+use super::state_generator::{Distribution, Initialize};
+use super::SimulationPrototype;
+use glam::Vec3;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SimulationConfig {
+    pub dt: Option<f32>,
+    pub steps_per_frame: Option<usize>,
+    pub frame_budget_ms: Option<f32>,
+    pub pressure_sampling_period: Option<f32>,
+    pub history_capacity: Option<usize>,
+    pub ext_accel: Option<[f32; 3]>,
+    pub remove_drift: Option<bool>,
+    pub seed: Option<u64>,
+    pub inert_fraction: Option<f32>,
+    pub mass_distribution: Option<Distribution>,
+    pub radius_distribution: Option<Distribution>,
+    pub integrator: Option<Integrator>,
+    pub boundary: Option<BoundaryConfig>,
+    pub thermostat: Option<ThermostatConfig>,
+    pub force_field: Option<ForceFieldConfig>,
+    pub neighbors: Option<NeighborsConfig>,
+    pub electrostatics: Option<ElectrostaticsConfig>,
+    pub dipoles: Option<DipolesConfig>,
+    pub external_field: Option<Vec<ExternalFieldConfig>>,
+    pub obstacle: Option<Vec<ObstacleConfig>>,
+    pub pores: Option<PoresConfig>,
+    pub minimize: Option<MinimizeConfig>,
+    pub fixed_region: Option<Vec<FixedRegionConfig>>,
+    pub event: Option<Vec<EventConfig>>,
+    pub initializer: Option<InitializerConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BoundaryConfig {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub lo: Option<[f32; 3]>, // lower corner of the box, x, y, z are the lengths from there
+    pub face_rates_lo: Option<[f32; 3]>,
+    pub face_rates_hi: Option<[f32; 3]>,
+    pub wall_model: Option<WallModel>,
+    pub shear_rate: Option<f32>, // of the sliding images of a periodic box
+    pub adhesion: Option<AdhesionConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AdhesionConfig {
+    pub strength: f32,
+    pub faces: Vec<Face>,
+    pub sigma: Option<f32>,
+    pub cutoff: Option<f32>, // 3 sigma if not given
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThermostatConfig {
+    pub target_temp: f32,
+    pub inject_rate: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ForceFieldConfig {
+    pub grid_unit_size: Option<f32>,
+    pub grid_reach: Option<usize>,
+    pub cutoff_radius: Option<f32>,
+    pub switch_start: Option<f32>,
+    pub potential: Option<PotentialModel>,
+    pub well_depths: Option<Vec<f32>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NeighborsConfig {
+    pub metric: Option<NeighborMetric>,
+    pub distance: Option<f32>,
+    pub nearest: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ElectrostaticsConfig {
+    pub charges: Vec<f32>,
+    pub strength: Option<f32>,
+    pub damping: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DipolesConfig {
+    pub moments: Vec<[f32; 3]>,
+    pub strength: Option<f32>,
+    pub field: Option<[f32; 3]>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FixedRegionConfig {
+    pub lo: [f32; 3],
+    pub hi: [f32; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MinimizeConfig {
+    pub max_iters: usize,
+    pub tolerance: f32,
+}
+
+// Events of the schedule
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum EventConfig {
+    Set {
+        parameter: ScheduleParameter,
+        step: usize,
+        value: f32,
+    },
+    Ramp {
+        parameter: ScheduleParameter,
+        step: usize,
+        steps: usize,
+        from: f32,
+        to: f32,
+    },
+}
+
+// Presets of the ExternalField trait
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ExternalFieldConfig {
+    Gravity {
+        g: [f32; 3],
+    },
+    HarmonicTrap {
+        k: f32,
+        center: Option<[f32; 3]>,
+    },
+    Oscillating {
+        amplitude: [f32; 3],
+        frequency: f32,
+        phase: Option<f32>,
+    },
+}
+
+// Shapes of Obstacle
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ObstacleConfig {
+    Sphere { center: [f32; 3], radius: f32 },
+    Box { lo: [f32; 3], hi: [f32; 3] },
+}
+
+// Layouts of PoreLayout
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum PoresConfig {
+    Slit { width: f32 },
+    SpherePacking { porosity: f32, radius: f32 },
+}
+
+// Initializers of the Initialize trait
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum InitializerConfig {
+    SphericalCloud {
+        n: usize,
+        sigma: f32,
+        temp: f32,
+    },
+    Chains {
+        n_chains: usize,
+        chain_len: usize,
+        bond_len: f32,
+        k: f32,
+        temp: f32,
+    },
+}
+
+impl SimulationConfig {
+    pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+        toml::from_str(text).map_err(|err| ConfigError::parse(None, err))
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|err| ConfigError::io(path, err))?;
+        Self::from_toml(&text).map_err(|err| err.in_file(path))
+    }
+
+    // Build a prototype from the config
+    // The boundary is set before running the initializer so particles are placed inside it
+    pub fn to_prototype(&self) -> SimulationPrototype {
+        let mut prototype = SimulationPrototype::new();
+
+        if let Some(dt) = self.dt {
+            prototype = prototype.set_dt(dt);
+        }
+        if let Some(spf) = self.steps_per_frame {
+            prototype = prototype.set_steps_per_frame(spf);
+        }
+        if let Some(ms) = self.frame_budget_ms {
+            prototype = prototype.set_frame_budget(ms);
+        }
+        if let Some(period) = self.pressure_sampling_period {
+            prototype = prototype.set_pressure_sampling_period(period);
+        }
+        if let Some(capacity) = self.history_capacity {
+            prototype = prototype.set_history_capacity(capacity);
+        }
+        if let Some([x, y, z]) = self.ext_accel {
+            prototype = prototype.set_ext_a(Vec3::new(x, y, z));
+        }
+        if let Some(remove_drift) = self.remove_drift {
+            prototype = prototype.set_remove_drift(remove_drift);
+        }
+        if let Some(seed) = self.seed {
+            prototype = prototype.set_seed(seed);
+        }
+        if let Some(integrator) = self.integrator {
+            prototype = prototype.set_integrator(integrator);
+        }
+        if let Some(bound) = &self.boundary {
+            let lo = Vec3::from(bound.lo.unwrap_or([0.0; 3]));
+            prototype = prototype.set_bound_corners(lo, lo + Vec3::new(bound.x, bound.y, bound.z));
+            if bound.face_rates_lo.is_some() || bound.face_rates_hi.is_some() {
+                prototype = prototype.set_face_rates(
+                    bound.face_rates_lo.unwrap_or([0.0; 3]).into(),
+                    bound.face_rates_hi.unwrap_or([0.0; 3]).into(),
+                );
+            }
+            if let Some(wall_model) = bound.wall_model {
+                prototype = prototype.set_wall_model(wall_model);
+            }
+            if let Some(rate) = bound.shear_rate {
+                prototype = prototype.set_lees_edwards(rate);
+            }
+            if let Some(adhesion) = &bound.adhesion {
+                let mut wall = WallAdhesion::new(adhesion.strength, &adhesion.faces);
+                if let Some(sigma) = adhesion.sigma {
+                    wall.sigma = sigma;
+                    wall.cutoff = 3.0 * sigma;
+                }
+                if let Some(cutoff) = adhesion.cutoff {
+                    wall.cutoff = cutoff;
+                }
+                prototype = prototype.set_wall_adhesion(wall);
+            }
+        }
+        if let Some(thermostat) = &self.thermostat {
+            prototype = prototype
+                .set_target_temp(thermostat.target_temp)
+                .set_inject_rate(thermostat.inject_rate);
+        }
+        if let Some(force_field) = &self.force_field {
+            if let Some(unit_size) = force_field.grid_unit_size {
+                prototype = prototype.set_grid_unit_size(unit_size);
+            }
+            if let Some(reach) = force_field.grid_reach {
+                prototype = prototype.set_grid_reach(reach);
+            }
+            if let Some(radius) = force_field.cutoff_radius {
+                prototype = prototype.set_cutoff_radius(radius);
+            }
+            if let Some(r_switch) = force_field.switch_start {
+                prototype = prototype.set_switch_start(r_switch);
+            }
+            if let Some(potential) = force_field.potential {
+                prototype = prototype.set_potential(potential);
+            }
+        }
+        if let Some(neighbors) = &self.neighbors {
+            let default = NeighborRule::default();
+            prototype = prototype.set_neighbor_rule(NeighborRule {
+                metric: neighbors.metric.unwrap_or(default.metric),
+                distance: neighbors.distance.unwrap_or(default.distance),
+                nearest: neighbors.nearest.unwrap_or(default.nearest),
+            });
+        }
+
+        for field in self.external_field.iter().flatten() {
+            prototype = match *field {
+                ExternalFieldConfig::Gravity { g } => {
+                    prototype.add_external_field(Box::new(Gravity::new(g.into())))
+                }
+                ExternalFieldConfig::HarmonicTrap { k, center } => {
+                    let center = center.map_or(prototype.get_bound().center(), Vec3::from);
+                    prototype.add_external_field(Box::new(HarmonicTrap::new(center, k)))
+                }
+                ExternalFieldConfig::Oscillating {
+                    amplitude,
+                    frequency,
+                    phase,
+                } => prototype.add_external_field(Box::new(OscillatingField::new(
+                    amplitude.into(),
+                    frequency,
+                    phase.unwrap_or(0.0),
+                ))),
+            };
+        }
+
+        for event in self.event.iter().flatten() {
+            prototype = prototype.add_event(match *event {
+                EventConfig::Set {
+                    parameter,
+                    step,
+                    value,
+                } => Event::set(step, parameter, value),
+                EventConfig::Ramp {
+                    parameter,
+                    step,
+                    steps,
+                    from,
+                    to,
+                } => Event::ramp(step, parameter, from, to, steps),
+            });
+        }
+
+        // also before the initializer, which leaves the obstacles empty
+        for obstacle in self.obstacle.iter().flatten() {
+            prototype = prototype.add_obstacle(match *obstacle {
+                ObstacleConfig::Sphere { center, radius } => Obstacle::Sphere {
+                    center: center.into(),
+                    radius,
+                },
+                ObstacleConfig::Box { lo, hi } => Obstacle::Box {
+                    lo: lo.into(),
+                    hi: hi.into(),
+                },
+            });
+        }
+
+        prototype = match self.pores {
+            Some(PoresConfig::Slit { width }) => prototype.initialize_channel(width),
+            Some(PoresConfig::SpherePacking { porosity, radius }) => {
+                prototype.add_pores(PoreLayout::SpherePacking { porosity, radius })
+            }
+            None => prototype,
+        };
+
+        // must come before the initializer, which keeps overlapping particles if set
+        if let Some(minimize) = &self.minimize {
+            prototype = prototype.set_minimization(minimize.max_iters, minimize.tolerance);
+        }
+
+        if let Some(fraction) = self.inert_fraction {
+            prototype = prototype.set_inert_fraction(fraction);
+        }
+        prototype = prototype
+            .set_mass_distribution(self.mass_distribution)
+            .set_radius_distribution(self.radius_distribution);
+
+        prototype = match self.initializer {
+            Some(InitializerConfig::SphericalCloud { n, sigma, temp }) => {
+                prototype.initialize_spherical_cloud(n, sigma, temp)
+            }
+            Some(InitializerConfig::Chains {
+                n_chains,
+                chain_len,
+                bond_len,
+                k,
+                temp,
+            }) => prototype.initialize_chains(n_chains, chain_len, bond_len, k, temp),
+            None => prototype,
+        };
+
+        // well depths, charges, dipoles and fixed regions are given to the particles placed by the initializer
+        if let Some(depths) = self
+            .force_field
+            .as_ref()
+            .and_then(|f| f.well_depths.as_ref())
+        {
+            prototype = prototype.set_well_depths(depths);
+        }
+        if let Some(electrostatics) = &self.electrostatics {
+            let defaults = Coulomb::default();
+            prototype = prototype
+                .set_coulomb(Coulomb {
+                    strength: electrostatics.strength.unwrap_or(defaults.strength),
+                    damping: electrostatics.damping.unwrap_or(defaults.damping),
+                })
+                .set_charges(&electrostatics.charges);
+        }
+        if let Some(dipoles) = &self.dipoles {
+            let defaults = Dipolar::default();
+            let moments: Vec<Vec3> = dipoles.moments.iter().map(|&mu| mu.into()).collect();
+            prototype = prototype
+                .set_dipolar(Dipolar {
+                    strength: dipoles.strength.unwrap_or(defaults.strength),
+                    field: dipoles.field.unwrap_or(defaults.field),
+                })
+                .set_dipoles(&moments);
+        }
+        for region in self.fixed_region.iter().flatten() {
+            prototype = prototype.set_fixed_region(region.lo.into(), region.hi.into());
+        }
+
+        prototype
+    }
+}
+
+impl SimulationPrototype {
+    // Read a prototype from a TOML config file
+    pub fn from_config_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Ok(SimulationConfig::from_file(path)?.to_prototype())
+    }
+}
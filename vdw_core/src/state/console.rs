@@ -0,0 +1,281 @@
+// Console commands
+// Text commands typed into the gui console or read from a macro file, one per line
+// Parameters are changed through the same ScheduleParameter the schedule uses
+use super::bond::Bond;
+use super::particle::Particle;
+use super::physics;
+use super::schedule::ScheduleParameter;
+use super::sim_space::Boundary;
+use super::state_generator::{Distribution, Initialize};
+use super::SimulationState;
+
+pub const HELP: [&str; 7] = [
+    "set <temp|inject_rate|bound_rate|pressure|dt> <value>",
+    "add particles <n> sphere <sigma>",
+    "add particles <n> uniform",
+    "toggle <pause|drift|pin>",
+    "save <file.ron>",
+    "run <file>  (one command per line, # starts a comment)",
+    "help",
+];
+
+// Flags that can be flipped
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Toggle {
+    Pause,
+    Drift,       // momentum drift removal
+    PinPressure, // pins the pressure at its current target
+}
+
+// Where added particles are placed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Placement {
+    Sphere { sigma: f32 }, // gaussian cloud at the box center
+    Uniform,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConsoleCommand {
+    Set(ScheduleParameter, f32),
+    AddParticles { n: usize, placement: Placement },
+    Toggle(Toggle),
+    Save(String),
+    Run(String), // execute the commands of a macro file
+    Help,
+}
+
+impl ConsoleCommand {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["set", parameter, value] => {
+                let parameter = parse_parameter(parameter)?;
+                let value = parse_number(value)?;
+                parameter.check(value)?;
+                Ok(ConsoleCommand::Set(parameter, value))
+            }
+            ["add", "particles", n, "sphere", sigma] => Ok(ConsoleCommand::AddParticles {
+                n: parse_number(n)?,
+                placement: Placement::Sphere {
+                    sigma: parse_positive(sigma)?,
+                },
+            }),
+            ["add", "particles", n, "uniform"] => Ok(ConsoleCommand::AddParticles {
+                n: parse_number(n)?,
+                placement: Placement::Uniform,
+            }),
+            ["toggle", flag] => match *flag {
+                "pause" => Ok(ConsoleCommand::Toggle(Toggle::Pause)),
+                "drift" => Ok(ConsoleCommand::Toggle(Toggle::Drift)),
+                "pin" => Ok(ConsoleCommand::Toggle(Toggle::PinPressure)),
+                _ => Err(format!("unknown flag {}", flag)),
+            },
+            ["save", path] => Ok(ConsoleCommand::Save(path.to_string())),
+            ["run", path] => Ok(ConsoleCommand::Run(path.to_string())),
+            ["help"] => Ok(ConsoleCommand::Help),
+            [] => Err("empty command".to_string()),
+            _ => Err(format!("unknown command '{}', try help", line.trim())),
+        }
+    }
+}
+
+impl Toggle {
+    pub fn apply(self, state: &mut SimulationState) {
+        match self {
+            Toggle::Pause => state.paused = !state.paused,
+            Toggle::Drift => state.remove_drift = !state.remove_drift,
+            Toggle::PinPressure => {
+                state.pressure_pinned.is_pinned = !state.pressure_pinned.is_pinned
+            }
+        }
+    }
+}
+
+impl Placement {
+    // New particles at the temperature of the state, generated by the initializers
+    // Random, so generate once and hand the same particles to every copy of the state
+    pub fn particles(self, n: usize, state: &SimulationState) -> Vec<Particle> {
+        let temperature = if state.particles.is_empty() {
+            state.target_temp
+        } else {
+            state.temperature()
+        };
+        let speed = physics::velocity_spread(temperature);
+
+        let batch = Batch {
+            bound: state.bound,
+            particles: Vec::new(),
+            inert_fraction: 0.0,
+            mass_distribution: None,
+            radius_distribution: None,
+        };
+        let batch = match self {
+            Placement::Sphere { sigma } => batch.initialize_spherical_cloud(n, sigma, speed),
+            Placement::Uniform => batch.initialize_uniform(n, speed),
+        };
+        batch.particles
+    }
+}
+
+// Particles generated for a box, without the rest of a prototype
+struct Batch {
+    bound: Boundary,
+    particles: Vec<Particle>,
+    inert_fraction: f32,
+    mass_distribution: Option<Distribution>,
+    radius_distribution: Option<Distribution>,
+}
+
+impl Initialize for Batch {
+    fn get_bound(&self) -> Boundary {
+        self.bound
+    }
+
+    fn set_particles(mut self, particles: Vec<Particle>) -> Self {
+        self.particles = particles;
+        self
+    }
+
+    fn set_bonds(self, _bonds: Vec<Bond>) -> Self {
+        self
+    }
+
+    fn set_inert_fraction(mut self, fraction: f32) -> Self {
+        self.inert_fraction = fraction;
+        self
+    }
+
+    fn get_inert_fraction(&self) -> f32 {
+        self.inert_fraction
+    }
+
+    fn set_mass_distribution(mut self, distribution: Option<Distribution>) -> Self {
+        self.mass_distribution = distribution;
+        self
+    }
+
+    fn get_mass_distribution(&self) -> Option<Distribution> {
+        self.mass_distribution
+    }
+
+    fn set_radius_distribution(mut self, distribution: Option<Distribution>) -> Self {
+        self.radius_distribution = distribution;
+        self
+    }
+
+    fn get_radius_distribution(&self) -> Option<Distribution> {
+        self.radius_distribution
+    }
+}
+
+// internal helper function
+fn parse_parameter(name: &str) -> Result<ScheduleParameter, String> {
+    match name {
+        "temp" | "target_temp" => Ok(ScheduleParameter::TargetTemp),
+        "inject_rate" => Ok(ScheduleParameter::InjectRate),
+        "bound_rate" => Ok(ScheduleParameter::BoundRate),
+        "pressure" | "pinned_pressure" => Ok(ScheduleParameter::PinnedPressure),
+        "dt" => Ok(ScheduleParameter::Dt),
+        _ => Err(format!("unknown parameter {}", name)),
+    }
+}
+
+// internal helper function
+fn parse_number<T: std::str::FromStr>(word: &str) -> Result<T, String> {
+    word.parse()
+        .map_err(|_| format!("{} is not a valid number", word))
+}
+
+// internal helper function
+fn parse_positive(word: &str) -> Result<f32, String> {
+    let value: f32 = parse_number(word)?;
+    if value.is_finite() && value > 0.0 {
+        Ok(value)
+    } else {
+        Err(format!("{} is not a finite number above 0", word))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_are_parsed_word_by_word() {
+        let parsed = [
+            (
+                "set temp 1.5",
+                ConsoleCommand::Set(ScheduleParameter::TargetTemp, 1.5),
+            ),
+            (
+                "  set   dt  0.002 ",
+                ConsoleCommand::Set(ScheduleParameter::Dt, 0.002),
+            ),
+            (
+                "add particles 20 sphere 0.5",
+                ConsoleCommand::AddParticles {
+                    n: 20,
+                    placement: Placement::Sphere { sigma: 0.5 },
+                },
+            ),
+            (
+                "add particles 5 uniform",
+                ConsoleCommand::AddParticles {
+                    n: 5,
+                    placement: Placement::Uniform,
+                },
+            ),
+            ("toggle pin", ConsoleCommand::Toggle(Toggle::PinPressure)),
+            ("save out.ron", ConsoleCommand::Save("out.ron".to_string())),
+            (
+                "run setup.txt",
+                ConsoleCommand::Run("setup.txt".to_string()),
+            ),
+            ("help", ConsoleCommand::Help),
+        ];
+        for (line, command) in parsed.iter() {
+            assert_eq!(
+                ConsoleCommand::parse(line).as_ref(),
+                Ok(command),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_words_are_reported() {
+        let errors = [
+            ("", "empty command"),
+            ("set volume 2", "unknown parameter volume"),
+            ("set temp warm", "warm is not a valid number"),
+            ("add particles -3 uniform", "-3 is not a valid number"),
+            ("toggle walls", "unknown flag walls"),
+            ("jump", "unknown command 'jump', try help"),
+        ];
+        for (line, message) in errors.iter() {
+            assert_eq!(
+                ConsoleCommand::parse(line),
+                Err(message.to_string()),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn values_out_of_range_are_parse_errors() {
+        for line in [
+            "set temp -1",
+            "set dt NaN",
+            "set pressure inf",
+            "add particles 10 sphere 0",
+            "add particles 10 sphere -0.2",
+            "add particles 10 sphere inf",
+        ]
+        .iter()
+        {
+            assert!(ConsoleCommand::parse(line).is_err(), "{}", line);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+// Dipole-dipole interactions between particles carrying a dipole in their body frame
+// Together with the pair potential this makes a Stockmayer fluid
+// The dipoles turn with the particles, so the interactions exert torques as well as forces
+// Pairs interact up to the cutoff and not beyond, the energy jumps there
+use super::physics;
+use super::sim_space::Boundary;
+use crate::par::*;
+use glam::{Mat3, Vec3};
+use serde::Deserialize;
+
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Dipolar {
+    pub strength: f32, // coupling constant, the energy of two unit dipoles side by side at unit distance
+    #[serde(default)]
+    pub field: [f32; 3], // uniform applied field the dipoles align with, none by default
+}
+
+impl Default for Dipolar {
+    fn default() -> Self {
+        Self {
+            strength: 1.0,
+            field: [0.0; 3],
+        }
+    }
+}
+
+impl Dipolar {
+    pub fn is_valid(&self) -> bool {
+        self.strength >= 0.0 && self.strength.is_finite() && Vec3::from(self.field).is_finite()
+    }
+
+    pub fn get_field(&self) -> Vec3 {
+        Vec3::from(self.field)
+    }
+
+    // Direction the dipoles align with, along the field or up without one
+    pub fn alignment_axis(&self) -> Vec3 {
+        let field = self.get_field();
+        if field == Vec3::ZERO {
+            Vec3::Z
+        } else {
+            field.normalize()
+        }
+    }
+
+    // Force on and energy of the dipole mu_targ at separation r from mu_other, r = r_targ - r_other
+    // Both dipoles in the world frame, pairs further apart than cutoff don't interact
+    pub fn interaction(&self, r: Vec3, mu_targ: Vec3, mu_other: Vec3, cutoff: f32) -> (Vec3, f32) {
+        let r_norm = r.length();
+        if r_norm >= cutoff || r_norm == 0.0 {
+            return (Vec3::ZERO, 0.0);
+        }
+        let n = r / r_norm;
+        let (along_targ, along_other) = (mu_targ.dot(n), mu_other.dot(n));
+        let mutual = mu_targ.dot(mu_other);
+
+        // U = (mu_i . mu_j - 3 (mu_i . n)(mu_j . n)) / r^3
+        let potential = self.strength * (mutual - 3.0 * along_targ * along_other) / r_norm.powi(3);
+        // F = 3 / r^4 ((mu_i . n) mu_j + (mu_j . n) mu_i + (mu_i . mu_j) n - 5 (mu_i . n)(mu_j . n) n)
+        let force = 3.0 * self.strength / r_norm.powi(4)
+            * (along_targ * mu_other + along_other * mu_targ + mutual * n
+                - 5.0 * along_targ * along_other * n);
+        (force, potential)
+    }
+
+    // Field of the dipole mu at separation r from it, up to the cutoff
+    pub fn field_of(&self, r: Vec3, mu: Vec3, cutoff: f32) -> Vec3 {
+        let r_norm = r.length();
+        if r_norm >= cutoff || r_norm == 0.0 {
+            return Vec3::ZERO;
+        }
+        let n = r / r_norm;
+        self.strength * (3.0 * mu.dot(n) * n - mu) / r_norm.powi(3)
+    }
+}
+
+// Calculate the dipolar forces and torques between the given pairs of particles
+// The forces, torques and potential energies are written into reused buffers, the virial is returned
+// dipoles are in the world frame, the applied field adds its torque and energy -mu . field
+// The energy of each pair is split evenly between the two particles
+// Pairs across the periodic faces of bound interact through their nearest images
+#[allow(clippy::too_many_arguments)]
+pub fn calculate_force_into(
+    dipolar: &Dipolar,
+    particles: &[Vec3],
+    bound: &Boundary,
+    dipoles: &[Vec3],
+    pairs: &[(usize, usize)],
+    cutoff: f32,
+    forces: &mut Vec<Vec3>,
+    torques: &mut Vec<Vec3>,
+    potential_energies: &mut Vec<f32>,
+) -> Mat3 {
+    let n = particles.len();
+    forces.clear();
+    forces.resize(n, Vec3::ZERO);
+    torques.clear();
+    torques.resize(n, Vec3::ZERO);
+    potential_energies.clear();
+    potential_energies.resize(n, 0.0);
+    let mut virial = Mat3::ZERO;
+
+    let field = dipolar.get_field();
+    for ((torque, energy), &mu) in torques
+        .iter_mut()
+        .zip(potential_energies.iter_mut())
+        .zip(dipoles.iter())
+    {
+        *torque = mu.cross(field);
+        *energy = -mu.dot(field);
+    }
+
+    let interactions: Vec<_> = pairs
+        .par_iter()
+        .filter(|&&(i, j)| dipoles[i] != Vec3::ZERO && dipoles[j] != Vec3::ZERO)
+        .map(|&(i, j)| {
+            let r = bound.minimum_image(particles[i] - particles[j]);
+            let (force, potential) = dipolar.interaction(r, dipoles[i], dipoles[j], cutoff);
+            // each dipole turns towards the field of the other
+            let torque_i = dipoles[i].cross(dipolar.field_of(r, dipoles[j], cutoff));
+            let torque_j = dipoles[j].cross(dipolar.field_of(-r, dipoles[i], cutoff));
+            (i, j, r, force, (torque_i, torque_j), potential)
+        })
+        .collect();
+
+    for (i, j, r, force, (torque_i, torque_j), potential) in interactions {
+        // equal and opposite forces on both ends
+        forces[i] += force;
+        forces[j] -= force;
+        torques[i] += torque_i;
+        torques[j] += torque_j;
+        potential_energies[i] += potential / 2.0;
+        potential_energies[j] += potential / 2.0;
+        virial = virial + physics::outer_product(r, force);
+    }
+
+    virial
+}
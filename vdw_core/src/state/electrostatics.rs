@@ -0,0 +1,135 @@
+// Electrostatic interactions between charged particles
+// Uses the damped shifted force approximation (Fennell and Gezelter 2006)
+// Force and potential both go to zero at the cutoff, damping screens the long range part
+use super::physics;
+use super::sim_space::Boundary;
+use crate::par::*;
+use glam::{Mat3, Vec3};
+use serde::Deserialize;
+use std::f32::consts::PI;
+
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Coulomb {
+    pub strength: f32, // coupling constant, the energy of two unit charges at unit distance
+    pub damping: f32,  // inverse screening length, 0 gives the plain shifted force potential
+}
+
+impl Default for Coulomb {
+    fn default() -> Self {
+        Self {
+            strength: 1.0,
+            damping: 0.0,
+        }
+    }
+}
+
+impl Coulomb {
+    pub fn is_valid(&self) -> bool {
+        self.strength >= 0.0 && self.damping >= 0.0
+    }
+
+    // calculate force and pair energy on position 1 from the charges q1 and q2
+    // Pairs further apart than cutoff don't interact
+    pub fn interaction(
+        &self,
+        pos_targ: Vec3,
+        pos_other: Vec3,
+        q_targ: f32,
+        q_other: f32,
+        cutoff: f32,
+    ) -> (Vec3, f32) {
+        let shift = self.screened(cutoff);
+        self.shifted_interaction(pos_targ - pos_other, q_targ * q_other, cutoff, shift)
+    }
+
+    // interaction with the screened values at the cutoff precomputed
+    // internal helper function
+    fn shifted_interaction(
+        &self,
+        r: Vec3,
+        q_product: f32,
+        cutoff: f32,
+        shift: (f32, f32),
+    ) -> (Vec3, f32) {
+        let r_norm = r.length();
+        if r_norm >= cutoff || r_norm == 0.0 || q_product == 0.0 {
+            return (Vec3::ZERO, 0.0);
+        }
+
+        let coupling = self.strength * q_product;
+        let (u_r, f_r) = self.screened(r_norm);
+        let (u_cut, f_cut) = shift;
+
+        let force = coupling * (f_r - f_cut) * r / r_norm;
+        let potential = coupling * (u_r - u_cut + f_cut * (r_norm - cutoff));
+        (force, potential)
+    }
+
+    // Screened potential erfc(a r) / r and its negative derivative, per unit coupling
+    // internal helper function
+    fn screened(&self, r: f32) -> (f32, f32) {
+        let a = self.damping;
+        let u = erfc(a * r) / r;
+        let f = u / r + 2.0 * a / PI.sqrt() * (-(a * r).powi(2)).exp() / r;
+        (u, f)
+    }
+}
+
+// Calculate the electrostatic forces between the given pairs of particles
+// The forces and potential energies are written into reused buffers, the virial is returned
+// The energy of each pair is split evenly between the two particles
+// Pairs across the periodic faces of bound interact through their nearest images
+#[allow(clippy::too_many_arguments)]
+pub fn calculate_force_into(
+    coulomb: &Coulomb,
+    particles: &[Vec3],
+    bound: &Boundary,
+    charges: &[f32],
+    pairs: &[(usize, usize)],
+    cutoff: f32,
+    forces: &mut Vec<Vec3>,
+    potential_energies: &mut Vec<f32>,
+) -> Mat3 {
+    forces.clear();
+    forces.resize(particles.len(), Vec3::ZERO);
+    potential_energies.clear();
+    potential_energies.resize(particles.len(), 0.0);
+    let mut virial = Mat3::ZERO;
+
+    let shift = coulomb.screened(cutoff);
+    let interactions: Vec<_> = pairs
+        .par_iter()
+        .filter(|&&(i, j)| charges[i] != 0.0 && charges[j] != 0.0)
+        .map(|&(i, j)| {
+            let r = bound.minimum_image(particles[i] - particles[j]);
+            let (force, potential) =
+                coulomb.shifted_interaction(r, charges[i] * charges[j], cutoff, shift);
+            (i, j, r, force, potential)
+        })
+        .collect();
+
+    for (i, j, r, force, potential) in interactions {
+        // equal and opposite forces on both ends
+        forces[i] += force;
+        forces[j] -= force;
+        potential_energies[i] += potential / 2.0;
+        potential_energies[j] += potential / 2.0;
+        virial = virial + physics::outer_product(r, force);
+    }
+
+    virial
+}
+
+// Complementary error function, Abramowitz and Stegun 7.1.26
+// Absolute error below 1.5e-7
+fn erfc(x: f32) -> f32 {
+    if x < 0.0 {
+        return 2.0 - erfc(-x);
+    }
+    let t = 1.0 / (1.0 + 0.327_591_1 * x);
+    let poly = t
+        * (0.254_829_6
+            + t * (-0.284_496_75 + t * (1.421_413_7 + t * (-1.453_152_1 + t * 1.061_405_4))));
+    poly * (-x * x).exp()
+}
@@ -0,0 +1,330 @@
+// Errors of the simulation core
+// Every error carries what is needed to tell what went wrong and where, the parameter,
+// the file and line or the diverged particles, and wraps the error it comes from as its source
+use super::watchdog::{Cause, Divergence};
+use std::path::{Path, PathBuf};
+use std::{error::Error, fmt, io};
+
+// Error for invalid input
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ErrorKind {
+    Bound,
+    Adhesion,
+    TargTemp,
+    InjectRate,
+    UnitSize,
+    Reach,
+    Cutoff,
+    Switch,
+    Potential,
+    Neighbors,
+    Coulomb,
+    Dipole,
+    Integrator,
+    Dt,
+    StepsPerFrame,
+    FrameBudget,
+    PressureSampling,
+    HistoryCapacity,
+    Particle,
+    Bond,
+    Probe,
+    Obstacle,
+    RigidCluster,
+    Replicas,
+    Sweep,
+    Schedule,
+}
+
+// One invalid parameter, or a suspicious one, with the offending values and how to fix it
+#[derive(Clone, PartialEq, Debug)]
+pub struct Problem {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl Problem {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.kind, self.message)
+    }
+}
+
+#[derive(Debug)]
+pub struct InvalidParamError {
+    problems: Vec<Problem>,
+}
+
+impl InvalidParamError {
+    pub fn new(problems: Vec<Problem>) -> Self {
+        Self { problems }
+    }
+
+    // Every invalid parameter, in the order they were checked
+    pub fn problems(&self) -> &[Problem] {
+        &self.problems
+    }
+
+    // Kinds of the invalid parameters, a kind appears once per failed check
+    pub fn kinds(&self) -> Vec<ErrorKind> {
+        self.problems.iter().map(|problem| problem.kind).collect()
+    }
+
+    pub fn contains(&self, kind: ErrorKind) -> bool {
+        self.problems.iter().any(|problem| problem.kind == kind)
+    }
+}
+
+impl fmt::Display for InvalidParamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "InvalidInputError:")?;
+        for problem in self.problems.iter() {
+            write!(f, "\n  {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for InvalidParamError {}
+
+// Error for unreadable config files
+// Lines and columns count from 1
+#[derive(Debug)]
+pub enum ConfigError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Parse {
+        path: Option<PathBuf>, // None for text that didn't come from a file
+        line: Option<usize>,
+        column: Option<usize>,
+        source: toml::de::Error,
+    },
+}
+
+impl ConfigError {
+    pub fn io(path: impl AsRef<Path>, source: io::Error) -> Self {
+        ConfigError::Io {
+            path: path.as_ref().to_path_buf(),
+            source,
+        }
+    }
+
+    pub fn parse(path: Option<&Path>, source: toml::de::Error) -> Self {
+        let position = source.line_col();
+        ConfigError::Parse {
+            path: path.map(Path::to_path_buf),
+            line: position.map(|(line, _)| line + 1),
+            column: position.map(|(_, column)| column + 1),
+            source,
+        }
+    }
+
+    // The same error, read from the file at path
+    pub fn in_file(self, path: impl AsRef<Path>) -> Self {
+        match self {
+            ConfigError::Parse {
+                line,
+                column,
+                source,
+                ..
+            } => ConfigError::Parse {
+                path: Some(path.as_ref().to_path_buf()),
+                line,
+                column,
+                source,
+            },
+            other => other,
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            ConfigError::Io { path, .. } => Some(path),
+            ConfigError::Parse { path, .. } => path.as_deref(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => {
+                write!(f, "ConfigError: cannot read {}: {}", path.display(), source)
+            }
+            ConfigError::Parse {
+                path,
+                line,
+                column,
+                source,
+            } => {
+                write!(f, "ConfigError: invalid config")?;
+                if let Some(path) = path {
+                    write!(f, " {}", path.display())?;
+                }
+                if let (Some(line), Some(column)) = (line, column) {
+                    write!(f, " at line {} column {}", line, column)?;
+                }
+                write!(f, ": {}", source)
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io { source, .. } => Some(source),
+            ConfigError::Parse { source, .. } => Some(source),
+        }
+    }
+}
+
+// Error for data files, such as snapshots, that can't be written or read back
+#[derive(Debug)]
+pub enum FileError {
+    Read {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Write {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Format {
+        path: PathBuf,
+        line: Option<usize>, // counted from 1, None when it isn't known
+        message: String,
+    },
+}
+
+impl FileError {
+    pub fn path(&self) -> &Path {
+        match self {
+            FileError::Read { path, .. }
+            | FileError::Write { path, .. }
+            | FileError::Format { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileError::Read { path, source } => {
+                write!(f, "FileError: cannot read {}: {}", path.display(), source)
+            }
+            FileError::Write { path, source } => {
+                write!(f, "FileError: cannot write {}: {}", path.display(), source)
+            }
+            FileError::Format {
+                path,
+                line: Some(line),
+                message,
+            } => write!(
+                f,
+                "FileError: invalid {} at line {}: {}",
+                path.display(),
+                line,
+                message
+            ),
+            FileError::Format {
+                path,
+                line: None,
+                message,
+            } => write!(f, "FileError: invalid {}: {}", path.display(), message),
+        }
+    }
+}
+
+impl Error for FileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FileError::Read { source, .. } | FileError::Write { source, .. } => Some(source),
+            FileError::Format { .. } => None,
+        }
+    }
+}
+
+// Error for a run that blew up, see watchdog::diverged_particles
+#[derive(Clone, PartialEq, Debug)]
+pub struct DivergenceError {
+    pub steps: usize, // step count of the frame it was found in
+    pub time: f32,
+    pub particles: Vec<usize>, // diverged particles, in index order
+    pub cause: Cause,
+}
+
+impl From<Divergence> for DivergenceError {
+    fn from(divergence: Divergence) -> Self {
+        Self {
+            steps: divergence.steps,
+            time: divergence.time,
+            particles: divergence.particles,
+            cause: divergence.cause,
+        }
+    }
+}
+
+impl fmt::Display for DivergenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "DivergenceError: {} particles diverged at step {} (t = {}): {}",
+            self.particles.len(),
+            self.steps,
+            self.time,
+            self.cause.describe()
+        )
+    }
+}
+
+impl Error for DivergenceError {}
+
+// Error for running a simulation without rendering
+// A wrapper that shows the text of the error it holds, its source is the source of that error
+// so error chains don't repeat the text
+#[derive(Debug)]
+pub enum SimulationError {
+    Invalid(InvalidParamError), // the prototype didn't compile
+    Diverged(DivergenceError),
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimulationError::Invalid(e) => write!(f, "{}", e),
+            SimulationError::Diverged(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for SimulationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SimulationError::Invalid(e) => e.source(),
+            SimulationError::Diverged(e) => e.source(),
+        }
+    }
+}
+
+impl From<InvalidParamError> for SimulationError {
+    fn from(error: InvalidParamError) -> Self {
+        SimulationError::Invalid(error)
+    }
+}
+
+impl From<DivergenceError> for SimulationError {
+    fn from(error: DivergenceError) -> Self {
+        SimulationError::Diverged(error)
+    }
+}
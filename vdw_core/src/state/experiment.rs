@@ -0,0 +1,427 @@
+// Experiments
+// Scripted protocols that drive a simulation and report what they saw
+// The runner steps the simulation without rendering, the experiment steers the state between frames
+// An isotherm sweep can also run inside the gui, see SimulationState::isotherm
+use super::cluster::ClusterStats;
+use super::coexistence::Coexistence;
+use super::error::SimulationError;
+use super::observables::Moments;
+use super::{SimulationPrototype, SimulationState};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+pub trait Experiment {
+    type Report: fmt::Display;
+
+    // Prepare the freshly compiled state before the first frame
+    fn start(&mut self, _state: &mut SimulationState) {}
+
+    // Look at the state after a frame and change it for the next one
+    // Returns false once the experiment is over
+    fn observe(&mut self, state: &mut SimulationState) -> bool;
+
+    fn report(self) -> Self::Report;
+}
+
+// Compile the prototype and advance it frame by frame until the experiment is over
+// Stops with an error as soon as the simulation diverges
+pub fn run<E: Experiment>(
+    prototype: &SimulationPrototype,
+    mut experiment: E,
+) -> Result<E::Report, SimulationError> {
+    let mut state = prototype.compile()?.into_state();
+    experiment.start(&mut state);
+    loop {
+        state.advance_frame();
+        state.check_divergence()?;
+        if !experiment.observe(&mut state) {
+            break;
+        }
+    }
+    Ok(experiment.report())
+}
+
+/////////////////////////////////////////////////
+// Two temperature quench
+// Equilibrate with the thermostat at t_high, then set it to t_low at once
+// and follow the growth of the largest cluster of neighbors
+//
+pub struct Quench {
+    pub t_high: f32,
+    pub t_low: f32,
+    pub equilibration: f32, // simulated time spent at t_high
+    pub duration: f32,      // simulated time followed after the quench
+    pub interval: usize,    // frames between cluster measurements
+    pub nucleus: usize,     // size the largest cluster has to reach to count as nucleated
+    quenched: bool,
+    frames: usize,
+    samples: Vec<QuenchSample>,
+}
+
+impl Quench {
+    // used when the prototype has no thermostat, the quench would do nothing without one
+    const INJECT_RATE: f32 = 0.1;
+
+    pub fn new(t_high: f32, t_low: f32) -> Self {
+        Self {
+            t_high,
+            t_low,
+            equilibration: 1.0,
+            duration: 2.0,
+            interval: 5,
+            nucleus: 50,
+            quenched: false,
+            frames: 0,
+            samples: Vec::new(),
+        }
+    }
+
+    // Mean largest cluster over the second half of the equilibration
+    // internal helper function
+    fn baseline(&self) -> f32 {
+        let before: Vec<_> = self.samples.iter().filter(|s| s.time < 0.0).collect();
+        let settled = &before[before.len() / 2..];
+        settled.iter().map(|s| s.largest as f32).sum::<f32>() / settled.len().max(1) as f32
+    }
+}
+
+impl Experiment for Quench {
+    type Report = QuenchReport;
+
+    fn start(&mut self, state: &mut SimulationState) {
+        state.target_temp = self.t_high;
+        if state.inject_rate <= 0.0 {
+            state.inject_rate = Self::INJECT_RATE;
+        }
+    }
+
+    fn observe(&mut self, state: &mut SimulationState) -> bool {
+        if !self.quenched && state.time >= self.equilibration {
+            state.target_temp = self.t_low;
+            self.quenched = true;
+            self.frames = 0; // measure right at the quench
+        }
+
+        if self.frames % self.interval.max(1) == 0 {
+            let stats = ClusterStats::from_network(&state.neighbor_network());
+            self.samples.push(QuenchSample {
+                time: state.time - self.equilibration,
+                temperature: state.temperature(),
+                largest: stats.largest,
+                clusters: stats.clusters,
+            });
+        }
+        self.frames += 1;
+
+        state.time < self.equilibration + self.duration
+    }
+
+    fn report(self) -> QuenchReport {
+        // a cluster that big already at t_high says nothing about nucleation
+        let baseline = self.baseline();
+        let nucleation_time = if baseline < self.nucleus as f32 {
+            self.samples
+                .iter()
+                .find(|s| s.time >= 0.0 && s.largest >= self.nucleus)
+                .map(|s| s.time)
+        } else {
+            None
+        };
+        QuenchReport {
+            t_high: self.t_high,
+            t_low: self.t_low,
+            nucleus: self.nucleus,
+            baseline,
+            nucleation_time,
+            samples: self.samples,
+        }
+    }
+}
+
+// Cluster measurement of one frame
+#[derive(Clone, Copy)]
+pub struct QuenchSample {
+    pub time: f32, // since the quench, negative during the equilibration
+    pub temperature: f32,
+    pub largest: usize,
+    pub clusters: usize,
+}
+
+pub struct QuenchReport {
+    pub t_high: f32,
+    pub t_low: f32,
+    pub nucleus: usize,
+    pub baseline: f32,                // mean largest cluster at t_high
+    pub nucleation_time: Option<f32>, // first time after the quench the largest cluster reached nucleus
+    pub samples: Vec<QuenchSample>,
+}
+
+impl fmt::Display for QuenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Quench from T = {} to T = {}", self.t_high, self.t_low)?;
+        writeln!(f, "Largest cluster before the quench: {:.1}", self.baseline)?;
+        match self.nucleation_time {
+            Some(time) => writeln!(
+                f,
+                "Nucleated {:.3} after the quench (largest cluster >= {})",
+                time, self.nucleus
+            )?,
+            None if self.baseline >= self.nucleus as f32 => writeln!(
+                f,
+                "Clusters of {} particles were there before the quench, pick a larger nucleus",
+                self.nucleus
+            )?,
+            None => writeln!(f, "No cluster of {} particles formed", self.nucleus)?,
+        }
+        writeln!(
+            f,
+            "{:>12} {:>12} {:>12} {:>12}",
+            "time", "T", "largest", "clusters"
+        )?;
+        for sample in self.samples.iter() {
+            writeln!(
+                f,
+                "{:>12.5} {:>12.5} {:>12} {:>12}",
+                sample.time, sample.temperature, sample.largest, sample.clusters
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/////////////////////////////////////////////////
+// Isotherm sweep
+// Compress the box in steps at a fixed thermostat temperature,
+// equilibrate at every volume and then average the pressure over a sampling period
+// The pressure is itself a running average over the pressure sampling period of the state,
+// so the equilibration should be at least that long
+//
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum SweepPhase {
+    Compress, // walls moving in until the next volume is reached
+    Equilibrate,
+    Sample,
+}
+
+#[derive(Clone)]
+pub struct IsothermSweep {
+    pub temperature: f32,
+    pub final_volume: f32,  // last volume as a fraction of the starting one
+    pub points: usize,      // volumes visited, spaced evenly on a log scale
+    pub equilibration: f32, // simulated time at each volume before sampling
+    pub sampling: f32,      // simulated time the pressure is averaged over at each volume
+    pub rate: f32,          // speed at which the walls move in between volumes
+    start_volume: f32,
+    index: usize, // volume being visited
+    phase: SweepPhase,
+    phase_end: f32,       // simulated time the equilibration or sampling ends
+    previous_volume: f32, // volume the frame before while compressing
+    pressure: Moments,
+    measured_temperature: Moments,
+    results: Vec<IsothermPoint>,
+}
+
+impl IsothermSweep {
+    // used when the prototype has no thermostat, the sweep wouldn't be isothermal without one
+    const INJECT_RATE: f32 = 0.1;
+
+    pub fn new(temperature: f32) -> Self {
+        Self {
+            temperature,
+            final_volume: 0.3,
+            points: 10,
+            equilibration: 2.0,
+            sampling: 2.0,
+            rate: 0.2,
+            start_volume: 0.0,
+            index: 0,
+            phase: SweepPhase::Equilibrate,
+            phase_end: 0.0,
+            previous_volume: f32::INFINITY,
+            pressure: Moments::default(),
+            measured_temperature: Moments::default(),
+            results: Vec::new(),
+        }
+    }
+
+    // Points measured so far, from the largest volume down
+    pub fn results(&self) -> &[IsothermPoint] {
+        &self.results
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.results.len() >= self.points.max(1)
+    }
+
+    // Short description of what the sweep is doing
+    pub fn status(&self) -> String {
+        if self.is_done() {
+            return format!("Done, {} points", self.results.len());
+        }
+        let phase = match self.phase {
+            SweepPhase::Compress => "compressing to",
+            SweepPhase::Equilibrate => "equilibrating at",
+            SweepPhase::Sample => "sampling at",
+        };
+        format!(
+            "Point {} of {}: {} V = {:.3}",
+            self.index + 1,
+            self.points.max(1),
+            phase,
+            self.target_volume()
+        )
+    }
+
+    // Volume of the point being visited
+    // internal helper function
+    fn target_volume(&self) -> f32 {
+        let steps = self.points.max(2) - 1;
+        self.start_volume * self.final_volume.powf(self.index as f32 / steps as f32)
+    }
+}
+
+impl Experiment for IsothermSweep {
+    type Report = IsothermReport;
+
+    fn start(&mut self, state: &mut SimulationState) {
+        state.target_temp = self.temperature;
+        if state.inject_rate <= 0.0 {
+            state.inject_rate = Self::INJECT_RATE;
+        }
+        // the sweep moves the walls itself
+        state.pressure_pinned.is_pinned = false;
+        state.bound_rate = 0.0;
+        self.start_volume = state.bound.get_volume();
+        self.index = 0;
+        self.phase = SweepPhase::Equilibrate;
+        self.phase_end = state.time + self.equilibration;
+        self.results.clear();
+    }
+
+    fn observe(&mut self, state: &mut SimulationState) -> bool {
+        if self.is_done() {
+            return false;
+        }
+        match self.phase {
+            SweepPhase::Compress => {
+                // the box stops shrinking at its minimum size, the point is taken there
+                let volume = state.bound.get_volume();
+                let stuck = volume >= self.previous_volume;
+                self.previous_volume = volume;
+                if volume <= self.target_volume() || stuck {
+                    state.bound_rate = 0.0;
+                    self.phase = SweepPhase::Equilibrate;
+                    self.phase_end = state.time + self.equilibration;
+                }
+            }
+            SweepPhase::Equilibrate => {
+                if state.time >= self.phase_end {
+                    self.phase = SweepPhase::Sample;
+                    self.phase_end = state.time + self.sampling;
+                    self.pressure = Moments::default();
+                    self.measured_temperature = Moments::default();
+                }
+            }
+            SweepPhase::Sample => {
+                let n = state.particles.len();
+                self.pressure.push(state.pressure.get_pressure());
+                self.measured_temperature.push(state.temperature());
+                if state.time >= self.phase_end {
+                    let volume = state.bound.get_volume();
+                    self.results.push(IsothermPoint {
+                        volume,
+                        density: n as f32 / volume,
+                        pressure: self.pressure.mean(),
+                        pressure_error: self.pressure.mean_error(),
+                        temperature: self.measured_temperature.mean(),
+                    });
+                    if self.is_done() {
+                        return false;
+                    }
+                    self.index += 1;
+                    self.phase = SweepPhase::Compress;
+                    self.previous_volume = f32::INFINITY;
+                    state.bound_rate = -self.rate;
+                }
+            }
+        }
+        true
+    }
+
+    fn report(self) -> IsothermReport {
+        IsothermReport {
+            temperature: self.temperature,
+            points: self.results,
+        }
+    }
+}
+
+// Averages at one volume of the sweep
+// The error treats the frames as uncorrelated, so it is an underestimate
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct IsothermPoint {
+    pub volume: f32,
+    pub density: f32,
+    pub pressure: f32,
+    pub pressure_error: f32,
+    pub temperature: f32, // measured, the thermostat only keeps it close to the target
+}
+
+pub struct IsothermReport {
+    pub temperature: f32,
+    pub points: Vec<IsothermPoint>,
+}
+
+impl IsothermReport {
+    // Maxwell construction on the points, None without a van der Waals loop
+    pub fn coexistence(&self) -> Option<Coexistence> {
+        Coexistence::of(&self.points)
+    }
+}
+
+// Write the points of an isotherm to a csv file
+pub fn export_isotherm(points: &[IsothermPoint], path: impl AsRef<Path>) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    writeln!(file, "volume,density,pressure,pressure_error,temperature")?;
+    for point in points.iter() {
+        writeln!(
+            file,
+            "{},{},{},{},{}",
+            point.volume, point.density, point.pressure, point.pressure_error, point.temperature
+        )?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for IsothermReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Isotherm at T = {}", self.temperature)?;
+        writeln!(
+            f,
+            "{:>12} {:>12} {:>12} {:>12} {:>12}",
+            "V", "density", "P", "error", "T"
+        )?;
+        for point in self.points.iter() {
+            writeln!(
+                f,
+                "{:>12.5} {:>12.5} {:>12.5} {:>12.5} {:>12.5}",
+                point.volume,
+                point.density,
+                point.pressure,
+                point.pressure_error,
+                point.temperature
+            )?;
+        }
+        match self.coexistence() {
+            Some(coexistence) => writeln!(
+                f,
+                "Coexistence at P = {:.5}, liquid density {:.5}, gas density {:.5}",
+                coexistence.pressure, coexistence.liquid_density, coexistence.gas_density
+            )?,
+            None => writeln!(f, "No van der Waals loop, the isotherm is above the critical temperature or too short")?,
+        }
+        Ok(())
+    }
+}
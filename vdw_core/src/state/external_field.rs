@@ -0,0 +1,144 @@
+use glam::Vec3;
+
+////////////////////////////////////////////////////////////
+// External fields act on every particle independently of the others
+// Fields give an acceleration that may depend on position and time
+//
+pub trait ExternalField: Send + Sync {
+    // Acceleration of a particle at pos at the given time
+    fn acceleration(&self, pos: Vec3, time: f32) -> Vec3;
+
+    // Name shown in the gui
+    fn name(&self) -> &'static str;
+
+    // Named parameters of the field, exposed so the gui can adjust them
+    fn parameters(&mut self) -> Vec<(&'static str, &mut f32)>;
+
+    fn box_clone(&self) -> Box<dyn ExternalField>;
+}
+
+impl Clone for Box<dyn ExternalField> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+// Sum the accelerations of a set of fields
+pub fn total_acceleration(fields: &[Box<dyn ExternalField>], pos: Vec3, time: f32) -> Vec3 {
+    fields
+        .iter()
+        .map(|field| field.acceleration(pos, time))
+        .fold(Vec3::ZERO, |acc, a| acc + a)
+}
+
+// Same acceleration everywhere and at all times
+#[derive(Clone)]
+pub struct Gravity {
+    pub g: Vec3,
+}
+
+impl Gravity {
+    pub fn new(g: Vec3) -> Self {
+        Self { g }
+    }
+}
+
+impl ExternalField for Gravity {
+    fn acceleration(&self, _pos: Vec3, _time: f32) -> Vec3 {
+        self.g
+    }
+
+    fn name(&self) -> &'static str {
+        "Gravity"
+    }
+
+    fn parameters(&mut self) -> Vec<(&'static str, &mut f32)> {
+        let [x, y, z] = self.g.as_mut();
+        vec![("g x", x), ("g y", y), ("g z", z)]
+    }
+
+    fn box_clone(&self) -> Box<dyn ExternalField> {
+        Box::new(self.clone())
+    }
+}
+
+// Pulls particles towards a center with a strength proportional to the distance
+#[derive(Clone)]
+pub struct HarmonicTrap {
+    pub center: Vec3,
+    pub k: f32, // acceleration per unit of distance from the center
+}
+
+impl HarmonicTrap {
+    pub fn new(center: Vec3, k: f32) -> Self {
+        Self { center, k }
+    }
+}
+
+impl ExternalField for HarmonicTrap {
+    fn acceleration(&self, pos: Vec3, _time: f32) -> Vec3 {
+        -self.k * (pos - self.center)
+    }
+
+    fn name(&self) -> &'static str {
+        "Harmonic Trap"
+    }
+
+    fn parameters(&mut self) -> Vec<(&'static str, &mut f32)> {
+        let [x, y, z] = self.center.as_mut();
+        vec![
+            ("k", &mut self.k),
+            ("center x", x),
+            ("center y", y),
+            ("center z", z),
+        ]
+    }
+
+    fn box_clone(&self) -> Box<dyn ExternalField> {
+        Box::new(self.clone())
+    }
+}
+
+// Uniform field that oscillates sinusoidally in time
+#[derive(Clone)]
+pub struct OscillatingField {
+    pub amplitude: Vec3,
+    pub frequency: f32, // oscillations per unit of time
+    pub phase: f32,     // in radians
+}
+
+impl OscillatingField {
+    pub fn new(amplitude: Vec3, frequency: f32, phase: f32) -> Self {
+        Self {
+            amplitude,
+            frequency,
+            phase,
+        }
+    }
+}
+
+impl ExternalField for OscillatingField {
+    fn acceleration(&self, _pos: Vec3, time: f32) -> Vec3 {
+        let angle = 2.0 * std::f32::consts::PI * self.frequency * time + self.phase;
+        self.amplitude * angle.sin()
+    }
+
+    fn name(&self) -> &'static str {
+        "Oscillating Field"
+    }
+
+    fn parameters(&mut self) -> Vec<(&'static str, &mut f32)> {
+        let [x, y, z] = self.amplitude.as_mut();
+        vec![
+            ("frequency", &mut self.frequency),
+            ("phase", &mut self.phase),
+            ("amplitude x", x),
+            ("amplitude y", y),
+            ("amplitude z", z),
+        ]
+    }
+
+    fn box_clone(&self) -> Box<dyn ExternalField> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,255 @@
+use super::particle::Particle;
+use super::sim_space::Boundary;
+use glam::Vec3;
+use ndarray::{Array2, Array3, Axis};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+// Axis perpendicular to a slice of a field
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FieldAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl FieldAxis {
+    pub const ALL: [FieldAxis; 3] = [FieldAxis::X, FieldAxis::Y, FieldAxis::Z];
+
+    pub fn index(self) -> usize {
+        match self {
+            FieldAxis::X => 0,
+            FieldAxis::Y => 1,
+            FieldAxis::Z => 2,
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////
+// Coarse grid of cells covering the boundary
+// Particle quantities are deposited onto its cells to build local fields
+// Separate from the force Grid, which is sized by the interaction range
+//
+#[derive(Clone)]
+pub struct AnalysisGrid {
+    counts: Array3<f32>,    // number of particles in each cell
+    kinetic: Array3<f32>,   // total kinetic energy in each cell
+    momentum: Array3<Vec3>, // total momentum in each cell
+    mass: Array3<f32>,      // total mass in each cell
+    origin: Vec3,           // lower corner of the first cell
+    cell_size: Vec3,
+}
+
+impl AnalysisGrid {
+    // Bin particles into resolution^3 cells spanning the boundary
+    pub fn new(particles: &[Particle], bound: Boundary, resolution: usize) -> Self {
+        let resolution = resolution.max(1);
+        let dim = (resolution, resolution, resolution);
+        let cell_size = (bound.hi_corner() - bound.lo_corner()) / resolution as f32;
+
+        let mut counts = Array3::zeros(dim);
+        let mut kinetic = Array3::zeros(dim);
+        let mut momentum = Array3::from_elem(dim, Vec3::ZERO);
+        let mut mass = Array3::zeros(dim);
+        for particle in particles {
+            let rel = (particle.get_pos() - bound.lo_corner()) / cell_size;
+            let cell = |x: f32| (x.max(0.0) as usize).min(resolution - 1);
+            let idx = [cell(rel.x), cell(rel.y), cell(rel.z)];
+
+            counts[idx] += 1.0;
+            kinetic[idx] += 0.5 * particle.get_mass() * particle.get_vel().length_squared();
+            momentum[idx] += particle.get_mass() * particle.get_vel();
+            mass[idx] += particle.get_mass();
+        }
+
+        Self {
+            counts,
+            kinetic,
+            momentum,
+            mass,
+            origin: bound.lo_corner(),
+            cell_size,
+        }
+    }
+
+    pub fn get_cell_size(&self) -> Vec3 {
+        self.cell_size
+    }
+
+    // Position of the middle of a cell
+    pub fn cell_center(&self, (i, j, k): (usize, usize, usize)) -> Vec3 {
+        self.origin + (Vec3::new(i as f32, j as f32, k as f32) + Vec3::splat(0.5)) * self.cell_size
+    }
+
+    // Center of mass velocity of each cell, zero in empty cells
+    pub fn velocity(&self) -> Array3<Vec3> {
+        let mut velocity = self.momentum.clone();
+        velocity.zip_mut_with(&self.mass, |v, &m| {
+            *v = if m > 0.0 { *v / m } else { Vec3::ZERO };
+        });
+        velocity
+    }
+
+    // Number of particles per unit volume in each cell
+    pub fn density(&self) -> Array3<f32> {
+        let volume = self.cell_size.x * self.cell_size.y * self.cell_size.z;
+        &self.counts / volume
+    }
+
+    // Kinetic energy per particle in each cell, same convention as the global temperature
+    // Empty cells have zero temperature
+    pub fn temperature(&self) -> Array3<f32> {
+        let mut temperature = self.kinetic.clone();
+        temperature.zip_mut_with(&self.counts, |t, &n| {
+            *t = if n > 0.0 { *t / n } else { 0.0 };
+        });
+        temperature
+    }
+}
+
+// 2D slice of a field perpendicular to an axis
+pub fn slice(field: &Array3<f32>, axis: FieldAxis, index: usize) -> Array2<f32> {
+    let ax = Axis(axis.index());
+    let index = index.min(field.len_of(ax) - 1);
+    field.index_axis(ax, index).to_owned()
+}
+
+// Smallest and largest value of a slice, spread over the colormap when drawing it
+pub fn slice_range(slice: &Array2<f32>) -> (f32, f32) {
+    slice
+        .iter()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), &v| {
+            (lo.min(v), hi.max(v))
+        })
+}
+
+// Write a field to a csv file, one row per cell
+pub fn export_csv(field: &Array3<f32>, name: &str, path: impl AsRef<Path>) -> io::Result<()> {
+    let mut file = File::create(path)?;
+
+    writeln!(file, "i,j,k,{}", name)?;
+    for ((i, j, k), value) in field.indexed_iter() {
+        writeln!(file, "{},{},{},{}", i, j, k, value)?;
+    }
+
+    Ok(())
+}
+
+// Field shown by the field window
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FieldKind {
+    Density,
+    Temperature,
+}
+
+impl FieldKind {
+    pub const ALL: [FieldKind; 2] = [FieldKind::Density, FieldKind::Temperature];
+}
+
+// Settings and latest result of the field analysis
+pub struct FieldView {
+    pub enabled: bool,
+    pub kind: FieldKind,
+    pub resolution: usize, // number of cells along each axis
+    pub axis: FieldAxis,
+    pub slice: usize,
+    pub in_scene: bool,  // also draw the slice inside the box
+    pub interval: usize, // number of frames between updates
+    frames: usize,
+    grid: Option<AnalysisGrid>,
+}
+
+impl Default for FieldView {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            kind: FieldKind::Temperature,
+            resolution: 10,
+            axis: FieldAxis::Z,
+            slice: 5,
+            in_scene: false,
+            interval: 10,
+            frames: 0,
+            grid: None,
+        }
+    }
+}
+
+impl FieldView {
+    // Rebuild the analysis grid once every interval frames
+    pub fn update(&mut self, particles: &[Particle], bound: Boundary) {
+        if !self.enabled {
+            return;
+        }
+        if self.frames % self.interval.max(1) == 0 {
+            self.grid = Some(AnalysisGrid::new(particles, bound, self.resolution));
+        }
+        self.frames += 1;
+    }
+
+    // The selected field, None before the first update
+    pub fn field(&self) -> Option<Array3<f32>> {
+        self.grid.as_ref().map(|grid| match self.kind {
+            FieldKind::Density => grid.density(),
+            FieldKind::Temperature => grid.temperature(),
+        })
+    }
+}
+
+// Settings and latest result of the coarse grained velocity field
+pub struct FlowView {
+    pub enabled: bool,
+    pub resolution: usize, // number of cells along each axis
+    pub interval: usize,   // number of frames between updates
+    pub arrow_scale: f32,  // length of an arrow per unit of velocity
+    frames: usize,
+    grid: Option<AnalysisGrid>,
+}
+
+impl Default for FlowView {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            resolution: 6,
+            interval: 10,
+            arrow_scale: 1.0,
+            frames: 0,
+            grid: None,
+        }
+    }
+}
+
+impl FlowView {
+    // Rebuild the analysis grid once every interval frames
+    pub fn update(&mut self, particles: &[Particle], bound: Boundary) {
+        if !self.enabled {
+            return;
+        }
+        if self.frames % self.interval.max(1) == 0 {
+            self.grid = Some(AnalysisGrid::new(particles, bound, self.resolution));
+        }
+        self.frames += 1;
+    }
+
+    // (cell center, mean velocity) of every cell that holds particles
+    pub fn arrows(&self) -> Vec<(Vec3, Vec3)> {
+        let grid = match &self.grid {
+            Some(grid) => grid,
+            None => return Vec::new(),
+        };
+        grid.velocity()
+            .indexed_iter()
+            .filter(|&(idx, _)| grid.counts[idx] > 0.0)
+            .map(|(idx, &vel)| (grid.cell_center(idx), vel))
+            .collect()
+    }
+
+    // Largest mean velocity of a cell, shown next to the arrows for scale
+    pub fn max_speed(&self) -> f32 {
+        self.arrows()
+            .iter()
+            .map(|(_, vel)| vel.length())
+            .fold(0.0, f32::max)
+    }
+}
@@ -0,0 +1,170 @@
+// Named groups of particles
+// Particles are tagged into a group by index, by a box region or by species,
+// then the group is worked on as a whole: frozen in place, colored, kicked or deleted
+// Members are particle indices, SimulationState::remove_particles renumbers them
+// A particle can be in several groups, the last group with a color decides its color
+use super::particle::Particle;
+use glam::Vec3;
+
+// Which particles a new group takes in
+#[derive(Clone, PartialEq, Debug)]
+pub enum Selection {
+    Indices(Vec<usize>),
+    Region { lo: Vec3, hi: Vec3 }, // box with these corners
+    Species(usize),
+}
+
+impl Selection {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Selection::Indices(_) => "Indices",
+            Selection::Region { .. } => "Region",
+            Selection::Species(_) => "Species",
+        }
+    }
+
+    // Sorted indices of the selected particles
+    pub fn select(&self, particles: &[Particle]) -> Vec<usize> {
+        let mut members: Vec<usize> = match self {
+            Selection::Indices(indices) => indices
+                .iter()
+                .copied()
+                .filter(|&i| i < particles.len())
+                .collect(),
+            Selection::Region { lo, hi } => {
+                let (lo, hi) = (lo.min(*hi), lo.max(*hi));
+                particles
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, p)| {
+                        let pos = p.get_pos();
+                        pos.cmpge(lo).all() && pos.cmple(hi).all()
+                    })
+                    .map(|(i, _)| i)
+                    .collect()
+            }
+            Selection::Species(species) => particles
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| p.get_species() == *species)
+                .map(|(i, _)| i)
+                .collect(),
+        };
+        members.sort_unstable();
+        members.dedup();
+        members
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Group {
+    pub name: String,
+    pub frozen: bool, // members are fixed, set with SimulationState::freeze_group
+    pub color: Option<[f32; 3]>, // drawn in this linear color instead of the color mode
+    members: Vec<usize>, // sorted particle indices
+}
+
+impl Group {
+    pub fn new(name: String, members: Vec<usize>) -> Self {
+        let mut members = members;
+        members.sort_unstable();
+        members.dedup();
+        Self {
+            name,
+            frozen: false,
+            color: None,
+            members,
+        }
+    }
+
+    pub fn get_members(&self) -> &[usize] {
+        &self.members
+    }
+
+    pub fn contains(&self, i: usize) -> bool {
+        self.members.binary_search(&i).is_ok()
+    }
+
+    // Drop the removed particles and shift the indices after them down
+    // removed has to be sorted
+    pub fn renumber(&mut self, removed: &[usize]) {
+        self.members = self
+            .members
+            .iter()
+            .filter(|i| removed.binary_search(i).is_err())
+            .map(|&i| i - removed.partition_point(|&r| r < i))
+            .collect();
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Groups {
+    groups: Vec<Group>,
+}
+
+impl Groups {
+    pub fn add(&mut self, group: Group) -> usize {
+        self.groups.push(group);
+        self.groups.len() - 1
+    }
+
+    // Forget the group, its particles stay
+    pub fn remove(&mut self, index: usize) -> Option<Group> {
+        if index < self.groups.len() {
+            Some(self.groups.remove(index))
+        } else {
+            None
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Group> {
+        self.groups.get(index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Group> {
+        self.groups.get_mut(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Group> {
+        self.groups.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    // Sorted indices of the particles of all frozen groups
+    pub fn frozen_members(&self) -> Vec<usize> {
+        let mut frozen: Vec<usize> = self
+            .groups
+            .iter()
+            .filter(|g| g.frozen)
+            .flat_map(|g| g.members.iter().copied())
+            .collect();
+        frozen.sort_unstable();
+        frozen.dedup();
+        frozen
+    }
+
+    // Color of each of n particles, None where no group colors it
+    pub fn colors(&self, n: usize) -> Vec<Option<[f32; 3]>> {
+        let mut colors = vec![None; n];
+        for group in self.groups.iter() {
+            if let Some(color) = group.color {
+                for &i in group.members.iter().filter(|&&i| i < n) {
+                    colors[i] = Some(color);
+                }
+            }
+        }
+        colors
+    }
+
+    // internal helper function
+    pub(crate) fn renumber(&mut self, removed: &[usize]) {
+        self.groups.iter_mut().for_each(|g| g.renumber(removed));
+    }
+}
@@ -0,0 +1,128 @@
+// Heat current and thermal conductivity
+// Every step the heat current of the box is summed from the energy each particle carries along and
+// the energy the pair forces pass on between particles, J = sum_i e_i v_i + W_i v_i, where e_i is the
+// kinetic and potential energy of the particle and W_i its share of the pair virial
+// (1/2 sum_j r_ij (x) f_ij), collected by the force loop of the grid
+// Thermal conductivity follows from the autocorrelation of the heat current (Green-Kubo)
+// Only the pair forces of the potential enter the virial, bonds, charges and walls are left out
+use super::particle::Particle;
+use super::stress::autocorrelation;
+use crate::ring_buffer::RingBuffer;
+use glam::{Mat3, Vec3};
+
+// Heat carried by one particle, virial is its share of the pair virial
+pub fn particle_heat_flux(particle: &Particle, virial: Mat3) -> Vec3 {
+    let vel = particle.get_vel();
+    let energy = 0.5 * particle.get_mass() * vel.length_squared() + particle.potential;
+    energy * vel + virial * vel
+}
+
+// Heat current of all particles, virials are the shares of the pair virial by particle
+// Particles without a virial, as before the first force calculation, only carry their energy
+pub fn heat_current(particles: &[Particle], virials: &[Mat3]) -> Vec3 {
+    particles
+        .iter()
+        .enumerate()
+        .map(|(i, particle)| {
+            particle_heat_flux(particle, virials.get(i).copied().unwrap_or(Mat3::ZERO))
+        })
+        .fold(Vec3::ZERO, |acc, flux| acc + flux)
+}
+
+// Recent heat currents, one per step
+#[derive(Clone)]
+pub struct HeatFluxHistory {
+    pub latest: Vec3,
+    components: [RingBuffer<f32>; 3], // x, y, z
+    sample_dt: f32,                   // time between samples, all samples share it
+}
+
+impl HeatFluxHistory {
+    pub fn with_capacity(capacity: usize, dt: f32) -> Self {
+        Self {
+            latest: Vec3::ZERO,
+            components: [
+                RingBuffer::with_capacity(capacity),
+                RingBuffer::with_capacity(capacity),
+                RingBuffer::with_capacity(capacity),
+            ],
+            sample_dt: dt,
+        }
+    }
+
+    // Record the heat current of a step
+    // The samples are cleared when the time step changes, they have to be evenly spaced
+    pub fn push(&mut self, current: Vec3, dt: f32) {
+        if (dt - self.sample_dt).abs() > f32::EPSILON {
+            *self = Self::with_capacity(self.capacity(), dt);
+        }
+
+        self.latest = current;
+        let values: [f32; 3] = current.into();
+        for (series, &value) in self.components.iter_mut().zip(values.iter()) {
+            series.push(value);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.components[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.components[0].len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.components[0].capacity()
+    }
+
+    pub fn get_sample_dt(&self) -> f32 {
+        self.sample_dt
+    }
+}
+
+// Green-Kubo estimate of the thermal conductivity
+// lambda = 1 / (V kT^2) * integral of <J_x(0) J_x(t)> dt, averaged over the three components
+pub struct ThermalConductivity {
+    pub sample_dt: f32,
+    pub autocorrelation: Vec<f32>, // heat current autocorrelation, by lag
+    pub running_integral: Vec<f32>, // conductivity integrated up to each lag
+    pub conductivity: f32,         // integral up to the largest lag
+}
+
+impl ThermalConductivity {
+    pub fn from_flux(flux: &HeatFluxHistory, kt: f32, volume: f32, max_lag: usize) -> Self {
+        let mut acf = vec![0.0; max_lag.min(flux.len())];
+        for series in flux.components.iter() {
+            for (sum, c) in acf
+                .iter_mut()
+                .zip(autocorrelation(&series.to_vec(), max_lag))
+            {
+                *sum += c / flux.components.len() as f32;
+            }
+        }
+
+        // trapezoidal rule
+        let dt = flux.sample_dt;
+        let scale = if kt > 0.0 && volume > 0.0 {
+            1.0 / (volume * kt * kt)
+        } else {
+            0.0
+        };
+        let mut integral = 0.0;
+        let mut running_integral = Vec::with_capacity(acf.len());
+        for (lag, &c) in acf.iter().enumerate() {
+            if lag > 0 {
+                integral += 0.5 * (acf[lag - 1] + c) * dt;
+            }
+            running_integral.push(integral * scale);
+        }
+
+        Self {
+            sample_dt: dt,
+            conductivity: running_integral.last().copied().unwrap_or(0.0),
+            autocorrelation: acf,
+            running_integral,
+        }
+    }
+}
@@ -0,0 +1,48 @@
+// Equations of motion the particles are stepped with
+// The thermostat target temperature doubles as the bath temperature of the stochastic integrators
+use serde::Deserialize;
+
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Integrator {
+    Verlet, // deterministic, the default, temperature is controlled by heat injection
+    Langevin { gamma: f32 }, // friction coefficient gamma with matching random kicks, samples the canonical ensemble
+    Brownian { gamma: f32 }, // overdamped limit of Langevin, no inertia, positions follow the forces plus noise
+}
+
+impl Default for Integrator {
+    fn default() -> Self {
+        Integrator::Verlet
+    }
+}
+
+impl Integrator {
+    pub const ALL: [Integrator; 3] = [
+        Integrator::Verlet,
+        Integrator::Langevin { gamma: 1.0 },
+        Integrator::Brownian { gamma: 10.0 },
+    ];
+
+    // Name of the integrator without its parameters
+    pub fn name(&self) -> &'static str {
+        match self {
+            Integrator::Verlet => "Verlet",
+            Integrator::Langevin { .. } => "Langevin",
+            Integrator::Brownian { .. } => "Brownian",
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            Integrator::Verlet => true,
+            Integrator::Langevin { gamma } => gamma >= 0.0,
+            Integrator::Brownian { gamma } => gamma > 0.0,
+        }
+    }
+
+    // Stochastic integrators act as their own thermostat, heat injection is skipped with them
+    // The overdamped one has no velocities, so kinetic energy and temperature read zero
+    pub fn is_stochastic(&self) -> bool {
+        !matches!(self, Integrator::Verlet)
+    }
+}
@@ -0,0 +1,241 @@
+// Planar liquid-vapor interfaces and their surface tension
+// The interfaces are found in the averaged density profile along an axis: the liquid and vapor
+// densities are the means of the densest and the emptiest slabs, and an interface is wherever
+// the profile passes from one to the other
+// Each interface is fitted with rho(z) = (rho_l + rho_v) / 2 +- (rho_l - rho_v) / 2 tanh((z - z0) / w)
+// The surface tension follows from the anisotropy of the stress tensor (Kirkwood-Buff),
+// gamma = L (P_n - P_t) / n for n interfaces across a box of length L along the normal
+use super::field::FieldAxis;
+use super::profile::{Profile, ProfileView};
+use super::SimulationState;
+use glam::Mat3;
+
+// Interfaces need the liquid to be at least this much denser than the vapor, relative to the liquid
+const MIN_CONTRAST: f32 = 0.3;
+
+// Share of the slabs averaged for the liquid and for the vapor density
+const BULK_FRACTION: f32 = 0.2;
+
+// Interfaces start and end at these fractions of the way from vapor to liquid density
+const LOW: f32 = 0.25;
+const HIGH: f32 = 0.75;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Interface {
+    pub position: f32, // where the density is halfway between vapor and liquid
+    pub width: f32,    // w of the tanh, the 10-90 width is 2.2 w
+    pub rising: bool,  // whether the density rises along the axis
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct InterfaceFit {
+    pub liquid_density: f32,
+    pub vapor_density: f32,
+    pub interfaces: Vec<Interface>,
+}
+
+impl InterfaceFit {
+    // Find and fit the interfaces of a density profile, None without two distinct phases
+    pub fn of(profile: &Profile) -> Option<Self> {
+        let density = &profile.density;
+        let slabs = density.len();
+        if slabs < 4 {
+            return None;
+        }
+        let mut sorted = density.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let bulk = ((slabs as f32 * BULK_FRACTION).ceil() as usize).max(1);
+        let vapor_density = sorted[..bulk].iter().sum::<f32>() / bulk as f32;
+        let liquid_density = sorted[slabs - bulk..].iter().sum::<f32>() / bulk as f32;
+        if liquid_density <= 0.0 || (liquid_density - vapor_density) / liquid_density < MIN_CONTRAST
+        {
+            return None;
+        }
+
+        // passages from below LOW to above HIGH or back, as (last slab on one side, first on the other)
+        let contrast = liquid_density - vapor_density;
+        let level = |i: usize| (density[i] - vapor_density) / contrast;
+        let mut passages = Vec::new();
+        let mut side: Option<(bool, usize)> = None; // (in the liquid, last slab there)
+        for i in 0..slabs {
+            let liquid = if level(i) >= HIGH {
+                true
+            } else if level(i) <= LOW {
+                false
+            } else {
+                continue;
+            };
+            match side {
+                Some((was_liquid, last)) if was_liquid != liquid => {
+                    passages.push((last, i, liquid))
+                }
+                _ => (),
+            }
+            side = Some((liquid, i));
+        }
+
+        let interfaces = passages
+            .iter()
+            .enumerate()
+            .map(|(k, &(from, to, rising))| {
+                // the middle crossing between the two sides
+                let mid = (from..to)
+                    .find(|&i| (level(i) - 0.5) * (level(i + 1) - 0.5) <= 0.0)
+                    .unwrap_or(from);
+                let (a, b) = (level(mid), level(mid + 1));
+                let t = if (b - a).abs() > 0.0 {
+                    ((0.5 - a) / (b - a)).clamp(0.0, 1.0)
+                } else {
+                    0.5
+                };
+                let position = profile.slab_center(mid) + t * profile.width;
+
+                // the slabs closer to this interface than to its neighbors
+                let start = if k == 0 {
+                    0
+                } else {
+                    (passages[k - 1].1 + from) / 2
+                };
+                let end = passages
+                    .get(k + 1)
+                    .map_or(slabs, |next| (to + next.0) / 2 + 1);
+                let fit = |width: f32| {
+                    let sign = if rising { 1.0 } else { -1.0 };
+                    (start..end)
+                        .map(|i| {
+                            let z = profile.slab_center(i);
+                            let model = 0.5 * (liquid_density + vapor_density)
+                                + sign * 0.5 * contrast * ((z - position) / width).tanh();
+                            (density[i] - model).powi(2)
+                        })
+                        .sum::<f32>()
+                };
+                let span = (to - from) as f32 * profile.width;
+                let width = minimize(fit, 0.05 * profile.width, span.max(profile.width) * 2.0);
+                Interface {
+                    position,
+                    width,
+                    rising,
+                }
+            })
+            .collect();
+
+        Some(Self {
+            liquid_density,
+            vapor_density,
+            interfaces,
+        })
+    }
+}
+
+// Minimum of f between lo and hi by golden section search, for functions with one minimum
+// internal helper function
+fn minimize(f: impl Fn(f32) -> f32, lo: f32, hi: f32) -> f32 {
+    const ITERATIONS: usize = 40;
+    let ratio = (5f32.sqrt() - 1.0) / 2.0;
+    let (mut lo, mut hi) = (lo, hi);
+    // the two inner points of the bracket and the values there
+    let mut left = hi - ratio * (hi - lo);
+    let mut right = lo + ratio * (hi - lo);
+    let (mut f_left, mut f_right) = (f(left), f(right));
+    for _ in 0..ITERATIONS {
+        if f_left < f_right {
+            hi = right;
+            right = left;
+            f_right = f_left;
+            left = hi - ratio * (hi - lo);
+            f_left = f(left);
+        } else {
+            lo = left;
+            left = right;
+            f_left = f_right;
+            right = lo + ratio * (hi - lo);
+            f_right = f(right);
+        }
+    }
+    0.5 * (lo + hi)
+}
+
+// Normal and tangential pressure of a stress tensor, about the axis as the normal of the interfaces
+pub fn normal_and_tangential(stress: Mat3, axis: FieldAxis) -> (f32, f32) {
+    let diagonal = [stress.x_axis.x, stress.y_axis.y, stress.z_axis.z];
+    let a = axis.index();
+    let normal = diagonal[a];
+    let tangential = (diagonal.iter().sum::<f32>() - normal) / 2.0;
+    (normal, tangential)
+}
+
+// Kirkwood-Buff surface tension of interfaces normal to the axis
+// length is the extent of the box along the axis, None without interfaces
+pub fn surface_tension(
+    stress: Mat3,
+    axis: FieldAxis,
+    length: f32,
+    interfaces: usize,
+) -> Option<f32> {
+    if interfaces == 0 {
+        return None;
+    }
+    let (normal, tangential) = normal_and_tangential(stress, axis);
+    Some(length * (normal - tangential) / interfaces as f32)
+}
+
+// Interfaces shown by the interface window
+// The density profile and the stress are averaged over the frames since the last change
+#[derive(Clone)]
+pub struct InterfaceView {
+    pub enabled: bool,
+    pub profile: ProfileView,
+    pub stress: Mat3, // mean stress tensor of the frames of the profile
+    pub fit: Option<InterfaceFit>,
+    pub surface_tension: Option<f32>,
+}
+
+impl Default for InterfaceView {
+    fn default() -> Self {
+        let mut profile = ProfileView::default();
+        profile.enabled = true;
+        profile.slabs = 50;
+        Self {
+            enabled: false,
+            profile,
+            stress: Mat3::ZERO,
+            fit: None,
+            surface_tension: None,
+        }
+    }
+}
+
+impl InterfaceView {
+    // Add this frame to the averages and fit them again, call once per frame
+    pub fn update(&mut self, state: &SimulationState) {
+        if !self.enabled {
+            return;
+        }
+        self.profile.enabled = true;
+        self.profile.average = true;
+        self.profile.update(state);
+        let frames = self.profile.frames().max(1);
+        if frames == 1 {
+            self.stress = state.stress.latest;
+        } else {
+            self.stress = self.stress + (state.stress.latest - self.stress) * (1.0 / frames as f32);
+        }
+
+        self.fit = self.profile.profile.as_ref().and_then(InterfaceFit::of);
+        let axis = self.profile.axis;
+        let length = state.bound.size()[axis.index()];
+        self.surface_tension = self
+            .fit
+            .as_ref()
+            .and_then(|fit| surface_tension(self.stress, axis, length, fit.interfaces.len()));
+    }
+
+    // Start the averages over
+    pub fn clear(&mut self) {
+        self.profile.clear();
+        self.stress = Mat3::ZERO;
+        self.fit = None;
+        self.surface_tension = None;
+    }
+}
@@ -0,0 +1,220 @@
+// Kinetic theory predictions
+// The measurements are compared with the ideal gas and with the van der Waals equation of state
+//   P = n kT / (1 - n b) - a n^2, with the number density n
+// a and b are fitted to the pressure, volume and temperature recorded in the history
+// Mean free paths are those of hard spheres, 1 / (sqrt(2) pi d^2 n) for spheres of diameter d
+use super::physics;
+use super::{History, SimulationState};
+use std::f32::consts::PI;
+
+// Recorded state of the gas, densities are particles per volume
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Sample {
+    pub density: f32,
+    pub temperature: f32,
+    pub pressure: f32,
+}
+
+// Entries of the history as samples, n is the number of particles
+pub fn samples(history: &History, n: usize) -> Vec<Sample> {
+    history
+        .volume
+        .iter()
+        .zip(history.temperature.iter())
+        .zip(history.pressure.iter())
+        .map(|((&volume, &temperature), &pressure)| Sample {
+            density: n as f32 / volume,
+            temperature,
+            pressure,
+        })
+        .collect()
+}
+
+pub fn ideal_pressure(density: f32, temperature: f32) -> f32 {
+    density * physics::kt(temperature)
+}
+
+pub fn ideal_mean_free_path(diameter: f32, density: f32) -> f32 {
+    1.0 / (2f32.sqrt() * PI * diameter.powi(2) * density)
+}
+
+// Diameter at which two particles of the mean radius collide, see PotentialModel::length_scale
+pub fn collision_diameter(state: &SimulationState) -> f32 {
+    let n = state.particles.len().max(1) as f32;
+    let mean_radius = state.particles.iter().map(|p| p.get_radius()).sum::<f32>() / n;
+    state.get_potential().length_scale() * mean_radius / physics::DEFAULT_RADIUS
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct VanDerWaals {
+    pub a: f32, // attraction, lowers the pressure by a n^2
+    pub b: f32, // volume excluded by each particle
+}
+
+impl VanDerWaals {
+    // b is scanned over this many values, a has a closed form for each of them
+    const B_STEPS: usize = 400;
+    // relative spread below which the samples say nothing about the density or temperature dependence
+    const MIN_SPREAD: f32 = 1e-3;
+
+    pub fn pressure(&self, density: f32, temperature: f32) -> f32 {
+        ideal_pressure(density, temperature) / (1.0 - self.b * density) - self.a * density.powi(2)
+    }
+
+    // Diameter of the hard spheres excluding the volume b, b = 2/3 pi d^3
+    pub fn diameter(&self) -> f32 {
+        (1.5 * self.b / PI).cbrt()
+    }
+
+    // Least squares fit of the pressures of the samples
+    // None without at least three samples spread out in density or temperature,
+    // a single state point can't tell the attraction and the excluded volume apart
+    pub fn fit(samples: &[Sample]) -> Option<Self> {
+        if samples.len() < 3
+            || !(is_spread(samples, |s| s.density) || is_spread(samples, |s| s.temperature))
+        {
+            return None;
+        }
+        let max_density = samples.iter().map(|s| s.density).fold(0.0, f32::max);
+        if max_density <= 0.0 {
+            return None;
+        }
+
+        // for a given b the pressure is linear in a
+        let fit_a = |b: f32| {
+            let (mut xy, mut xx) = (0.0f64, 0.0f64);
+            for s in samples.iter() {
+                let x = s.density.powi(2) as f64;
+                let y = (ideal_pressure(s.density, s.temperature) / (1.0 - b * s.density)
+                    - s.pressure) as f64;
+                xy += x * y;
+                xx += x * x;
+            }
+            let a = if xx > 0.0 { (xy / xx) as f32 } else { 0.0 };
+            let fit = Self { a, b };
+            let residual: f64 = samples
+                .iter()
+                .map(|s| ((fit.pressure(s.density, s.temperature) - s.pressure) as f64).powi(2))
+                .sum();
+            (fit, residual)
+        };
+
+        // b stays below the close packing of the densest sample
+        (0..Self::B_STEPS)
+            .map(|i| fit_a(i as f32 / Self::B_STEPS as f32 / max_density))
+            .min_by(|(_, r1), (_, r2)| r1.partial_cmp(r2).unwrap())
+            .map(|(fit, _)| fit)
+    }
+}
+
+// Linear fit of the volume against the pressure, for points recorded at a roughly fixed temperature
+// Its slope gives the isothermal compressibility, kappa = -1/V dV/dP
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PressureVolume {
+    pub slope: f32, // dV/dP
+    pub mean_volume: f32,
+    pub mean_pressure: f32,
+    pub correlation: f32, // of pressure and volume, near -1 while the box is compressed or expanded slowly
+}
+
+impl PressureVolume {
+    // Fit of (volume, pressure) points
+    // None without at least three points, or if the volume hardly changed: a fixed box only shows pressure noise
+    pub fn fit(points: &[(f32, f32)]) -> Option<Self> {
+        if points.len() < 3 {
+            return None;
+        }
+        let n = points.len() as f64;
+        let mean_v = points.iter().map(|&(v, _)| v as f64).sum::<f64>() / n;
+        let mean_p = points.iter().map(|&(_, p)| p as f64).sum::<f64>() / n;
+        let (mut vv, mut pp, mut vp) = (0.0f64, 0.0f64, 0.0f64);
+        for &(v, p) in points.iter() {
+            let (dv, dp) = (v as f64 - mean_v, p as f64 - mean_p);
+            vv += dv * dv;
+            pp += dp * dp;
+            vp += dv * dp;
+        }
+        let spread = VanDerWaals::MIN_SPREAD as f64 * mean_v.abs();
+        if (vv / n).sqrt() <= spread || pp <= 0.0 {
+            return None;
+        }
+        Some(Self {
+            slope: (vp / pp) as f32,
+            mean_volume: mean_v as f32,
+            mean_pressure: mean_p as f32,
+            correlation: (vp / (vv * pp).sqrt()) as f32,
+        })
+    }
+
+    pub fn compressibility(&self) -> f32 {
+        -self.slope / self.mean_volume
+    }
+
+    // Volume the fit gives at a pressure
+    pub fn volume(&self, pressure: f32) -> f32 {
+        self.mean_volume + self.slope * (pressure - self.mean_pressure)
+    }
+}
+
+// Entries of the history as (volume, pressure) points, the last window of them
+pub fn pressure_volume(history: &History, window: usize) -> Vec<(f32, f32)> {
+    let skip = history.len().saturating_sub(window);
+    history
+        .volume
+        .iter()
+        .zip(history.pressure.iter())
+        .skip(skip)
+        .map(|(&v, &p)| (v, p))
+        .collect()
+}
+
+// Whether a quantity varies over the samples
+// internal helper function
+fn is_spread(samples: &[Sample], value: impl Fn(&Sample) -> f32) -> bool {
+    let lo = samples.iter().map(&value).fold(f32::INFINITY, f32::min);
+    let hi = samples.iter().map(&value).fold(f32::NEG_INFINITY, f32::max);
+    hi - lo > VanDerWaals::MIN_SPREAD * hi.abs().max(lo.abs())
+}
+
+// Measured and predicted values for the current state
+pub struct Comparison {
+    pub density: f32,
+    pub temperature: f32,
+    pub pressure: f32,
+    pub ideal_pressure: f32,
+    pub van_der_waals: Option<VanDerWaals>,
+    pub mean_free_path: Option<f32>, // from the counted collisions
+    pub ideal_mean_free_path: f32,   // of hard spheres of the collision diameter
+}
+
+impl Comparison {
+    pub fn of(state: &SimulationState) -> Self {
+        let n = state.particles.len();
+        let density = n as f32 / state.bound.get_volume();
+        let temperature = state.temperature();
+        Self {
+            density,
+            temperature,
+            pressure: state.pressure.get_pressure(),
+            ideal_pressure: ideal_pressure(density, temperature),
+            van_der_waals: VanDerWaals::fit(&samples(&state.history, n)),
+            mean_free_path: state
+                .collisions
+                .mean_free_path(state.mean_speed(), n)
+                .filter(|_| state.collisions.is_measured()),
+            ideal_mean_free_path: ideal_mean_free_path(collision_diameter(state), density),
+        }
+    }
+
+    pub fn van_der_waals_pressure(&self) -> Option<f32> {
+        self.van_der_waals
+            .map(|fit| fit.pressure(self.density, self.temperature))
+    }
+
+    // Hard spheres excluding the fitted volume, None if the fit has no excluded volume
+    pub fn van_der_waals_mean_free_path(&self) -> Option<f32> {
+        self.van_der_waals
+            .filter(|fit| fit.b > 0.0)
+            .map(|fit| ideal_mean_free_path(fit.diameter(), self.density))
+    }
+}
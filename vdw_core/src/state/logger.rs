@@ -0,0 +1,118 @@
+// Per frame record of the observables
+// Rows are appended to a CSV file and summaries printed to the console depending on the verbosity
+// The heat current of the last step is logged for Green-Kubo analysis over longer runs than the
+// history holds, see heat_flux.rs
+use super::SimulationState;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+// How much the simulation prints to the console
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Verbosity {
+    Quiet,   // nothing
+    Normal,  // total and kinetic energy every few hundred steps
+    Verbose, // every observable of every frame
+}
+
+impl Verbosity {
+    pub const NAMES: [&'static str; 3] = ["quiet", "normal", "verbose"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "quiet" => Some(Verbosity::Quiet),
+            "normal" => Some(Verbosity::Normal),
+            "verbose" => Some(Verbosity::Verbose),
+            _ => None,
+        }
+    }
+}
+
+pub struct ObservableLogger {
+    verbosity: Verbosity,
+    file: Option<BufWriter<File>>,
+    flush_interval: usize, // number of frames between flushes of the file
+    frames: usize,
+}
+
+impl ObservableLogger {
+    // Steps between energy summaries at normal verbosity
+    const SUMMARY_INTERVAL: usize = 300;
+
+    const HEADER: &'static str =
+        "step,time,temperature,pressure,volume,kinetic,potential,n,heat_flux_x,heat_flux_y,heat_flux_z";
+
+    // A logger that only prints to the console
+    pub fn new(verbosity: Verbosity) -> Self {
+        Self {
+            verbosity,
+            file: None,
+            flush_interval: 1,
+            frames: 0,
+        }
+    }
+
+    // Also append a row per frame to the CSV file at path
+    // The header is written when the file is new or empty
+    pub fn with_file(mut self, path: impl AsRef<Path>, flush_interval: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let is_empty = file.metadata()?.len() == 0;
+        let mut file = BufWriter::new(file);
+        if is_empty {
+            writeln!(file, "{}", Self::HEADER)?;
+        }
+
+        self.file = Some(file);
+        self.flush_interval = flush_interval.max(1);
+        Ok(self)
+    }
+
+    // Record the state at the end of a frame, call once per frame
+    // A failing file is reported once and closed, the simulation keeps running
+    pub fn log_frame(&mut self, state: &SimulationState) {
+        self.frames += 1;
+
+        if let Some(file) = &mut self.file {
+            let mut result = writeln!(file, "{}", Self::row(state));
+            if result.is_ok() && self.frames % self.flush_interval == 0 {
+                result = file.flush();
+            }
+            if let Err(err) = result {
+                eprintln!("Observable log closed: {}", err);
+                self.file = None;
+            }
+        }
+
+        match self.verbosity {
+            Verbosity::Quiet => {}
+            Verbosity::Normal => {
+                // the frame crossed a multiple of the interval
+                if state.steps % Self::SUMMARY_INTERVAL < state.last_frame_steps {
+                    println!("{}, {}", state.energy.total(), state.energy.kinetic);
+                }
+            }
+            Verbosity::Verbose => println!("{}", Self::row(state)),
+        }
+    }
+
+    // One line of observables in the order of HEADER
+    // internal helper function
+    fn row(state: &SimulationState) -> String {
+        let n = state.particles.len();
+        let flux = state.heat_flux.latest;
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{}",
+            state.steps,
+            state.time,
+            state.temperature(),
+            state.pressure.get_pressure(),
+            state.bound.get_volume(),
+            state.energy.kinetic,
+            state.energy.potential,
+            n,
+            flux.x,
+            flux.y,
+            flux.z
+        )
+    }
+}
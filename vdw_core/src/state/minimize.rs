@@ -0,0 +1,188 @@
+// Energy minimization
+// Relaxes a configuration to the nearest minimum of the interaction energy before dynamics begin
+// Uses FIRE (fast inertial relaxation engine), a damped dynamics that speeds up while going downhill
+use super::{bond, ForceWorkspace, SimulationState};
+use crate::par::*;
+use glam::Vec3;
+use rand::Rng;
+use rand_distr::StandardNormal;
+use std::fmt;
+
+// Outcome of a minimization
+#[derive(Clone, Copy, Debug)]
+pub struct MinimizationReport {
+    pub iterations: usize,
+    pub potential: f32, // total interaction energy at the end
+    pub max_force: f32, // largest force on a single particle at the end
+    pub converged: bool,
+}
+
+impl fmt::Display for MinimizationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "minimization: {} iterations, max force {}, potential {}, converged {}",
+            self.iterations, self.max_force, self.potential, self.converged
+        )
+    }
+}
+
+// FIRE parameters, the defaults of the original paper
+const ALPHA_START: f32 = 0.1;
+const ALPHA_SHRINK: f32 = 0.99;
+const DT_GROW: f32 = 1.1;
+const DT_SHRINK: f32 = 0.5;
+const MIN_DOWNHILL_STEPS: usize = 5; // steps going downhill before speeding up
+const MAX_DT_FACTOR: f32 = 10.0; // largest time step as a multiple of the simulation time step
+
+// A particle moves at most this far per iteration, keeps overlapping pairs from flying apart
+const MAX_DISPLACEMENT: f32 = 0.01;
+
+impl SimulationState {
+    // Move the particles to lower the interaction energy, velocities are left alone
+    // Stops after max_iters iterations or once no force is larger than tolerance
+    // Members of rigid clusters are held in place, the box walls only confine the particles
+    pub fn minimize_energy(&mut self, max_iters: usize, tolerance: f32) -> MinimizationReport {
+        let mut rng = rand::thread_rng();
+        let is_free = self.free_particles();
+
+        let mut pos: Vec<Vec3> = self.particles.iter().map(|p| p.get_pos()).collect();
+        let mass: Vec<f32> = self.particles.iter().map(|p| p.get_mass()).collect();
+        let mut vel = vec![Vec3::ZERO; pos.len()];
+        let mut dt = self.dt;
+        let mut alpha = ALPHA_START;
+        let mut downhill_steps = 0;
+
+        let mut iterations = 0;
+        let (mut forces, mut potential) = self.interaction_forces(&pos, &is_free);
+        let mut max_force = max_length(&forces);
+        while iterations < max_iters && max_force > tolerance {
+            // overlapping particles give infinite forces, push them apart in a random direction
+            for force in forces.iter_mut().filter(|f| !f.is_finite()) {
+                let dir = Vec3::new(
+                    rng.sample(StandardNormal),
+                    rng.sample(StandardNormal),
+                    rng.sample(StandardNormal),
+                );
+                *force = dir.normalize_or_zero() * MAX_DISPLACEMENT / dt.powi(2);
+            }
+
+            // steer the velocity towards the force, stop entirely when going uphill
+            let power: f32 = forces.iter().zip(vel.iter()).map(|(f, v)| f.dot(*v)).sum();
+            if power > 0.0 {
+                let vel_norm = norm(&vel);
+                let force_norm = norm(&forces).max(f32::MIN_POSITIVE);
+                for (v, f) in vel.iter_mut().zip(forces.iter()) {
+                    *v = (1.0 - alpha) * *v + alpha * vel_norm / force_norm * *f;
+                }
+                downhill_steps += 1;
+                if downhill_steps > MIN_DOWNHILL_STEPS {
+                    dt = (dt * DT_GROW).min(self.dt * MAX_DT_FACTOR);
+                    alpha *= ALPHA_SHRINK;
+                }
+            } else {
+                vel.iter_mut().for_each(|v| *v = Vec3::ZERO);
+                dt *= DT_SHRINK;
+                alpha = ALPHA_START;
+                downhill_steps = 0;
+            }
+
+            // semi implicit euler step, kept inside the box
+            let (lo, hi) = (self.bound.lo_corner(), self.bound.hi_corner());
+            (&mut pos, &mut vel, &forces, &mass)
+                .into_par_iter()
+                .for_each(|(p, v, &f, &m)| {
+                    *v += f / m * dt;
+                    let mut step = *v * dt;
+                    if step.length() > MAX_DISPLACEMENT {
+                        step = step.normalize() * MAX_DISPLACEMENT;
+                    }
+                    *p = (*p + step).max(lo).min(hi);
+                });
+
+            let (new_forces, new_potential) = self.interaction_forces(&pos, &is_free);
+            forces = new_forces;
+            potential = new_potential;
+            max_force = max_length(&forces);
+            iterations += 1;
+        }
+
+        (&mut self.particles, pos)
+            .into_par_iter()
+            .for_each(|(particle, p)| {
+                let vel = particle.get_vel();
+                particle.move_to(p, vel);
+            });
+        self.energy.potential = potential;
+
+        MinimizationReport {
+            iterations,
+            potential,
+            max_force,
+            converged: max_force <= tolerance,
+        }
+    }
+
+    // Forces between particles and the total interaction energy, at arbitrary positions
+    // Forces on particles that aren't free are zeroed
+    // internal helper function
+    fn interaction_forces(&self, pos: &[Vec3], is_free: &[bool]) -> (Vec<Vec3>, f32) {
+        let mut workspace = ForceWorkspace::default();
+        workspace.positions.extend_from_slice(pos);
+        // laid over the box, so pairs across periodic faces are found
+        workspace.grid.anchor_to(self.bound);
+        self.grid
+            .calculate_force_with(pos, &self.interaction_sites(), &mut workspace.grid);
+        bond::calculate_force_into(
+            &self.bonds,
+            pos,
+            &self.bound,
+            &mut workspace.bond_forces,
+            &mut workspace.bond_energies,
+        );
+        self.calculate_coulomb_force(&mut workspace);
+        // the particles only move, the dipoles keep their directions
+        self.calculate_dipole_force(&mut workspace);
+        let grid_energies = workspace.grid.potentials();
+        let (bond_energies, coulomb_energies, dipole_energies) = (
+            &workspace.bond_energies,
+            &workspace.coulomb_energies,
+            &workspace.dipole_energies,
+        );
+
+        let forces = (
+            workspace.grid.forces(),
+            &workspace.bond_forces,
+            &workspace.coulomb_forces,
+            &workspace.dipole_forces,
+            is_free,
+        )
+            .into_par_iter()
+            .map(|(&grd_f, &bnd_s, &elc_f, &dip_f, &free)| {
+                if free {
+                    grd_f + bnd_s + elc_f + dip_f
+                } else {
+                    Vec3::ZERO
+                }
+            })
+            .collect();
+        let potential = grid_energies.iter().sum::<f32>()
+            + bond_energies.iter().sum::<f32>()
+            + coulomb_energies.iter().sum::<f32>()
+            + dipole_energies.iter().sum::<f32>();
+
+        (forces, potential)
+    }
+}
+
+fn norm(vectors: &[Vec3]) -> f32 {
+    vectors
+        .iter()
+        .map(|v| v.length_squared())
+        .sum::<f32>()
+        .sqrt()
+}
+
+fn max_length(vectors: &[Vec3]) -> f32 {
+    vectors.iter().map(|v| v.length()).fold(0.0, f32::max)
+}
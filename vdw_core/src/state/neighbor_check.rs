@@ -0,0 +1,148 @@
+// Neighbor grid diagnostics
+// Compares the grid accelerated interactions against a brute force sum over every particle
+// Catches pairs the grid misses, for example near the edges of the box
+// or across the periodic faces, where the brute force sum takes the nearest image
+use super::sim_space::GridWorkspace;
+use super::SimulationState;
+use glam::Vec3;
+use std::collections::HashSet;
+
+// Force differences below this fraction of the force are put down to summation order
+pub const FORCE_TOLERANCE: f32 = 1e-3;
+
+// Outcome of comparing the grid against the brute force sum on a sample of particles
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NeighborReport {
+    pub sampled: usize,              // number of particles compared
+    pub max_force_deviation: f32,    // largest |grid force - brute force force| in the sample
+    pub max_relative_deviation: f32, // the same deviation relative to the force
+    pub max_potential_deviation: f32,
+    pub missed_pairs: usize, // interacting pairs of the sample the grid didn't find
+    pub worst_particle: Option<usize>, // particle with the largest force deviation
+}
+
+impl NeighborReport {
+    pub fn is_consistent(&self) -> bool {
+        self.missed_pairs == 0 && self.max_relative_deviation <= FORCE_TOLERANCE
+    }
+}
+
+impl SimulationState {
+    // Compare the grid interactions of the sampled particles against a brute force sum
+    // The grid is computed for every particle, the brute force sum is O(N) per sampled particle
+    pub fn check_neighbors(&self, sample: &[usize]) -> NeighborReport {
+        let positions: Vec<Vec3> = self.particles.iter().map(|p| p.get_pos()).collect();
+        let sites = self.interaction_sites();
+        // laid over the box as in the simulation, which periodic faces need
+        let mut workspace = GridWorkspace::default();
+        workspace.anchor_to(self.bound);
+        self.grid
+            .calculate_force_with(&positions, &sites, &mut workspace);
+        let (grid_force, grid_potential) =
+            (workspace.forces().to_vec(), workspace.potentials().to_vec());
+        let mut pairs = Vec::new();
+        self.grid
+            .find_pairs_with(&positions, &mut workspace, &mut pairs);
+        let grid_pairs: HashSet<(usize, usize)> = pairs.into_iter().collect();
+        let range = self.grid.get_range();
+        let switch_start = self.grid.get_switch_start();
+        let potential_model = self.grid.get_potential();
+
+        let mut report = NeighborReport {
+            sampled: sample.len(),
+            ..NeighborReport::default()
+        };
+        for &i in sample.iter() {
+            let mut force = Vec3::ZERO;
+            let mut potential = 0.0;
+            for (j, &other) in positions.iter().enumerate() {
+                let separation = self.bound.minimum_image(positions[i] - other);
+                if j == i || separation.length_squared() >= range.powi(2) {
+                    continue;
+                }
+                let (f, u) = potential_model.site_interaction(
+                    positions[i],
+                    sites[i],
+                    positions[i] - separation,
+                    sites[j],
+                    range,
+                    switch_start,
+                );
+                force += f;
+                potential += u;
+                if !grid_pairs.contains(&(i.min(j), i.max(j))) {
+                    report.missed_pairs += 1;
+                }
+            }
+
+            let deviation = (grid_force[i] - force).length();
+            if deviation > report.max_force_deviation || report.worst_particle.is_none() {
+                report.max_force_deviation = deviation;
+                report.worst_particle = Some(i);
+            }
+            report.max_relative_deviation = report
+                .max_relative_deviation
+                .max(deviation / force.length().max(1.0));
+            report.max_potential_deviation = report
+                .max_potential_deviation
+                .max((grid_potential[i] - potential).abs());
+        }
+
+        report
+    }
+}
+
+////////////////////////////////////////////////////////
+// Periodic neighbor check of the latest snapshot
+//
+pub struct NeighborCheck {
+    pub enabled: bool,
+    pub interval: usize,    // number of frames between checks
+    pub sample_size: usize, // number of particles compared per check
+    frames: usize,
+    pub checks: usize,   // number of checks done since enabled
+    pub failures: usize, // number of checks that found an inconsistency
+    pub latest: NeighborReport,
+    pub last_failure: Option<(f32, NeighborReport)>, // simulated time and report of the latest failed check
+}
+
+impl Default for NeighborCheck {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 30,
+            sample_size: 50,
+            frames: 0,
+            checks: 0,
+            failures: 0,
+            latest: NeighborReport::default(),
+            last_failure: None,
+        }
+    }
+}
+
+impl NeighborCheck {
+    // Check a random sample of particles once every interval frames while enabled
+    // Inconsistencies are counted and kept in last_failure, nothing is printed
+    // Counts frames, call once per frame
+    pub fn update(&mut self, state: &SimulationState) {
+        if !self.enabled || state.particles.is_empty() {
+            return;
+        }
+        let due = self.frames % self.interval.max(1) == 0;
+        self.frames += 1;
+        if !due {
+            return;
+        }
+
+        let n = state.particles.len();
+        let sample = rand::seq::index::sample(&mut rand::thread_rng(), n, self.sample_size.min(n));
+        self.latest = state.check_neighbors(&sample.into_vec());
+        self.checks += 1;
+
+        if !self.latest.is_consistent() {
+            self.failures += 1;
+            self.last_failure = Some((state.time, self.latest));
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use super::sim_space::Boundary;
+use glam::Vec3;
+use std::cmp::Reverse;
+
+////////////////////////////////////////////////////////////
+// Network is a graph over the particles
+// Edges connect bonded particles, components are found with union-find
+//
+#[derive(Clone, Default)]
+pub struct Network {
+    edges: Vec<(usize, usize)>,
+    labels: Vec<usize>, // component of each particle, labelled by its smallest member
+}
+
+impl Network {
+    // Build a network of n particles from a list of edges
+    pub fn from_edges(n: usize, edges: Vec<(usize, usize)>) -> Self {
+        let mut parents: Vec<usize> = (0..n).collect();
+
+        fn find(parents: &mut [usize], mut i: usize) -> usize {
+            while parents[i] != i {
+                parents[i] = parents[parents[i]]; // path halving
+                i = parents[i];
+            }
+            i
+        }
+
+        for &(i, j) in edges.iter() {
+            let root_i = find(&mut parents, i);
+            let root_j = find(&mut parents, j);
+            // keep the smallest index as root
+            if root_i < root_j {
+                parents[root_j] = root_i;
+            } else {
+                parents[root_i] = root_j;
+            }
+        }
+
+        let labels = (0..n).map(|i| find(&mut parents, i)).collect();
+        Self { edges, labels }
+    }
+
+    pub fn get_edges(&self) -> &[(usize, usize)] {
+        &self.edges
+    }
+
+    // Number of edges at each particle
+    pub fn degrees(&self) -> Vec<usize> {
+        let mut degrees = vec![0; self.labels.len()];
+        for &(i, j) in self.edges.iter() {
+            degrees[i] += 1;
+            degrees[j] += 1;
+        }
+        degrees
+    }
+
+    // Lists of particle indices, one per connected component, largest first
+    pub fn components(&self) -> Vec<Vec<usize>> {
+        let mut components = vec![vec![]; self.labels.len()];
+        for (i, &label) in self.labels.iter().enumerate() {
+            components[label].push(i);
+        }
+
+        let mut components: Vec<_> = components.into_iter().filter(|c| !c.is_empty()).collect();
+        components.sort_by_key(|c| Reverse(c.len()));
+        components
+    }
+
+    // Check whether any component reaches from one wall to the opposite wall along each axis
+    pub fn percolating_axes(&self, positions: &[Vec3], bound: Boundary, margin: f32) -> [bool; 3] {
+        let mut ret = [false; 3];
+        for component in self.components() {
+            // components are sorted, single particles can't span the box
+            if component.len() < 2 {
+                break;
+            }
+
+            let spans = spanned_axes(&component, positions, bound, margin);
+            for (percolates, spans) in ret.iter_mut().zip(spans.iter()) {
+                *percolates |= spans;
+            }
+        }
+        ret
+    }
+
+    // Check whether the largest component spans the box along any axis
+    pub fn largest_percolates(&self, positions: &[Vec3], bound: Boundary, margin: f32) -> bool {
+        match self.components().first() {
+            Some(largest) if largest.len() >= 2 => spanned_axes(largest, positions, bound, margin)
+                .iter()
+                .any(|&spans| spans),
+            _ => false,
+        }
+    }
+}
+
+// Axes along which a set of particles reaches from one wall to the opposite wall
+// A set touches a wall if one of its particles is within margin of it
+fn spanned_axes(
+    component: &[usize],
+    positions: &[Vec3],
+    bound: Boundary,
+    margin: f32,
+) -> [bool; 3] {
+    let lo = component
+        .iter()
+        .fold(Vec3::splat(f32::INFINITY), |acc, &i| acc.min(positions[i]));
+    let hi = component
+        .iter()
+        .fold(Vec3::splat(f32::NEG_INFINITY), |acc, &i| {
+            acc.max(positions[i])
+        });
+
+    let touches_lo = lo.cmplt(bound.lo_corner() + Vec3::splat(margin));
+    let touches_hi = hi.cmpgt(bound.hi_corner() - Vec3::splat(margin));
+    let spans = (touches_lo & touches_hi).bitmask();
+    [spans & 1 != 0, spans & 2 != 0, spans & 4 != 0]
+}
+
+// Settings and latest result of the bond network analysis
+pub struct NetworkView {
+    pub enabled: bool,
+    pub threshold: f32, // pairs with interaction energy below this are bonded
+    pub network: Network,
+    pub percolation: [bool; 3],
+}
+
+impl Default for NetworkView {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: -0.3,
+            network: Network::default(),
+            percolation: [false; 3],
+        }
+    }
+}
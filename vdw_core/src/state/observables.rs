@@ -0,0 +1,128 @@
+// Fluctuation based observables
+// Response functions are estimated from the fluctuations of quantities recorded in History
+use super::physics;
+use super::History;
+
+// Running mean and variance of a series of samples (Welford's algorithm)
+#[derive(Clone, Copy, Default)]
+pub struct Moments {
+    count: usize,
+    mean: f64,
+    m2: f64, // sum of squared deviations from the mean
+}
+
+impl Moments {
+    pub fn push(&mut self, x: f32) {
+        let x = x as f64;
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn mean(&self) -> f32 {
+        self.mean as f32
+    }
+
+    // Sample variance, zero with fewer than two samples
+    pub fn variance(&self) -> f32 {
+        if self.count < 2 {
+            return 0.0;
+        }
+        (self.m2 / (self.count - 1) as f64) as f32
+    }
+
+    // Standard error of the mean
+    pub fn mean_error(&self) -> f32 {
+        if self.count < 2 {
+            return 0.0;
+        }
+        (self.variance() / self.count as f32).sqrt()
+    }
+
+    // Standard error of the variance, assuming normally distributed samples
+    pub fn variance_error(&self) -> f32 {
+        if self.count < 2 {
+            return 0.0;
+        }
+        self.variance() * (2.0 / (self.count - 1) as f32).sqrt()
+    }
+}
+
+// Accumulators of the quantities recorded in History
+// Unlike the ring buffers these cover every entry since the last reset
+#[derive(Clone, Copy, Default)]
+pub struct HistoryMoments {
+    pub total_energy: Moments,
+    pub temperature: Moments,
+    pub pressure: Moments,
+    pub volume: Moments,
+}
+
+// A value with its standard error
+#[derive(Clone, Copy, Default)]
+pub struct Estimate {
+    pub value: f32,
+    pub error: f32,
+}
+
+// Observables derived from the accumulated history
+// Samples are assumed uncorrelated, so error bars are underestimated if entries are recorded too often
+pub struct Observables {
+    pub samples: usize,
+    pub total_energy: Estimate,
+    pub temperature: Estimate,
+    pub pressure: Estimate,
+    pub volume: Estimate,
+    pub heat_capacity: Estimate, // per particle, from total energy fluctuations at fixed temperature
+    pub compressibility: Estimate, // isothermal, from volume fluctuations at fixed pressure
+}
+
+impl Observables {
+    pub fn from_history(history: &History, n_particles: usize) -> Self {
+        let moments = &history.moments;
+        let mean = |m: &Moments| Estimate {
+            value: m.mean(),
+            error: m.mean_error(),
+        };
+
+        let kt = physics::kt(moments.temperature.mean());
+
+        // C = var(E) / kT^2
+        let heat_capacity = if kt > 0.0 && n_particles > 0 {
+            let scale = 1.0 / (kt * kt * n_particles as f32);
+            Estimate {
+                value: moments.total_energy.variance() * scale,
+                error: moments.total_energy.variance_error() * scale,
+            }
+        } else {
+            Estimate::default()
+        };
+
+        // kappa = var(V) / (kT <V>)
+        let volume = moments.volume.mean();
+        let compressibility = if kt > 0.0 && volume > 0.0 {
+            let scale = 1.0 / (kt * volume);
+            Estimate {
+                value: moments.volume.variance() * scale,
+                error: moments.volume.variance_error() * scale,
+            }
+        } else {
+            Estimate::default()
+        };
+
+        Self {
+            samples: moments.total_energy.count(),
+            total_energy: mean(&moments.total_energy),
+            temperature: mean(&moments.temperature),
+            pressure: mean(&moments.pressure),
+            volume: mean(&moments.volume),
+            heat_capacity,
+            compressibility,
+        }
+    }
+}
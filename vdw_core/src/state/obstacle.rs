@@ -0,0 +1,219 @@
+// Immovable obstacles inside the box
+// They keep particles out the same way the walls of the box do:
+// soft walls push back with a stiff spring, hard walls reflect the particles crossing the surface
+use super::sim_space::{Boundary, WallModel};
+use crate::par::*;
+use glam::Vec3;
+use rand::Rng;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Obstacle {
+    Sphere { center: Vec3, radius: f32 },
+    Box { lo: Vec3, hi: Vec3 }, // axis aligned, lo and hi are opposite corners
+}
+
+impl Obstacle {
+    // as stiff as the soft walls of the box
+    const DEFLECT_STR: f32 = 10000.0;
+
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            Obstacle::Sphere { radius, .. } => radius > 0.0,
+            Obstacle::Box { lo, hi } => lo.cmplt(hi).all(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Obstacle::Sphere { .. } => "Sphere",
+            Obstacle::Box { .. } => "Box",
+        }
+    }
+
+    pub fn center(&self) -> Vec3 {
+        match *self {
+            Obstacle::Sphere { center, .. } => center,
+            Obstacle::Box { lo, hi } => (lo + hi) / 2.0,
+        }
+    }
+
+    pub fn volume(&self) -> f32 {
+        match *self {
+            Obstacle::Sphere { radius, .. } => 4.0 / 3.0 * std::f32::consts::PI * radius.powi(3),
+            Obstacle::Box { lo, hi } => {
+                let size = hi - lo;
+                size.x * size.y * size.z
+            }
+        }
+    }
+
+    pub fn contains(&self, pos: Vec3) -> bool {
+        self.escape(pos).is_some()
+    }
+
+    // Stiff spring pushing a particle inside the obstacle back out
+    pub fn force(&self, pos: Vec3) -> Vec3 {
+        match self.escape(pos) {
+            Some((normal, depth)) => Self::DEFLECT_STR * depth * normal,
+            None => Vec3::ZERO,
+        }
+    }
+
+    // Mirror a particle that has crossed the surface back out and flip its normal velocity
+    // Thermal walls only thermalize at the box, obstacles reflect elastically
+    pub fn reflect(&self, pos: &mut Vec3, vel: &mut Vec3) {
+        if let Some((normal, depth)) = self.escape(*pos) {
+            *pos += 2.0 * depth * normal;
+            let v_normal = vel.dot(normal);
+            if v_normal < 0.0 {
+                *vel -= 2.0 * v_normal * normal;
+            }
+        }
+    }
+
+    // Direction out of the obstacle and distance to its surface, for positions inside
+    // internal helper function
+    fn escape(&self, pos: Vec3) -> Option<(Vec3, f32)> {
+        match *self {
+            Obstacle::Sphere { center, radius } => {
+                let r = pos - center;
+                let distance = r.length();
+                if distance >= radius {
+                    return None;
+                }
+                // the exact center has no preferred direction
+                let normal = if distance > 0.0 {
+                    r / distance
+                } else {
+                    Vec3::X
+                };
+                Some((normal, radius - distance))
+            }
+            Obstacle::Box { lo, hi } => {
+                if !(pos.cmpgt(lo).all() && pos.cmplt(hi).all()) {
+                    return None;
+                }
+                // leave through the closest face
+                let mut best = (Vec3::ZERO, f32::INFINITY);
+                for axis in 0..3 {
+                    let mut normal = Vec3::ZERO;
+                    let to_lo = pos[axis] - lo[axis];
+                    let to_hi = hi[axis] - pos[axis];
+                    let depth = if to_lo < to_hi {
+                        normal[axis] = -1.0;
+                        to_lo
+                    } else {
+                        normal[axis] = 1.0;
+                        to_hi
+                    };
+                    if depth < best.1 {
+                        best = (normal, depth);
+                    }
+                }
+                Some(best)
+            }
+        }
+    }
+}
+
+// Forces of all obstacles on the particles, written into a reused buffer
+// Hard walls exert no force, they act through reflect instead
+pub fn calculate_force_into(
+    obstacles: &[Obstacle],
+    wall_model: WallModel,
+    ps: &[Vec3],
+    forces: &mut Vec<Vec3>,
+) {
+    if obstacles.is_empty() || wall_model.is_hard() {
+        forces.clear();
+        forces.resize(ps.len(), Vec3::ZERO);
+        return;
+    }
+    ps.par_iter()
+        .map(|&p| {
+            obstacles
+                .iter()
+                .map(|obstacle| obstacle.force(p))
+                .fold(Vec3::ZERO, |acc, f| acc + f)
+        })
+        .collect_into_vec(forces);
+}
+
+/////////////////////////////////////////////////
+// Porous layouts
+// Obstacle arrangements that confine the fluid to pores of a chosen size
+//
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PoreLayout {
+    Slit { width: f32 }, // two plates leaving a gap of width across the middle of the y axis
+    SpherePacking { porosity: f32, radius: f32 }, // random non overlapping spheres of one radius
+}
+
+impl PoreLayout {
+    // spheres that can't be placed after this many tries in a row end the packing
+    const MAX_ATTEMPTS: usize = 10000;
+
+    // Obstacles of the layout filling the boundary
+    // The packing is random, it stops short of the porosity if no more spheres fit
+    pub fn obstacles(&self, bound: &Boundary) -> Vec<Obstacle> {
+        let lo = bound.lo_corner();
+        let hi = bound.hi_corner();
+        match *self {
+            PoreLayout::Slit { width } => {
+                let plate = (bound.size().y - width) / 2.0;
+                if plate <= 0.0 {
+                    return Vec::new();
+                }
+                vec![
+                    Obstacle::Box {
+                        lo,
+                        hi: Vec3::new(hi.x, lo.y + plate, hi.z),
+                    },
+                    Obstacle::Box {
+                        lo: Vec3::new(lo.x, hi.y - plate, lo.z),
+                        hi,
+                    },
+                ]
+            }
+            PoreLayout::SpherePacking { porosity, radius } => {
+                let mut rng = rand::thread_rng();
+                let target_volume = (1.0 - porosity) * bound.get_volume();
+                let margin = Vec3::splat(radius);
+                if radius <= 0.0 || (hi - lo).cmple(2.0 * margin).any() {
+                    return Vec::new();
+                }
+
+                // random sequential addition, the spheres stay inside the box
+                let mut spheres: Vec<Obstacle> = Vec::new();
+                let mut volume = 0.0;
+                let mut attempts = 0;
+                while volume < target_volume && attempts < Self::MAX_ATTEMPTS {
+                    let center = Vec3::new(
+                        rng.gen_range(lo.x + radius..hi.x - radius),
+                        rng.gen_range(lo.y + radius..hi.y - radius),
+                        rng.gen_range(lo.z + radius..hi.z - radius),
+                    );
+                    let overlaps = spheres
+                        .iter()
+                        .any(|other| (other.center() - center).length() < 2.0 * radius);
+                    if overlaps {
+                        attempts += 1;
+                        continue;
+                    }
+                    let sphere = Obstacle::Sphere { center, radius };
+                    volume += sphere.volume();
+                    spheres.push(sphere);
+                    attempts = 0;
+                }
+                spheres
+            }
+        }
+    }
+}
+
+// Share of the box volume left open by the obstacles
+// Assumes the obstacles don't overlap each other, as in the pore layouts
+pub fn porosity(bound: &Boundary, obstacles: &[Obstacle]) -> f32 {
+    let solid: f32 = obstacles.iter().map(|obstacle| obstacle.volume()).sum();
+    1.0 - solid / bound.get_volume()
+}
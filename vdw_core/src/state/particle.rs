@@ -0,0 +1,293 @@
+use super::physics::{self, InteractionSite};
+use glam::{Mat3, Quat, Vec3};
+
+// simulated particle
+// Point particles and oriented ones share the same model, a particle that never turns keeps the
+// identity orientation, which the isotropic interactions don't look at
+#[derive(Clone)]
+pub struct Particle {
+    pub neighbors: usize,
+    pub potential: f32, // share of the potential energy held by this particle
+    mass: f32,
+    radius: f32,    // size of the particle, sums of radii set how close pairs get
+    epsilon: f32,   // well depth relative to the potential model, pairs take the geometric mean
+    species: usize, // used to tell apart particles of a mixture
+    charge: f32,
+    inert: bool, // solvent particles, they only feel the repulsive core of the potential
+    fixed: bool, // static particles, they exert their forces but never move, as if of infinite mass
+    pos: Vec3,
+    vel: Vec3,
+    orientation: Quat,     // rotation from the body frame to the world frame
+    ang_vel: Vec3,         // angular velocity in the world frame
+    inertia: Option<Vec3>, // principal moments of inertia along the body axes, a uniform ball if none
+    dipole: Vec3,          // dipole moment in the body frame, turns with the particle
+}
+
+impl Default for Particle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Particle {
+    // Create a particle with mass = 1 and the default radius, at the origin, unturned and resting
+    // Parameters can be set using the corresponding builders
+    pub fn new() -> Self {
+        Self {
+            neighbors: 0,
+            potential: 0.0,
+            mass: 1.0,
+            radius: physics::DEFAULT_RADIUS,
+            epsilon: 1.0,
+            species: 0,
+            charge: 0.0,
+            inert: false,
+            fixed: false,
+            pos: Vec3::new(0.0, 0.0, 0.0),
+            vel: Vec3::new(0.0, 0.0, 0.0),
+            orientation: Quat::IDENTITY,
+            ang_vel: Vec3::ZERO,
+            inertia: None,
+            dipole: Vec3::ZERO,
+        }
+    }
+
+    //////////////////////
+    // Builders
+    // Use these after initialization to specify relevant attributes
+    //
+
+    pub fn set_mass(mut self, mass: f32) -> Self {
+        self.mass = mass;
+        self
+    }
+
+    pub fn set_radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    pub fn set_epsilon(mut self, epsilon: f32) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    pub fn set_species(mut self, species: usize) -> Self {
+        self.species = species;
+        self
+    }
+
+    pub fn set_charge(mut self, charge: f32) -> Self {
+        self.charge = charge;
+        self
+    }
+
+    // Dipole moment along the body axes
+    pub fn set_dipole(mut self, x: f32, y: f32, z: f32) -> Self {
+        self.dipole = Vec3::new(x, y, z);
+        self
+    }
+
+    pub fn set_inert(mut self, inert: bool) -> Self {
+        self.inert = inert;
+        self
+    }
+
+    pub fn set_fixed(mut self, fixed: bool) -> Self {
+        self.fix(fixed);
+        self
+    }
+
+    pub fn set_pos(mut self, x: f32, y: f32, z: f32) -> Self {
+        self.pos = Vec3::new(x, y, z);
+        self
+    }
+
+    pub fn set_vel(mut self, x: f32, y: f32, z: f32) -> Self {
+        self.vel = Vec3::new(x, y, z);
+        self
+    }
+
+    // Normalized, any non-zero quaternion will do
+    pub fn set_orientation(mut self, orientation: Quat) -> Self {
+        self.orientation = orientation.normalize();
+        self
+    }
+
+    pub fn set_ang_vel(mut self, x: f32, y: f32, z: f32) -> Self {
+        self.ang_vel = Vec3::new(x, y, z);
+        self
+    }
+
+    // Principal moments of inertia along the x, y and z axes of the body
+    pub fn set_inertia(mut self, x: f32, y: f32, z: f32) -> Self {
+        self.inertia = Some(Vec3::new(x, y, z));
+        self
+    }
+
+    /////////////////////////
+    // Getters
+    //
+
+    pub fn get_mass(&self) -> f32 {
+        self.mass
+    }
+
+    pub fn get_radius(&self) -> f32 {
+        self.radius
+    }
+
+    pub fn get_epsilon(&self) -> f32 {
+        self.epsilon
+    }
+
+    pub fn get_species(&self) -> usize {
+        self.species
+    }
+
+    pub fn get_charge(&self) -> f32 {
+        self.charge
+    }
+
+    // Dipole moment in the body frame
+    pub fn get_dipole(&self) -> Vec3 {
+        self.dipole
+    }
+
+    // Dipole moment in the world frame, as the particle is turned
+    pub fn world_dipole(&self) -> Vec3 {
+        self.orientation * self.dipole
+    }
+
+    pub fn is_inert(&self) -> bool {
+        self.inert
+    }
+
+    pub fn is_fixed(&self) -> bool {
+        self.fixed
+    }
+
+    // What the particle brings to its pair interactions
+    pub fn get_site(&self) -> InteractionSite {
+        InteractionSite {
+            radius: self.radius,
+            epsilon: self.epsilon,
+            inert: self.inert,
+        }
+    }
+
+    pub fn get_pos(&self) -> Vec3 {
+        self.pos
+    }
+
+    pub fn get_vel(&self) -> Vec3 {
+        self.vel
+    }
+
+    pub fn get_orientation(&self) -> Quat {
+        self.orientation
+    }
+
+    pub fn get_ang_vel(&self) -> Vec3 {
+        self.ang_vel
+    }
+
+    // Principal moments of inertia, 2/5 m r^2 about every axis unless set
+    pub fn get_inertia(&self) -> Vec3 {
+        self.inertia
+            .unwrap_or_else(|| Vec3::splat(0.4 * self.mass * self.radius.powi(2)))
+    }
+
+    // Whether the moments of inertia were set, instead of following from the mass and radius
+    pub fn has_inertia(&self) -> bool {
+        self.inertia.is_some()
+    }
+
+    // Moment of inertia tensor in the world frame, R I R^T
+    pub fn world_inertia(&self) -> Mat3 {
+        let rotation = Mat3::from_quat(self.orientation);
+        rotation * Mat3::from_diagonal(self.get_inertia()) * rotation.transpose()
+    }
+
+    // Angular momentum in the world frame
+    pub fn get_ang_momentum(&self) -> Vec3 {
+        self.world_inertia() * self.ang_vel
+    }
+
+    // Kinetic energy of the rotation, 1/2 w . L
+    pub fn rotational_energy(&self) -> f32 {
+        0.5 * self.ang_vel.dot(self.get_ang_momentum())
+    }
+
+    //////////////////////////
+    // Steppers
+    // Step the relevant quantities through time
+    //
+
+    // Fixed particles ignore all of these and stay at rest
+
+    // Also turns the particle at its angular velocity
+    // Without a torque the angular momentum stays put while the body turns, so the angular velocity
+    // of a body with unequal moments follows the turn, as the Euler equations have it
+    pub fn step_pos(&mut self, dt: f32, coeff: f32) {
+        if !self.fixed {
+            self.pos += coeff * dt * self.vel;
+            let angle = self.ang_vel.length() * coeff * dt;
+            if angle > 0.0 {
+                let ang_momentum = self.get_ang_momentum();
+                let rotation = Quat::from_axis_angle(self.ang_vel.normalize(), angle);
+                self.orientation = (rotation * self.orientation).normalize();
+                self.ang_vel = self.world_inertia().inverse() * ang_momentum;
+            }
+        }
+    }
+
+    pub fn step_vel(&mut self, acc: Vec3, dt: f32, coeff: f32) {
+        if !self.fixed {
+            self.vel += coeff * dt * acc;
+        }
+    }
+
+    // Change the angular momentum by the torque, given in the world frame
+    pub fn step_ang_vel(&mut self, torque: Vec3, dt: f32, coeff: f32) {
+        if !self.fixed {
+            self.ang_vel += self.world_inertia().inverse() * (coeff * dt * torque);
+        }
+    }
+
+    // Place the particle directly, used by rigid clusters to move their members as a unit
+    pub fn move_to(&mut self, pos: Vec3, vel: Vec3) {
+        if !self.fixed {
+            self.pos = pos;
+            self.vel = vel;
+        }
+    }
+
+    // Turn the particle directly, used by rigid clusters to turn their members with them
+    pub fn turn_to(&mut self, orientation: Quat, ang_vel: Vec3) {
+        if !self.fixed {
+            self.orientation = orientation;
+            self.ang_vel = ang_vel;
+        }
+    }
+
+    pub fn heat(&mut self, dt: f32, amount: f32) {
+        if !self.fixed {
+            self.vel += self.vel * amount * dt;
+        }
+    }
+
+    // Same as heat for the rotation
+    pub fn heat_rotation(&mut self, dt: f32, amount: f32) {
+        if !self.fixed {
+            self.ang_vel += self.ang_vel * amount * dt;
+        }
+    }
+
+    // Fix the particle in place or release it, it's at rest either way
+    pub fn fix(&mut self, fixed: bool) {
+        self.fixed = fixed;
+        self.vel = Vec3::ZERO;
+        self.ang_vel = Vec3::ZERO;
+    }
+}
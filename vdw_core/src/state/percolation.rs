@@ -0,0 +1,78 @@
+// Percolation scan
+// Runs simulations without rendering over a set of densities and temperatures
+// Measures how often the largest cluster of neighbors spans the box
+use super::error::InvalidParamError;
+use super::SimulationPrototype;
+use std::fmt;
+
+// Result of all runs at one density and temperature
+#[derive(Clone, Copy)]
+pub struct PercolationSample {
+    pub density: f32,
+    pub temperature: f32,
+    pub probability: f32, // fraction of measured frames in which the system percolates
+}
+
+pub struct PercolationReport {
+    pub samples: Vec<PercolationSample>,
+}
+
+impl fmt::Display for PercolationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Percolation probability")?;
+        writeln!(f, "{:>12} {:>12} {:>12}", "density", "T", "probability")?;
+        for sample in self.samples.iter() {
+            writeln!(
+                f,
+                "{:>12.5} {:>12.5} {:>12.5}",
+                sample.density, sample.temperature, sample.probability
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Run simulations for every combination of density and temperature
+// make_prototype builds a fresh prototype for a (density, temperature) pair and is called once per run
+// Each run lasts for duration units of simulated time, the first half is not measured
+pub fn scan(
+    make_prototype: impl Fn(f32, f32) -> SimulationPrototype,
+    densities: &[f32],
+    temperatures: &[f32],
+    runs: usize,
+    duration: f32,
+) -> Result<PercolationReport, InvalidParamError> {
+    let mut samples = Vec::with_capacity(densities.len() * temperatures.len());
+
+    for &density in densities {
+        for &temperature in temperatures {
+            let mut percolating = 0;
+            let mut measured = 0;
+
+            for _run in 0..runs {
+                let mut state = make_prototype(density, temperature).compile()?.into_state();
+
+                let frames = (duration / (state.dt * state.steps_per_frame as f32)).ceil() as usize;
+                for frame in 0..frames {
+                    state.advance_frame();
+
+                    // let the system settle for the first half of the run
+                    if frame >= frames / 2 {
+                        if state.neighbor_network_percolates() {
+                            percolating += 1;
+                        }
+                        measured += 1;
+                    }
+                }
+            }
+
+            samples.push(PercolationSample {
+                density,
+                temperature,
+                probability: percolating as f32 / measured.max(1) as f32,
+            });
+        }
+    }
+
+    Ok(PercolationReport { samples })
+}
@@ -0,0 +1,372 @@
+use glam::{Mat3, Vec3};
+use serde::Deserialize;
+
+// this roughly determines how close the particle can approach each other before getting repelled
+const R0: f32 = 0.15;
+
+// particles closer than this are counted as neighbors unless the neighbor rule says otherwise
+pub const NEIGHBOR_DISTANCE: f32 = 2.0 * R0;
+
+// two particles of this radius sit at the bottom of the well when they touch
+pub const DEFAULT_RADIUS: f32 = R0 / 2.0;
+
+// Temperature is the kinetic energy per particle, 3/2 kT
+pub fn kt(temperature: f32) -> f32 {
+    2.0 / 3.0 * temperature
+}
+
+// Spread of each velocity component of a unit mass at a temperature, sqrt(kT)
+// Negative temperatures count as 0
+pub fn velocity_spread(temperature: f32) -> f32 {
+    kt(temperature.max(0.0)).sqrt()
+}
+
+// What a particle brings to its pair interactions
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct InteractionSite {
+    pub radius: f32,  // half of the size sigma_i of the particle
+    pub epsilon: f32, // well depth relative to that of the potential model
+    pub inert: bool,  // pairs with an inert site only feel the repulsive core
+}
+
+impl Default for InteractionSite {
+    fn default() -> Self {
+        Self {
+            radius: DEFAULT_RADIUS,
+            epsilon: 1.0,
+            inert: false,
+        }
+    }
+}
+
+impl InteractionSite {
+    // Lorentz-Berthelot mixing rules: sigma_ij = (sigma_i + sigma_j) / 2 and epsilon_ij = sqrt(epsilon_i epsilon_j)
+    // Return the length and energy scales of the pair relative to a pair of default particles
+    pub fn mix(&self, other: &InteractionSite) -> (f32, f32) {
+        (
+            (self.radius + other.radius) / (2.0 * DEFAULT_RADIUS),
+            (self.epsilon * other.epsilon).sqrt(),
+        )
+    }
+}
+
+// How particles are told to be neighbors, for the neighbor counts, the coloring and the cluster analysis
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NeighborMetric {
+    Distance, // every particle within the neighbor distance
+    Nearest, // the closest few within the neighbor distance, a rough stand-in for the faces of a Voronoi cell
+}
+
+impl NeighborMetric {
+    pub const ALL: [NeighborMetric; 2] = [NeighborMetric::Distance, NeighborMetric::Nearest];
+    pub const NAMES: [&'static str; 2] = ["distance", "nearest"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .position(|&n| n == name)
+            .map(|i| Self::ALL[i])
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NeighborMetric::Distance => "Distance",
+            NeighborMetric::Nearest => "Nearest",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct NeighborRule {
+    pub metric: NeighborMetric,
+    pub distance: f32, // for two default particles, stretched with the size of the pair like the potential
+    pub nearest: usize, // neighbors each particle picks by the nearest metric
+}
+
+impl Default for NeighborRule {
+    fn default() -> Self {
+        Self {
+            metric: NeighborMetric::Distance,
+            distance: NEIGHBOR_DISTANCE,
+            nearest: 12, // as many as touch a particle in a close packed crystal
+        }
+    }
+}
+
+impl NeighborRule {
+    pub fn is_valid(&self) -> bool {
+        self.distance > 0.0 && self.nearest > 0
+    }
+
+    // Whether a pair at squared separation r_norm_sqr is close enough, scale is the size of the pair
+    // relative to two default particles, see InteractionSite::mix
+    pub fn within(&self, r_norm_sqr: f32, scale: f32) -> bool {
+        r_norm_sqr < (self.distance * scale).powi(2)
+    }
+}
+
+// Pair potentials the particles can interact through
+// All of them share the cutoff handling
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum PotentialModel {
+    LennardJones,                              // the default, well of depth 4 * R0 at R0
+    Morse { depth: f32, width: f32, r0: f32 }, // well of the given depth at r0, width is the inverse decay length
+    SoftSphere { epsilon: f32, n: i32 },       // purely repulsive epsilon * (R0 / r)^n
+}
+
+impl Default for PotentialModel {
+    fn default() -> Self {
+        PotentialModel::LennardJones
+    }
+}
+
+impl PotentialModel {
+    // The alternatives with parameters matching the Lennard-Jones well
+    pub const ALL: [PotentialModel; 3] = [
+        PotentialModel::LennardJones,
+        PotentialModel::Morse {
+            depth: 4.0 * R0,
+            width: 40.0, // same curvature at the bottom of the well
+            r0: R0,
+        },
+        PotentialModel::SoftSphere {
+            epsilon: 4.0 * R0,
+            n: 12,
+        },
+    ];
+
+    // Name of the model without its parameters
+    pub fn name(&self) -> &'static str {
+        match self {
+            PotentialModel::LennardJones => "LennardJones",
+            PotentialModel::Morse { .. } => "Morse",
+            PotentialModel::SoftSphere { .. } => "SoftSphere",
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            PotentialModel::LennardJones => true,
+            PotentialModel::Morse { depth, width, r0 } => depth > 0.0 && width > 0.0 && r0 > 0.0,
+            PotentialModel::SoftSphere { epsilon, n } => epsilon > 0.0 && n > 0,
+        }
+    }
+
+    // calculate force and potential on position 1
+    // Without switch_start the potential is shifted to zero at range and the force jumps to zero there
+    // With switch_start both are smoothly switched off between switch_start and range
+    // The returned potential is the share of position 1, half of the pair energy
+    pub fn interaction(
+        &self,
+        pos_targ: Vec3,
+        pos_other: Vec3,
+        range: f32,
+        switch_start: Option<f32>,
+    ) -> (Vec3, f32) {
+        let r = pos_targ - pos_other;
+        let r_norm_sqr = r.length_squared();
+
+        if r_norm_sqr > range.powi(2) {
+            return (Vec3::new(0.0, 0.0, 0.0), 0.0);
+        }
+
+        let (force, potential) = self.pair(r);
+
+        match switch_start {
+            Some(r_switch) => {
+                let (switch, switch_slope) = switch_function(r_norm_sqr.sqrt(), r_switch, range);
+                let r_dir = r.normalize_or_zero();
+                (
+                    force * switch - potential * switch_slope * r_dir,
+                    potential * switch / 2.0,
+                )
+            }
+            None => {
+                // this is the potential energy between two non-interacting particles need to shift this point to zero
+                let (_, free_potential) = self.pair(Vec3::new(range, 0.0, 0.0));
+                (force, (potential - free_potential) / 2.0)
+            }
+        }
+    }
+
+    // Only the repulsive core of the potential, used for pairs with an inert particle
+    // The pair is cut at the bottom of the well and shifted up by the well depth,
+    // so force and potential both go smoothly to zero there
+    // Models without a well are purely repulsive already and use the regular cutoff
+    pub fn repulsive_interaction(
+        &self,
+        pos_targ: Vec3,
+        pos_other: Vec3,
+        range: f32,
+    ) -> (Vec3, f32) {
+        let r_min = match self.well_minimum() {
+            Some(r_min) if r_min < range => r_min,
+            _ => return self.interaction(pos_targ, pos_other, range, None),
+        };
+
+        let r = pos_targ - pos_other;
+        if r.length_squared() >= r_min.powi(2) {
+            return (Vec3::ZERO, 0.0);
+        }
+
+        let (force, potential) = self.pair(r);
+        let (_, well_potential) = self.pair(Vec3::new(r_min, 0.0, 0.0));
+        (force, (potential - well_potential) / 2.0)
+    }
+
+    // Interaction between two particles of arbitrary sizes and well depths
+    // The lengths of the pair are stretched by sigma_ij over the size of a default particle,
+    // so the core and the well move out with the size of the pair, and the energies scale with epsilon_ij
+    // The range is still the cutoff of the grid
+    pub fn site_interaction(
+        &self,
+        pos_targ: Vec3,
+        site_targ: InteractionSite,
+        pos_other: Vec3,
+        site_other: InteractionSite,
+        range: f32,
+        switch_start: Option<f32>,
+    ) -> (Vec3, f32) {
+        let (scale, depth) = site_targ.mix(&site_other);
+        let (pos_targ, pos_other, range) = (pos_targ / scale, pos_other / scale, range / scale);
+        let (force, potential) = if site_targ.inert || site_other.inert {
+            self.repulsive_interaction(pos_targ, pos_other, range)
+        } else {
+            self.interaction(pos_targ, pos_other, range, switch_start.map(|s| s / scale))
+        };
+        // stretching keeps the energy and shrinks its gradient
+        (force * depth / scale, potential * depth)
+    }
+
+    // Separation at the bottom of the well, None for purely repulsive models
+    pub fn well_minimum(&self) -> Option<f32> {
+        match *self {
+            PotentialModel::LennardJones => Some(R0),
+            PotentialModel::Morse { r0, .. } => Some(r0),
+            PotentialModel::SoftSphere { .. } => None,
+        }
+    }
+
+    // Energy scale of the model, the depth of the well or the repulsion at R0 without one
+    pub fn well_depth(&self) -> f32 {
+        match *self {
+            PotentialModel::LennardJones => 4.0 * R0,
+            PotentialModel::Morse { depth, .. } => depth,
+            PotentialModel::SoftSphere { epsilon, .. } => epsilon,
+        }
+    }
+
+    // Separation at the bottom of the well, none for the purely repulsive model
+    pub fn well_position(&self) -> Option<f32> {
+        match *self {
+            PotentialModel::LennardJones => Some(R0),
+            PotentialModel::Morse { r0, .. } => Some(r0),
+            PotentialModel::SoftSphere { .. } => None,
+        }
+    }
+
+    // Length scale of the model, the separation at which the pair energy crosses zero
+    // The purely repulsive model never does, R0 is where it reaches its energy scale
+    pub fn length_scale(&self) -> f32 {
+        match *self {
+            PotentialModel::LennardJones => R0 / 2f32.powf(1.0 / 6.0),
+            PotentialModel::Morse { width, r0, .. } => r0 - 2f32.ln() / width,
+            PotentialModel::SoftSphere { .. } => R0,
+        }
+    }
+
+    // Unshifted force on position 1 and pair energy at separation r
+    // internal helper function
+    fn pair(&self, r: Vec3) -> (Vec3, f32) {
+        match *self {
+            PotentialModel::LennardJones => {
+                let r_unit = r / R0;
+                let r_unit2 = r_unit.length_squared();
+                let r_unit6 = r_unit2.powi(3);
+                let r_unit8 = r_unit2 * r_unit6;
+                let r_unit12 = r_unit6.powi(2);
+                let r_unit14 = r_unit6 * r_unit8;
+
+                let force = 24.0 * ((2.0 / r_unit14) - (2.0 / r_unit8)) * r_unit;
+                let potential = 4.0 * ((1.0 / r_unit12) - (2.0 / r_unit6)) * R0;
+                (force, potential)
+            }
+            PotentialModel::Morse { depth, width, r0 } => {
+                let r_norm = r.length();
+                let decay = (-width * (r_norm - r0)).exp();
+                let force = -2.0 * depth * width * decay * (1.0 - decay) * r / r_norm;
+                let potential = depth * ((1.0 - decay).powi(2) - 1.0);
+                (force, potential)
+            }
+            PotentialModel::SoftSphere { epsilon, n } => {
+                let r_norm_sqr = r.length_squared();
+                let potential = epsilon * (R0 * R0 / r_norm_sqr).powf(n as f32 / 2.0);
+                let force = n as f32 * potential * r / r_norm_sqr;
+                (force, potential)
+            }
+        }
+    }
+}
+
+// calculate force and potential on position 1 through the Lennard-Jones style potential
+// sigma and epsilon of the pair are mixed from the two sites, see PotentialModel::site_interaction
+// See PotentialModel::interaction for the cutoff handling
+pub fn vdw_interaction(
+    pos_targ: Vec3,
+    site_targ: InteractionSite,
+    pos_other: Vec3,
+    site_other: InteractionSite,
+    range: f32,
+    switch_start: Option<f32>,
+) -> (Vec3, f32) {
+    PotentialModel::LennardJones.site_interaction(
+        pos_targ,
+        site_targ,
+        pos_other,
+        site_other,
+        range,
+        switch_start,
+    )
+}
+
+// Cubic switch that goes from 1 at r_switch to 0 at r_cut with zero slope at both ends
+// Return the value of the switch and its derivative with respect to r
+fn switch_function(r: f32, r_switch: f32, r_cut: f32) -> (f32, f32) {
+    if r <= r_switch {
+        return (1.0, 0.0);
+    }
+    if r >= r_cut {
+        return (0.0, 0.0);
+    }
+
+    let (r2, rs2, rc2) = (r * r, r_switch * r_switch, r_cut * r_cut);
+    let denom = (rc2 - rs2).powi(3);
+    let value = (rc2 - r2).powi(2) * (rc2 + 2.0 * r2 - 3.0 * rs2) / denom;
+    let slope = 12.0 * r * (rc2 - r2) * (rs2 - r2) / denom;
+    (value, slope)
+}
+
+// Tensor product a (x) b, the entry at row i and column j is a_i * b_j
+pub fn outer_product(a: Vec3, b: Vec3) -> Mat3 {
+    Mat3::from_cols(a * b.x, a * b.y, a * b.z)
+}
+
+// calculate force and potential on position 1 from a harmonic spring connecting it to position 2
+// k is the spring constant and r0 is the rest length of the spring
+pub fn harmonic_interaction(pos_targ: Vec3, pos_other: Vec3, k: f32, r0: f32) -> (Vec3, f32) {
+    let r = pos_targ - pos_other;
+    let r_norm = r.length();
+
+    // overlapping particles have no well defined spring direction
+    if r_norm == 0.0 {
+        return (Vec3::ZERO, 0.0);
+    }
+
+    let stretch = r_norm - r0;
+    let force = -k * stretch * r / r_norm;
+    let potential = 0.5 * k * stretch.powi(2);
+
+    (force, potential)
+}
@@ -0,0 +1,122 @@
+// Curated setups showing the phases of the fluid
+// The state points are picked from the Lennard-Jones phase diagram,
+// sigma is R0 / 2^(1/6) and epsilon is the well depth 4 * R0
+use super::physics;
+use super::state_generator::Initialize;
+use super::SimulationPrototype;
+
+// How the particles of a preset are placed
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PresetInitializer {
+    Uniform,                  // scattered over the whole box
+    Lattice { spacing: f32 }, // fcc block in the middle of the box, melts when the preset is hot enough
+}
+
+// Parameters of a preset
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PresetParams {
+    pub particles: usize,
+    pub density: f32,     // particles per unit volume of the box, sets its size
+    pub temperature: f32, // kinetic energy per particle, also the thermostat target
+    pub initializer: PresetInitializer,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Preset {
+    Gas,
+    Liquid,
+    Solid,
+    TwoPhase,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 4] = [Preset::Gas, Preset::Liquid, Preset::Solid, Preset::TwoPhase];
+    pub const NAMES: [&'static str; 4] = ["gas", "liquid", "solid", "two_phase"];
+
+    // interactions are cut off at about 2.6 sigma, the default reach is far too long for dense presets
+    const CUTOFF_RADIUS: f32 = 0.35;
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .position(|&n| n == name)
+            .map(|i| Self::ALL[i])
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Gas => "Gas",
+            Preset::Liquid => "Liquid",
+            Preset::Solid => "Solid",
+            Preset::TwoPhase => "Two Phase",
+        }
+    }
+
+    // One line summary for the gui
+    pub fn description(&self) -> &'static str {
+        match self {
+            Preset::Gas => "Dilute and hot, particles fly freely between rare collisions",
+            Preset::Liquid => "Dense and above the triple point, the lattice melts into a liquid",
+            Preset::Solid => "Dense and cold, particles rattle around their fcc lattice sites",
+            Preset::TwoPhase => "A liquid droplet in coexistence with its vapor",
+        }
+    }
+
+    pub fn params(&self) -> PresetParams {
+        match self {
+            // T* = 2.5, rho* = 0.05
+            Preset::Gas => PresetParams {
+                particles: 1000,
+                density: 20.0,
+                temperature: 2.25,
+                initializer: PresetInitializer::Uniform,
+            },
+            // T* = 1.0, rho* = 0.8, the lattice fills the box
+            Preset::Liquid => PresetParams {
+                particles: 2916,
+                density: 335.0,
+                temperature: 0.9,
+                initializer: PresetInitializer::Lattice { spacing: 0.16 },
+            },
+            // T* = 0.4, the lattice has room to expand
+            Preset::Solid => PresetParams {
+                particles: 2048,
+                density: 250.0,
+                temperature: 0.36,
+                initializer: PresetInitializer::Lattice { spacing: 0.15 },
+            },
+            // T* = 0.85, a dense block in a mostly empty box
+            Preset::TwoPhase => PresetParams {
+                particles: 1372,
+                density: 20.0,
+                temperature: 0.765,
+                initializer: PresetInitializer::Lattice { spacing: 0.16 },
+            },
+        }
+    }
+
+    // Build the setup of the preset in a cubic box
+    pub fn prototype(&self) -> SimulationPrototype {
+        let params = self.params();
+        let side = (params.particles as f32 / params.density).cbrt();
+        let speed = physics::velocity_spread(params.temperature);
+        let prototype = SimulationPrototype::new()
+            .set_bound_x(side)
+            .set_bound_y(side)
+            .set_bound_z(side)
+            .set_dt(0.001)
+            .set_steps_per_frame(20)
+            .set_cutoff_radius(Self::CUTOFF_RADIUS)
+            .set_target_temp(params.temperature)
+            .set_inject_rate(0.1);
+
+        match params.initializer {
+            PresetInitializer::Uniform => prototype.initialize_uniform(params.particles, speed),
+            // the lattice starts at the bottom of the wells,
+            // half of the kinetic energy goes into the potential as the particles spread out
+            PresetInitializer::Lattice { spacing } => {
+                prototype.initialize_lattice(params.particles, spacing, speed * 2f32.sqrt())
+            }
+        }
+    }
+}
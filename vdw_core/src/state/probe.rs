@@ -0,0 +1,163 @@
+use super::particle::Particle;
+use crate::ring_buffer::RingBuffer;
+use glam::Vec3;
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+// Where a probe takes its measurements
+#[derive(Clone, Copy, PartialEq)]
+pub enum ProbeTarget {
+    Particle(usize), // follows a particle by index
+    Point(Vec3),     // stays at a fixed position
+}
+
+// What a probe measures
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ProbeQuantity {
+    LocalDensity,
+    Speed,
+    PotentialEnergy,
+}
+
+impl ProbeQuantity {
+    pub const ALL: [ProbeQuantity; 3] = [
+        ProbeQuantity::LocalDensity,
+        ProbeQuantity::Speed,
+        ProbeQuantity::PotentialEnergy,
+    ];
+}
+
+////////////////////////////////////////////////////////////
+// Probe records a quantity around a particle or a fixed point
+// Keeps its own history of measurements
+//
+#[derive(Clone)]
+pub struct Probe {
+    pub name: String,
+    pub target: ProbeTarget,
+    quantity: ProbeQuantity, // set with set_quantity, the history only holds one quantity
+    history: RingBuffer<f32>,
+}
+
+impl Probe {
+    const RADIUS: f32 = 0.5; // particles within this distance are considered local
+
+    pub fn new(
+        name: String,
+        target: ProbeTarget,
+        quantity: ProbeQuantity,
+        capacity: usize,
+    ) -> Self {
+        Self {
+            name,
+            target,
+            quantity,
+            history: RingBuffer::with_capacity(capacity),
+        }
+    }
+
+    pub fn get_history(&self) -> &RingBuffer<f32> {
+        &self.history
+    }
+
+    pub fn get_quantity(&self) -> ProbeQuantity {
+        self.quantity
+    }
+
+    // Measure another quantity from now on
+    // The history of the old one is dropped so the values of the two aren't mixed
+    pub fn set_quantity(&mut self, quantity: ProbeQuantity) {
+        if quantity != self.quantity {
+            self.quantity = quantity;
+            self.history = RingBuffer::with_capacity(self.history.capacity());
+        }
+    }
+
+    // Keep the newest measurements that fit in the new capacity
+    pub fn resize_history(&mut self, capacity: usize) {
+        self.history.resize(capacity);
+    }
+
+    // Measure the quantity and save it to history
+    pub fn record(&mut self, particles: &[Particle]) {
+        let value = self.measure(particles);
+        self.history.push(value);
+    }
+
+    // Position of the probe, None if it follows a particle that doesn't exist
+    pub fn get_pos(&self, particles: &[Particle]) -> Option<Vec3> {
+        match self.target {
+            ProbeTarget::Particle(i) => particles.get(i).map(|p| p.get_pos()),
+            ProbeTarget::Point(pos) => Some(pos),
+        }
+    }
+
+    // Measure the quantity at the current state
+    // A probe attached to a particle measures speed and potential energy of that particle
+    // A probe at a fixed point averages over the particles around it
+    pub fn measure(&self, particles: &[Particle]) -> f32 {
+        let pos = match self.get_pos(particles) {
+            Some(pos) => pos,
+            None => return 0.0,
+        };
+        let local: Vec<&Particle> = particles
+            .iter()
+            .filter(|p| (p.get_pos() - pos).length_squared() < Self::RADIUS.powi(2))
+            .collect();
+
+        match (self.quantity, self.target) {
+            (ProbeQuantity::LocalDensity, _) => {
+                local.len() as f32 / (4.0 / 3.0 * PI * Self::RADIUS.powi(3))
+            }
+            (ProbeQuantity::Speed, ProbeTarget::Particle(i)) => particles[i].get_vel().length(),
+            (ProbeQuantity::PotentialEnergy, ProbeTarget::Particle(i)) => particles[i].potential,
+            (ProbeQuantity::Speed, ProbeTarget::Point(_)) => {
+                mean(local.iter().map(|p| p.get_vel().length()))
+            }
+            (ProbeQuantity::PotentialEnergy, ProbeTarget::Point(_)) => {
+                mean(local.iter().map(|p| p.potential))
+            }
+        }
+    }
+}
+
+// Average of the values, 0 if there are none
+fn mean(values: impl Iterator<Item = f32>) -> f32 {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), x| (sum + x, count + 1));
+    if count == 0 {
+        0.0
+    } else {
+        sum / count as f32
+    }
+}
+
+// Write the history of all probes to a csv file
+// One column per probe, one row per recorded frame
+pub fn export_csv(probes: &[Probe], path: impl AsRef<Path>) -> io::Result<()> {
+    let mut file = File::create(path)?;
+
+    let header: Vec<String> = probes
+        .iter()
+        .map(|probe| format!("{} ({:?})", probe.name, probe.quantity))
+        .collect();
+    writeln!(file, "frame,{}", header.join(","))?;
+
+    let rows = probes.iter().map(|p| p.history.len()).max().unwrap_or(0);
+    for row in 0..rows {
+        let values: Vec<String> = probes
+            .iter()
+            .map(|probe| {
+                // align the newest entries of every probe
+                let skip = rows - probe.history.len();
+                row.checked_sub(skip)
+                    .and_then(|i| probe.history.iter().nth(i))
+                    .map_or(String::new(), |v| v.to_string())
+            })
+            .collect();
+        writeln!(file, "{},{}", row, values.join(","))?;
+    }
+
+    Ok(())
+}
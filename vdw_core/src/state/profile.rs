@@ -0,0 +1,203 @@
+// Profiles along an axis
+// The box is cut into slabs perpendicular to the axis, and the density, temperature and mean velocity
+// of the particles of each slab are found, for sedimentation under gravity, interfaces between
+// phases and heat conduction between walls
+// The temperature of a slab is the kinetic energy per particle relative to the flow of the slab,
+// fixed particles count towards the density only
+use super::field::FieldAxis;
+use super::particle::Particle;
+use super::sim_space::Boundary;
+use super::SimulationState;
+use glam::Vec3;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Profile {
+    pub axis: FieldAxis,
+    pub lo: f32,    // lower end of the first slab
+    pub width: f32, // of every slab
+    pub density: Vec<f32>,
+    pub temperature: Vec<f32>,
+    pub velocity: Vec<Vec3>, // center of mass velocity, zero in empty slabs
+}
+
+impl Profile {
+    // Bin the particles into slabs spanning the boundary, particles outside go to the end slabs
+    pub fn of(particles: &[Particle], bound: Boundary, axis: FieldAxis, slabs: usize) -> Self {
+        let slabs = slabs.max(1);
+        let a = axis.index();
+        let lo = bound.lo_corner()[a];
+        let size = bound.size();
+        let width = size[a] / slabs as f32;
+        let cross_section = size.x * size.y * size.z / size[a];
+
+        let mut counts = vec![0usize; slabs];
+        let mut mobile = vec![0usize; slabs];
+        let mut kinetic = vec![0.0f32; slabs];
+        let mut momentum = vec![Vec3::ZERO; slabs];
+        let mut mass = vec![0.0f32; slabs];
+        for particle in particles {
+            let rel = (particle.get_pos()[a] - lo) / width;
+            let slab = (rel.max(0.0) as usize).min(slabs - 1);
+            counts[slab] += 1;
+            if particle.is_fixed() {
+                continue;
+            }
+            let vel = particle.get_vel();
+            mobile[slab] += 1;
+            kinetic[slab] += 0.5 * particle.get_mass() * vel.length_squared();
+            momentum[slab] += particle.get_mass() * vel;
+            mass[slab] += particle.get_mass();
+        }
+
+        let velocity: Vec<Vec3> = momentum
+            .iter()
+            .zip(mass.iter())
+            .map(|(&p, &m)| if m > 0.0 { p / m } else { Vec3::ZERO })
+            .collect();
+        let temperature = (0..slabs)
+            .map(|i| {
+                if mobile[i] == 0 {
+                    return 0.0;
+                }
+                // less the kinetic energy of the flow of the slab
+                let flow = 0.5 * mass[i] * velocity[i].length_squared();
+                ((kinetic[i] - flow) / mobile[i] as f32).max(0.0)
+            })
+            .collect();
+        let slab_volume = width * cross_section;
+        let density = counts
+            .iter()
+            .map(|&n| {
+                if slab_volume > 0.0 {
+                    n as f32 / slab_volume
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+
+        Self {
+            axis,
+            lo,
+            width,
+            density,
+            temperature,
+            velocity,
+        }
+    }
+
+    pub fn slabs(&self) -> usize {
+        self.density.len()
+    }
+
+    // Position of the middle of a slab along the axis
+    pub fn slab_center(&self, i: usize) -> f32 {
+        self.lo + (i as f32 + 0.5) * self.width
+    }
+
+    // Add a profile of the same slabs to a running mean of count profiles
+    // internal helper function
+    fn accumulate(&mut self, other: &Profile, count: usize) {
+        let weight = 1.0 / count as f32;
+        let mix = |mean: &mut f32, x: f32| *mean += (x - *mean) * weight;
+        for (mean, &x) in self.density.iter_mut().zip(other.density.iter()) {
+            mix(mean, x);
+        }
+        for (mean, &x) in self.temperature.iter_mut().zip(other.temperature.iter()) {
+            mix(mean, x);
+        }
+        for (mean, &x) in self.velocity.iter_mut().zip(other.velocity.iter()) {
+            *mean += (x - *mean) * weight;
+        }
+        // the box may have moved
+        self.lo = other.lo;
+        self.width = other.width;
+    }
+
+    // Write the profile to a csv file, one row per slab
+    pub fn export_csv(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = File::create(path)?;
+
+        writeln!(file, "position,density,temperature,vx,vy,vz")?;
+        for i in 0..self.slabs() {
+            let v = self.velocity[i];
+            writeln!(
+                file,
+                "{},{},{},{},{},{}",
+                self.slab_center(i),
+                self.density[i],
+                self.temperature[i],
+                v.x,
+                v.y,
+                v.z
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+// Profile shown by the profile window
+#[derive(Clone)]
+pub struct ProfileView {
+    pub enabled: bool,
+    pub axis: FieldAxis,
+    pub slabs: usize,
+    pub average: bool, // average over the frames since the last change, otherwise the latest frame
+    pub profile: Option<Profile>,
+    frames: usize, // averaged into the profile
+}
+
+impl Default for ProfileView {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            axis: FieldAxis::Z,
+            slabs: 30,
+            average: true,
+            profile: None,
+            frames: 0,
+        }
+    }
+}
+
+impl ProfileView {
+    pub const MAX_SLABS: usize = 200;
+
+    // Bin the particles of this frame, call once per frame
+    // The average starts over when the axis or the number of slabs changes
+    pub fn update(&mut self, state: &SimulationState) {
+        if !self.enabled {
+            return;
+        }
+        let latest = Profile::of(&state.particles, state.bound, self.axis, self.slabs);
+        match &mut self.profile {
+            Some(profile)
+                if self.average
+                    && profile.axis == latest.axis
+                    && profile.slabs() == latest.slabs() =>
+            {
+                self.frames += 1;
+                profile.accumulate(&latest, self.frames);
+            }
+            _ => {
+                self.frames = 1;
+                self.profile = Some(latest);
+            }
+        }
+    }
+
+    // Number of frames in the average
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    // Start the average over
+    pub fn clear(&mut self) {
+        self.profile = None;
+        self.frames = 0;
+    }
+}
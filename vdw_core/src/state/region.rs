@@ -0,0 +1,146 @@
+// Box-shaped probe regions
+// Every frame the particles are binned into the regions they are inside of, and each region records
+// the temperature, the number density and an estimate of the pressure tensor of its particles
+// The pressure tensor is the kinetic part m v (x) v of the particles inside plus their share of the
+// pair virial, over the volume of the region, as the stress of the whole box is measured
+// Only the pair forces of the potential enter the virial, bonds, charges and walls are left out
+use super::particle::Particle;
+use super::physics;
+use crate::ring_buffer::RingBuffer;
+use glam::{Mat3, Vec3};
+
+// One measurement of a region
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RegionSample {
+    pub particles: usize,
+    pub temperature: f32, // kinetic energy per particle that can move
+    pub density: f32,     // particles per volume
+    pub pressure: Mat3,
+}
+
+impl Default for RegionSample {
+    fn default() -> Self {
+        Self {
+            particles: 0,
+            temperature: 0.0,
+            density: 0.0,
+            pressure: Mat3::ZERO,
+        }
+    }
+}
+
+impl RegionSample {
+    // Mean of the normal stresses
+    pub fn scalar_pressure(&self) -> f32 {
+        (self.pressure.x_axis.x + self.pressure.y_axis.y + self.pressure.z_axis.z) / 3.0
+    }
+}
+
+#[derive(Clone)]
+pub struct RegionProbe {
+    pub name: String,
+    lo: Vec3,
+    hi: Vec3,
+    history: RingBuffer<RegionSample>,
+}
+
+impl RegionProbe {
+    // the corners are sorted, any two opposite corners of the box do
+    pub fn new(name: String, lo: Vec3, hi: Vec3, capacity: usize) -> Self {
+        Self {
+            name,
+            lo: lo.min(hi),
+            hi: lo.max(hi),
+            history: RingBuffer::with_capacity(capacity),
+        }
+    }
+
+    pub fn lo_corner(&self) -> Vec3 {
+        self.lo
+    }
+
+    pub fn hi_corner(&self) -> Vec3 {
+        self.hi
+    }
+
+    // Move the region, keeping its history
+    pub fn set_corners(&mut self, lo: Vec3, hi: Vec3) {
+        self.lo = lo.min(hi);
+        self.hi = lo.max(hi);
+    }
+
+    pub fn volume(&self) -> f32 {
+        let size = self.hi - self.lo;
+        size.x * size.y * size.z
+    }
+
+    pub fn contains(&self, pos: Vec3) -> bool {
+        pos.cmpge(self.lo).all() && pos.cmple(self.hi).all()
+    }
+
+    pub fn get_history(&self) -> &RingBuffer<RegionSample> {
+        &self.history
+    }
+
+    // Measure the particles inside
+    // virials holds the share of the pair virial of each particle, missing entries count as zero
+    pub fn measure(&self, particles: &[Particle], virials: &[Mat3]) -> RegionSample {
+        let inside: Vec<usize> = (0..particles.len())
+            .filter(|&i| self.contains(particles[i].get_pos()))
+            .collect();
+        self.sample(&inside, particles, virials)
+    }
+
+    // Sample of the particles at the given indices
+    // internal helper function
+    fn sample(&self, inside: &[usize], particles: &[Particle], virials: &[Mat3]) -> RegionSample {
+        let volume = self.volume();
+        if volume <= 0.0 {
+            return RegionSample::default();
+        }
+        let mut kinetic = 0.0;
+        let mut mobile = 0;
+        let mut stress = Mat3::ZERO;
+        for &i in inside.iter() {
+            let particle = &particles[i];
+            let vel = particle.get_vel();
+            if !particle.is_fixed() {
+                kinetic += 0.5 * particle.get_mass() * vel.length_squared();
+                mobile += 1;
+            }
+            stress = stress
+                + physics::outer_product(vel * particle.get_mass(), vel)
+                + virials.get(i).copied().unwrap_or(Mat3::ZERO);
+        }
+        RegionSample {
+            particles: inside.len(),
+            temperature: if mobile == 0 {
+                0.0
+            } else {
+                kinetic / mobile as f32
+            },
+            density: inside.len() as f32 / volume,
+            pressure: stress * (1.0 / volume),
+        }
+    }
+}
+
+// Bin the particles into the regions and record a sample of each, in one pass over the particles
+pub fn record(regions: &mut [RegionProbe], particles: &[Particle], virials: &[Mat3]) {
+    if regions.is_empty() {
+        return;
+    }
+    let mut bins: Vec<Vec<usize>> = vec![Vec::new(); regions.len()];
+    for (i, particle) in particles.iter().enumerate() {
+        let pos = particle.get_pos();
+        for (bin, region) in bins.iter_mut().zip(regions.iter()) {
+            if region.contains(pos) {
+                bin.push(i);
+            }
+        }
+    }
+    for (region, bin) in regions.iter_mut().zip(bins.iter()) {
+        let sample = region.sample(bin, particles, virials);
+        region.history.push(sample);
+    }
+}
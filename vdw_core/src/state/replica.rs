@@ -0,0 +1,229 @@
+// Replica exchange (parallel tempering)
+// Copies of one setup run side by side with their thermostats at different temperatures
+// Every few frames neighboring temperatures try to trade configurations with a Metropolis test,
+// so configurations stuck in a basin at low temperature can escape through the hot replicas
+use super::error::{ErrorKind, InvalidParamError, Problem, SimulationError};
+use super::physics;
+use super::{SimulationPrototype, SimulationState};
+use crate::par::*;
+use rand::Rng;
+use std::fmt;
+
+#[derive(Clone)]
+pub struct ReplicaExchange {
+    pub replicas: Vec<SimulationState>, // one per temperature, in the order of the temperatures
+    pub selected: usize,                // replica shown by the gui
+    pub interval: usize,                // frames between swap attempts
+    frames: usize,
+    attempts: Vec<usize>, // swap attempts between replica i and i + 1
+    accepted: Vec<usize>,
+}
+
+impl ReplicaExchange {
+    // used when the prototype has no thermostat, the replicas would keep their initial temperature
+    const INJECT_RATE: f32 = 0.1;
+
+    // Compile one replica per temperature, the temperatures are sorted from cold to hot
+    pub fn new(
+        prototype: &SimulationPrototype,
+        temperatures: &[f32],
+    ) -> Result<Self, InvalidParamError> {
+        if temperatures.len() < 2 || temperatures.iter().any(|&t| t.is_nan() || t <= 0.0) {
+            return Err(InvalidParamError::new(vec![Problem::new(
+                ErrorKind::Replicas,
+                format!(
+                    "temperatures = {:?}, replica exchange needs at least 2 temperatures, all above 0",
+                    temperatures
+                ),
+            )]));
+        }
+        let mut temperatures = temperatures.to_vec();
+        temperatures.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let replicas = temperatures
+            .iter()
+            .map(|&temperature| {
+                let mut state = prototype.compile()?.into_state();
+                state.target_temp = temperature;
+                if state.inject_rate <= 0.0 {
+                    state.inject_rate = Self::INJECT_RATE;
+                }
+                // start every replica at its own temperature
+                state.recalculate_kinetic_energy();
+                let current = state.temperature();
+                if current > 0.0 {
+                    state.scale_velocities((temperature / current).sqrt());
+                }
+                Ok(state)
+            })
+            .collect::<Result<Vec<_>, InvalidParamError>>()?;
+
+        let pairs = replicas.len() - 1;
+        Ok(Self {
+            replicas,
+            selected: 0,
+            interval: 5,
+            frames: 0,
+            attempts: vec![0; pairs],
+            accepted: vec![0; pairs],
+        })
+    }
+
+    pub fn temperatures(&self) -> Vec<f32> {
+        self.replicas
+            .iter()
+            .map(|state| state.target_temp)
+            .collect()
+    }
+
+    pub fn get_selected(&self) -> &SimulationState {
+        &self.replicas[self.selected]
+    }
+
+    // Share of the swap attempts between replica i and i + 1 that were accepted
+    pub fn acceptance(&self, i: usize) -> f32 {
+        self.accepted[i] as f32 / self.attempts[i].max(1) as f32
+    }
+
+    // Step every replica one frame in parallel, then try the swaps if it's time to
+    // The paused flag of the selected replica pauses all of them
+    pub fn advance_frame(&mut self) {
+        if self.replicas[self.selected].paused {
+            return;
+        }
+        self.replicas
+            .par_iter_mut()
+            .for_each(|state| state.advance_frame());
+
+        self.frames += 1;
+        if self.frames % self.interval.max(1) == 0 {
+            // even and odd pairs take turns so every pair is tried equally often
+            let parity = (self.frames / self.interval.max(1)) % 2;
+            self.attempt_swaps(parity);
+        }
+    }
+
+    // Metropolis test on the pairs (i, i + 1) starting at parity
+    // internal helper function
+    fn attempt_swaps(&mut self, parity: usize) {
+        let mut rng = rand::thread_rng();
+        for i in (parity..self.replicas.len() - 1).step_by(2) {
+            let (cold, hot) = self.replicas.split_at_mut(i + 1);
+            let (cold, hot) = (&mut cold[i], &mut hot[0]);
+
+            let beta_cold = 1.0 / physics::kt(cold.target_temp);
+            let beta_hot = 1.0 / physics::kt(hot.target_temp);
+            let delta = (beta_cold - beta_hot) * (cold.energy.potential - hot.energy.potential);
+
+            self.attempts[i] += 1;
+            if delta >= 0.0 || rng.gen::<f32>() < delta.exp() {
+                cold.exchange_configuration(hot);
+                self.accepted[i] += 1;
+            }
+        }
+    }
+
+    pub fn report(&self) -> ReplicaReport {
+        ReplicaReport {
+            temperatures: self.temperatures(),
+            potential: self
+                .replicas
+                .iter()
+                .map(|state| state.energy.potential / state.particles.len().max(1) as f32)
+                .collect(),
+            acceptance: (0..self.attempts.len())
+                .map(|i| self.acceptance(i))
+                .collect(),
+        }
+    }
+}
+
+// Run the replicas for a duration of simulated time without rendering
+// Stops with an error as soon as one of the replicas diverges
+pub fn run(
+    prototype: &SimulationPrototype,
+    temperatures: &[f32],
+    duration: f32,
+) -> Result<ReplicaReport, SimulationError> {
+    let mut exchange = ReplicaExchange::new(prototype, temperatures)?;
+    while exchange.get_selected().time < duration {
+        exchange.advance_frame();
+        for state in exchange.replicas.iter() {
+            state.check_divergence()?;
+        }
+    }
+    Ok(exchange.report())
+}
+
+pub struct ReplicaReport {
+    pub temperatures: Vec<f32>,
+    pub potential: Vec<f32>,  // potential energy per particle at the end
+    pub acceptance: Vec<f32>, // between replica i and i + 1
+}
+
+impl fmt::Display for ReplicaReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Replica exchange over {} temperatures",
+            self.temperatures.len()
+        )?;
+        writeln!(f, "{:>12} {:>12} {:>12}", "T", "U / N", "accepted")?;
+        for (i, (t, u)) in self
+            .temperatures
+            .iter()
+            .zip(self.potential.iter())
+            .enumerate()
+        {
+            match self.acceptance.get(i) {
+                Some(acceptance) => writeln!(f, "{:>12.5} {:>12.5} {:>12.3}", t, u, acceptance)?,
+                None => writeln!(f, "{:>12.5} {:>12.5} {:>12}", t, u, "")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::state_generator::Initialize;
+
+    // internal helper function
+    fn prototype() -> SimulationPrototype {
+        SimulationPrototype::new()
+            .set_bound_x(3.0)
+            .set_bound_y(3.0)
+            .set_bound_z(3.0)
+            .initialize_uniform(30, 1.0)
+    }
+
+    #[test]
+    fn replicas_are_sorted_from_cold_to_hot() {
+        let exchange = ReplicaExchange::new(&prototype(), &[2.0, 0.5, 1.0]).unwrap();
+        assert_eq!(exchange.temperatures(), vec![0.5, 1.0, 2.0]);
+        for state in exchange.replicas.iter() {
+            let error = (state.temperature() - state.target_temp).abs() / state.target_temp;
+            assert!(error < 1e-3, "replica at {}", state.target_temp);
+        }
+    }
+
+    #[test]
+    fn swaps_are_counted_per_neighboring_pair() {
+        let mut exchange = ReplicaExchange::new(&prototype(), &[0.5, 1.0, 1.5, 2.0]).unwrap();
+        exchange.attempt_swaps(0);
+        assert_eq!(exchange.attempts, vec![1, 0, 1]);
+        exchange.attempt_swaps(1);
+        assert_eq!(exchange.attempts, vec![1, 1, 1]);
+        // the temperatures stay with their replicas, the configurations move
+        assert_eq!(exchange.temperatures(), vec![0.5, 1.0, 1.5, 2.0]);
+    }
+
+    #[test]
+    fn too_few_or_invalid_temperatures_are_refused() {
+        for temperatures in [&[1.0][..], &[1.0, 0.0], &[f32::NAN, 1.0]].iter() {
+            let err = ReplicaExchange::new(&prototype(), temperatures).err();
+            assert!(err.map_or(false, |err| err.contains(ErrorKind::Replicas)));
+        }
+    }
+}
@@ -0,0 +1,78 @@
+// Rewind buffer
+// Keeps full copies of the simulation state taken every few frames
+// Used to go back to before an interactive change that blew up the system
+use super::SimulationState;
+use std::collections::VecDeque;
+
+pub struct RewindBuffer {
+    pub enabled: bool,
+    pub interval: usize, // number of simulation frames between snapshots
+    pub budget_mb: f32,  // memory the snapshots may take up
+    pub selected: usize, // snapshot picked in the gui, 0 is the oldest
+    frames: usize,       // simulation frames seen, a snapshot is taken on every interval-th
+    last_steps: usize,   // step count of the latest frame seen, to skip repeated frames
+    snapshots: VecDeque<SimulationState>, // old to new
+}
+
+impl Default for RewindBuffer {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 30,
+            budget_mb: 100.0,
+            selected: 0,
+            frames: 0,
+            last_steps: 0,
+            snapshots: VecDeque::new(),
+        }
+    }
+}
+
+impl RewindBuffer {
+    // Look at the latest state and keep a copy once every interval frames
+    // The oldest snapshots are dropped to stay within the memory budget
+    pub fn update(&mut self, state: &SimulationState) {
+        if !self.enabled || state.steps == self.last_steps {
+            return;
+        }
+        self.last_steps = state.steps;
+
+        if self.frames % self.interval.max(1) == 0 {
+            self.snapshots.push_back(state.clone());
+        }
+        self.frames += 1;
+
+        let capacity = self.capacity(state);
+        while self.snapshots.len() > capacity {
+            self.snapshots.pop_front();
+        }
+        self.selected = self.selected.min(self.snapshots.len().saturating_sub(1));
+    }
+
+    // Number of snapshots that fit in the memory budget, at least one
+    pub fn capacity(&self, state: &SimulationState) -> usize {
+        let budget = (self.budget_mb * 1024.0 * 1024.0) as usize;
+        (budget / state.memory_size().max(1)).max(1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    // Simulated time of a snapshot
+    pub fn time_of(&self, index: usize) -> Option<f32> {
+        self.snapshots.get(index).map(|state| state.time)
+    }
+
+    // Take out an earlier state, snapshots newer than it are dropped
+    pub fn rewind(&mut self, index: usize) -> Option<SimulationState> {
+        self.snapshots.truncate(index + 1);
+        self.frames = 1; // the restored state is the latest snapshot
+        self.selected = index.min(self.snapshots.len().saturating_sub(1));
+        self.snapshots.back().cloned()
+    }
+}
@@ -0,0 +1,175 @@
+use super::particle::Particle;
+use super::sim_space::{Boundary, FaceImpulses};
+use glam::{Mat3, Quat, Vec3};
+
+////////////////////////////////////////////////////////////
+// RigidCluster groups several particles into one rigid body
+// The members translate and rotate as a unit, driven by the net force and torque on them
+// Used to build multi-site molecules
+// To be used internally by State
+//
+#[derive(Clone)]
+pub struct RigidCluster {
+    members: Vec<usize>, // indices of the member particles
+    offsets: Vec<Vec3>,  // member positions relative to the center of mass, in the body frame
+    turns: Vec<Quat>,    // member orientations, in the body frame
+    mass: f32,
+    inertia: Mat3, // moment of inertia tensor about the center of mass, in the body frame
+
+    pos: Vec3, // center of mass
+    vel: Vec3,
+    orientation: Quat,
+    ang_momentum: Vec3,
+}
+
+impl RigidCluster {
+    // Keeps the inertia tensor invertible for linear molecules
+    // Rotation around the molecule axis receives no torque so this does not affect the dynamics
+    const INERTIA_REGULARIZATION: f32 = 1e-6;
+
+    // Freeze the current arrangement of the members into a rigid body
+    // The body frame is aligned with the world frame at creation
+    pub fn new(members: Vec<usize>, particles: &[Particle]) -> Self {
+        let mass: f32 = members.iter().map(|&i| particles[i].get_mass()).sum();
+        let pos = members
+            .iter()
+            .map(|&i| particles[i].get_mass() * particles[i].get_pos())
+            .fold(Vec3::ZERO, |acc, x| acc + x)
+            / mass;
+        let vel = members
+            .iter()
+            .map(|&i| particles[i].get_mass() * particles[i].get_vel())
+            .fold(Vec3::ZERO, |acc, x| acc + x)
+            / mass;
+
+        let offsets: Vec<Vec3> = members
+            .iter()
+            .map(|&i| particles[i].get_pos() - pos)
+            .collect();
+        let turns = members
+            .iter()
+            .map(|&i| particles[i].get_orientation())
+            .collect();
+
+        // I = sum of m * (|r|^2 * Identity - r * r^T)
+        let mut cols = [Vec3::ZERO; 3];
+        let mut ang_momentum = Vec3::ZERO;
+        for (&i, &r) in members.iter().zip(offsets.iter()) {
+            let m = particles[i].get_mass();
+            cols[0] += m * (r.length_squared() * Vec3::X - r * r.x);
+            cols[1] += m * (r.length_squared() * Vec3::Y - r * r.y);
+            cols[2] += m * (r.length_squared() * Vec3::Z - r * r.z);
+            ang_momentum += m * r.cross(particles[i].get_vel() - vel);
+        }
+        let inertia = Mat3::from_cols(cols[0], cols[1], cols[2])
+            + Mat3::from_diagonal(Vec3::splat(Self::INERTIA_REGULARIZATION * mass));
+
+        Self {
+            members,
+            offsets,
+            turns,
+            mass,
+            inertia,
+            pos,
+            vel,
+            orientation: Quat::IDENTITY,
+            ang_momentum,
+        }
+    }
+
+    pub fn get_members(&self) -> &[usize] {
+        &self.members
+    }
+
+    // Shift the member indices down past removed particles, none of them a member
+    // removed has to be sorted
+    pub fn renumber(&mut self, removed: &[usize]) {
+        for member in self.members.iter_mut() {
+            *member -= removed.partition_point(|&r| r < *member);
+        }
+    }
+
+    // Angular velocity in the world frame
+    pub fn get_ang_vel(&self) -> Vec3 {
+        let rotation = Mat3::from_quat(self.orientation);
+        let world_inertia = rotation * self.inertia * rotation.transpose();
+        world_inertia.inverse() * self.ang_momentum
+    }
+
+    //////////////////////////
+    // Steppers
+    // Step the relevant quantities through time
+    //
+
+    pub fn step_pos(&mut self, dt: f32, coeff: f32) {
+        self.pos += coeff * dt * self.vel;
+
+        let ang_vel = self.get_ang_vel();
+        let angle = ang_vel.length() * coeff * dt;
+        if angle > 0.0 {
+            let rotation = Quat::from_axis_angle(ang_vel.normalize(), angle);
+            self.orientation = (rotation * self.orientation).normalize();
+        }
+    }
+
+    // Integrate the net force and torque from the accelerations of the members
+    pub fn step_vel(
+        &mut self,
+        particles: &[Particle],
+        accelerations: &[Vec3],
+        dt: f32,
+        coeff: f32,
+    ) {
+        let mut force = Vec3::ZERO;
+        let mut torque = Vec3::ZERO;
+        for &i in self.members.iter() {
+            let member_force = particles[i].get_mass() * accelerations[i];
+            force += member_force;
+            torque += (particles[i].get_pos() - self.pos).cross(member_force);
+        }
+
+        self.vel += coeff * dt * force / self.mass;
+        self.ang_momentum += coeff * dt * torque;
+    }
+
+    pub fn heat(&mut self, dt: f32, amount: f32) {
+        self.vel += self.vel * amount * dt;
+        self.ang_momentum += self.ang_momentum * amount * dt;
+    }
+
+    // Multiply the velocity of the center of mass and the angular momentum by factor
+    pub fn scale_vel(&mut self, factor: f32) {
+        self.vel *= factor;
+        self.ang_momentum *= factor;
+    }
+
+    pub fn get_pos(&self) -> Vec3 {
+        self.pos
+    }
+
+    // Add a velocity to the center of mass
+    pub fn shift_vel(&mut self, dv: Vec3) {
+        self.vel += dv;
+    }
+
+    // Bounce the body off hard walls as a point at its center of mass
+    // Return the impulse delivered to each wall
+    pub fn reflect(&mut self, bound: &Boundary) -> FaceImpulses {
+        bound.reflect(&mut self.pos, &mut self.vel, self.mass)
+    }
+
+    // Move and turn the members to match the position and orientation of the body
+    pub fn apply(&self, particles: &mut [Particle]) {
+        let ang_vel = self.get_ang_vel();
+        let members = self
+            .members
+            .iter()
+            .zip(self.offsets.iter())
+            .zip(self.turns.iter());
+        for ((&i, &offset), &turn) in members {
+            let r = self.orientation * offset;
+            particles[i].move_to(self.pos + r, self.vel + ang_vel.cross(r));
+            particles[i].turn_to(self.orientation * turn, ang_vel);
+        }
+    }
+}
@@ -0,0 +1,256 @@
+// Schedule of timed parameter changes
+// Events set a parameter at a given step or ramp it linearly over a range of steps
+// Used for reproducible annealing and compression protocols
+use super::SimulationState;
+use serde::Deserialize;
+
+// Parameters that events can change
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleParameter {
+    TargetTemp,
+    InjectRate,
+    BoundRate,
+    PinnedPressure, // value the pressure is pinned at, pins it if it isn't already
+    Dt,
+}
+
+impl ScheduleParameter {
+    // Whether the parameter can take the value, the same limits compile puts on the prototype
+    pub fn check(self, value: f32) -> Result<(), String> {
+        let name = self.name();
+        if !value.is_finite() {
+            return Err(format!("{} = {:?}, it needs a finite value", name, value));
+        }
+        match self {
+            ScheduleParameter::TargetTemp if value < 0.0 => Err(format!(
+                "{} = {:?} < 0, temperatures are 0 or more",
+                name, value
+            )),
+            ScheduleParameter::InjectRate if value < 0.0 => Err(format!(
+                "{} = {:?} < 0, use 0 to turn the thermostat off",
+                name, value
+            )),
+            ScheduleParameter::Dt if value <= 0.0 => Err(format!(
+                "{} = {:?} <= 0, use a small positive time step such as 0.001",
+                name, value
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    // Set the parameter of the state, values check refuses leave it unchanged
+    pub fn apply(self, state: &mut SimulationState, value: f32) -> Result<(), String> {
+        self.check(value)?;
+        match self {
+            ScheduleParameter::TargetTemp => state.target_temp = value,
+            ScheduleParameter::InjectRate => state.inject_rate = value,
+            ScheduleParameter::BoundRate => state.bound_rate = value,
+            ScheduleParameter::PinnedPressure => {
+                state.pressure_pinned.is_pinned = true;
+                state.pressure_pinned.at_value = value;
+            }
+            // dt needs to go through its setter to keep pressure consistent
+            ScheduleParameter::Dt => state.set_dt(value),
+        }
+        Ok(())
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ScheduleParameter::TargetTemp => "target_temp",
+            ScheduleParameter::InjectRate => "inject_rate",
+            ScheduleParameter::BoundRate => "bound_rate",
+            ScheduleParameter::PinnedPressure => "pinned_pressure",
+            ScheduleParameter::Dt => "dt",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum EventAction {
+    Set(f32),
+    Ramp { from: f32, to: f32, steps: usize }, // linear change over the given number of steps
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Event {
+    pub step: usize, // step at which the event starts
+    pub parameter: ScheduleParameter,
+    pub action: EventAction,
+}
+
+impl Event {
+    pub fn set(step: usize, parameter: ScheduleParameter, value: f32) -> Self {
+        Self {
+            step,
+            parameter,
+            action: EventAction::Set(value),
+        }
+    }
+
+    pub fn ramp(
+        step: usize,
+        parameter: ScheduleParameter,
+        from: f32,
+        to: f32,
+        steps: usize,
+    ) -> Self {
+        Self {
+            step,
+            parameter,
+            action: EventAction::Ramp { from, to, steps },
+        }
+    }
+
+    // Value the event gives its parameter at a step, None when the event isn't active
+    pub fn value_at(&self, step: usize) -> Option<f32> {
+        match self.action {
+            EventAction::Set(value) => Some(value).filter(|_| step == self.step),
+            EventAction::Ramp { from, to, steps } => {
+                if step < self.step || step > self.step.saturating_add(steps) {
+                    return None;
+                }
+                let progress = if steps == 0 {
+                    1.0
+                } else {
+                    (step - self.step) as f32 / steps as f32
+                };
+                Some(from + (to - from) * progress)
+            }
+        }
+    }
+
+    // Whether the parameter can take the values of the event
+    // The allowed values of every parameter are a range, so checking the ends covers a ramp
+    pub fn check(&self) -> Result<(), String> {
+        match self.action {
+            EventAction::Set(value) => self.parameter.check(value),
+            EventAction::Ramp { from, to, .. } => self
+                .parameter
+                .check(from)
+                .and_then(|_| self.parameter.check(to)),
+        }
+    }
+
+    // Last step at which the event is active
+    pub fn end_step(&self) -> usize {
+        match self.action {
+            EventAction::Set(_) => self.step,
+            EventAction::Ramp { steps, .. } => self.step.saturating_add(steps),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Schedule {
+    events: Vec<Event>,
+}
+
+impl Schedule {
+    pub fn push(&mut self, event: Event) {
+        self.events.push(event);
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    // Apply the events active at the current step of the state
+    // Events are applied in order, so later events win when they change the same parameter
+    pub fn apply(&self, state: &mut SimulationState) {
+        let step = state.steps;
+        for event in self.events.iter() {
+            if let Some(value) = event.value_at(step) {
+                // compile refuses events with values apply would refuse
+                let _ = event.parameter.apply(state, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_compile_would_refuse_are_refused() {
+        let refused = [
+            (ScheduleParameter::TargetTemp, -1.0),
+            (ScheduleParameter::InjectRate, -0.5),
+            (ScheduleParameter::Dt, 0.0),
+            (ScheduleParameter::Dt, f32::NAN),
+            (ScheduleParameter::PinnedPressure, f32::INFINITY),
+            (ScheduleParameter::BoundRate, f32::NEG_INFINITY),
+        ];
+        for &(parameter, value) in refused.iter() {
+            assert!(
+                parameter.check(value).is_err(),
+                "{:?} = {}",
+                parameter,
+                value
+            );
+        }
+        assert!(ScheduleParameter::TargetTemp.check(0.0).is_ok());
+        assert!(ScheduleParameter::BoundRate.check(-0.1).is_ok());
+        assert!(ScheduleParameter::PinnedPressure.check(-0.2).is_ok());
+    }
+
+    #[test]
+    fn ramps_are_checked_at_both_ends() {
+        let cooling = Event::ramp(0, ScheduleParameter::TargetTemp, 1.0, 0.0, 10);
+        assert!(cooling.check().is_ok());
+        let too_far = Event::ramp(0, ScheduleParameter::TargetTemp, 1.0, -0.1, 10);
+        assert!(too_far.check().is_err());
+    }
+
+    #[test]
+    fn ramps_interpolate_between_their_ends() {
+        let ramp = Event::ramp(100, ScheduleParameter::TargetTemp, 2.0, 1.0, 50);
+        assert_eq!(ramp.value_at(99), None);
+        assert_eq!(ramp.value_at(100), Some(2.0));
+        assert_eq!(ramp.value_at(125), Some(1.5));
+        assert_eq!(ramp.value_at(150), Some(1.0));
+        assert_eq!(ramp.value_at(151), None);
+        assert_eq!(ramp.end_step(), 150);
+
+        // a ramp over no steps jumps to its end
+        let jump = Event::ramp(10, ScheduleParameter::Dt, 0.001, 0.002, 0);
+        assert_eq!(jump.value_at(10), Some(0.002));
+
+        let set = Event::set(7, ScheduleParameter::InjectRate, 0.3);
+        assert_eq!(set.value_at(7), Some(0.3));
+        assert_eq!(set.value_at(8), None);
+    }
+
+    #[test]
+    fn later_events_win() {
+        let mut state = crate::state::SimulationPrototype::new()
+            .compile()
+            .unwrap()
+            .into_state();
+        let mut schedule = Schedule::default();
+        schedule.push(Event::ramp(0, ScheduleParameter::TargetTemp, 0.0, 1.0, 10));
+        schedule.push(Event::set(5, ScheduleParameter::TargetTemp, 3.0));
+
+        state.steps = 4;
+        schedule.apply(&mut state);
+        assert!((state.target_temp - 0.4).abs() < 1e-6);
+        state.steps = 5;
+        schedule.apply(&mut state);
+        assert!((state.target_temp - 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn ramps_ending_past_the_last_step_stay_active() {
+        let endless = Event::ramp(10, ScheduleParameter::BoundRate, 0.0, 1.0, usize::MAX);
+        assert_eq!(endless.end_step(), usize::MAX);
+        assert_eq!(endless.value_at(9), None);
+        assert_eq!(endless.value_at(10), Some(0.0));
+        assert!(endless.value_at(usize::MAX - 1).is_some());
+    }
+}
@@ -0,0 +1,85 @@
+// Crystal seeds for heterogeneous nucleation
+// A seed is a ball cut out of an fcc lattice, with a lattice site at its center
+// SimulationState::insert_seed removes the fluid particles in its way and tags the seed as a group
+// Frozen seeds are fixed in place, mobile ones start at the temperature of the fluid
+use super::particle::Particle;
+use super::physics;
+use super::SimulationState;
+use glam::Vec3;
+use itertools::iproduct;
+use rand::Rng;
+use rand_distr::StandardNormal;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Seed {
+    pub center: Vec3,
+    pub radius: f32,  // sites farther than this from the center are left out
+    pub spacing: f32, // nearest neighbor distance of the lattice
+    pub frozen: bool,
+}
+
+impl Default for Seed {
+    fn default() -> Self {
+        Self {
+            center: Vec3::ZERO,
+            radius: 0.4,
+            spacing: 0.15, // the spacing of the solid preset
+            frozen: true,
+        }
+    }
+}
+
+impl Seed {
+    pub fn is_valid(&self) -> bool {
+        self.radius >= 0.0 && self.spacing > 0.0
+    }
+
+    // Lattice sites of the seed
+    pub fn sites(&self) -> Vec<Vec3> {
+        if !self.is_valid() {
+            return Vec::new();
+        }
+        // four sites per cubic cell as in Initialize::initialize_lattice
+        let basis = [
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.5, 0.5, 0.0),
+            Vec3::new(0.5, 0.0, 0.5),
+            Vec3::new(0.0, 0.5, 0.5),
+        ];
+        let cell = self.spacing * 2f32.sqrt();
+        let reach = (self.radius / cell).ceil() as i32 + 1;
+        iproduct!(-reach..=reach, -reach..=reach, -reach..=reach, basis.iter())
+            .map(|(i, j, k, offset)| {
+                self.center + (Vec3::new(i as f32, j as f32, k as f32) + *offset) * cell
+            })
+            .filter(|pos| (*pos - self.center).length() <= self.radius)
+            .collect()
+    }
+
+    // Default particles on the sites, the mobile ones with random velocities at the temperature of the state
+    // Random, so generate once and hand the same particles to every copy of the state
+    pub fn particles(&self, state: &SimulationState) -> Vec<Particle> {
+        let mut rng = rand::thread_rng();
+        let temperature = if state.particles.is_empty() {
+            state.target_temp
+        } else {
+            state.temperature()
+        };
+        let speed = physics::velocity_spread(temperature);
+        self.sites()
+            .into_iter()
+            .map(|pos| {
+                let particle = Particle::new().set_pos(pos.x, pos.y, pos.z);
+                if self.frozen {
+                    particle.set_fixed(true)
+                } else {
+                    particle.set_vel(
+                        rng.sample::<f32, _>(StandardNormal) * speed,
+                        rng.sample::<f32, _>(StandardNormal) * speed,
+                        rng.sample::<f32, _>(StandardNormal) * speed,
+                    )
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,189 @@
+// Parameter sensitivity scan
+// Runs short simulations without rendering while varying one parameter
+// Reports how strongly the observables respond to that parameter
+use super::error::InvalidParamError;
+use super::SimulationPrototype;
+use std::fmt;
+
+// Parameters that can be varied by a scan
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScanParameter {
+    Dt,
+    CutoffRadius, // the grid squares follow it
+    Epsilon,      // well depth of every particle relative to the potential, mixtures become uniform
+    Sigma,        // size of every particle, twice its radius
+    TargetTemp,   // needs a thermostat, an inject_rate above 0 or a stochastic integrator
+}
+
+impl ScanParameter {
+    pub const NAMES: [&'static str; 5] = ["dt", "cutoff", "epsilon", "sigma", "temp"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dt" => Some(ScanParameter::Dt),
+            "cutoff" => Some(ScanParameter::CutoffRadius),
+            "epsilon" => Some(ScanParameter::Epsilon),
+            "sigma" => Some(ScanParameter::Sigma),
+            "temp" => Some(ScanParameter::TargetTemp),
+            _ => None,
+        }
+    }
+
+    // Return a prototype with the parameter set to value
+    fn apply(self, prototype: SimulationPrototype, value: f32) -> SimulationPrototype {
+        match self {
+            ScanParameter::Dt => prototype.set_dt(value),
+            ScanParameter::CutoffRadius => prototype.set_cutoff_radius(value),
+            ScanParameter::Epsilon => prototype.set_well_depths(&[value]),
+            ScanParameter::Sigma => prototype.set_radii(&[value / 2.0]),
+            ScanParameter::TargetTemp => prototype.set_target_temp(value),
+        }
+    }
+}
+
+// Observables averaged over the second half of a run
+#[derive(Clone, Copy, Default)]
+pub struct Observables {
+    pub kinetic: f32,
+    pub potential: f32,
+    pub total: f32,
+    pub pressure: f32,
+}
+
+impl Observables {
+    pub const NAMES: [&'static str; 4] = ["KE", "PE", "Total Energy", "P"];
+
+    fn to_array(self) -> [f32; 4] {
+        [self.kinetic, self.potential, self.total, self.pressure]
+    }
+}
+
+// Result of a single run of the scan
+#[derive(Clone, Copy)]
+pub struct ScanSample {
+    pub value: f32,
+    pub observables: Observables,
+}
+
+pub struct ScanReport {
+    pub parameter: ScanParameter,
+    pub samples: Vec<ScanSample>,
+}
+
+impl ScanReport {
+    // Relative sensitivity of each observable: (dO/dp) * (mean p / mean O)
+    // The slope dO/dp is found by a least squares fit over all samples
+    // A value of 1 means a 1% change in the parameter changes the observable by 1%
+    pub fn sensitivities(&self) -> [f32; 4] {
+        let n = self.samples.len() as f32;
+        let p_mean = self.samples.iter().map(|s| s.value).sum::<f32>() / n;
+        let p_var: f32 = self
+            .samples
+            .iter()
+            .map(|s| (s.value - p_mean).powi(2))
+            .sum();
+
+        let mut ret = [0.0; 4];
+        for (k, sensitivity) in ret.iter_mut().enumerate() {
+            let o_mean = self
+                .samples
+                .iter()
+                .map(|s| s.observables.to_array()[k])
+                .sum::<f32>()
+                / n;
+            let covariance: f32 = self
+                .samples
+                .iter()
+                .map(|s| (s.value - p_mean) * (s.observables.to_array()[k] - o_mean))
+                .sum();
+
+            if p_var > 0.0 && o_mean != 0.0 {
+                *sensitivity = covariance / p_var * p_mean / o_mean.abs();
+            }
+        }
+        ret
+    }
+}
+
+impl fmt::Display for ScanReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Sensitivity scan over {:?}", self.parameter)?;
+        writeln!(
+            f,
+            "{:>12} {:>12} {:>12} {:>12} {:>12}",
+            "value", "KE", "PE", "Total", "P"
+        )?;
+        for sample in self.samples.iter() {
+            let o = sample.observables;
+            writeln!(
+                f,
+                "{:>12.5} {:>12.5} {:>12.5} {:>12.5} {:>12.5}",
+                sample.value, o.kinetic, o.potential, o.total, o.pressure
+            )?;
+        }
+
+        writeln!(f, "Relative sensitivity:")?;
+        for (name, sensitivity) in Observables::NAMES.iter().zip(self.sensitivities().iter()) {
+            writeln!(f, "{:>12}: {:.5}", name, sensitivity)?;
+        }
+        Ok(())
+    }
+}
+
+// Run one simulation for every value of the parameter
+// Every run starts from the same initial particles and lasts for duration units of simulated time
+pub fn scan(
+    prototype: &SimulationPrototype,
+    parameter: ScanParameter,
+    values: &[f32],
+    duration: f32,
+) -> Result<ScanReport, InvalidParamError> {
+    let mut samples = Vec::with_capacity(values.len());
+
+    for &value in values {
+        let mut state = parameter
+            .apply(prototype.clone(), value)
+            .compile()?
+            .into_state();
+
+        let frames = (duration / (state.dt * state.steps_per_frame as f32)).ceil() as usize;
+        let mut sum = Observables::default();
+        let mut measured = 0;
+        for frame in 0..frames {
+            state.advance_frame();
+
+            // let the system settle for the first half of the run
+            if frame >= frames / 2 {
+                sum.kinetic += state.energy.kinetic;
+                sum.potential += state.energy.potential;
+                sum.total += state.energy.total();
+                sum.pressure += state.pressure.get_pressure();
+                measured += 1;
+            }
+        }
+
+        let measured = measured.max(1) as f32;
+        samples.push(ScanSample {
+            value,
+            observables: Observables {
+                kinetic: sum.kinetic / measured,
+                potential: sum.potential / measured,
+                total: sum.total / measured,
+                pressure: sum.pressure / measured,
+            },
+        });
+    }
+
+    Ok(ScanReport { parameter, samples })
+}
+
+// Evenly spaced values from start to end, inclusive
+pub fn linspace(start: f32, end: f32, n: usize) -> Vec<f32> {
+    match n {
+        0 => vec![],
+        1 => vec![start],
+        _ => (0..n)
+            .map(|i| start + (end - start) * i as f32 / (n - 1) as f32)
+            .collect(),
+    }
+}
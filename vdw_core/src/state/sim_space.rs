@@ -0,0 +1,1166 @@
+use super::physics;
+use super::timing::Stopwatch;
+use crate::par::*;
+use glam::{Mat3, Vec3};
+use itertools::{iproduct, Either};
+use ndarray::Array3;
+use rand::Rng;
+use rand_distr::StandardNormal;
+use serde::Deserialize;
+use std::cmp::{max, min};
+
+// index of a grid square
+type GridLocation = (usize, usize, usize);
+
+// neighbors and contacts of a particle
+type PairCounts = (usize, usize);
+
+// Grid squares and particle locations, kept between steps so their allocations are reused
+// Only scratch space, clones start out empty
+// Without an anchor the grid spans the particles, so its origin and size follow them every step
+// Anchored to a boundary the origin is the lower corner of the box and the size follows from the box
+// alone, the squares are only reallocated when the box grows or shrinks by a square, and every
+// square stays at the same place in the box, as periodic indexing needs
+#[derive(Default)]
+pub struct GridWorkspace {
+    cells: Array3<Vec<usize>>,    // particle indices in each grid square
+    locations: Vec<GridLocation>, // grid square of each particle
+    raw_locations: Vec<(isize, isize, isize)>, // before moving the origin to the lowest square
+    anchor: Option<Boundary>,     // box the grid is laid over, none to span the particles
+    forces: Vec<Vec3>,            // force on each particle, from the last force calculation
+    potentials: Vec<f32>, // potential energy of each particle, from the last force calculation
+    neighbors: Vec<usize>, // neighbors of each particle, from the last force calculation
+    contacts: Vec<usize>, // particles in contact with each particle, from the last force calculation
+    virials: Vec<Mat3>, // share of the pair virial of each particle, from the last force calculation
+    timing: (f32, f32), // milliseconds of filling the grid and of the pair loop in the last force calculation
+}
+
+impl GridWorkspace {
+    // Lay the grid over the box from now on, call again whenever the box changes
+    pub fn anchor_to(&mut self, bound: Boundary) {
+        self.anchor = Some(bound);
+    }
+
+    // Number of grid squares along each axis, from the last fill
+    pub fn dim(&self) -> (usize, usize, usize) {
+        self.cells.dim()
+    }
+
+    // Results of the last force calculation, in particle order
+    pub fn forces(&self) -> &[Vec3] {
+        &self.forces
+    }
+
+    pub fn potentials(&self) -> &[f32] {
+        &self.potentials
+    }
+
+    pub fn neighbors(&self) -> &[usize] {
+        &self.neighbors
+    }
+
+    // Pairs closer than their collision diameter, see PotentialModel::length_scale
+    // Each pair is counted at both of its particles
+    pub fn contacts(&self) -> &[usize] {
+        &self.contacts
+    }
+
+    // Each particle takes half of the virial of every pair it is in
+    pub fn virials(&self) -> &[Mat3] {
+        &self.virials
+    }
+
+    // Milliseconds spent filling the grid and in the pair loop by the last force calculation
+    pub fn timing(&self) -> (f32, f32) {
+        self.timing
+    }
+
+    // Box of the anchor if its faces are periodic
+    // internal helper function
+    fn periodic_anchor(&self) -> Option<Boundary> {
+        self.anchor.filter(|bound| bound.wall_model.is_periodic())
+    }
+}
+
+impl Clone for GridWorkspace {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+////////////////////////////////////////////////////////////
+// Grid splits the space up into boxes
+// Determines which particles can interact with each other
+// The squares only speed up the search for pairs, interactions are cut off at the cutoff,
+// which the squares within reach have to cover
+// To be used internally by State
+//
+#[derive(Clone, Copy)]
+pub struct Grid {
+    reach: usize,              // squares searched for partners in each direction
+    unit_size: f32,            // size of a single grid square
+    cutoff: f32,               // range of interactions between particles
+    switch_start: Option<f32>, // distance at which interactions start to be switched off
+    potential: physics::PotentialModel,
+    neighbor_rule: physics::NeighborRule, // pairs counted as neighbors in the force calculation
+}
+
+impl Grid {
+    // Grid of squares of unit_size, interactions reach over reach squares
+    pub fn new(unit_size: f32, reach: usize) -> Self {
+        Self {
+            reach,
+            unit_size,
+            cutoff: unit_size * reach as f32,
+            switch_start: None,
+            potential: physics::PotentialModel::default(),
+            neighbor_rule: physics::NeighborRule::default(),
+        }
+    }
+
+    // Grid for interactions cut off at cutoff, with squares of its own choosing
+    // Squares as large as the cutoff keep the search to the neighboring squares, smaller ones would
+    // look at fewer particles but at more squares
+    pub fn with_cutoff(cutoff: f32) -> Self {
+        Self::new(cutoff, 1)
+    }
+
+    // Smoothly switch interactions off between switch_start and the interaction range
+    pub fn with_switch(mut self, switch_start: Option<f32>) -> Self {
+        self.switch_start = switch_start;
+        self
+    }
+
+    // Pair potential the particles interact through
+    pub fn with_potential(mut self, potential: physics::PotentialModel) -> Self {
+        self.potential = potential;
+        self
+    }
+
+    pub fn set_potential(&mut self, potential: physics::PotentialModel) {
+        self.potential = potential;
+    }
+
+    // Which pairs count as neighbors, the force calculation always counts by distance
+    pub fn with_neighbor_rule(mut self, neighbor_rule: physics::NeighborRule) -> Self {
+        self.neighbor_rule = neighbor_rule;
+        self
+    }
+
+    pub fn set_neighbor_rule(&mut self, neighbor_rule: physics::NeighborRule) {
+        self.neighbor_rule = neighbor_rule;
+    }
+
+    // Calculate the interactions between particles using the grid approximation
+    // Return (accelerations, potential energies, # of neighbors, virial)
+    // The virial is the sum of r_ij (x) f_ij over all interacting pairs
+    // sites has the size of each particle and whether it is inert, see PotentialModel::site_interaction
+    pub fn calculate_force(
+        &self,
+        particles: &[Vec3],
+        sites: &[physics::InteractionSite],
+    ) -> (Vec<Vec3>, Vec<f32>, Vec<usize>, Mat3) {
+        let mut workspace = GridWorkspace::default();
+        let virial = self.calculate_force_with(particles, sites, &mut workspace);
+        (
+            workspace.forces,
+            workspace.potentials,
+            workspace.neighbors,
+            virial,
+        )
+    }
+
+    // Same as calculate_force, sorting the particles into the cells of a reused workspace
+    // The forces, potential energies and neighbors are left in the workspace, the virial is returned
+    pub fn calculate_force_with(
+        &self,
+        particles: &[Vec3],
+        sites: &[physics::InteractionSite],
+        workspace: &mut GridWorkspace,
+    ) -> Mat3 {
+        let stopwatch = Stopwatch::start();
+        self.fill_grid(particles, workspace);
+        let fill_ms = stopwatch.elapsed_ms();
+        let n = workspace.locations.len();
+        workspace.forces.clear();
+        workspace.forces.resize(n, Vec3::ZERO);
+        workspace.potentials.clear();
+        workspace.potentials.resize(n, 0.0);
+        workspace.neighbors.clear();
+        workspace.neighbors.resize(n, 0);
+        workspace.contacts.clear();
+        workspace.contacts.resize(n, 0);
+        workspace.virials.clear();
+        workspace.virials.resize(n, Mat3::ZERO);
+
+        let grid = &workspace.cells;
+        let particle_locations = &workspace.locations;
+        let periodic = workspace.periodic_anchor();
+        (
+            &mut workspace.forces,
+            &mut workspace.potentials,
+            &mut workspace.neighbors,
+            &mut workspace.contacts,
+            &mut workspace.virials,
+        )
+            .into_par_iter()
+            .enumerate() // locations and particles has matching indices
+            .for_each(
+                |(particle_id, (force, potential, neighbors, contacts, virial))| {
+                    let location = particle_locations[particle_id];
+                    let (f, (u, ((nei, con), v))) = self.calculate_force_single(
+                        particle_id,
+                        location,
+                        particles,
+                        sites,
+                        grid,
+                        periodic.as_ref(),
+                    );
+                    *force = f;
+                    *potential = u;
+                    *neighbors = nei;
+                    *contacts = con;
+                    *virial = v;
+                },
+            );
+        let virial = workspace.virials.iter().fold(Mat3::ZERO, |acc, &v| acc + v);
+        workspace.timing = (fill_ms, stopwatch.elapsed_ms() - fill_ms);
+
+        virial
+    }
+
+    // Find all pairs of particles within interaction range of each other
+    // Each pair is listed once, with the smaller index first
+    pub fn find_pairs(&self, particles: &[Vec3]) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        self.find_pairs_with(particles, &mut GridWorkspace::default(), &mut pairs);
+        pairs
+    }
+
+    // Same as find_pairs, sorting the particles into the cells of a reused workspace
+    // and listing the pairs in a reused buffer
+    pub fn find_pairs_with(
+        &self,
+        particles: &[Vec3],
+        workspace: &mut GridWorkspace,
+        pairs: &mut Vec<(usize, usize)>,
+    ) {
+        let range_sqr = self.cutoff.powi(2);
+        self.fill_grid(particles, workspace);
+        let (grid, particle_locations) = (&workspace.cells, &workspace.locations);
+        let periodic = workspace.periodic_anchor();
+        let separation = move |i: usize, j: usize| match periodic {
+            Some(bound) => bound.minimum_image(particles[i] - particles[j]),
+            None => particles[i] - particles[j],
+        };
+
+        pairs.clear();
+        pairs.par_extend(particle_locations.par_iter().enumerate().flat_map_iter(
+            |(i, &location)| {
+                self.generate_neighbor_grid_loc(location, grid, periodic.as_ref())
+                    .flat_map(move |(x, y, z)| grid[[x, y, z]].iter().copied())
+                    .filter(move |&j| j > i && separation(i, j).length_squared() < range_sqr)
+                    .map(move |j| (i, j))
+            },
+        ));
+    }
+
+    // Indices of the particles within radius of center, in increasing order
+    // Only the grid squares overlapping the ball are searched
+    pub fn find_within(&self, particles: &[Vec3], center: Vec3, radius: f32) -> Vec<usize> {
+        if particles.is_empty() || radius < 0.0 {
+            return Vec::new();
+        }
+        let mut workspace = GridWorkspace::default();
+        self.fill_grid(particles, &mut workspace);
+        let grid = &workspace.cells;
+
+        // the squares of the ball, shifted like the particle locations and clipped to the grid
+        let (x0, y0, z0) = workspace.raw_locations[0];
+        let (lx, ly, lz) = workspace.locations[0];
+        let shift = |raw: (isize, isize, isize)| {
+            (
+                raw.0 - x0 + lx as isize,
+                raw.1 - y0 + ly as isize,
+                raw.2 - z0 + lz as isize,
+            )
+        };
+        let lo = shift(self.find_grid_location(center - Vec3::splat(radius)));
+        let hi = shift(self.find_grid_location(center + Vec3::splat(radius)));
+        let (dim_x, dim_y, dim_z) = grid.dim();
+        let span = |lo: isize, hi: isize, dim: usize| {
+            lo.max(0) as usize..(hi + 1).min(dim as isize).max(0) as usize
+        };
+
+        let radius_sqr = radius * radius;
+        let mut found: Vec<usize> = iproduct!(
+            span(lo.0, hi.0, dim_x),
+            span(lo.1, hi.1, dim_y),
+            span(lo.2, hi.2, dim_z)
+        )
+        .flat_map(|(x, y, z)| grid[[x, y, z]].iter().copied())
+        .filter(|&i| (particles[i] - center).length_squared() <= radius_sqr)
+        .collect();
+        found.sort_unstable();
+        found
+    }
+
+    // Interaction range
+    pub fn get_range(&self) -> f32 {
+        self.cutoff
+    }
+
+    // Size of a single grid square
+    pub fn get_unit_size(&self) -> f32 {
+        self.unit_size
+    }
+
+    pub fn get_switch_start(&self) -> Option<f32> {
+        self.switch_start
+    }
+
+    pub fn get_potential(&self) -> physics::PotentialModel {
+        self.potential
+    }
+
+    pub fn get_neighbor_rule(&self) -> physics::NeighborRule {
+        self.neighbor_rule
+    }
+
+    // Calculate the total force acted on a particle by all nearby particles
+    // Calculate the potential energy of the system
+    // Awkward return format so that it can be used by unzip
+    // To be used internally
+    fn calculate_force_single(
+        &self,
+        tpid: usize,                        // target particle index
+        loc: (usize, usize, usize),         // target particle grid location
+        particles: &[Vec3],                 // Set of all particle positions
+        sites: &[physics::InteractionSite], // size and inertness of each particle
+        grid: &Array3<Vec<usize>>,          // division grid
+        periodic: Option<&Boundary>,        // periodic box the grid is laid over, if any
+    ) -> (Vec3, (f32, (PairCounts, Mat3))) {
+        let relevant_grid_points = self.generate_neighbor_grid_loc(loc, grid, periodic);
+        let target_particle = particles[tpid];
+        // across periodic faces the nearest image of the other particle interacts
+        let image = |other: Vec3| match periodic {
+            Some(bound) => target_particle - bound.minimum_image(target_particle - other),
+            None => other,
+        };
+
+        let relevant_particles = relevant_grid_points
+            .flat_map(|(x, y, z)| &grid[[x, y, z]]) // retrieve particle ids from grid points
+            .filter(|&&pid| pid != tpid) // remove target particle id
+            .map(|&pid| (image(particles[pid]), sites[pid])); // retrieve particles from particle ids
+
+        let mut total_force = Vec3::ZERO;
+        let mut total_potential = 0.0;
+        let mut total_neighbor = 0;
+        let mut total_contacts = 0;
+        let mut total_virial = Mat3::ZERO;
+        let diameter = self.potential.length_scale();
+        // iterate through relevant particles, sum up forces and potentials
+        for (other_particle, other_site) in relevant_particles {
+            let range = self.cutoff;
+
+            let (force, potential) = self.potential.site_interaction(
+                target_particle,
+                sites[tpid],
+                other_particle,
+                other_site,
+                range,
+                self.switch_start,
+            );
+
+            total_force += force;
+            total_potential += potential;
+            // both distances are stretched with the size of the pair like the potential
+            let (scale, _) = sites[tpid].mix(&other_site);
+            let r_norm_sqr = (target_particle - other_particle).length_squared();
+            if self.neighbor_rule.within(r_norm_sqr, scale) {
+                total_neighbor += 1;
+            }
+            // closer than the collision diameter of the pair
+            if r_norm_sqr < (diameter * scale).powi(2) {
+                total_contacts += 1;
+            }
+            // every pair is visited from both ends, each end takes half
+            total_virial = total_virial
+                + physics::outer_product(target_particle - other_particle, force * 0.5);
+        }
+
+        (
+            total_force,
+            (
+                total_potential,
+                ((total_neighbor, total_contacts), total_virial),
+            ),
+        )
+    }
+
+    // Generate indices that satisfy:
+    //   Within reach of the input index
+    //   Is a valid index in the grid
+    // On a periodic grid the indices wrap around instead, each square is generated once
+    // To be used internally
+    fn generate_neighbor_grid_loc(
+        &self,
+        loc: (usize, usize, usize),
+        grid: &Array3<Vec<usize>>,
+        periodic: Option<&Boundary>,
+    ) -> impl Iterator<Item = GridLocation> {
+        let (this_x, this_y, this_z) = loc;
+        let (dim_x, dim_y, dim_z) = grid.dim();
+
+        if let Some(bound) = periodic {
+            let wrapped = |this: usize, dim: usize| {
+                let mut indices: Vec<usize> = (-(self.reach as isize)..=self.reach as isize)
+                    .map(|offset| (this as isize + offset).rem_euclid(dim as isize) as usize)
+                    .collect();
+                indices.sort_unstable();
+                indices.dedup();
+                indices
+            };
+            let ys = wrapped(this_y, dim_y);
+            // the sliding images above and below are shifted along x by any amount,
+            // so the squares across a y face may be anywhere along x
+            let crosses_y = this_y < self.reach || this_y + self.reach >= dim_y;
+            let xs = if bound.lees_edwards.is_some() && crosses_y {
+                (0..dim_x).collect()
+            } else {
+                wrapped(this_x, dim_x)
+            };
+            let zs = wrapped(this_z, dim_z);
+            return Either::Left(iproduct!(xs, ys, zs));
+        }
+
+        // iterators that cover the range of possible index values
+        let xs =
+            (this_x.saturating_sub(self.reach)..=this_x + self.reach).filter(move |&x| x < dim_x);
+
+        let ys =
+            (this_y.saturating_sub(self.reach)..=this_y + self.reach).filter(move |&y| y < dim_y);
+
+        let zs =
+            (this_z.saturating_sub(self.reach)..=this_z + self.reach).filter(move |&z| z < dim_z);
+
+        // return the cartesian product of xs, yx, zs
+        Either::Right(iproduct!(xs, ys, zs))
+    }
+
+    // Sort particles into grid locations
+    // Is used to approximate particle interactions
+    // Fills the workspace with the list of particle indices in each grid square
+    //     and the locations of the corresponding particles on the grid
+    // The squares are emptied and refilled in place while the size of the grid stays the same
+    // to be used internally
+    fn fill_grid(&self, ps: &[Vec3], workspace: &mut GridWorkspace) {
+        let dim = match workspace.anchor {
+            Some(bound) => self.locate_in_bound(ps, &bound, workspace),
+            None => self.locate_in_extent(ps, workspace),
+        };
+
+        // Making and adding indicies into the grid
+        if workspace.cells.dim() == dim {
+            workspace.cells.iter_mut().for_each(|cell| cell.clear());
+        } else {
+            workspace.cells = Array3::from_elem(dim, Vec::with_capacity(0));
+        }
+        let cells = &mut workspace.cells;
+        workspace
+            .locations
+            .iter()
+            .enumerate()
+            .for_each(|(i, (x, y, z))| cells[[*x, *y, *z]].push(i));
+    }
+
+    // Number of grid squares along each axis of a grid laid over the box
+    // A grid without a square size is a single square
+    // A periodic box is split into whole squares at least unit_size wide, the reach of the squares
+    // has to span the cutoff across the faces as well
+    pub fn dim_in(&self, bound: &Boundary) -> (usize, usize, usize) {
+        let squares = |length: f32| {
+            let n = if bound.wall_model.is_periodic() {
+                (length / self.unit_size).floor()
+            } else {
+                (length / self.unit_size).ceil()
+            };
+            if self.unit_size > 0.0 && n.is_finite() {
+                (n as usize).max(1)
+            } else {
+                1
+            }
+        };
+        let size = bound.size();
+        (squares(size.x), squares(size.y), squares(size.z))
+    }
+
+    // Find the grid squares of the particles on a grid laid over the box and return its size
+    // Particles outside the box go to the nearest square at its edge, which only brings squares
+    // closer to each other, so all pairs within range are still within reach
+    // to be used internally
+    fn locate_in_bound(
+        &self,
+        ps: &[Vec3],
+        bound: &Boundary,
+        workspace: &mut GridWorkspace,
+    ) -> GridLocation {
+        let dim = self.dim_in(bound);
+        let lo = bound.lo_corner();
+        let clamp = |index: isize, n: usize| index.max(0).min(n as isize - 1) as usize;
+        workspace.raw_locations.clear();
+        workspace.locations.clear();
+        if bound.wall_model.is_periodic() {
+            // squares of the image inside the box, the squares may be wider than unit_size
+            let square = bound.size() / Vec3::new(dim.0 as f32, dim.1 as f32, dim.2 as f32);
+            workspace.locations.extend(ps.iter().map(|&p| {
+                let (mut pos, mut vel) = (p, Vec3::ZERO);
+                bound.wrap(&mut pos, &mut vel);
+                let index = ((pos - lo) / square).floor();
+                (
+                    clamp(index.x as isize, dim.0),
+                    clamp(index.y as isize, dim.1),
+                    clamp(index.z as isize, dim.2),
+                )
+            }));
+            return dim;
+        }
+        workspace.locations.extend(ps.iter().map(|&p| {
+            let (x, y, z) = if dim == (1, 1, 1) {
+                (0, 0, 0)
+            } else {
+                self.find_grid_location(p - lo)
+            };
+            (clamp(x, dim.0), clamp(y, dim.1), clamp(z, dim.2))
+        }));
+        dim
+    }
+
+    // Find the grid squares of the particles on a grid spanning them and return its size
+    // to be used internally
+    fn locate_in_extent(&self, ps: &[Vec3], workspace: &mut GridWorkspace) -> GridLocation {
+        // get a list of positional indicies from the particles
+        workspace.raw_locations.clear();
+        workspace
+            .raw_locations
+            .extend(ps.iter().map(|&p| self.find_grid_location(p)));
+
+        // find the smallest indexes to set the position of the origin
+        let init_min = isize::MAX;
+        let (xmin, ymin, zmin) = workspace.raw_locations.iter().fold(
+            (init_min, init_min, init_min),
+            |(xacc, yacc, zacc), (x, y, z)| (min(xacc, *x), min(yacc, *y), min(zacc, *z)),
+        );
+
+        // translate the coordinate so that the smallest indices are at 0
+        workspace.locations.clear();
+        workspace
+            .locations
+            .extend(workspace.raw_locations.iter().map(|(x, y, z)| {
+                (
+                    (x - xmin) as usize,
+                    (y - ymin) as usize,
+                    (z - zmin) as usize,
+                )
+            }));
+
+        // find the largest indecies to find the size of the grid
+        let init_max = usize::MIN;
+        let (xmax, ymax, zmax) = workspace.locations.iter().fold(
+            (init_max, init_max, init_max),
+            |(xacc, yacc, zacc), (x, y, z)| (max(xacc, *x), max(yacc, *y), max(zacc, *z)),
+        );
+
+        (xmax + 1, ymax + 1, zmax + 1)
+    }
+
+    // find location of a position on a grid
+    // to be used internally
+    fn find_grid_location(&self, p: Vec3) -> (isize, isize, isize) {
+        let gridx = f32::floor(p[0] / self.unit_size) as isize;
+        let gridy = f32::floor(p[1] / self.unit_size) as isize;
+        let gridz = f32::floor(p[2] / self.unit_size) as isize;
+
+        (gridx, gridy, gridz)
+    }
+}
+
+// How the walls of the box keep particles inside
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WallModel {
+    Soft,         // stiff spring force on particles past the wall, leaks energy at large dt
+    HardReflect,  // particles crossing a wall bounce back elastically
+    Thermal(f32), // particles crossing a wall leave with a velocity drawn at the wall temperature
+    Periodic,     // no walls, particles leaving through a face come back through the opposite one
+}
+
+impl WallModel {
+    pub const ALL: [WallModel; 4] = [
+        WallModel::Soft,
+        WallModel::HardReflect,
+        WallModel::Thermal(1.0),
+        WallModel::Periodic,
+    ];
+
+    // Name of the model without its parameters
+    pub fn name(&self) -> &'static str {
+        match self {
+            WallModel::Soft => "Soft",
+            WallModel::HardReflect => "HardReflect",
+            WallModel::Thermal(_) => "Thermal",
+            WallModel::Periodic => "Periodic",
+        }
+    }
+
+    // Hard walls act on crossings instead of through a force, periodic faces wrap the crossings
+    pub fn is_hard(&self) -> bool {
+        !matches!(self, WallModel::Soft)
+    }
+
+    pub fn is_periodic(&self) -> bool {
+        matches!(self, WallModel::Periodic)
+    }
+}
+
+// Lees-Edwards sliding images of a periodic box, for shear flow along x with the gradient along y
+// The copies of the box above it slide along x at rate * length_y, the ones below at the opposite
+// velocity, so the particles settle into the flow profile rate * (y - center y) along x
+// Particles leaving through a y face come back shifted by the offset of the images and with their
+// x velocity changed by the velocity of the images
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct LeesEdwards {
+    pub rate: f32,   // shear rate, the x velocity gained per unit of height
+    pub offset: f32, // distance the images above have slid along x, from 0 to the length of x
+}
+
+impl LeesEdwards {
+    pub fn new(rate: f32) -> Self {
+        Self { rate, offset: 0.0 }
+    }
+}
+
+// The six faces of the box
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Face {
+    XLo,
+    XHi,
+    YLo,
+    YHi,
+    ZLo,
+    ZHi,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [
+        Face::XLo,
+        Face::XHi,
+        Face::YLo,
+        Face::YHi,
+        Face::ZLo,
+        Face::ZHi,
+    ];
+    pub const NAMES: [&'static str; 6] = ["x_lo", "x_hi", "y_lo", "y_hi", "z_lo", "z_hi"];
+
+    pub fn name(&self) -> &'static str {
+        Self::NAMES[self.index()]
+    }
+
+    // Position in ALL
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    // Axis the face is perpendicular to
+    pub fn axis(&self) -> usize {
+        self.index() / 2
+    }
+
+    // Whether the face is at the corner with higher values
+    pub fn is_hi(&self) -> bool {
+        self.index() % 2 == 1
+    }
+
+    // Face perpendicular to axis, at the higher or the lower corner
+    pub fn of(axis: usize, hi: bool) -> Self {
+        Self::ALL[2 * axis + hi as usize]
+    }
+}
+
+// Normal impulse delivered to each face of the box, indexed by Face::index
+// Positive values push the face outwards
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct FaceImpulses(pub [f32; 6]);
+
+impl FaceImpulses {
+    pub fn get(&self, face: Face) -> f32 {
+        self.0[face.index()]
+    }
+
+    pub fn add(&mut self, face: Face, amount: f32) {
+        self.0[face.index()] += amount;
+    }
+
+    // Sum over all faces
+    pub fn total(&self) -> f32 {
+        self.0.iter().sum()
+    }
+
+    pub fn scale(mut self, factor: f32) -> Self {
+        self.0.iter_mut().for_each(|value| *value *= factor);
+        self
+    }
+}
+
+impl std::ops::AddAssign for FaceImpulses {
+    fn add_assign(&mut self, other: Self) {
+        for (value, other) in self.0.iter_mut().zip(other.0.iter()) {
+            *value += other;
+        }
+    }
+}
+
+impl std::ops::Add for FaceImpulses {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        self += other;
+        self
+    }
+}
+
+impl std::iter::Sum for FaceImpulses {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |acc, impulses| acc + impulses)
+    }
+}
+
+// Attractive 9-3 wall, the Lennard-Jones interaction integrated over a half space of wall atoms
+// U(z) = strength * (2/15 (sigma / z)^9 - (sigma / z)^3) at distance z from the face,
+// the well is at 0.86 sigma with a depth of about strength
+// Shifted to zero at cutoff, only the selected faces attract
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct WallAdhesion {
+    pub strength: f32,
+    pub sigma: f32,
+    pub cutoff: f32,
+    pub faces: [bool; 6], // indexed by Face::index
+}
+
+impl WallAdhesion {
+    // same size as the particles, sigma of the pair potential
+    pub const SIGMA: f32 = 0.1336;
+    // closer than this the force stays constant, so particles past a soft wall aren't blown away
+    const MIN_DISTANCE: f32 = 0.5;
+
+    // Adhesion of the given faces with the width of the pair potential, cut at 3 sigma
+    pub fn new(strength: f32, faces: &[Face]) -> Self {
+        let mut mask = [false; 6];
+        for face in faces.iter() {
+            mask[face.index()] = true;
+        }
+        Self {
+            strength,
+            sigma: Self::SIGMA,
+            cutoff: 3.0 * Self::SIGMA,
+            faces: mask,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.strength >= 0.0 && self.sigma > 0.0 && self.cutoff > self.sigma
+    }
+
+    pub fn attracts(&self, face: Face) -> bool {
+        self.faces[face.index()]
+    }
+
+    // Force pushing a particle at distance z away from the face, and its potential energy
+    // internal helper function
+    fn interaction(&self, z: f32) -> (f32, f32) {
+        if z >= self.cutoff {
+            return (0.0, 0.0);
+        }
+        let z_eval = z.max(Self::MIN_DISTANCE * self.sigma);
+        let (force, potential) = self.unshifted(z_eval);
+        let (_, cutoff_potential) = self.unshifted(self.cutoff);
+        // linear continuation below the minimum distance
+        let potential = potential - force * (z - z_eval) - cutoff_potential;
+        (force, potential)
+    }
+
+    // internal helper function
+    fn unshifted(&self, z: f32) -> (f32, f32) {
+        let s3 = (self.sigma / z).powi(3);
+        let s9 = s3.powi(3);
+        let potential = self.strength * (2.0 / 15.0 * s9 - s3);
+        let force = self.strength * (18.0 / 15.0 * s9 - 3.0 * s3) / z;
+        (force, potential)
+    }
+}
+
+////////////////////////////////////////////////////////////////
+// Boundary sets the limit of the simulation box
+// Is responsible for keeping the particles within its border
+// Each of the 6 faces can be placed and moved independently
+// To be used internally by State
+//
+#[derive(Clone, Copy)]
+pub struct Boundary {
+    lo: Vec3, // corner with lower values
+    hi: Vec3, // corner with higher values
+    pub wall_model: WallModel,
+    pub adhesion: Option<WallAdhesion>, // attraction of some of the faces, none by default
+    pub lees_edwards: Option<LeesEdwards>, // sliding images of a periodic box, none by default
+}
+
+// Speeds at which each face of the box moves outwards
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct FaceRates {
+    pub lo: Vec3, // faces at the lower corner, positive values move them towards negative coordinates
+    pub hi: Vec3, // faces at the higher corner
+}
+
+impl Default for Boundary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Boundary {
+    pub const MIN_LEN: f32 = 2.0; // Minimum length of each side of the box
+    const DEFLECT_STR: f32 = 10000.0;
+
+    // Set up a boundary with default config
+    pub fn new() -> Self {
+        Self {
+            lo: Vec3::ZERO,
+            hi: Vec3::splat(5.0),
+            wall_model: WallModel::Soft,
+            adhesion: None,
+            lees_edwards: None,
+        }
+    }
+
+    // Surface area of the boundary, useful for calculating pressure
+    pub fn get_surface_area(&self) -> f32 {
+        let size = self.size();
+        (size.x * size.y + size.y * size.z + size.z * size.x) * 2.0
+    }
+
+    // Volume inside of the boundary
+    pub fn get_volume(&self) -> f32 {
+        let size = self.size();
+        size.x * size.y * size.z
+    }
+
+    // Coordinates of the corner with higher values
+    pub fn hi_corner(&self) -> Vec3 {
+        self.hi
+    }
+
+    // Coordinates of the corner with lower values
+    pub fn lo_corner(&self) -> Vec3 {
+        self.lo
+    }
+
+    // Length of the box along each axis
+    pub fn size(&self) -> Vec3 {
+        self.hi - self.lo
+    }
+
+    // Set the length along one axis, keeping the lower face in place
+    pub fn set_length(&mut self, axis: usize, len: f32) {
+        self.hi[axis] = self.lo[axis] + len;
+    }
+
+    // Place the two corners of the box
+    pub fn set_corners(&mut self, lo: Vec3, hi: Vec3) {
+        self.lo = lo;
+        self.hi = hi;
+    }
+
+    // Coordinates of center of box
+    pub fn center(&self) -> Vec3 {
+        (self.hi_corner() + self.lo_corner()) / 2.0
+    }
+
+    // Check for a valid box size
+    pub fn is_valid(&self) -> bool {
+        self.size().cmpge(Vec3::splat(Self::MIN_LEN)).all()
+    }
+
+    // check if the position vector lies within the box
+    // is used for checking the initial state of particles
+    pub fn contains_position(&self, pos: Vec3) -> bool {
+        let bound_check = self.bound_check(pos);
+        bound_check.length_squared() == 0.0
+    }
+
+    // Return a vector of forces that keeps the particles inside the box,
+    // and the force on each wall along its outward normal
+    // Hard walls exert no force, they act through reflect instead
+    pub fn calculate_force(&self, ps: &[Vec3]) -> (Vec<Vec3>, FaceImpulses) {
+        let mut forces = Vec::new();
+        let push = self.calculate_force_into(ps, &mut forces);
+        (forces, push)
+    }
+
+    // Same as calculate_force, writing the forces into a reused buffer
+    pub fn calculate_force_into(&self, ps: &[Vec3], forces: &mut Vec<Vec3>) -> FaceImpulses {
+        if self.wall_model.is_hard() {
+            forces.clear();
+            forces.resize(ps.len(), Vec3::ZERO);
+            return FaceImpulses::default();
+        }
+        ps.par_iter()
+            .map(|&p| self.calculate_force_single(p))
+            .collect_into_vec(forces);
+
+        // a wall pushes inwards, positive components come from the faces at the lower corner
+        let mut push = FaceImpulses::default();
+        for force in forces.iter() {
+            for axis in 0..3 {
+                if force[axis] > 0.0 {
+                    push.add(Face::of(axis, false), force[axis]);
+                } else if force[axis] < 0.0 {
+                    push.add(Face::of(axis, true), -force[axis]);
+                }
+            }
+        }
+        push
+    }
+
+    // Area of one face
+    pub fn face_area(&self, face: Face) -> f32 {
+        let size = self.size();
+        let axis = face.axis();
+        size[(axis + 1) % 3] * size[(axis + 2) % 3]
+    }
+
+    // Return (forces, potential energies, push on the walls) of the wall adhesion
+    // The push is the force on each wall along its outward normal, negative while it attracts
+    pub fn calculate_adhesion(&self, ps: &[Vec3]) -> (Vec<Vec3>, Vec<f32>, FaceImpulses) {
+        let (mut forces, mut potentials) = (Vec::new(), Vec::new());
+        let push = self.calculate_adhesion_into(ps, &mut forces, &mut potentials);
+        (forces, potentials, push)
+    }
+
+    // Same as calculate_adhesion, writing the forces and potential energies into reused buffers
+    pub fn calculate_adhesion_into(
+        &self,
+        ps: &[Vec3],
+        forces: &mut Vec<Vec3>,
+        potentials: &mut Vec<f32>,
+    ) -> FaceImpulses {
+        forces.clear();
+        forces.resize(ps.len(), Vec3::ZERO);
+        potentials.clear();
+        potentials.resize(ps.len(), 0.0);
+        let adhesion = match self.adhesion {
+            Some(adhesion) if adhesion.faces.iter().any(|&f| f) => adhesion,
+            _ => return FaceImpulses::default(),
+        };
+
+        (ps, &mut *forces, &mut *potentials)
+            .into_par_iter()
+            .map(|(&p, total_force, total_potential)| {
+                let mut force = Vec3::ZERO;
+                let mut potential = 0.0;
+                let mut push = FaceImpulses::default();
+                for face in Face::ALL.iter().filter(|&&face| adhesion.attracts(face)) {
+                    let axis = face.axis();
+                    let (z, inward) = if face.is_hi() {
+                        (self.hi[axis] - p[axis], -1.0)
+                    } else {
+                        (p[axis] - self.lo[axis], 1.0)
+                    };
+                    let (f, u) = adhesion.interaction(z);
+                    force[axis] += inward * f;
+                    potential += u;
+                    push.add(*face, f);
+                }
+                *total_force = force;
+                *total_potential = potential;
+                push
+            })
+            .sum()
+    }
+
+    // Bounce a body that has crossed a wall back inside the box
+    // The position is mirrored across the wall
+    // Reflecting walls flip the normal velocity, thermal walls draw a new velocity
+    // Periodic faces move the body to the image inside the box instead, see wrap
+    // Return the impulse delivered to each wall
+    // Does nothing unless the walls are hard
+    pub fn reflect(&self, pos: &mut Vec3, vel: &mut Vec3, mass: f32) -> FaceImpulses {
+        let mut impulse = FaceImpulses::default();
+        if !self.wall_model.is_hard() {
+            return impulse;
+        }
+        if self.wall_model.is_periodic() {
+            self.wrap(pos, vel);
+            return impulse;
+        }
+
+        let lo = self.lo_corner();
+        let hi = self.hi_corner();
+        for axis in 0..3 {
+            // direction pointing back into the box, zero if the wall wasn't crossed
+            let inward = if pos[axis] < lo[axis] {
+                pos[axis] = 2.0 * lo[axis] - pos[axis];
+                1.0
+            } else if pos[axis] > hi[axis] {
+                pos[axis] = 2.0 * hi[axis] - pos[axis];
+                -1.0
+            } else {
+                continue;
+            };
+
+            // only bodies still moving outwards hit the wall
+            let v_in = -inward * vel[axis];
+            if v_in <= 0.0 {
+                continue;
+            }
+            let face = Face::of(axis, inward < 0.0);
+            match self.wall_model {
+                WallModel::Thermal(temp) => {
+                    let v_out = Self::thermalize(vel, axis, temp, mass);
+                    vel[axis] = inward * v_out;
+                    impulse.add(face, mass * (v_in + v_out));
+                }
+                _ => {
+                    vel[axis] = inward * v_in;
+                    impulse.add(face, 2.0 * mass * v_in);
+                }
+            }
+        }
+
+        // a body far outside of a shrinking box could be mirrored past the opposite wall
+        *pos = pos.max(lo).min(hi);
+        impulse
+    }
+
+    // Separation r_i - r_j of the nearest images of two particles, as it is without periodic faces
+    // The separation across the y faces of sliding images is first shifted by their offset
+    // Only the nearest image interacts, which needs an interaction range below half of every side
+    pub fn minimum_image(&self, r: Vec3) -> Vec3 {
+        if !self.wall_model.is_periodic() {
+            return r;
+        }
+        let size = self.size();
+        let mut r = r;
+        let images_y = (r.y / size.y).round();
+        if let Some(shear) = self.lees_edwards {
+            r.x -= images_y * shear.offset;
+        }
+        r.y -= images_y * size.y;
+        r.x -= (r.x / size.x).round() * size.x;
+        r.z -= (r.z / size.z).round() * size.z;
+        r
+    }
+
+    // Velocity of the flow of the sliding images at a position, zero without them
+    pub fn streaming_velocity(&self, pos: Vec3) -> Vec3 {
+        match self.lees_edwards {
+            Some(shear) if self.wall_model.is_periodic() => {
+                Vec3::new(shear.rate * (pos.y - self.center().y), 0.0, 0.0)
+            }
+            _ => Vec3::ZERO,
+        }
+    }
+
+    // Slide the images of Lees-Edwards faces along for a time step
+    pub fn advance_shear(&mut self, dt: f32) {
+        let size = self.size();
+        if let Some(shear) = self.lees_edwards.as_mut() {
+            shear.offset = (shear.offset + shear.rate * size.y * dt).rem_euclid(size.x);
+        }
+    }
+
+    ///////////////////////////////////////
+    // Interactive utilities
+
+    // Grow every side by rate per unit of time, only the faces at the higher corner move
+    pub fn expand(&mut self, rate: f32, dt: f32) {
+        self.expand_faces(
+            FaceRates {
+                lo: Vec3::ZERO,
+                hi: Vec3::splat(rate),
+            },
+            dt,
+        );
+    }
+
+    // Move each face outwards at its own rate
+    // Faces of a side that would become shorter than the minimum stay where they are
+    pub fn expand_faces(&mut self, rates: FaceRates, dt: f32) {
+        let lo = self.lo - rates.lo * dt;
+        let hi = self.hi + rates.hi * dt;
+        let too_short = (hi - lo).cmplt(Vec3::splat(Self::MIN_LEN));
+
+        self.lo = Vec3::select(too_short, self.lo, lo);
+        self.hi = Vec3::select(too_short, self.hi, hi);
+    }
+    ///////////////////////////////////////
+    // Internal Utilities
+    //
+
+    // Move a body outside of a periodic box to its image inside
+    // Crossing a y face of sliding images also shifts it along x and changes its x velocity
+    // to that of the images it came from
+    // to be used internally by reflect
+    fn wrap(&self, pos: &mut Vec3, vel: &mut Vec3) {
+        let size = self.size();
+        let images = ((*pos - self.lo) / size).floor();
+        if images.y != 0.0 {
+            if let Some(shear) = self.lees_edwards {
+                pos.x -= images.y * shear.offset;
+                vel.x -= images.y * shear.rate * size.y;
+            }
+        }
+        for axis in 0..3 {
+            let length = size[axis];
+            pos[axis] = self.lo[axis] + (pos[axis] - self.lo[axis]).rem_euclid(length);
+            // rounding can leave a body that was just below the lower face at the upper one
+            pos[axis] = pos[axis].min(self.hi[axis]);
+        }
+    }
+
+    // Draw the velocity of a body leaving a wall at temperature temp
+    // The tangential components are resampled in place
+    // Return the speed away from the wall along axis
+    // to be used internally by reflect
+    fn thermalize(vel: &mut Vec3, axis: usize, temp: f32, mass: f32) -> f32 {
+        let mut rng = rand::thread_rng();
+        let sigma = physics::velocity_spread(temp) / mass.sqrt();
+        for tangent in (0..3).filter(|&i| i != axis) {
+            vel[tangent] = sigma * rng.sample::<f32, _>(StandardNormal);
+        }
+
+        // the flux through a wall weighs the normal speed by itself, giving a Rayleigh distribution
+        let u: f32 = rng.gen_range(f32::EPSILON..1.0);
+        sigma * (-2.0 * u.ln()).sqrt()
+    }
+
+    // To be used internally by calculate_force
+    fn calculate_force_single(&self, p: Vec3) -> Vec3 {
+        let bound_check = self.bound_check(p);
+        Self::DEFLECT_STR * bound_check
+    }
+
+    // return a Vec3 showing the directions
+    //   in which a position is out of bounds
+    //   point towards the inside of the box
+    // Example: If the particle is outside of the box
+    //   and crossing the higher x wall by 10 units, the return value
+    //   would be: (-10, 0, 0)
+    // to be used internally
+    fn bound_check(&self, pos: Vec3) -> Vec3 {
+        let lower_bound_check = Vec3::max(self.lo_corner() - pos, Vec3::ZERO); // zero out negative values
+        let upper_bound_check = Vec3::min(self.hi_corner() - pos, Vec3::ZERO); // zero out positive values
+
+        lower_bound_check + upper_bound_check
+    }
+}
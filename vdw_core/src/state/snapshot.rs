@@ -0,0 +1,143 @@
+// Snapshots of the particles written to RON files
+// Keeps what is needed to look at or restart from a configuration, the measurements are left out
+use super::error::FileError;
+use super::particle::Particle;
+use super::SimulationState;
+use glam::Quat;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+#[derive(Serialize, Deserialize)]
+pub struct ParticleRecord {
+    pub pos: [f32; 3],
+    pub vel: [f32; 3],
+    pub mass: f32,
+    pub radius: f32,
+    pub epsilon: f32,
+    pub charge: f32,
+    pub species: usize,
+    pub inert: bool,
+    #[serde(default)] // missing from older snapshots
+    pub fixed: bool,
+    #[serde(default = "unturned")] // missing from older snapshots, as are the angular velocities
+    pub orientation: [f32; 4],
+    #[serde(default)]
+    pub ang_vel: [f32; 3],
+    #[serde(default)] // none follows from the mass and radius
+    pub inertia: Option<[f32; 3]>,
+    #[serde(default)]
+    pub dipole: [f32; 3],
+}
+
+// Identity rotation as x, y, z, w
+fn unturned() -> [f32; 4] {
+    Quat::IDENTITY.into()
+}
+
+impl From<&Particle> for ParticleRecord {
+    fn from(particle: &Particle) -> Self {
+        Self {
+            pos: particle.get_pos().into(),
+            vel: particle.get_vel().into(),
+            mass: particle.get_mass(),
+            radius: particle.get_radius(),
+            epsilon: particle.get_epsilon(),
+            charge: particle.get_charge(),
+            species: particle.get_species(),
+            inert: particle.is_inert(),
+            fixed: particle.is_fixed(),
+            orientation: particle.get_orientation().into(),
+            ang_vel: particle.get_ang_vel().into(),
+            inertia: if particle.has_inertia() {
+                Some(particle.get_inertia().into())
+            } else {
+                None
+            },
+            dipole: particle.get_dipole().into(),
+        }
+    }
+}
+
+impl From<&ParticleRecord> for Particle {
+    fn from(record: &ParticleRecord) -> Self {
+        let [x, y, z] = record.pos;
+        let [vx, vy, vz] = record.vel;
+        let [wx, wy, wz] = record.ang_vel;
+        let [dx, dy, dz] = record.dipole;
+        // fixing zeroes the velocity, fixed particles were saved at rest anyway
+        let particle = Particle::new()
+            .set_fixed(record.fixed)
+            .set_pos(x, y, z)
+            .set_vel(vx, vy, vz)
+            .set_orientation(Quat::from(record.orientation))
+            .set_ang_vel(wx, wy, wz)
+            .set_mass(record.mass)
+            .set_radius(record.radius)
+            .set_epsilon(record.epsilon)
+            .set_charge(record.charge)
+            .set_dipole(dx, dy, dz)
+            .set_species(record.species)
+            .set_inert(record.inert);
+        match record.inertia {
+            Some([x, y, z]) => particle.set_inertia(x, y, z),
+            None => particle,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub time: f32,
+    pub steps: usize,
+    pub lo_corner: [f32; 3],
+    pub hi_corner: [f32; 3],
+    pub particles: Vec<ParticleRecord>,
+}
+
+impl Snapshot {
+    pub fn of(state: &SimulationState) -> Self {
+        Self {
+            time: state.time,
+            steps: state.steps,
+            lo_corner: state.bound.lo_corner().into(),
+            hi_corner: state.bound.hi_corner().into(),
+            particles: state.particles.iter().map(ParticleRecord::from).collect(),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), FileError> {
+        let path = path.as_ref();
+        let text =
+            ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).map_err(|err| {
+                FileError::Format {
+                    path: path.to_path_buf(),
+                    line: None,
+                    message: err.to_string(),
+                }
+            })?;
+        fs::write(path, text).map_err(|source| FileError::Write {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    // Read back a snapshot written by save
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, FileError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|source| FileError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        ron::de::from_str(&text).map_err(|err| FileError::Format {
+            path: path.to_path_buf(),
+            line: Some(err.position.line),
+            message: err.code.to_string(),
+        })
+    }
+
+    // The particles of the snapshot, to restart from with SimulationPrototype::set_particles
+    pub fn particles(&self) -> Vec<Particle> {
+        self.particles.iter().map(Particle::from).collect()
+    }
+}
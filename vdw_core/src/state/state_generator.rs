@@ -0,0 +1,416 @@
+use super::bond::Bond;
+use super::particle::Particle;
+use super::physics;
+use super::sim_space::Boundary;
+use super::SimulationPrototype;
+use glam::{Quat, Vec3};
+use itertools::iproduct;
+use rand::Rng;
+use rand_distr::StandardNormal;
+use serde::Deserialize;
+
+// Distributions the masses and radii of generated particles can be drawn from
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Distribution {
+    Uniform { lo: f32, hi: f32 },
+    Normal { mean: f32, std: f32 }, // truncated to positive values
+    Bimodal { a: f32, b: f32, fraction: f32 }, // b for that share of the particles, a for the rest
+}
+
+impl Distribution {
+    // draws of a normal distribution below zero are repeated at most this many times
+    const MAX_DRAWS: usize = 100;
+
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            Distribution::Uniform { lo, hi } => lo > 0.0 && hi >= lo,
+            Distribution::Normal { mean, std } => mean > 0.0 && std >= 0.0,
+            Distribution::Bimodal { a, b, fraction } => {
+                a > 0.0 && b > 0.0 && (0.0..=1.0).contains(&fraction)
+            }
+        }
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> f32 {
+        match *self {
+            Distribution::Uniform { lo, hi } => {
+                if hi > lo {
+                    rng.gen_range(lo..hi)
+                } else {
+                    lo
+                }
+            }
+            Distribution::Normal { mean, std } => (0..Self::MAX_DRAWS)
+                .map(|_| mean + std * rng.sample::<f32, _>(StandardNormal))
+                .find(|&value| value > 0.0)
+                .unwrap_or(mean),
+            Distribution::Bimodal { a, b, fraction } => {
+                if rng.gen::<f32>() < fraction {
+                    b
+                } else {
+                    a
+                }
+            }
+        }
+    }
+}
+
+pub trait Initialize: Sized {
+    fn get_bound(&self) -> Boundary;
+    fn set_particles(self, particles: Vec<Particle>) -> Self;
+    fn set_bonds(self, bonds: Vec<Bond>) -> Self;
+    // Share of the generated particles that are inert, set it before initializing
+    fn set_inert_fraction(self, fraction: f32) -> Self;
+    fn get_inert_fraction(&self) -> f32;
+    // Distributions of the masses and radii of the generated particles, set them before initializing
+    // Without one every particle gets mass 1 and the default radius
+    fn set_mass_distribution(self, distribution: Option<Distribution>) -> Self;
+    fn get_mass_distribution(&self) -> Option<Distribution>;
+    fn set_radius_distribution(self, distribution: Option<Distribution>) -> Self;
+    fn get_radius_distribution(&self) -> Option<Distribution>;
+    // Whether initializers delete particles that are too close to each other
+    // Not needed when the overlaps are relaxed away later
+    fn prunes_overlaps(&self) -> bool {
+        true
+    }
+    // Whether a position is taken by an obstacle, particles placed there are left out
+    // Chains keep all their particles, their bonds would break otherwise
+    fn is_blocked(&self, _pos: Vec3) -> bool {
+        false
+    }
+    fn initialize_spherical_cloud(self, n: usize, sigma: f32, temp: f32) -> Self {
+        let bound = self.get_bound();
+        let mut rng = rand::thread_rng();
+        let mut particles = vec![];
+
+        for _i in 0..n {
+            let mut pos = Vec3::new(
+                rng.sample(StandardNormal),
+                rng.sample(StandardNormal),
+                rng.sample(StandardNormal),
+            );
+            pos = (pos * sigma) + bound.center(); // control spread and move to center of boundary
+
+            // Trim invalid positions
+            pos = pos.min(bound.hi_corner());
+            pos = pos.max(bound.lo_corner());
+
+            particles.push(draw_particle(&self, &mut rng, pos, temp));
+        }
+        // obstacles are solid, nothing can start inside them
+        particles.retain(|p| !self.is_blocked(p.get_pos()));
+        let particles = if self.prunes_overlaps() {
+            prune(particles)
+        } else {
+            particles
+        };
+        self.set_particles(particles)
+    }
+
+    // Scatter n particles uniformly over the boundary
+    fn initialize_uniform(self, n: usize, temp: f32) -> Self {
+        self.initialize_uniform_with(n, temp, &mut rand::thread_rng())
+    }
+
+    // initialize_uniform drawing from the given random numbers, for reproducible setups
+    fn initialize_uniform_with(self, n: usize, temp: f32, rng: &mut impl Rng) -> Self {
+        let bound = self.get_bound();
+        let mut particles = vec![];
+
+        let lo = bound.lo_corner();
+        let hi = bound.hi_corner();
+        for _i in 0..n {
+            let pos = Vec3::new(
+                rng.gen_range(lo.x..hi.x),
+                rng.gen_range(lo.y..hi.y),
+                rng.gen_range(lo.z..hi.z),
+            );
+
+            particles.push(draw_particle(&self, rng, pos, temp));
+        }
+        // obstacles are solid, nothing can start inside them
+        particles.retain(|p| !self.is_blocked(p.get_pos()));
+        let particles = if self.prunes_overlaps() {
+            prune(particles)
+        } else {
+            particles
+        };
+        self.set_particles(particles)
+    }
+
+    // Lay out n_chains straight chains of chain_len particles along the x axis
+    // Chains are stacked on a square lattice in the y-z plane, centered in the boundary
+    // Chains that don't fit are folded instead, see fold_chains
+    // Consecutive particles in a chain are connected by springs of constant k and rest length bond_len
+    fn initialize_chains(
+        self,
+        n_chains: usize,
+        chain_len: usize,
+        bond_len: f32,
+        k: f32,
+        temp: f32,
+    ) -> Self {
+        let bound = self.get_bound();
+        let mut rng = rand::thread_rng();
+        let mut particles = vec![];
+        let mut bonds = vec![];
+
+        // chains are spaced further apart than consecutive particles within a chain
+        let spacing = 2.0 * bond_len;
+        let side = (n_chains as f32).sqrt().ceil() as usize;
+        let chain_length = bond_len * chain_len.saturating_sub(1) as f32;
+        let layout_width = spacing * side.saturating_sub(1) as f32;
+        let extent = Vec3::new(chain_length, layout_width, layout_width);
+        let origin = bound.center() - extent / 2.0;
+        let fits = extent.cmple(bound.size()).all();
+        let folded = if fits {
+            Vec::new()
+        } else {
+            fold_chains(&bound, n_chains * chain_len, bond_len)
+        };
+
+        for c in 0..n_chains {
+            let offset = Vec3::new(0.0, (c % side) as f32, (c / side) as f32) * spacing;
+            for m in 0..chain_len {
+                let pos = if fits {
+                    origin + offset + Vec3::new(m as f32 * bond_len, 0.0, 0.0)
+                } else {
+                    folded[c * chain_len + m]
+                };
+
+                if m > 0 {
+                    bonds.push(Bond::new(particles.len() - 1, particles.len(), k, bond_len));
+                }
+                particles.push(draw_particle(&self, &mut rng, pos, temp));
+            }
+        }
+        self.set_particles(particles).set_bonds(bonds)
+    }
+
+    // Place n particles on an fcc lattice with nearest neighbors spacing apart
+    // The lattice is a cube of unit cells centered in the boundary, the last cell may be partly filled
+    fn initialize_lattice(self, n: usize, spacing: f32, temp: f32) -> Self {
+        let bound = self.get_bound();
+        let mut rng = rand::thread_rng();
+        let mut particles = vec![];
+
+        // four sites per cubic cell, the face diagonal is twice the neighbor distance
+        let basis = [
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.5, 0.5, 0.0),
+            Vec3::new(0.5, 0.0, 0.5),
+            Vec3::new(0.0, 0.5, 0.5),
+        ];
+        let cell = spacing * 2f32.sqrt();
+        let side = (n as f32 / basis.len() as f32).cbrt().ceil() as usize;
+        let origin = bound.center() - Vec3::splat(cell * side as f32 - 0.5 * cell) / 2.0;
+
+        let sites = iproduct!(0..side, 0..side, 0..side, basis.iter());
+        for (i, j, k, offset) in sites.take(n) {
+            let mut pos = origin + (Vec3::new(i as f32, j as f32, k as f32) + *offset) * cell;
+
+            // Trim invalid positions
+            pos = pos.min(bound.hi_corner());
+            pos = pos.max(bound.lo_corner());
+
+            particles.push(draw_particle(&self, &mut rng, pos, temp));
+        }
+        particles.retain(|p| !self.is_blocked(p.get_pos()));
+        self.set_particles(particles)
+    }
+}
+
+// Sites of a path that winds through a cubic lattice filling the boundary, for chains too long
+// for the box: rows run back and forth along x, stepping over in y, then in z
+// Consecutive sites are one lattice step apart, so consecutive particles of a chain stay bonded
+// The step is bond_len, or shorter when the box has fewer sites than n, which compresses the
+// bonds; compile refuses bonds that start too compressed, see SimulationPrototype::compile
+fn fold_chains(bound: &Boundary, n: usize, bond_len: f32) -> Vec<Vec3> {
+    let size = bound.size();
+    let sites = |step: f32| {
+        let count = |length: f32| ((length / step) as usize).max(1);
+        (count(size.x), count(size.y), count(size.z))
+    };
+    let total = |(nx, ny, nz): (usize, usize, usize)| nx.saturating_mul(ny).saturating_mul(nz);
+    let mut step = bond_len;
+    while total(sites(step)) < n && step > 0.0 {
+        step *= 0.9;
+    }
+    let (nx, ny, nz) = sites(step);
+
+    // the lattice is centered in the boundary
+    let origin = bound.center() - Vec3::new(nx as f32, ny as f32, nz as f32) * step / 2.0
+        + Vec3::splat(step / 2.0);
+    (0..n)
+        .map(|t| {
+            let (layer, rest) = (t / (nx * ny), t % (nx * ny));
+            let row = layer * ny + rest / nx;
+            let x = if row % 2 == 0 {
+                rest % nx
+            } else {
+                nx - 1 - rest % nx
+            };
+            let y = if layer % 2 == 0 {
+                rest / nx
+            } else {
+                ny - 1 - rest / nx
+            };
+            origin + Vec3::new(x as f32, y as f32, layer as f32) * step
+        })
+        .collect()
+}
+
+impl Initialize for SimulationPrototype {
+    fn get_bound(&self) -> Boundary {
+        self.bound
+    }
+
+    fn set_particles(mut self, particles: Vec<Particle>) -> Self {
+        self.particles = particles;
+        self
+    }
+
+    fn set_bonds(mut self, bonds: Vec<Bond>) -> Self {
+        self.bonds = bonds;
+        self
+    }
+
+    fn set_inert_fraction(mut self, fraction: f32) -> Self {
+        self.inert_fraction = fraction;
+        self
+    }
+
+    fn get_inert_fraction(&self) -> f32 {
+        self.inert_fraction
+    }
+
+    fn set_mass_distribution(mut self, distribution: Option<Distribution>) -> Self {
+        self.mass_distribution = distribution;
+        self
+    }
+
+    fn get_mass_distribution(&self) -> Option<Distribution> {
+        self.mass_distribution
+    }
+
+    fn set_radius_distribution(mut self, distribution: Option<Distribution>) -> Self {
+        self.radius_distribution = distribution;
+        self
+    }
+
+    fn get_radius_distribution(&self) -> Option<Distribution> {
+        self.radius_distribution
+    }
+
+    fn prunes_overlaps(&self) -> bool {
+        self.get_minimization().is_none()
+    }
+
+    fn is_blocked(&self, pos: Vec3) -> bool {
+        self.get_obstacles()
+            .iter()
+            .any(|obstacle| obstacle.contains(pos))
+    }
+}
+
+// New particle at pos with its mass, radius and inertness drawn as set on the initializer
+// Velocities are scaled down by the square root of the mass, so every mass starts equally hot
+// The particle is turned at random, which only the orientation-aware interactions notice
+fn draw_particle<T: Initialize>(init: &T, rng: &mut impl Rng, pos: Vec3, temp: f32) -> Particle {
+    let mass = init.get_mass_distribution().map_or(1.0, |d| d.sample(rng));
+    let radius = init
+        .get_radius_distribution()
+        .map_or(physics::DEFAULT_RADIUS, |d| d.sample(rng));
+    let speed = temp / mass.sqrt();
+    Particle::new()
+        .set_mass(mass)
+        .set_radius(radius)
+        .set_inert(is_inert(rng, init.get_inert_fraction()))
+        .set_pos(pos.x, pos.y, pos.z)
+        .set_vel(
+            rng.sample::<f32, _>(StandardNormal) * speed,
+            rng.sample::<f32, _>(StandardNormal) * speed,
+            rng.sample::<f32, _>(StandardNormal) * speed,
+        )
+        .set_orientation(random_orientation(rng))
+}
+
+// Uniformly distributed rotation, a quaternion with normally distributed components points
+// the same way in every direction once normalized
+fn random_orientation(rng: &mut impl Rng) -> Quat {
+    Quat::from_xyzw(
+        rng.sample(StandardNormal),
+        rng.sample(StandardNormal),
+        rng.sample(StandardNormal),
+        rng.sample(StandardNormal),
+    )
+}
+
+// Draw whether a new particle is inert, fractions outside of [0, 1] saturate
+fn is_inert(rng: &mut impl Rng, fraction: f32) -> bool {
+    fraction > 0.0 && rng.gen::<f32>() < fraction
+}
+
+// Delete particles that are closer to each other than the sum of their radii
+fn prune(particles: Vec<Particle>) -> Vec<Particle> {
+    let mut ret: Vec<Particle> = vec![];
+    for p1 in particles.iter() {
+        let mut qual = true;
+        for p2 in ret.iter() {
+            let r = p1.get_pos() - p2.get_pos();
+            let rnorm = r.length();
+            if rnorm == 0.0 {
+                continue;
+            }
+            qual = qual && rnorm >= p1.get_radius() + p2.get_radius()
+        }
+        if qual {
+            ret.push(p1.clone());
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // internal helper function
+    fn steps(sites: &[Vec3]) -> Vec<f32> {
+        sites
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).length())
+            .collect()
+    }
+
+    #[test]
+    fn folded_sites_are_one_step_apart_inside_the_box() {
+        let bound = Boundary::new();
+        let sites = fold_chains(&bound, 300, 0.5);
+        assert_eq!(sites.len(), 300);
+        assert!(sites.iter().all(|&site| bound.contains_position(site)));
+        // 10 sites of 0.5 fit along each side of 5, every step is a full bond
+        for step in steps(&sites) {
+            assert!((step - 0.5).abs() < 1e-4, "step {}", step);
+        }
+    }
+
+    #[test]
+    fn crowded_boxes_shrink_the_step_evenly() {
+        let bound = Boundary::new();
+        let sites = fold_chains(&bound, 2000, 0.5);
+        let steps = steps(&sites);
+        let first = steps[0];
+        assert!(first < 0.5);
+        assert!(steps.iter().all(|step| (step - first).abs() < 1e-4));
+        assert!(sites.iter().all(|&site| bound.contains_position(site)));
+
+        // every site is taken once
+        for (i, a) in sites.iter().enumerate() {
+            for b in sites[i + 1..].iter() {
+                assert!((*a - *b).length() > 0.5 * first);
+            }
+        }
+    }
+}
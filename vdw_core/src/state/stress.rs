@@ -0,0 +1,126 @@
+// Stress tensor and shear viscosity
+// The stress tensor is sampled every step from the velocities and the pair virial
+// Shear viscosity follows from the autocorrelation of its off-diagonal entries (Green-Kubo)
+use crate::ring_buffer::RingBuffer;
+use glam::Mat3;
+
+// The off-diagonal entries, as (row, column)
+const SHEAR_COMPONENTS: [(usize, usize); 3] = [(0, 1), (0, 2), (1, 2)];
+
+// Recent stress tensors, one per step
+#[derive(Clone)]
+pub struct StressHistory {
+    pub latest: Mat3,
+    shear: [RingBuffer<f32>; 3], // xy, xz, yz, symmetrized
+    sample_dt: f32,              // time between samples, all samples share it
+}
+
+impl StressHistory {
+    pub fn with_capacity(capacity: usize, dt: f32) -> Self {
+        Self {
+            latest: Mat3::ZERO,
+            shear: [
+                RingBuffer::with_capacity(capacity),
+                RingBuffer::with_capacity(capacity),
+                RingBuffer::with_capacity(capacity),
+            ],
+            sample_dt: dt,
+        }
+    }
+
+    // Record the stress of a step
+    // The samples are cleared when the time step changes, they have to be evenly spaced
+    pub fn push(&mut self, stress: Mat3, dt: f32) {
+        if (dt - self.sample_dt).abs() > f32::EPSILON {
+            *self = Self::with_capacity(self.capacity(), dt);
+        }
+
+        self.latest = stress;
+        for (series, &(i, j)) in self.shear.iter_mut().zip(SHEAR_COMPONENTS.iter()) {
+            series.push(0.5 * (entry(stress, i, j) + entry(stress, j, i)));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.shear[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shear[0].len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shear[0].capacity()
+    }
+
+    pub fn get_sample_dt(&self) -> f32 {
+        self.sample_dt
+    }
+
+    // Pressure, a third of the trace
+    pub fn pressure(&self) -> f32 {
+        (self.latest.x_axis.x + self.latest.y_axis.y + self.latest.z_axis.z) / 3.0
+    }
+}
+
+fn entry(m: Mat3, row: usize, col: usize) -> f32 {
+    m.col(col)[row]
+}
+
+// Autocorrelation <x(0) x(t)> for lags 0..max_lag, averaged over all time origins
+pub fn autocorrelation(series: &[f32], max_lag: usize) -> Vec<f32> {
+    let max_lag = max_lag.min(series.len());
+    (0..max_lag)
+        .map(|lag| {
+            let pairs = series.len() - lag;
+            let sum: f64 = series
+                .iter()
+                .zip(series[lag..].iter())
+                .map(|(&a, &b)| a as f64 * b as f64)
+                .sum();
+            (sum / pairs as f64) as f32
+        })
+        .collect()
+}
+
+// Green-Kubo estimate of the shear viscosity
+// eta = V / kT * integral of <P_xy(0) P_xy(t)> dt, averaged over the three off-diagonal entries
+pub struct ShearViscosity {
+    pub sample_dt: f32,
+    pub autocorrelation: Vec<f32>,  // stress autocorrelation, by lag
+    pub running_integral: Vec<f32>, // viscosity integrated up to each lag
+    pub viscosity: f32,             // integral up to the largest lag
+}
+
+impl ShearViscosity {
+    pub fn from_stress(stress: &StressHistory, kt: f32, volume: f32, max_lag: usize) -> Self {
+        let mut acf = vec![0.0; max_lag.min(stress.len())];
+        for series in stress.shear.iter() {
+            for (sum, c) in acf
+                .iter_mut()
+                .zip(autocorrelation(&series.to_vec(), max_lag))
+            {
+                *sum += c / SHEAR_COMPONENTS.len() as f32;
+            }
+        }
+
+        // trapezoidal rule
+        let dt = stress.sample_dt;
+        let scale = if kt > 0.0 { volume / kt } else { 0.0 };
+        let mut integral = 0.0;
+        let mut running_integral = Vec::with_capacity(acf.len());
+        for (lag, &c) in acf.iter().enumerate() {
+            if lag > 0 {
+                integral += 0.5 * (acf[lag - 1] + c) * dt;
+            }
+            running_integral.push(integral * scale);
+        }
+
+        Self {
+            sample_dt: dt,
+            viscosity: running_integral.last().copied().unwrap_or(0.0),
+            autocorrelation: acf,
+            running_integral,
+        }
+    }
+}
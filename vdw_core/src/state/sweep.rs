@@ -0,0 +1,381 @@
+// Parameter sweeps
+// Runs simulations without rendering for every combination of temperature, density and seed of a
+// sweep specification, in parallel, and keeps the observables of every frame of every run
+// The base config supplies the force field, integrator and time step, the sweep replaces the box with
+// a cube holding the particles at the density, the thermostat and the particles
+// The seed decides the initial particles and the random kicks of stochastic integrators
+//
+// Example:
+//   config = "base.toml" # optional, relative to the sweep file
+//   temperatures = [0.5, 1.0, 1.5]
+//   densities = [0.1, 0.5]
+//   seeds = [1, 2, 3] # optional, one run per seed
+//   particles = 500 # optional, before overlapping ones are pruned
+//   inject_rate = 0.1 # optional, strength of the thermostat
+//   equilibration = 1.0 # optional, simulated time before the measurement
+//   duration = 1.0 # optional, simulated time measured
+use super::error::{ConfigError, ErrorKind, InvalidParamError, Problem, SimulationError};
+use super::observables::{Estimate, Moments};
+use super::physics;
+use super::state_generator::Initialize;
+use super::{SimulationPrototype, SimulationState};
+use crate::par::*;
+use itertools::iproduct;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::Deserialize;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct SweepSpec {
+    pub config: Option<PathBuf>,
+    pub temperatures: Vec<f32>,
+    pub densities: Vec<f32>,
+    pub seeds: Vec<u64>,
+    pub particles: usize,
+    pub inject_rate: f32,
+    pub equilibration: f32,
+    pub duration: f32,
+}
+
+impl Default for SweepSpec {
+    fn default() -> Self {
+        Self {
+            config: None,
+            temperatures: Vec::new(),
+            densities: Vec::new(),
+            seeds: vec![0],
+            particles: 500,
+            inject_rate: 0.1,
+            equilibration: 1.0,
+            duration: 1.0,
+        }
+    }
+}
+
+// One combination of the sweep
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SweepPoint {
+    pub temperature: f32,
+    pub density: f32,
+    pub seed: u64,
+}
+
+impl SweepPoint {
+    // Name of the results file of the run
+    pub fn file_name(&self) -> String {
+        format!(
+            "T{}_rho{}_seed{}.csv",
+            self.temperature, self.density, self.seed
+        )
+    }
+}
+
+impl SweepSpec {
+    pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+        toml::from_str(text).map_err(|err| ConfigError::parse(None, err))
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|err| ConfigError::io(path, err))?;
+        Self::from_toml(&text).map_err(|err| err.in_file(path))
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.problems().is_empty()
+    }
+
+    // What is wrong with the specification, empty if it's valid
+    pub fn problems(&self) -> Vec<Problem> {
+        let positive = |values: &[f32]| !values.is_empty() && values.iter().all(|&x| x > 0.0);
+        let mut problems = Vec::new();
+        let mut check = |valid: bool, message: String| {
+            if !valid {
+                problems.push(Problem::new(ErrorKind::Sweep, message));
+            }
+        };
+        check(
+            positive(&self.temperatures),
+            format!(
+                "temperatures = {:?}, give at least one, all above 0",
+                self.temperatures
+            ),
+        );
+        check(
+            positive(&self.densities),
+            format!(
+                "densities = {:?}, give at least one, all above 0",
+                self.densities
+            ),
+        );
+        check(
+            !self.seeds.is_empty(),
+            "seeds = [], give at least one seed or leave them out".to_string(),
+        );
+        check(
+            self.particles > 0,
+            "particles = 0, the runs need at least one particle".to_string(),
+        );
+        check(
+            self.inject_rate >= 0.0,
+            format!("inject_rate = {:?} < 0, use 0 or more", self.inject_rate),
+        );
+        check(
+            self.equilibration >= 0.0,
+            format!(
+                "equilibration = {:?} < 0, use 0 or more",
+                self.equilibration
+            ),
+        );
+        check(
+            self.duration > 0.0,
+            format!("duration = {:?} <= 0, measure for some time", self.duration),
+        );
+        problems
+    }
+
+    // Every combination, by temperature, then density, then seed
+    pub fn points(&self) -> Vec<SweepPoint> {
+        iproduct!(
+            self.temperatures.iter(),
+            self.densities.iter(),
+            self.seeds.iter()
+        )
+        .map(|(&temperature, &density, &seed)| SweepPoint {
+            temperature,
+            density,
+            seed,
+        })
+        .collect()
+    }
+
+    // The base prototype set up for one point of the sweep
+    pub fn prototype(&self, base: &SimulationPrototype, point: SweepPoint) -> SimulationPrototype {
+        // cubic box holding the particles at the density
+        let side = (self.particles as f32 / point.density).cbrt();
+        let mut rng = StdRng::seed_from_u64(point.seed);
+        base.clone()
+            .set_bound_x(side)
+            .set_bound_y(side)
+            .set_bound_z(side)
+            .set_target_temp(point.temperature)
+            .set_inject_rate(self.inject_rate)
+            .set_seed(point.seed)
+            .initialize_uniform_with(
+                self.particles,
+                physics::velocity_spread(point.temperature),
+                &mut rng,
+            )
+    }
+}
+
+// Observables at the end of a frame
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SweepFrame {
+    pub step: usize,
+    pub time: f32,
+    pub temperature: f32,
+    pub pressure: f32,
+    pub kinetic: f32,
+    pub potential: f32,
+}
+
+impl SweepFrame {
+    pub fn of(state: &SimulationState) -> Self {
+        Self {
+            step: state.steps,
+            time: state.time,
+            temperature: state.temperature(),
+            pressure: state.pressure.get_pressure(),
+            kinetic: state.energy.kinetic,
+            potential: state.energy.potential,
+        }
+    }
+}
+
+pub struct SweepRun {
+    pub point: SweepPoint,
+    pub particles: usize, // left after the overlapping ones were pruned
+    pub frames: Vec<SweepFrame>,
+    pub measured: usize, // the last measured frames, the ones before are equilibration
+}
+
+impl SweepRun {
+    // Mean of a quantity over the measured frames
+    // internal helper function
+    fn estimate(&self, quantity: impl Fn(&SweepFrame) -> f32) -> Estimate {
+        let mut moments = Moments::default();
+        for frame in self.frames[self.frames.len() - self.measured..].iter() {
+            moments.push(quantity(frame));
+        }
+        Estimate {
+            value: moments.mean(),
+            error: moments.mean_error(),
+        }
+    }
+
+    pub fn temperature(&self) -> Estimate {
+        self.estimate(|frame| frame.temperature)
+    }
+
+    pub fn pressure(&self) -> Estimate {
+        self.estimate(|frame| frame.pressure)
+    }
+
+    // Potential energy per particle
+    pub fn potential(&self) -> Estimate {
+        let n = self.particles.max(1) as f32;
+        self.estimate(|frame| frame.potential / n)
+    }
+
+    // Write the observables of every frame to a csv file
+    pub fn export_csv(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        writeln!(
+            file,
+            "step,time,temperature,pressure,kinetic,potential,measured"
+        )?;
+        let start = self.frames.len() - self.measured;
+        for (i, frame) in self.frames.iter().enumerate() {
+            writeln!(
+                file,
+                "{},{},{},{},{},{},{}",
+                frame.step,
+                frame.time,
+                frame.temperature,
+                frame.pressure,
+                frame.kinetic,
+                frame.potential,
+                (i >= start) as u8
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Equilibrate and measure one point of the sweep
+// Stops with an error as soon as the simulation diverges
+pub fn run_point(
+    spec: &SweepSpec,
+    base: &SimulationPrototype,
+    point: SweepPoint,
+) -> Result<SweepRun, SimulationError> {
+    let mut state = spec.prototype(base, point).compile()?.into_state();
+    let mut frames = Vec::new();
+    while state.time < spec.equilibration {
+        state.advance_frame();
+        state.check_divergence()?;
+        frames.push(SweepFrame::of(&state));
+    }
+    let start = frames.len();
+    let end = spec.equilibration + spec.duration;
+    while state.time < end {
+        state.advance_frame();
+        state.check_divergence()?;
+        frames.push(SweepFrame::of(&state));
+    }
+    Ok(SweepRun {
+        point,
+        particles: state.particles.len(),
+        measured: frames.len() - start,
+        frames,
+    })
+}
+
+// Run every point of the sweep, in parallel with the parallel feature
+// Points that fail don't stop the others, they are reported with their error
+pub fn run(spec: &SweepSpec, base: &SimulationPrototype) -> Result<SweepReport, InvalidParamError> {
+    let problems = spec.problems();
+    if !problems.is_empty() {
+        return Err(InvalidParamError::new(problems));
+    }
+    let results: Vec<(SweepPoint, Result<SweepRun, SimulationError>)> = spec
+        .points()
+        .into_par_iter()
+        .map(|point| (point, run_point(spec, base, point)))
+        .collect();
+    let mut report = SweepReport {
+        runs: Vec::new(),
+        failed: Vec::new(),
+    };
+    for (point, result) in results {
+        match result {
+            Ok(run) => report.runs.push(run),
+            Err(err) => report.failed.push((point, err)),
+        }
+    }
+    Ok(report)
+}
+
+pub struct SweepReport {
+    pub runs: Vec<SweepRun>, // in the order of SweepSpec::points
+    pub failed: Vec<(SweepPoint, SimulationError)>,
+}
+
+impl SweepReport {
+    // Write the measured means of every run to a csv file, naming the results file of each
+    pub fn export_summary(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        writeln!(
+            file,
+            "target_temp,density,seed,particles,temperature,temperature_error,pressure,pressure_error,potential,potential_error,file"
+        )?;
+        for run in self.runs.iter() {
+            let (temperature, pressure, potential) =
+                (run.temperature(), run.pressure(), run.potential());
+            writeln!(
+                file,
+                "{},{},{},{},{},{},{},{},{},{},{}",
+                run.point.temperature,
+                run.point.density,
+                run.point.seed,
+                run.particles,
+                temperature.value,
+                temperature.error,
+                pressure.value,
+                pressure.error,
+                potential.value,
+                potential.error,
+                run.point.file_name()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SweepReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Sweep of {} runs", self.runs.len())?;
+        writeln!(
+            f,
+            "{:>10} {:>10} {:>8} {:>8} {:>12} {:>12} {:>12}",
+            "target T", "density", "seed", "n", "T", "P", "U/n"
+        )?;
+        for run in self.runs.iter() {
+            writeln!(
+                f,
+                "{:>10.5} {:>10.5} {:>8} {:>8} {:>12.5} {:>12.5} {:>12.5}",
+                run.point.temperature,
+                run.point.density,
+                run.point.seed,
+                run.particles,
+                run.temperature().value,
+                run.pressure().value,
+                run.potential().value
+            )?;
+        }
+        for (point, err) in self.failed.iter() {
+            writeln!(
+                f,
+                "T = {}, density = {}, seed {} failed: {}",
+                point.temperature, point.density, point.seed, err
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+// Timing of the phases of a frame
+// Wall clock milliseconds spent in each phase are summed over a frame and kept for a rolling window of frames,
+// so slow phases show up before anyone reaches for a profiler
+// The simulation times its own phases, the app times copying the snapshot over and drawing the gui
+// Reading the clock a few times per step costs next to nothing, so the timings are on by default
+use crate::ring_buffer::RingBuffer;
+use std::time::Instant;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Phase {
+    Grid,        // sorting the particles into the grid squares
+    Forces,      // the pair loop over the grid
+    Integration, // everything else of a step: moving the particles, walls, thermostat, measurements
+    RenderSync,  // taking the snapshot and moving the rendered particles
+    Ui,          // drawing the gui windows
+}
+
+impl Phase {
+    pub const ALL: [Phase; 5] = [
+        Phase::Grid,
+        Phase::Forces,
+        Phase::Integration,
+        Phase::RenderSync,
+        Phase::Ui,
+    ];
+    pub const NAMES: [&'static str; 5] = ["grid", "forces", "integration", "render_sync", "ui"];
+
+    // The phases the simulation times itself
+    pub const SIMULATION: [Phase; 3] = [Phase::Grid, Phase::Forces, Phase::Integration];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .position(|&n| n == name)
+            .map(|i| Self::ALL[i])
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Grid => "Grid",
+            Phase::Forces => "Forces",
+            Phase::Integration => "Integration",
+            Phase::RenderSync => "Render Sync",
+            Phase::Ui => "UI",
+        }
+    }
+
+    // internal helper function
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+// Wall clock started at creation
+#[derive(Clone, Copy, Debug)]
+pub struct Stopwatch {
+    start: Instant,
+}
+
+impl Stopwatch {
+    pub fn start() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+
+    pub fn elapsed_ms(&self) -> f32 {
+        self.start.elapsed().as_secs_f32() * 1000.0
+    }
+}
+
+#[derive(Clone)]
+pub struct Timings {
+    pub enabled: bool,
+    current: [f32; 5], // milliseconds of each phase in the frame being timed
+    frames: [RingBuffer<f32>; 5], // milliseconds of each phase in the last frames
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Self::new(Self::WINDOW)
+    }
+}
+
+impl Timings {
+    // frames the breakdown is averaged over
+    pub const WINDOW: usize = 120;
+
+    pub fn new(window: usize) -> Self {
+        Self {
+            enabled: true,
+            current: [0.0; 5],
+            // a ring buffer holds one less entry than its capacity
+            frames: [(); 5].map(|_| RingBuffer::with_capacity(window + 1)),
+        }
+    }
+
+    // Add time spent in a phase to the current frame
+    pub fn record(&mut self, phase: Phase, ms: f32) {
+        if self.enabled {
+            self.current[phase.index()] += ms;
+        }
+    }
+
+    // Run f and add the time it took to a phase
+    pub fn time<T>(&mut self, phase: Phase, f: impl FnOnce() -> T) -> T {
+        let stopwatch = Stopwatch::start();
+        let result = f();
+        self.record(phase, stopwatch.elapsed_ms());
+        result
+    }
+
+    // Time spent in a phase so far in the current frame
+    pub fn current_ms(&self, phase: Phase) -> f32 {
+        self.current[phase.index()]
+    }
+
+    // Close the frame of the given phases and start over
+    pub fn finish_frame(&mut self, phases: &[Phase]) {
+        if !self.enabled {
+            return;
+        }
+        for phase in phases.iter() {
+            let i = phase.index();
+            self.frames[i].push(self.current[i]);
+            self.current[i] = 0.0;
+        }
+    }
+
+    // Drop the recorded frames, for example after switching the timings back on
+    pub fn clear(&mut self) {
+        self.current = [0.0; 5];
+        self.frames.iter_mut().for_each(|frames| {
+            frames.drain();
+        });
+    }
+
+    // Milliseconds per frame of a phase over the window, 0 before any frame was timed
+    pub fn mean_ms(&self, phase: Phase) -> f32 {
+        let frames = &self.frames[phase.index()];
+        if frames.is_empty() {
+            0.0
+        } else {
+            frames.iter().sum::<f32>() / frames.len() as f32
+        }
+    }
+
+    // Milliseconds of a phase in each frame of the window, oldest first
+    pub fn frames(&self, phase: Phase) -> impl Iterator<Item = &f32> {
+        self.frames[phase.index()].iter()
+    }
+}
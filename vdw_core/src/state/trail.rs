@@ -0,0 +1,87 @@
+// Particle trails
+// Remembers the recent positions of a subset of particles to draw their trajectories
+use super::SimulationState;
+use crate::ring_buffer::RingBuffer;
+use glam::Vec3;
+
+// Settings and recorded positions of the traced particles
+pub struct TrailView {
+    pub enabled: bool,
+    pub length: usize, // number of frames each trail covers
+    pub every: usize,  // every n-th particle is traced, starting at the first
+    last_steps: usize, // step count of the latest frame seen, to skip repeated frames
+    // particle index and its positions, old to new
+    traced: Vec<(usize, RingBuffer<Vec3>)>,
+}
+
+impl Default for TrailView {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            length: 100,
+            every: 50,
+            last_steps: 0,
+            traced: Vec::new(),
+        }
+    }
+}
+
+impl TrailView {
+    // Record the positions of the traced particles for a new frame
+    // Trails start over when the settings change or the simulation goes back in time
+    pub fn update(&mut self, state: &SimulationState) {
+        if !self.enabled {
+            self.traced.clear();
+            return;
+        }
+        if state.steps == self.last_steps {
+            return;
+        }
+
+        let every = self.every.max(1);
+        let indices = (0..state.particles.len()).step_by(every);
+        let capacity = self.length.max(1) + 1; // ring buffers hold one less than their capacity
+        let unchanged = self.traced.len() == indices.len()
+            && self
+                .traced
+                .first()
+                .map_or(true, |(_, trail)| trail.capacity() == capacity)
+            && self
+                .traced
+                .iter()
+                .zip(indices.clone())
+                .all(|(&(i, _), j)| i == j);
+        if !unchanged || state.steps < self.last_steps {
+            self.traced = indices
+                .map(|i| (i, RingBuffer::with_capacity(capacity)))
+                .collect();
+        }
+        self.last_steps = state.steps;
+
+        for (i, trail) in self.traced.iter_mut() {
+            trail.push(state.particles[*i].get_pos());
+        }
+    }
+
+    // Segments of all trails, each with its age as a fraction of the trail length
+    // 0 is the newest segment and 1 the oldest
+    pub fn segments(&self) -> Vec<(Vec3, Vec3, f32)> {
+        let length = self.length.max(1) as f32;
+        self.traced
+            .iter()
+            .flat_map(|(_, trail)| {
+                let positions = trail.to_vec();
+                let newest = positions.len();
+                positions
+                    .windows(2)
+                    .enumerate()
+                    .map(|(k, pair)| (pair[0], pair[1], (newest - 2 - k) as f32 / length))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    pub fn traced_count(&self) -> usize {
+        self.traced.len()
+    }
+}
@@ -0,0 +1,178 @@
+// Units of the measurements
+// The simulation has no units of its own: lengths are box units, particles weigh 1 by default
+// and temperature is the kinetic energy per particle, so 3/2 kT
+// Reduced units measure everything in the well depth epsilon, the size sigma and the mass m of the particles,
+// the numbers found in the Lennard-Jones literature. The argon units put real values on epsilon, sigma and m
+use super::physics::PotentialModel;
+use super::SimulationState;
+use serde::{Deserialize, Serialize};
+
+// Quantities that can be converted
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Quantity {
+    Length,
+    Time,
+    Energy,
+    Temperature,
+    Pressure,
+    Volume,
+    Density, // particles per volume
+}
+
+// Epsilon, sigma and m in simulation units
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ReducedUnits {
+    pub epsilon: f32,
+    pub sigma: f32,
+    pub mass: f32,
+}
+
+impl ReducedUnits {
+    pub fn new(potential: PotentialModel, mass: f32) -> Self {
+        Self {
+            epsilon: potential.well_depth(),
+            sigma: potential.length_scale(),
+            mass,
+        }
+    }
+
+    // Units of the pair potential and the mean particle mass of a state
+    pub fn of(state: &SimulationState) -> Self {
+        let mass = if state.particles.is_empty() {
+            1.0
+        } else {
+            state.particles.iter().map(|p| p.get_mass()).sum::<f32>() / state.particles.len() as f32
+        };
+        Self::new(state.get_potential(), mass)
+    }
+
+    // Time unit tau = sigma * sqrt(m / epsilon)
+    pub fn tau(&self) -> f32 {
+        self.sigma * (self.mass / self.epsilon).sqrt()
+    }
+
+    // Size of the reduced unit of a quantity in simulation units
+    // internal helper function
+    fn unit(&self, quantity: Quantity) -> f32 {
+        match quantity {
+            Quantity::Length => self.sigma,
+            Quantity::Time => self.tau(),
+            Quantity::Energy => self.epsilon,
+            // kT = epsilon is a temperature of 3/2 epsilon in simulation units
+            Quantity::Temperature => 1.5 * self.epsilon,
+            Quantity::Pressure => self.epsilon / self.sigma.powi(3),
+            Quantity::Volume => self.sigma.powi(3),
+            Quantity::Density => 1.0 / self.sigma.powi(3),
+        }
+    }
+
+    pub fn to_reduced(&self, quantity: Quantity, value: f32) -> f32 {
+        value / self.unit(quantity)
+    }
+
+    pub fn from_reduced(&self, quantity: Quantity, value: f32) -> f32 {
+        value * self.unit(quantity)
+    }
+}
+
+// Units the gui shows measurements in
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitSystem {
+    Simulation,
+    Reduced,
+    Argon,
+}
+
+impl Default for UnitSystem {
+    fn default() -> Self {
+        UnitSystem::Simulation
+    }
+}
+
+impl UnitSystem {
+    pub const ALL: [UnitSystem; 3] = [
+        UnitSystem::Simulation,
+        UnitSystem::Reduced,
+        UnitSystem::Argon,
+    ];
+    pub const NAMES: [&'static str; 3] = ["simulation", "reduced", "argon"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .position(|&n| n == name)
+            .map(|i| Self::ALL[i])
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            UnitSystem::Simulation => "Simulation",
+            UnitSystem::Reduced => "Reduced",
+            UnitSystem::Argon => "Argon",
+        }
+    }
+
+    // Value of a quantity given in simulation units
+    pub fn convert(&self, units: &ReducedUnits, quantity: Quantity, value: f32) -> f32 {
+        match self {
+            UnitSystem::Simulation => value,
+            UnitSystem::Reduced => units.to_reduced(quantity, value),
+            UnitSystem::Argon => units.to_reduced(quantity, value) * argon::unit(quantity),
+        }
+    }
+
+    pub fn symbol(&self, quantity: Quantity) -> &'static str {
+        match self {
+            UnitSystem::Simulation => "",
+            UnitSystem::Reduced => match quantity {
+                Quantity::Length => "σ",
+                Quantity::Time => "τ",
+                Quantity::Energy => "ε",
+                Quantity::Temperature => "ε/k",
+                Quantity::Pressure => "ε/σ³",
+                Quantity::Volume => "σ³",
+                Quantity::Density => "1/σ³",
+            },
+            UnitSystem::Argon => argon::symbol(quantity),
+        }
+    }
+
+    // Converted value followed by its unit
+    pub fn format(&self, units: &ReducedUnits, quantity: Quantity, value: f32) -> String {
+        let value = self.convert(units, quantity, value);
+        match self.symbol(quantity) {
+            "" => format!("{:.5}", value),
+            symbol => format!("{:.5} {}", value, symbol),
+        }
+    }
+}
+
+// Reduced units of argon, epsilon / k = 119.8 K, sigma = 0.3405 nm and m = 39.948 u
+mod argon {
+    use super::Quantity;
+
+    pub fn unit(quantity: Quantity) -> f32 {
+        match quantity {
+            Quantity::Length => 0.3405,
+            Quantity::Time => 2.156,
+            Quantity::Energy => 0.9961,
+            Quantity::Temperature => 119.8,
+            Quantity::Pressure => 41.90,
+            Quantity::Volume => 0.03948,
+            Quantity::Density => 25.33,
+        }
+    }
+
+    pub fn symbol(quantity: Quantity) -> &'static str {
+        match quantity {
+            Quantity::Length => "nm",
+            Quantity::Time => "ps",
+            Quantity::Energy => "kJ/mol",
+            Quantity::Temperature => "K",
+            Quantity::Pressure => "MPa",
+            Quantity::Volume => "nm³",
+            Quantity::Density => "1/nm³",
+        }
+    }
+}
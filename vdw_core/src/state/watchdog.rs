@@ -0,0 +1,183 @@
+// Watchdog for diverging simulations
+// After every frame the particles are checked for positions and velocities no stable run reaches:
+// NaN or infinite values, steps longer than a fraction of the interaction range, or positions
+// farther outside the box than its size
+// The gui pauses the simulation when it diverges, reports why and offers the last good state
+use super::error::DivergenceError;
+use super::SimulationState;
+use glam::Vec3;
+
+// A stable step moves a particle by at most this fraction of the interaction range
+pub const MAX_STEP_FRACTION: f32 = 0.5;
+
+// Pairs closer than this fraction of the sum of their radii count as overlapping
+const OVERLAP_FRACTION: f32 = 0.5;
+
+// Number of diverged particles looked at for overlaps
+const OVERLAP_SAMPLE: usize = 10;
+
+// Likely cause of a divergence
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Cause {
+    TimeStep, // the forces change too much within a step
+    Overlap,  // particles on top of each other, from the initializer or from an edit
+}
+
+impl Cause {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Cause::TimeStep => "The time step is likely too large for the forces, try a smaller dt",
+            Cause::Overlap => {
+                "Particles likely started on top of each other and their repulsion blew up, \
+                 try another initializer or relax the setup first"
+            }
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Divergence {
+    pub steps: usize, // step count of the frame it was found in
+    pub time: f32,
+    pub particles: Vec<usize>, // diverged particles, in index order
+    pub non_finite: bool,      // whether some of them are NaN or infinite, not just fast
+    pub cause: Cause,
+}
+
+// Particles whose position or velocity no stable run reaches
+pub fn diverged_particles(state: &SimulationState) -> Vec<usize> {
+    let max_speed = MAX_STEP_FRACTION * state.get_grid_range() / state.dt;
+    let (center, size) = (state.bound.center(), state.bound.size());
+    let max_offset = 0.5 * size + Vec3::splat(size.max_element());
+    state
+        .particles
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| {
+            let (pos, vel) = (p.get_pos(), p.get_vel());
+            !pos.is_finite()
+                || !vel.is_finite()
+                || vel.length() > max_speed
+                || (pos - center).abs().cmpgt(max_offset).any()
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+impl Divergence {
+    // Describe the diverged particles of a state
+    // last_good is the latest state seen before it diverged, without one a run that blew up
+    // in its first frame is put down to overlaps
+    pub fn of(
+        state: &SimulationState,
+        particles: Vec<usize>,
+        last_good: Option<&SimulationState>,
+    ) -> Self {
+        let non_finite = particles.iter().any(|&i| {
+            let p = &state.particles[i];
+            !p.get_pos().is_finite() || !p.get_vel().is_finite()
+        });
+        let cause = match last_good {
+            Some(good) if overlapping(good, &particles) => Cause::Overlap,
+            Some(_) => Cause::TimeStep,
+            None if state.steps <= state.steps_per_frame => Cause::Overlap,
+            None => Cause::TimeStep,
+        };
+        Self {
+            steps: state.steps,
+            time: state.time,
+            particles,
+            non_finite,
+            cause,
+        }
+    }
+}
+
+impl SimulationState {
+    // Err once some particles diverged, for runs without the gui
+    pub fn check_divergence(&self) -> Result<(), DivergenceError> {
+        let particles = diverged_particles(self);
+        if particles.is_empty() {
+            Ok(())
+        } else {
+            Err(Divergence::of(self, particles, None).into())
+        }
+    }
+}
+
+// Whether some of the particles overlapped another one in the state
+// internal helper function
+fn overlapping(state: &SimulationState, particles: &[usize]) -> bool {
+    particles
+        .iter()
+        .take(OVERLAP_SAMPLE)
+        .filter(|&&i| i < state.particles.len())
+        .any(|&i| {
+            let p = &state.particles[i];
+            state.particles.iter().enumerate().any(|(j, other)| {
+                let contact = OVERLAP_FRACTION * (p.get_radius() + other.get_radius());
+                j != i && (p.get_pos() - other.get_pos()).length_squared() < contact * contact
+            })
+        })
+}
+
+// Looks at every frame the gui gets and keeps a copy of a good state every few frames
+pub struct Watchdog {
+    pub enabled: bool,
+    pub interval: usize, // frames between copies of the last good state
+    pub divergence: Option<Divergence>,
+    frames: usize,
+    last_steps: usize, // step count of the latest frame seen, to skip repeated frames
+    last_good: Option<SimulationState>,
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval: 30,
+            divergence: None,
+            frames: 0,
+            last_steps: usize::MAX, // so the state before the first step is checked too
+            last_good: None,
+        }
+    }
+}
+
+impl Watchdog {
+    // Check the latest state, call once per frame
+    // Returns true on the frame a divergence is found, nothing is checked until it's dismissed
+    pub fn update(&mut self, state: &SimulationState) -> bool {
+        if !self.enabled || self.divergence.is_some() || state.steps == self.last_steps {
+            return false;
+        }
+        self.last_steps = state.steps;
+
+        let particles = diverged_particles(state);
+        if particles.is_empty() {
+            if self.frames % self.interval.max(1) == 0 {
+                self.last_good = Some(state.clone());
+            }
+            self.frames += 1;
+            return false;
+        }
+        self.divergence = Some(Divergence::of(state, particles, self.last_good.as_ref()));
+        true
+    }
+
+    pub fn last_good(&self) -> Option<&SimulationState> {
+        self.last_good.as_ref()
+    }
+
+    // Forget the divergence and keep watching
+    pub fn dismiss(&mut self) {
+        self.divergence = None;
+    }
+
+    // Forget the divergence and take out the last good state
+    pub fn rewind(&mut self) -> Option<SimulationState> {
+        self.divergence = None;
+        self.frames = 1; // the restored state is the latest copy
+        self.last_good.clone()
+    }
+}
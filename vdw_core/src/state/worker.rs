@@ -0,0 +1,175 @@
+// Runs the simulation on its own thread so slow steps don't stall rendering
+// The worker owns the state, bevy systems see the latest snapshot of it
+// Edits made by the gui are queued as commands and applied between frames
+use super::logger::ObservableLogger;
+use super::replica::ReplicaExchange;
+use super::SimulationState;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// An edit of the simulation state
+// Commands are kept until the worker confirms them so they can be replayed onto older snapshots
+pub type Command = Arc<dyn Fn(&mut SimulationState) + Send + Sync>;
+
+// Latest published state, with the number of commands applied to it
+type Snapshot = (usize, SimulationState);
+
+pub struct SimulationWorker {
+    commands: Mutex<Sender<Command>>,
+    latest: Arc<Mutex<Option<Snapshot>>>,
+    sent: usize,                    // number of commands sent so far
+    pending: Vec<(usize, Command)>, // commands not yet confirmed by a snapshot, with their number
+}
+
+impl SimulationWorker {
+    // The worker never publishes more than one frame per interval
+    const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+    // Start a worker thread that owns state, the logger records every frame it steps
+    // The thread stops when the worker is dropped
+    pub fn spawn(state: SimulationState, logger: ObservableLogger) -> Self {
+        Self::start(state, None, logger)
+    }
+
+    // Start a worker stepping all replicas of the exchange
+    // Snapshots, edits and the logger only see the selected replica
+    pub fn spawn_replicas(exchange: ReplicaExchange, logger: ObservableLogger) -> Self {
+        let state = exchange.get_selected().clone();
+        Self::start(state, Some(exchange), logger)
+    }
+
+    // internal helper function
+    fn start(
+        state: SimulationState,
+        replicas: Option<ReplicaExchange>,
+        logger: ObservableLogger,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let latest = Arc::new(Mutex::new(None));
+        let stepper = Stepper {
+            state,
+            replicas,
+            logger,
+            commands: receiver,
+            latest: Arc::clone(&latest),
+            applied: 0,
+        };
+
+        thread::spawn(move || run(stepper));
+
+        Self {
+            commands: Mutex::new(sender),
+            latest,
+            sent: 0,
+            pending: Vec::new(),
+        }
+    }
+
+    // Apply an edit to the local snapshot right away and queue it for the worker
+    pub fn edit(
+        &mut self,
+        state: &mut SimulationState,
+        edit: impl Fn(&mut SimulationState) + Send + Sync + 'static,
+    ) {
+        let command: Command = Arc::new(edit);
+        command(state);
+
+        self.sent += 1;
+        self.pending.push((self.sent, Arc::clone(&command)));
+        // a send error means the worker is gone, there's nothing left to edit
+        let _ = self.commands.lock().unwrap().send(command);
+    }
+
+    // Take the state published since the last call, if any
+    // Edits the worker hasn't applied yet are replayed onto it
+    pub fn take_snapshot(&mut self) -> Option<SimulationState> {
+        let (applied, mut state) = self.latest.lock().unwrap().take()?;
+
+        self.pending.retain(|(number, _)| *number > applied);
+        for (_, command) in self.pending.iter() {
+            command(&mut state);
+        }
+        Some(state)
+    }
+}
+
+// The state with everything needed to step it
+struct Stepper {
+    state: SimulationState,
+    replicas: Option<ReplicaExchange>, // the state is the selected replica, stepped along with the others
+    logger: ObservableLogger,
+    commands: Receiver<Command>,
+    latest: Arc<Mutex<Option<Snapshot>>>,
+    applied: usize, // number of commands applied to the state
+}
+
+impl Stepper {
+    // Apply the queued commands, step one frame and publish it
+    // Returns false once the worker is gone
+    fn frame(&mut self) -> bool {
+        loop {
+            match self.commands.try_recv() {
+                Ok(command) => {
+                    command(&mut self.state);
+                    self.applied += 1;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return false,
+            }
+        }
+
+        if !self.state.paused {
+            match self.replicas.as_mut() {
+                // the selected replica goes back in for the frame, edits made to it are kept
+                Some(exchange) => {
+                    std::mem::swap(&mut self.state, &mut exchange.replicas[exchange.selected]);
+                    exchange.advance_frame();
+                    std::mem::swap(&mut self.state, &mut exchange.replicas[exchange.selected]);
+                }
+                None => advance_frame(&mut self.state),
+            }
+            self.logger.log_frame(&self.state);
+        }
+        *self.latest.lock().unwrap() = Some((self.applied, self.state.clone()));
+        true
+    }
+}
+
+// Loop of the worker thread
+fn run(mut stepper: Stepper) {
+    loop {
+        let frame_start = Instant::now();
+        if !stepper.frame() {
+            return;
+        }
+        if let Some(rest) = SimulationWorker::FRAME_INTERVAL.checked_sub(frame_start.elapsed()) {
+            thread::sleep(rest);
+        }
+    }
+}
+
+// Advance one animation frame
+// Multiple simulation steps are executed in one animation frame
+fn advance_frame(state: &mut SimulationState) {
+    // Step simulation
+    if state.frame_budget.enabled {
+        let budget = Duration::from_secs_f32(state.frame_budget.ms_per_frame.max(0.0) / 1000.0);
+        state.advance_frame_within(budget);
+    } else {
+        state.advance_frame();
+    }
+
+    // Stablize pressure if applicable
+    if state.pressure_pinned.is_pinned {
+        let current_pressure = state.history.pressure.peak().unwrap_or(&0.0);
+        let delta = current_pressure - state.pressure_pinned.at_value;
+
+        state.bound_rate = delta;
+    }
+    // Reset bound_rate on toggle off
+    else if state.pressure_pinned.previous_state {
+        state.bound_rate = 0.0;
+    }
+}
@@ -0,0 +1,322 @@
+// Walls, obstacles and fixed particles: what keeps the particles in place and the forces it takes
+// Run with `cargo test -p vdw_core`
+mod common;
+
+use common::{assert_close, compile, lattice};
+use glam::Vec3;
+use vdw_core::state::error::ErrorKind;
+use vdw_core::state::external_field::Gravity;
+use vdw_core::state::integrator::Integrator;
+use vdw_core::state::obstacle::Obstacle;
+use vdw_core::state::particle::Particle;
+use vdw_core::state::sim_space::{Boundary, Face, Grid, GridWorkspace, LeesEdwards, WallModel};
+use vdw_core::state::SimulationPrototype;
+
+fn periodic_box() -> Boundary {
+    let mut bound = Boundary::new();
+    bound.wall_model = WallModel::Periodic;
+    bound
+}
+
+#[test]
+fn edge_pushes_each_face_by_its_normal_component() {
+    // a particle pressed into the edge between the x and y floors
+    let bound = Boundary::new();
+    let (forces, push) = bound.calculate_force(&[Vec3::new(-0.01, -0.02, 2.5)]);
+    let force = forces[0];
+    assert!(force.x > 0.0 && force.y > 0.0, "wall force {:?}", force);
+
+    assert_close(push.get(Face::XLo), force.x, 1e-6, "push on x lo");
+    assert_close(push.get(Face::YLo), force.y, 1e-6, "push on y lo");
+    for &face in [Face::XHi, Face::YHi, Face::ZLo, Face::ZHi].iter() {
+        assert!(
+            push.get(face).abs() < 1e-12,
+            "push on {:?}: {}",
+            face,
+            push.get(face)
+        );
+    }
+    // the faces share the sum of the components, not the length of the force
+    assert_close(push.total(), force.x + force.y, 1e-6, "total push");
+}
+
+#[test]
+fn gravity_loads_the_floor_more_than_the_ceiling() {
+    let prototype = SimulationPrototype::new()
+        .set_dt(0.002)
+        .set_pressure_sampling_period(0.5)
+        .add_external_field(Box::new(Gravity::new(Vec3::new(0.0, -10.0, 0.0))))
+        .set_particles(lattice(3, 1.2, 1.0));
+    let mut state = compile(prototype);
+
+    for _ in 0..1500 {
+        state.step();
+        state.commit_pressure();
+    }
+    let floor = state.face_pressure(Face::YLo);
+    let ceiling = state.face_pressure(Face::YHi);
+    assert!(
+        floor > ceiling,
+        "floor pressure {} is not above the ceiling's {}",
+        floor,
+        ceiling
+    );
+}
+
+#[test]
+fn hard_walls_contain_particles() {
+    for &wall_model in [WallModel::HardReflect, WallModel::Thermal(1.0)].iter() {
+        // a hot sparse gas reaches the walls many times over the run
+        let prototype = SimulationPrototype::new()
+            .set_wall_model(wall_model)
+            .set_dt(0.001)
+            .set_particles(lattice(4, 1.0, 20.0));
+        let mut state = compile(prototype);
+
+        for _ in 0..2000 {
+            state.step();
+            for particle in state.particles.iter() {
+                assert!(
+                    state.bound.contains_position(particle.get_pos()),
+                    "particle escaped to {:?} with {} walls",
+                    particle.get_pos(),
+                    wall_model.name()
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn hard_obstacles_keep_particles_out() {
+    let obstacles = [
+        Obstacle::Sphere {
+            center: Vec3::splat(2.5),
+            radius: 0.8,
+        },
+        Obstacle::Box {
+            lo: Vec3::splat(0.2),
+            hi: Vec3::new(0.8, 4.8, 4.8),
+        },
+    ];
+    let prototype = obstacles
+        .iter()
+        .fold(SimulationPrototype::new(), |prototype, &obstacle| {
+            prototype.add_obstacle(obstacle)
+        })
+        .set_wall_model(WallModel::HardReflect)
+        .set_dt(0.001)
+        // slow enough that no particle crosses the thin box within a step
+        .set_particles(lattice(4, 1.0, 5.0));
+    let mut state = compile(prototype);
+
+    for _ in 0..2000 {
+        state.step();
+        for particle in state.particles.iter() {
+            for obstacle in obstacles.iter() {
+                assert!(
+                    !obstacle.contains(particle.get_pos()),
+                    "particle entered the {} at {:?}",
+                    obstacle.name(),
+                    particle.get_pos()
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn fixed_particles_exert_forces_but_never_move() {
+    let mut state = compile(
+        SimulationPrototype::new()
+            .set_particles(vec![
+                Particle::new().set_pos(1.0, 1.0, 1.0),
+                Particle::new().set_pos(1.2, 1.0, 1.0),
+            ])
+            .set_fixed_region(Vec3::new(0.9, 0.9, 0.9), Vec3::new(1.1, 1.1, 1.1)),
+    );
+    assert!(state.particles[0].is_fixed());
+    assert!(!state.particles[1].is_fixed());
+
+    state.advance_frame();
+    assert_eq!(state.particles[0].get_pos(), Vec3::new(1.0, 1.0, 1.0));
+    assert_eq!(state.particles[0].get_vel(), Vec3::ZERO);
+    // the well pulls the mobile particle towards the fixed one
+    assert!(state.particles[1].get_vel().x < 0.0);
+    // the temperature only counts the particle that can move
+    assert_close(
+        state.temperature(),
+        state.energy.kinetic,
+        1e-6,
+        "temperature",
+    );
+}
+
+#[test]
+fn periodic_faces_wrap_particles_around() {
+    // a hot sparse gas crosses the faces many times over the run
+    let prototype = SimulationPrototype::new()
+        .set_wall_model(WallModel::Periodic)
+        .set_dt(0.001)
+        .set_particles(lattice(4, 1.0, 20.0));
+    let mut state = compile(prototype);
+    let momentum = |state: &vdw_core::state::SimulationState| {
+        state
+            .particles
+            .iter()
+            .fold(Vec3::ZERO, |acc, p| acc + p.get_mass() * p.get_vel())
+    };
+    let before = momentum(&state);
+
+    for _ in 0..2000 {
+        state.step();
+        for particle in state.particles.iter() {
+            assert!(
+                state.bound.contains_position(particle.get_pos()),
+                "particle escaped to {:?} through a periodic face",
+                particle.get_pos()
+            );
+        }
+    }
+    // without walls nothing takes momentum from the particles
+    assert!(
+        (momentum(&state) - before).length() < 1e-2 * state.particles.len() as f32,
+        "momentum went from {:?} to {:?}",
+        before,
+        momentum(&state)
+    );
+    // the faces are loaded with the pressure of the stress tensor instead
+    state.commit_pressure();
+    assert!(
+        state.face_pressure(Face::XLo) > 0.0,
+        "no pressure on a periodic face of a hot gas"
+    );
+}
+
+#[test]
+fn periodic_pairs_meet_across_the_faces() {
+    let bound = periodic_box();
+    let grid = Grid::with_cutoff(1.0);
+    let particles = [
+        Vec3::new(0.1, 2.5, 2.5),
+        Vec3::new(4.8, 2.5, 2.5), // 0.3 from the first through the x faces
+        Vec3::new(2.5, 2.5, 2.5), // too far from the rest
+        Vec3::new(2.5, 4.9, 0.1),
+        Vec3::new(2.5, 0.2, 4.8), // 0.3 from the one before through both the y and z faces
+    ];
+    assert!(bound
+        .minimum_image(particles[0] - particles[1])
+        .abs_diff_eq(Vec3::new(0.3, 0.0, 0.0), 1e-5));
+
+    let mut workspace = GridWorkspace::default();
+    workspace.anchor_to(bound);
+    let mut pairs = Vec::new();
+    grid.find_pairs_with(&particles, &mut workspace, &mut pairs);
+    pairs.sort_unstable();
+    assert_eq!(pairs, vec![(0, 1), (3, 4)]);
+
+    // the box has no walls to find pairs at, without the anchor the faces are far apart
+    assert!(grid.find_pairs(&particles).is_empty());
+}
+
+#[test]
+fn periodic_grid_matches_brute_force() {
+    // a dense lattice filling the box, so many pairs straddle the periodic faces
+    let particles: Vec<Particle> = lattice(12, 0.4, 0.0)
+        .into_iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let pos = p.get_pos() + 0.05 * Vec3::new((i as f32).sin(), (i as f32).cos(), 0.3);
+            p.set_pos(pos.x, pos.y, pos.z)
+        })
+        .collect();
+    for &shear in [None, Some(0.8)].iter() {
+        let mut prototype = SimulationPrototype::new()
+            .set_wall_model(WallModel::Periodic)
+            .set_grid_unit_size(0.5)
+            .set_grid_reach(2)
+            .set_particles(particles.clone());
+        if let Some(rate) = shear {
+            prototype = prototype.set_lees_edwards(rate);
+        }
+        let mut state = compile(prototype);
+        // slide the images part of the way along x
+        state.bound.lees_edwards = state.bound.lees_edwards.map(|mut le| {
+            le.offset = 1.3;
+            le
+        });
+
+        let sample: Vec<usize> = (0..state.particles.len()).step_by(7).collect();
+        let report = state.check_neighbors(&sample);
+        assert!(report.is_consistent(), "shear {:?}: {:?}", shear, report);
+    }
+}
+
+#[test]
+fn sliding_images_shift_particles_crossing_the_y_faces() {
+    let mut bound = periodic_box();
+    bound.lees_edwards = Some(LeesEdwards {
+        rate: 0.5,
+        offset: 1.0,
+    });
+
+    // leaving through the top it comes back at the bottom, behind by the offset and slower
+    // by the velocity of the images above
+    let mut pos = Vec3::new(2.0, 5.1, 2.5);
+    let mut vel = Vec3::new(0.2, 1.0, 0.0);
+    let impulse = bound.reflect(&mut pos, &mut vel, 1.0);
+    assert!(pos.abs_diff_eq(Vec3::new(1.0, 0.1, 2.5), 1e-5), "{:?}", pos);
+    assert!(
+        vel.abs_diff_eq(Vec3::new(-2.3, 1.0, 0.0), 1e-5),
+        "{:?}",
+        vel
+    );
+    assert!(impulse.total().abs() < 1e-12);
+
+    // a pair across the y faces meets the image below, held back by the offset along x
+    let r = bound.minimum_image(Vec3::new(2.0, 0.2, 2.5) - Vec3::new(1.2, 4.8, 2.5));
+    assert!(r.abs_diff_eq(Vec3::new(1.8, 0.4, 0.0), 1e-5), "{:?}", r);
+    assert_close(
+        bound.streaming_velocity(Vec3::new(0.0, 4.5, 0.0)).x,
+        1.0,
+        1e-6,
+        "flow velocity",
+    );
+
+    // the images slide along at rate * length_y, wrapping around the length of x
+    bound.advance_shear(5.0);
+    assert_close(
+        bound.lees_edwards.unwrap().offset,
+        (1.0 + 0.5 * 5.0 * 5.0) % 5.0,
+        1e-5,
+        "image offset",
+    );
+}
+
+#[test]
+fn periodic_setups_are_checked() {
+    // the cutoff reaches more than one image
+    let err = SimulationPrototype::new()
+        .set_wall_model(WallModel::Periodic)
+        .set_cutoff_radius(3.0)
+        .compile()
+        .err()
+        .expect("a cutoff beyond half the box is invalid");
+    assert!(err.contains(ErrorKind::Bound));
+
+    // sliding images need periodic faces and velocities
+    let err = SimulationPrototype::new()
+        .set_lees_edwards(0.5)
+        .compile()
+        .err()
+        .expect("sliding walls are invalid");
+    assert!(err.contains(ErrorKind::Bound));
+    let err = SimulationPrototype::new()
+        .set_wall_model(WallModel::Periodic)
+        .set_lees_edwards(0.5)
+        .set_integrator(Integrator::Brownian { gamma: 1.0 })
+        .compile()
+        .err()
+        .expect("sliding images of overdamped particles are invalid");
+    assert!(err.contains(ErrorKind::Bound));
+}
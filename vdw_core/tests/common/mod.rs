@@ -0,0 +1,58 @@
+// Helpers shared by the integration tests
+// Every test file uses some of them, the rest would be reported unused
+#![allow(dead_code)]
+use vdw_core::state::particle::Particle;
+use vdw_core::state::{SimulationPrototype, SimulationState};
+
+// Relative error with a floor, so values close to zero are compared absolutely
+pub fn assert_close(actual: f32, expected: f32, tolerance: f32, what: &str) {
+    let error = (actual - expected).abs() / expected.abs().max(1.0);
+    assert!(
+        error < tolerance,
+        "{}: got {}, expected {} (relative error {})",
+        what,
+        actual,
+        expected,
+        error
+    );
+}
+
+// A small cubic lattice around the center of the default box, with a deterministic velocity pattern
+pub fn lattice(n: usize, spacing: f32, speed: f32) -> Vec<Particle> {
+    let mut particles = Vec::new();
+    let offset = 2.5 - 0.5 * spacing * (n - 1) as f32;
+    for i in 0..n {
+        for j in 0..n {
+            for k in 0..n {
+                let id = (i * n + j) * n + k;
+                let phase = id as f32 * 2.399; // golden angle, spreads the directions
+                particles.push(
+                    Particle::new()
+                        .set_pos(
+                            offset + spacing * i as f32,
+                            offset + spacing * j as f32,
+                            offset + spacing * k as f32,
+                        )
+                        .set_vel(
+                            speed * phase.cos(),
+                            speed * phase.sin(),
+                            speed * (1.7 * phase).cos(),
+                        ),
+                );
+            }
+        }
+    }
+    particles
+}
+
+pub fn compile(prototype: SimulationPrototype) -> SimulationState {
+    prototype
+        .compile()
+        .unwrap_or_else(|err| panic!("invalid test setup: {}", err))
+        .into_state()
+}
+
+pub fn total_energy(state: &mut SimulationState) -> f32 {
+    state.recalculate_kinetic_energy();
+    state.energy.total()
+}
@@ -0,0 +1,477 @@
+// Measurements and fits: collisions, equations of state, probes, profiles, timings and headless runs
+// Run with `cargo test -p vdw_core`
+mod common;
+
+use common::{assert_close, compile};
+use glam::{Mat3, Vec3};
+use vdw_core::state::coexistence::Coexistence;
+use vdw_core::state::error::ErrorKind;
+use vdw_core::state::experiment::{self, IsothermPoint, IsothermSweep};
+use vdw_core::state::field::FieldAxis;
+use vdw_core::state::heat_flux::{self, HeatFluxHistory, ThermalConductivity};
+use vdw_core::state::interface::{self, InterfaceFit};
+use vdw_core::state::kinetic::{PressureVolume, Sample, VanDerWaals};
+use vdw_core::state::particle::Particle;
+use vdw_core::state::physics::{self};
+use vdw_core::state::preset::Preset;
+use vdw_core::state::probe::{Probe, ProbeQuantity, ProbeTarget};
+use vdw_core::state::profile::Profile;
+use vdw_core::state::sensitivity::{self, ScanParameter};
+use vdw_core::state::sim_space::{Boundary, WallModel};
+use vdw_core::state::state_generator::Initialize;
+use vdw_core::state::sweep::{self, SweepSpec};
+use vdw_core::state::timing::Phase;
+use vdw_core::state::SimulationPrototype;
+
+#[test]
+fn collisions_are_counted_once_per_event() {
+    for &wall_model in [WallModel::HardReflect, WallModel::Soft].iter() {
+        // a lone particle crossing a box 4 long at unit speed hits a wall at t = 2 and t = 6
+        let prototype = SimulationPrototype::new()
+            .set_wall_model(wall_model)
+            .set_bound_x(4.0)
+            .set_particles(vec![Particle::new()
+                .set_pos(2.0, 2.5, 2.5)
+                .set_vel(1.0, 0.0, 0.0)]);
+        let mut state = compile(prototype);
+        state.collisions.period = 9.0;
+        while !state.collisions.is_measured() {
+            state.step();
+        }
+        assert_close(
+            state.collisions.wall_rate() * 9.0,
+            2.0,
+            1e-3,
+            wall_model.name(),
+        );
+        assert!(state.collisions.pair_rate() < f32::EPSILON);
+    }
+
+    // two particles running into each other collide once
+    let prototype = SimulationPrototype::new().set_particles(vec![
+        Particle::new()
+            .set_pos(2.0, 2.5, 2.5)
+            .set_vel(1.0, 0.0, 0.0),
+        Particle::new()
+            .set_pos(3.0, 2.5, 2.5)
+            .set_vel(-1.0, 0.0, 0.0),
+    ]);
+    let mut state = compile(prototype);
+    state.collisions.period = 1.0;
+    while !state.collisions.is_measured() {
+        state.step();
+    }
+    assert_close(state.collisions.pair_rate(), 1.0, 1e-3, "pair collisions");
+    assert!(state
+        .collisions
+        .mean_free_path(state.mean_speed(), 2)
+        .is_some());
+}
+
+#[test]
+fn van_der_waals_fit_recovers_its_constants() {
+    let gas = VanDerWaals { a: 0.8, b: 0.02 };
+    let samples: Vec<Sample> = (0..20)
+        .map(|i| {
+            let density = 0.5 + 0.2 * i as f32;
+            let temperature = 1.0 + 0.05 * (i % 4) as f32;
+            Sample {
+                density,
+                temperature,
+                pressure: gas.pressure(density, temperature),
+            }
+        })
+        .collect();
+    let fit = VanDerWaals::fit(&samples).expect("spread out samples");
+    assert_close(fit.a, gas.a, 2e-2, "a");
+    assert_close(fit.b / gas.b, 1.0, 2e-2, "b");
+
+    // one state point can't be fitted
+    assert!(VanDerWaals::fit(&[samples[0]; 10]).is_none());
+}
+
+#[test]
+fn pressure_volume_fit_gives_the_compressibility() {
+    // V = 20 - 4 P for pressures from 0 to 2
+    let points: Vec<(f32, f32)> = (0..=20)
+        .map(|i| {
+            let pressure = 0.1 * i as f32;
+            (20.0 - 4.0 * pressure, pressure)
+        })
+        .collect();
+    let fit = PressureVolume::fit(&points).expect("the volume changes");
+    assert_close(fit.slope, -4.0, 1e-4, "slope");
+    assert_close(fit.correlation, -1.0, 1e-4, "correlation");
+    assert_close(fit.compressibility(), 4.0 / 16.0, 1e-4, "compressibility");
+    assert_close(fit.volume(0.5), 18.0, 1e-4, "fitted volume");
+
+    // a fixed box only has pressure noise
+    let fixed: Vec<(f32, f32)> = points.iter().map(|&(_, p)| (16.0, p)).collect();
+    assert!(PressureVolume::fit(&fixed).is_none());
+}
+
+#[test]
+fn isotherm_sweep_compresses_a_gas() {
+    // a dilute gas in a box of volume 27, compressed to half of it
+    let prototype = SimulationPrototype::new()
+        .set_bound_x(3.0)
+        .set_bound_y(3.0)
+        .set_bound_z(3.0)
+        .set_pressure_sampling_period(0.2)
+        .initialize_uniform(60, (1.0f32 / 1.5).sqrt());
+    let mut sweep = IsothermSweep::new(1.0);
+    sweep.final_volume = 0.5;
+    sweep.points = 2;
+    sweep.equilibration = 0.3;
+    sweep.sampling = 0.5;
+    sweep.rate = 1.0;
+    let report = experiment::run(&prototype, sweep).unwrap();
+
+    assert_eq!(report.points.len(), 2);
+    let (first, last) = (report.points[0], report.points[1]);
+    assert_close(first.volume, 27.0, 1e-3, "starting volume");
+    assert!(last.volume <= 13.5 && last.volume > 12.0);
+    // overlapping particles are pruned by the initializer, the count stays the same along the sweep
+    assert_close(
+        last.density * last.volume,
+        first.density * first.volume,
+        1e-4,
+        "particle count",
+    );
+    // twice the density, and the fast compression heats the gas on top of that
+    assert!(
+        last.pressure > 1.5 * first.pressure,
+        "pressure {} after {}",
+        last.pressure,
+        first.pressure
+    );
+}
+
+#[test]
+fn maxwell_construction_of_a_van_der_waals_isotherm() {
+    // with a = b = 1 the critical point is at density 1/3, pressure 1/27 and temperature 4/9
+    let gas = VanDerWaals { a: 1.0, b: 1.0 };
+    let (critical_density, critical_pressure, critical_temp) = (1.0 / 3.0, 1.0 / 27.0, 4.0 / 9.0);
+    let isotherm = |temperature: f32| -> Vec<IsothermPoint> {
+        // volumes per particle from 1.2 b to 20 b, spaced evenly on a log scale
+        (0..300)
+            .map(|i| {
+                let volume = 1.2 * (20.0f32 / 1.2).powf(i as f32 / 299.0);
+                IsothermPoint {
+                    volume,
+                    density: 1.0 / volume,
+                    pressure: gas.pressure(1.0 / volume, temperature),
+                    pressure_error: 0.0,
+                    temperature,
+                }
+            })
+            .collect()
+    };
+
+    // tabulated reduced values at 0.9 of the critical temperature
+    let coexistence = Coexistence::of(&isotherm(0.9 * critical_temp)).unwrap();
+    assert_close(
+        coexistence.pressure / critical_pressure,
+        0.647,
+        2e-3,
+        "pressure",
+    );
+    assert_close(
+        critical_density / coexistence.liquid_density,
+        0.6034,
+        5e-3,
+        "liquid volume",
+    );
+    assert_close(
+        critical_density / coexistence.gas_density,
+        2.3488,
+        5e-3,
+        "gas volume",
+    );
+
+    // above the critical temperature the pressure falls all the way
+    assert!(Coexistence::of(&isotherm(1.1 * critical_temp)).is_none());
+}
+
+#[test]
+fn timings_cover_the_simulation_phases() {
+    let mut state = compile(Preset::Gas.prototype());
+    state.advance_frame();
+    for &phase in Phase::SIMULATION.iter() {
+        assert!(state.timings.mean_ms(phase) > 0.0, "{}", phase.name());
+        assert_eq!(state.timings.frames(phase).count(), 1);
+    }
+    // the app phases are timed by the app
+    assert!(state.timings.mean_ms(Phase::Ui) < f32::EPSILON);
+
+    state.timings.enabled = false;
+    state.timings.clear();
+    state.advance_frame();
+    assert!(state.timings.mean_ms(Phase::Forces) < f32::EPSILON);
+}
+
+#[test]
+fn region_probes_measure_the_particles_inside() {
+    let mut state = compile(
+        SimulationPrototype::new()
+            .set_bound_x(3.0)
+            .set_bound_y(3.0)
+            .set_bound_z(3.0)
+            .initialize_uniform(300, 1.0),
+    );
+    let (lo, hi) = (state.bound.lo_corner(), state.bound.hi_corner());
+    // with room around the box for the particles pushed back by the soft walls
+    let margin = Vec3::splat(0.5);
+    state.add_region_probe("box".to_string(), lo - margin, hi + margin);
+    // the lower half along x, with the corners given in any order
+    let half = Vec3::new(lo.x + 0.5 * (hi.x - lo.x), hi.y, hi.z);
+    state.add_region_probe("half".to_string(), half, lo);
+    state.advance_frame();
+
+    let whole = *state.regions[0].get_history().peak().unwrap();
+    assert_eq!(whole.particles, state.particles.len());
+    assert_close(whole.temperature, state.temperature(), 1e-4, "temperature");
+    let volume = state.regions[0].volume();
+    assert_close(
+        whole.density,
+        state.particles.len() as f32 / volume,
+        1e-4,
+        "density",
+    );
+    // all the particles have the stress of the last step, spread over the larger volume
+    assert_close(
+        whole.scalar_pressure() * volume / state.bound.get_volume(),
+        state.stress.pressure(),
+        1e-3,
+        "pressure",
+    );
+
+    let region = &state.regions[1];
+    let inside = state
+        .particles
+        .iter()
+        .filter(|p| {
+            let pos = p.get_pos();
+            pos.cmpge(lo).all() && pos.cmple(half).all()
+        })
+        .count();
+    assert!(inside > 0);
+    let sample = *region.get_history().peak().unwrap();
+    assert_eq!(sample.particles, inside);
+    assert_close(
+        sample.density,
+        inside as f32 / region.volume(),
+        1e-4,
+        "half density",
+    );
+}
+
+#[test]
+fn profiles_bin_particles_into_slabs() {
+    let mut bound = Boundary::new();
+    bound.set_corners(Vec3::ZERO, Vec3::new(2.0, 2.0, 4.0));
+    let particles = vec![
+        Particle::new()
+            .set_pos(1.0, 1.0, 0.5)
+            .set_vel(1.0, 0.0, 0.0),
+        Particle::new()
+            .set_pos(0.5, 1.5, 1.5)
+            .set_vel(3.0, 0.0, 0.0),
+        Particle::new().set_pos(1.0, 1.0, 3.0).set_fixed(true),
+    ];
+    let profile = Profile::of(&particles, bound, FieldAxis::Z, 2);
+    assert_eq!(profile.slabs(), 2);
+    assert_close(profile.slab_center(1), 3.0, 1e-6, "slab center");
+    // slabs of volume 8
+    assert_close(profile.density[0], 0.25, 1e-6, "lower density");
+    assert_close(profile.density[1], 0.125, 1e-6, "upper density");
+    // the lower slab flows at 2 with the particles 1 to either side of it
+    assert_close(profile.velocity[0].x, 2.0, 1e-6, "flow");
+    assert_close(profile.temperature[0], 0.5, 1e-6, "lower temperature");
+    // the fixed particle is at rest and has no temperature
+    assert_eq!(profile.velocity[1], Vec3::ZERO);
+    assert!(profile.temperature[1].abs() < f32::EPSILON);
+}
+
+#[test]
+fn interfaces_are_fitted_in_a_liquid_slab() {
+    // a liquid slab between 3 and 7 with vapor on both sides, interfaces of width 0.4
+    let (liquid, vapor, width) = (0.8, 0.05, 0.4);
+    let slabs = 100;
+    let density = (0..slabs)
+        .map(|i| {
+            let z = (i as f32 + 0.5) * 0.1;
+            let rising = ((z - 3.0) / width).tanh();
+            let falling = -((z - 7.0) / width).tanh();
+            vapor + 0.5 * (liquid - vapor) * (rising + falling)
+        })
+        .collect();
+    let profile = Profile {
+        axis: FieldAxis::Z,
+        lo: 0.0,
+        width: 0.1,
+        density,
+        temperature: vec![0.0; slabs],
+        velocity: vec![Vec3::ZERO; slabs],
+    };
+    let fit = InterfaceFit::of(&profile).expect("two phases");
+    assert_eq!(fit.interfaces.len(), 2);
+    assert_close(fit.liquid_density, liquid, 0.02, "liquid density");
+    assert_close(fit.vapor_density, vapor, 0.02, "vapor density");
+    let (rising, falling) = (fit.interfaces[0], fit.interfaces[1]);
+    assert!(rising.rising && !falling.rising);
+    assert_close(rising.position, 3.0, 0.01, "rising position");
+    assert_close(falling.position, 7.0, 0.01, "falling position");
+    assert_close(rising.width, width, 0.05, "rising width");
+    assert_close(falling.width, width, 0.05, "falling width");
+
+    // a uniform fluid has no interface
+    let uniform = Profile {
+        density: vec![0.5; slabs],
+        ..profile
+    };
+    assert!(InterfaceFit::of(&uniform).is_none());
+
+    // two interfaces across a box of length 10, normal pressure 1 above the tangential
+    let stress = Mat3::from_cols(
+        Vec3::new(0.5, 0.0, 0.0),
+        Vec3::new(0.0, 0.5, 0.0),
+        Vec3::new(0.0, 0.0, 1.5),
+    );
+    let tension = interface::surface_tension(stress, FieldAxis::Z, 10.0, 2).unwrap();
+    assert_close(tension, 5.0, 1e-6, "surface tension");
+}
+
+#[test]
+fn sweeps_run_every_combination_reproducibly() {
+    let spec = SweepSpec::from_toml(
+        "temperatures = [0.5, 1.0]\n\
+         densities = [0.2]\n\
+         seeds = [1, 2]\n\
+         particles = 40\n\
+         equilibration = 0.04\n\
+         duration = 0.06\n",
+    )
+    .unwrap();
+    let base = SimulationPrototype::new();
+    let report = sweep::run(&spec, &base).unwrap();
+    assert_eq!(report.runs.len(), 4);
+    for (run, point) in report.runs.iter().zip(spec.points()) {
+        assert_eq!(run.point, point);
+        assert!(run.particles > 0 && run.particles <= 40);
+        assert!(run.measured > 0 && run.measured < run.frames.len());
+        assert!(run.temperature().value > 0.0);
+    }
+    // the box holds the particles at the density
+    let bound = spec.prototype(&base, spec.points()[0]).get_bound();
+    assert_close(bound.get_volume(), 40.0 / 0.2, 1e-2, "volume");
+
+    // the seed decides the whole run
+    let again = sweep::run_point(&spec, &base, report.runs[1].point).unwrap();
+    assert_eq!(again.frames, report.runs[1].frames);
+    assert_ne!(report.runs[0].frames, report.runs[1].frames);
+
+    let empty = SweepSpec {
+        densities: Vec::new(),
+        ..spec
+    };
+    assert!(sweep::run(&empty, &base).is_err());
+}
+
+#[test]
+fn heat_current_adds_up_carried_and_passed_on_energy() {
+    let mut particle = Particle::new().set_mass(2.0).set_vel(1.0, 0.0, 0.0);
+    particle.potential = 0.5;
+    // kinetic 1.0 and potential 0.5 carried along
+    let carried = heat_flux::particle_heat_flux(&particle, Mat3::ZERO);
+    assert_close(carried.x, 1.5, 1e-6, "carried energy");
+    // a virial share r (x) f / 2 along the velocity passes on (f . v) r / 2 more
+    let virial = physics::outer_product(Vec3::X, 2.0 * Vec3::X);
+    let total = heat_flux::heat_current(&[particle.clone(), particle], &[virial]);
+    assert_close(total.x, 1.5 + 3.5, 1e-6, "heat current");
+
+    // a constant current correlates with itself at every lag
+    let mut history = HeatFluxHistory::with_capacity(100, 0.01);
+    for _ in 0..50 {
+        history.push(Vec3::new(1.0, 2.0, 3.0), 0.01);
+    }
+    let conductivity = ThermalConductivity::from_flux(&history, 0.5, 8.0, 11);
+    assert_close(
+        conductivity.autocorrelation[5],
+        14.0 / 3.0,
+        1e-5,
+        "autocorrelation",
+    );
+    // 1 / (V kT^2) = 0.5, over 10 lags of 0.01
+    assert_close(
+        conductivity.conductivity,
+        14.0 / 3.0 * 0.1 * 0.5,
+        1e-4,
+        "conductivity",
+    );
+
+    let mut state = compile(
+        SimulationPrototype::new()
+            .set_steps_per_frame(10)
+            .initialize_uniform(30, 0.5),
+    );
+    state.advance_frame();
+    assert_eq!(state.heat_flux.len(), 10);
+}
+
+#[test]
+fn probe_history_holds_one_quantity() {
+    let particles = vec![Particle::new().set_vel(1.0, 0.0, 0.0)];
+    let mut probe = Probe::new(
+        "p".to_string(),
+        ProbeTarget::Particle(0),
+        ProbeQuantity::Speed,
+        10,
+    );
+    probe.record(&particles);
+    probe.record(&particles);
+
+    // the same quantity keeps the history
+    probe.set_quantity(ProbeQuantity::Speed);
+    assert_eq!(probe.get_history().len(), 2);
+
+    probe.set_quantity(ProbeQuantity::PotentialEnergy);
+    assert_eq!(probe.get_quantity(), ProbeQuantity::PotentialEnergy);
+    assert!(probe.get_history().is_empty());
+    assert_eq!(probe.get_history().capacity(), 10);
+}
+
+#[test]
+fn scans_vary_the_force_field_and_the_thermostat() {
+    let prototype = SimulationPrototype::new()
+        .set_bound_x(4.0)
+        .set_bound_y(4.0)
+        .set_bound_z(4.0)
+        .set_steps_per_frame(10)
+        .set_inject_rate(0.5)
+        .initialize_uniform(60, 0.5);
+    let kinetic = |parameter, values: &[f32]| -> Vec<f32> {
+        let report = match sensitivity::scan(&prototype, parameter, values, 0.5) {
+            Ok(report) => report,
+            Err(err) => panic!("{}", err),
+        };
+        report
+            .samples
+            .iter()
+            .map(|sample| sample.observables.kinetic)
+            .collect()
+    };
+
+    let heated = kinetic(ScanParameter::TargetTemp, &[0.2, 2.0]);
+    assert!(heated[1] > heated[0], "kinetic energies {:?}", heated);
+    for &name in ScanParameter::NAMES.iter() {
+        assert!(ScanParameter::from_name(name).is_some(), "{}", name);
+    }
+    assert_eq!(kinetic(ScanParameter::Epsilon, &[0.5, 2.0]).len(), 2);
+    assert_eq!(kinetic(ScanParameter::Sigma, &[0.1, 0.15]).len(), 2);
+
+    let err = sensitivity::scan(&prototype, ScanParameter::Sigma, &[0.0], 0.1)
+        .err()
+        .expect("particles of size 0");
+    assert!(err.contains(ErrorKind::Particle));
+}
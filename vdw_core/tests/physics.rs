@@ -0,0 +1,661 @@
+// Correctness checks of the interactions and the integrator
+// Run with `cargo test -p vdw_core`
+mod common;
+
+use common::{assert_close, compile, lattice, total_energy};
+use glam::Vec3;
+use vdw_core::state::config::SimulationConfig;
+use vdw_core::state::dipole::Dipolar;
+use vdw_core::state::electrostatics::Coulomb;
+use vdw_core::state::error::ErrorKind;
+use vdw_core::state::particle::Particle;
+use vdw_core::state::physics::{
+    self, InteractionSite, NeighborMetric, NeighborRule, PotentialModel,
+};
+use vdw_core::state::preset::Preset;
+use vdw_core::state::sim_space::{Boundary, Face, Grid, GridWorkspace, WallAdhesion, WallModel};
+use vdw_core::state::state_generator::Initialize;
+use vdw_core::state::units::{Quantity, ReducedUnits};
+use vdw_core::state::SimulationPrototype;
+
+const RANGE: f32 = 1.0;
+
+// Separations spanning the repulsive core, the well and the tail of the potential
+const SEPARATIONS: [f32; 6] = [0.13, 0.15, 0.2, 0.35, 0.6, 0.9];
+
+// Pair energy of two particles, the interaction gives each particle half of it
+fn pair_energy(model: PotentialModel, r: f32, switch_start: Option<f32>) -> f32 {
+    let (_, potential) = model.interaction(Vec3::new(r, 0.0, 0.0), Vec3::ZERO, RANGE, switch_start);
+    2.0 * potential
+}
+
+// Central difference of the pair energy
+fn pair_energy_slope(model: PotentialModel, r: f32, switch_start: Option<f32>) -> f32 {
+    let h = 1e-3 * r;
+    (pair_energy(model, r + h, switch_start) - pair_energy(model, r - h, switch_start)) / (2.0 * h)
+}
+
+#[test]
+fn force_is_negative_gradient_of_potential() {
+    for &model in PotentialModel::ALL.iter() {
+        for &switch_start in [None, Some(0.7)].iter() {
+            for &r in SEPARATIONS.iter() {
+                let (force, _) =
+                    model.interaction(Vec3::new(r, 0.0, 0.0), Vec3::ZERO, RANGE, switch_start);
+                let expected = -pair_energy_slope(model, r, switch_start);
+                assert_close(
+                    force.x,
+                    expected,
+                    2e-2,
+                    &format!(
+                        "{} force at r = {} with switch {:?}",
+                        model.name(),
+                        r,
+                        switch_start
+                    ),
+                );
+                assert!(force.y == 0.0 && force.z == 0.0);
+            }
+        }
+    }
+}
+
+#[test]
+fn vdw_interaction_vanishes_past_range() {
+    let (force, potential) = physics::vdw_interaction(
+        Vec3::new(1.01 * RANGE, 0.0, 0.0),
+        InteractionSite::default(),
+        Vec3::ZERO,
+        InteractionSite::default(),
+        RANGE,
+        None,
+    );
+    assert_eq!(force, Vec3::ZERO);
+    assert!(potential.abs() < f32::EPSILON);
+
+    // the switched potential also reaches zero continuously at the range
+    for &model in PotentialModel::ALL.iter() {
+        assert!(pair_energy(model, 0.999 * RANGE, Some(0.7)).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn larger_particles_meet_at_their_summed_radii() {
+    let small = InteractionSite::default();
+    let large = InteractionSite {
+        radius: 0.12,
+        ..small
+    };
+    let contact = small.radius + large.radius;
+    let energy = |r: f32| {
+        let (_, potential) = PotentialModel::LennardJones.site_interaction(
+            Vec3::new(r, 0.0, 0.0),
+            small,
+            Vec3::ZERO,
+            large,
+            RANGE,
+            None,
+        );
+        2.0 * potential
+    };
+    for &r in [0.9 * contact, contact, 1.5 * contact].iter() {
+        let (force, _) = PotentialModel::LennardJones.site_interaction(
+            Vec3::new(r, 0.0, 0.0),
+            small,
+            Vec3::ZERO,
+            large,
+            RANGE,
+            None,
+        );
+        let h = 1e-3 * r;
+        let slope = (energy(r + h) - energy(r - h)) / (2.0 * h);
+        assert_close(force.x, -slope, 1e-2, "force against energy slope");
+    }
+    // the bottom of the well moved out to the contact distance
+    assert!(energy(contact) < energy(0.97 * contact) && energy(contact) < energy(1.03 * contact));
+}
+
+#[test]
+fn well_depths_mix_as_the_geometric_mean() {
+    let plain = InteractionSite::default();
+    let shallow = InteractionSite {
+        epsilon: 0.25,
+        ..plain
+    };
+    for &r in SEPARATIONS.iter() {
+        let pos = Vec3::new(r, 0.0, 0.0);
+        let (f_plain, u_plain) =
+            physics::vdw_interaction(pos, plain, Vec3::ZERO, plain, RANGE, None);
+        let (f_mixed, u_mixed) =
+            physics::vdw_interaction(pos, plain, Vec3::ZERO, shallow, RANGE, None);
+        assert_close(u_mixed, 0.5 * u_plain, 1e-5, "mixed energy");
+        assert_close(f_mixed.x, 0.5 * f_plain.x, 1e-5, "mixed force");
+    }
+}
+
+#[test]
+fn inert_pairs_only_repel() {
+    for &model in PotentialModel::ALL.iter() {
+        for &r in SEPARATIONS.iter() {
+            let (force, potential) =
+                model.repulsive_interaction(Vec3::new(r, 0.0, 0.0), Vec3::ZERO, RANGE);
+            assert!(force.x >= 0.0, "{} attracts at {}", model.name(), r);
+            assert!(potential >= 0.0, "{} is negative at {}", model.name(), r);
+        }
+        // the cut at the bottom of the well is continuous
+        if let Some(r_min) = model.well_minimum() {
+            let (force, potential) =
+                model.repulsive_interaction(Vec3::new(0.9999 * r_min, 0.0, 0.0), Vec3::ZERO, RANGE);
+            assert!(
+                force.length() < 0.05 && potential < 1e-5,
+                "{}",
+                model.name()
+            );
+        }
+    }
+}
+
+#[test]
+fn harmonic_force_is_negative_gradient_of_potential() {
+    let (k, r0) = (50.0, 0.3);
+    let energy =
+        |r: f32| physics::harmonic_interaction(Vec3::new(r, 0.0, 0.0), Vec3::ZERO, k, r0).1;
+    for &r in [0.1, 0.25, 0.3, 0.4, 0.8].iter() {
+        let (force, _) = physics::harmonic_interaction(Vec3::new(r, 0.0, 0.0), Vec3::ZERO, k, r0);
+        let h = 1e-3;
+        let expected = -(energy(r + h) - energy(r - h)) / (2.0 * h);
+        assert_close(
+            force.x,
+            expected,
+            1e-2,
+            &format!("spring force at r = {}", r),
+        );
+    }
+}
+
+#[test]
+fn pair_forces_obey_third_law() {
+    let a = Vec3::new(0.1, 0.2, 0.3);
+    let others = [
+        Vec3::new(0.25, 0.2, 0.3),
+        Vec3::new(0.3, 0.1, 0.4),
+        Vec3::new(-0.2, 0.5, 0.1),
+    ];
+    // sizes and well depths are mixed symmetrically
+    let site_a = InteractionSite::default();
+    let site_b = InteractionSite {
+        radius: 0.09,
+        epsilon: 0.5,
+        ..site_a
+    };
+    for &b in others.iter() {
+        for &switch_start in [None, Some(0.7)].iter() {
+            let (f_ab, u_ab) = physics::vdw_interaction(a, site_a, b, site_b, RANGE, switch_start);
+            let (f_ba, u_ba) = physics::vdw_interaction(b, site_b, a, site_a, RANGE, switch_start);
+            assert!((f_ab + f_ba).length() <= 1e-5 * f_ab.length().max(1.0));
+            assert!((u_ab - u_ba).abs() <= 1e-6 * u_ab.abs().max(1.0));
+        }
+
+        let (f_ab, _) = physics::harmonic_interaction(a, b, 50.0, 0.3);
+        let (f_ba, _) = physics::harmonic_interaction(b, a, 50.0, 0.3);
+        assert!((f_ab + f_ba).length() <= 1e-5 * f_ab.length().max(1.0));
+    }
+}
+
+#[test]
+fn grid_forces_sum_to_zero() {
+    let positions: Vec<Vec3> = lattice(4, 0.17, 0.0)
+        .iter()
+        .enumerate()
+        .map(|(i, p)| p.get_pos() + 0.01 * Vec3::new((i as f32).sin(), (i as f32).cos(), 0.0))
+        .collect();
+
+    for &switch_start in [None, Some(0.7)].iter() {
+        let grid = Grid::new(0.5, 2).with_switch(switch_start);
+        let (forces, _, _, _) = grid.calculate_force(
+            &positions,
+            &vec![InteractionSite::default(); positions.len()],
+        );
+        let net: Vec3 = forces.iter().sum();
+        let scale: f32 = forces.iter().map(|f| f.length()).sum();
+        assert!(
+            net.length() <= 1e-4 * scale,
+            "net force {:?} with switch {:?}",
+            net,
+            switch_start
+        );
+    }
+}
+
+#[test]
+fn box_anchored_grid_finds_the_same_forces() {
+    let mut positions: Vec<Vec3> = lattice(4, 0.17, 0.0).iter().map(|p| p.get_pos()).collect();
+    // a pair straddling the wall and a particle far outside the box
+    positions.push(Vec3::new(4.95, 1.0, 1.0));
+    positions.push(Vec3::new(5.2, 1.0, 1.0));
+    positions.push(Vec3::new(100.0, 2.5, 2.5));
+    let sites = vec![InteractionSite::default(); positions.len()];
+    let grid = Grid::new(0.5, 2);
+    let (expected, _, _, _) = grid.calculate_force(&positions, &sites);
+
+    let bound = Boundary::new();
+    let mut workspace = GridWorkspace::default();
+    workspace.anchor_to(bound);
+    grid.calculate_force_with(&positions, &sites, &mut workspace);
+    let forces = workspace.forces();
+    for (i, (force, expected)) in forces.iter().zip(expected.iter()).enumerate() {
+        assert_close(
+            (*force - *expected).length(),
+            0.0,
+            1e-4,
+            &format!("force on {}", i),
+        );
+    }
+    assert_ne!(forces[positions.len() - 2], Vec3::ZERO);
+    // the buffers of the workspace are reused, a second pass gives the same forces
+    let virial = grid.calculate_force_with(&positions, &sites, &mut workspace);
+    assert_eq!(workspace.forces(), expected.as_slice());
+    assert_eq!(virial, grid.calculate_force(&positions, &sites).3);
+    // the size follows from the box alone, not from the particles
+    assert_eq!(workspace.dim(), (10, 10, 10));
+    positions.truncate(4);
+    grid.calculate_force_with(&positions, &sites[..4], &mut workspace);
+    assert_eq!(workspace.dim(), (10, 10, 10));
+}
+
+#[test]
+fn cutoff_radius_sets_the_range_and_the_grid_follows() {
+    let grid = Grid::with_cutoff(0.35);
+    assert_close(grid.get_range(), 0.35, 1e-6, "range");
+    assert_close(grid.get_unit_size(), 0.35, 1e-6, "square size");
+
+    // the cutoff replaces the grid settings
+    let state = SimulationConfig::from_toml(
+        "[force_field]\ngrid_unit_size = 0.01\ngrid_reach = 3\ncutoff_radius = 0.4\n",
+    )
+    .unwrap()
+    .to_prototype()
+    .initialize_uniform(50, 0.5)
+    .compile()
+    .unwrap()
+    .into_state();
+    assert_close(state.get_grid_range(), 0.4, 1e-6, "compiled range");
+
+    let err = SimulationPrototype::new()
+        .set_cutoff_radius(0.0)
+        .compile()
+        .err()
+        .expect("a zero cutoff is invalid");
+    assert!(err.contains(ErrorKind::Cutoff));
+    let short = SimulationPrototype::new()
+        .set_cutoff_radius(0.15)
+        .initialize_uniform(10, 0.5);
+    assert!(short.warnings().iter().any(|w| w.kind == ErrorKind::Cutoff));
+}
+
+#[test]
+fn nve_run_conserves_energy() {
+    for &switch_start in [None, Some(0.7)].iter() {
+        let mut prototype = SimulationPrototype::new()
+            .set_wall_model(WallModel::HardReflect)
+            .set_grid_unit_size(0.5)
+            .set_grid_reach(2)
+            .set_dt(0.0005)
+            .set_particles(lattice(3, 0.17, 0.3));
+        if let Some(r_switch) = switch_start {
+            prototype = prototype.set_switch_start(r_switch);
+        }
+        let mut state = compile(prototype);
+
+        // the first step fills in the potential energy
+        state.step();
+        let initial = total_energy(&mut state);
+        let mut largest_deviation: f32 = 0.0;
+        for _ in 0..2000 {
+            state.step();
+            largest_deviation = largest_deviation.max((total_energy(&mut state) - initial).abs());
+        }
+
+        let scale = state.energy.kinetic.abs().max(initial.abs());
+        assert!(
+            largest_deviation < 2e-2 * scale,
+            "energy drifted by {} from {} with switch {:?}",
+            largest_deviation,
+            initial,
+            switch_start
+        );
+    }
+}
+
+#[test]
+fn adhesion_force_is_negative_gradient_of_potential() {
+    let mut bound = Boundary::new();
+    bound.adhesion = Some(WallAdhesion::new(0.6, &[Face::YLo, Face::ZHi]));
+    let energy = |p: Vec3| bound.calculate_adhesion(&[p]).1[0];
+
+    for &z in [0.12, 0.15, 0.2, 0.3, 0.38].iter() {
+        for &p in [Vec3::new(2.5, z, 2.5), Vec3::new(2.5, 2.5, 5.0 - z)].iter() {
+            let (forces, _, push) = bound.calculate_adhesion(&[p]);
+            for axis in 0..3 {
+                let mut h = Vec3::ZERO;
+                h[axis] = 1e-3 * z;
+                let expected = -(energy(p + h) - energy(p - h)) / (2.0 * h[axis]);
+                assert_close(
+                    forces[0][axis],
+                    expected,
+                    2e-2,
+                    &format!("force at {:?}", p),
+                );
+            }
+            // the wall feels the inward force on the particle as an outward push
+            assert_close(
+                push.total(),
+                forces[0].y - forces[0].z,
+                1e-4,
+                "push on the wall",
+            );
+        }
+    }
+}
+
+#[test]
+fn grid_matches_brute_force() {
+    // a dense lattice filling the box, so many pairs straddle grid cells and box edges
+    let particles: Vec<Particle> = lattice(12, 0.4, 0.0)
+        .into_iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let pos = p.get_pos() + 0.05 * Vec3::new((i as f32).sin(), (i as f32).cos(), 0.3);
+            p.set_pos(pos.x, pos.y, pos.z)
+        })
+        .collect();
+    let state = compile(
+        SimulationPrototype::new()
+            .set_grid_unit_size(0.5)
+            .set_grid_reach(2)
+            .set_particles(particles),
+    );
+
+    let sample: Vec<usize> = (0..state.particles.len()).step_by(7).collect();
+    let report = state.check_neighbors(&sample);
+    assert!(report.is_consistent(), "{:?}", report);
+}
+
+#[test]
+fn coulomb_force_is_negative_gradient_of_potential() {
+    for &damping in [0.0, 2.0].iter() {
+        let coulomb = Coulomb {
+            strength: 0.5,
+            damping,
+        };
+        let energy = |r: f32| {
+            coulomb
+                .interaction(Vec3::new(r, 0.0, 0.0), Vec3::ZERO, 1.0, -2.0, RANGE)
+                .1
+        };
+        for &r in SEPARATIONS.iter() {
+            let (force, _) =
+                coulomb.interaction(Vec3::new(r, 0.0, 0.0), Vec3::ZERO, 1.0, -2.0, RANGE);
+            let h = 1e-3 * r;
+            let expected = -(energy(r + h) - energy(r - h)) / (2.0 * h);
+            assert_close(
+                force.x,
+                expected,
+                2e-2,
+                &format!("coulomb force at r = {} with damping {}", r, damping),
+            );
+        }
+
+        // shifted force, both the force and the energy vanish at the cutoff
+        let (force, potential) = coulomb.interaction(
+            Vec3::new(0.999 * RANGE, 0.0, 0.0),
+            Vec3::ZERO,
+            1.0,
+            -2.0,
+            RANGE,
+        );
+        assert!(force.length() < 1e-2 && potential.abs() < 1e-4);
+    }
+}
+
+#[test]
+fn reduced_units_match_the_potential() {
+    for &model in PotentialModel::ALL.iter() {
+        let units = ReducedUnits::new(model, 1.0);
+        if let Some(r_min) = model.well_minimum() {
+            // sigma is where the pair energy crosses zero, epsilon the depth of the well
+            assert_close(
+                pair_energy(model, units.sigma, None),
+                0.0,
+                1e-3,
+                model.name(),
+            );
+            assert_close(
+                pair_energy(model, r_min, None),
+                -units.epsilon,
+                1e-3,
+                model.name(),
+            );
+        }
+    }
+
+    // the gas preset sits at T* = 2.5 and rho* = 0.05 of the Lennard-Jones phase diagram
+    let units = ReducedUnits::new(PotentialModel::LennardJones, 1.0);
+    let gas = Preset::Gas.params();
+    assert_close(
+        units.to_reduced(Quantity::Temperature, gas.temperature),
+        2.5,
+        1e-3,
+        "reduced temperature",
+    );
+    assert_close(
+        units.to_reduced(Quantity::Density, gas.density),
+        0.0477,
+        1e-2,
+        "reduced density",
+    );
+    assert_close(
+        units.from_reduced(Quantity::Time, units.to_reduced(Quantity::Time, 0.3)),
+        0.3,
+        1e-6,
+        "time round trip",
+    );
+}
+
+#[test]
+fn neighbor_rules_pick_the_pairs() {
+    // a row of particles 0.2, 0.25 and 0.2 apart
+    let row = |rule: NeighborRule| {
+        let particles = [0.0, 0.2, 0.45, 0.65]
+            .iter()
+            .map(|&x| Particle::new().set_pos(2.0 + x, 2.5, 2.5))
+            .collect();
+        let mut state = compile(
+            SimulationPrototype::new()
+                .set_neighbor_rule(rule)
+                .set_particles(particles),
+        );
+        state.advance_frame();
+        let counts: Vec<usize> = state.particles.iter().map(|p| p.neighbors).collect();
+        (counts, state.neighbor_network().components().len())
+    };
+
+    assert_eq!(row(NeighborRule::default()), (vec![1, 2, 2, 1], 1));
+    let shorter = NeighborRule {
+        distance: 0.22,
+        ..NeighborRule::default()
+    };
+    assert_eq!(row(shorter), (vec![1, 1, 1, 1], 2));
+    // the middle pair is further apart than either particle's closest neighbor
+    let nearest = NeighborRule {
+        metric: NeighborMetric::Nearest,
+        nearest: 1,
+        ..NeighborRule::default()
+    };
+    assert_eq!(row(nearest), (vec![1, 1, 1, 1], 2));
+
+    // neighbors are only looked for within the interaction range
+    assert!(SimulationPrototype::new()
+        .set_neighbor_rule(NeighborRule {
+            distance: 2.0 * RANGE,
+            ..NeighborRule::default()
+        })
+        .compile()
+        .is_err());
+}
+
+#[test]
+fn particles_turn_at_their_angular_velocity() {
+    // a quarter turn around z in unit time, alone so nothing exerts a torque
+    let turning =
+        Particle::new()
+            .set_pos(2.5, 2.5, 2.5)
+            .set_ang_vel(0.0, 0.0, std::f32::consts::FRAC_PI_2);
+    let mut state = compile(SimulationPrototype::new().set_dt(0.001).set_particles(vec![
+        turning.clone(),
+        turning.set_pos(0.5, 0.5, 0.5).set_fixed(true),
+    ]));
+    for _ in 0..1000 {
+        state.step();
+    }
+
+    let turned = state.particles[0].get_orientation() * Vec3::X;
+    assert!(
+        turned.abs_diff_eq(Vec3::Y, 1e-3),
+        "x axis turned to {:?}",
+        turned
+    );
+    // fixed particles are at rest, they don't turn either
+    let fixed = state.particles[1].get_orientation() * Vec3::X;
+    assert!(
+        fixed.abs_diff_eq(Vec3::X, 1e-6),
+        "fixed particle turned to {:?}",
+        fixed
+    );
+}
+
+#[test]
+fn free_tops_keep_their_angular_momentum_and_energy() {
+    // spun close to the intermediate axis, where the Euler equations make it tumble
+    let top = Particle::new()
+        .set_inertia(1.0, 2.0, 3.0)
+        .set_ang_vel(0.05, 2.0, 0.05);
+    let energy = |p: &Particle| 0.5 * p.get_ang_vel().dot(p.get_ang_momentum());
+    let (momentum, before) = (top.get_ang_momentum(), energy(&top));
+
+    let mut tumbling = top.clone();
+    for _ in 0..5000 {
+        tumbling.step_pos(0.001, 1.0);
+    }
+    assert!(
+        tumbling.get_ang_momentum().abs_diff_eq(momentum, 1e-3),
+        "angular momentum went from {:?} to {:?}",
+        momentum,
+        tumbling.get_ang_momentum()
+    );
+    assert_close(energy(&tumbling), before, 1e-2, "rotational energy");
+    // unlike a ball, the angular velocity of the top changes as it turns
+    assert!(
+        !tumbling.get_ang_vel().abs_diff_eq(top.get_ang_vel(), 0.1),
+        "angular velocity stayed at {:?}",
+        tumbling.get_ang_vel()
+    );
+
+    // torques change the angular momentum by torque * dt
+    let torque = Vec3::new(0.0, 0.0, 3.0);
+    tumbling.step_ang_vel(torque, 0.01, 1.0);
+    assert!(tumbling
+        .get_ang_momentum()
+        .abs_diff_eq(momentum + 0.01 * torque, 1e-3));
+}
+
+#[test]
+fn dipole_forces_and_torques_follow_the_energy() {
+    let dipolar = Dipolar {
+        strength: 0.5,
+        field: [0.0; 3],
+    };
+    let (mu_i, mu_j) = (Vec3::new(0.3, -0.8, 0.5), Vec3::new(-0.6, 0.2, 0.9));
+    let energy = |r: Vec3, mu: Vec3| dipolar.interaction(r, mu, mu_j, RANGE).1;
+    for &r in SEPARATIONS.iter() {
+        let r = r * Vec3::new(0.6, 0.48, 0.64);
+        let (force, potential) = dipolar.interaction(r, mu_i, mu_j, RANGE);
+        let torque = mu_i.cross(dipolar.field_of(r, mu_j, RANGE));
+        let h = 1e-3 * r.length();
+        for &axis in [Vec3::X, Vec3::Y, Vec3::Z].iter() {
+            // the force pushes down the energy gradient
+            let slope = (energy(r + h * axis, mu_i) - energy(r - h * axis, mu_i)) / (2.0 * h);
+            let scale = potential.abs().max(1.0) / r.length();
+            assert_close(
+                force.dot(axis) / scale,
+                -slope / scale,
+                2e-2,
+                &format!("dipole force along {:?} at {:?}", axis, r),
+            );
+
+            // and the torque turns the dipole down the energy gradient
+            let turn = |angle: f32| glam::Quat::from_axis_angle(axis, angle) * mu_i;
+            let slope = (energy(r, turn(1e-3)) - energy(r, turn(-1e-3))) / 2e-3;
+            assert_close(
+                torque.dot(axis) / potential.abs().max(1.0),
+                -slope / potential.abs().max(1.0),
+                2e-2,
+                &format!("dipole torque around {:?} at {:?}", axis, r),
+            );
+        }
+    }
+
+    // both ends of a pair feel equal and opposite forces
+    let r = Vec3::new(0.2, 0.1, -0.15);
+    let (on_i, _) = dipolar.interaction(r, mu_i, mu_j, RANGE);
+    let (on_j, _) = dipolar.interaction(-r, mu_j, mu_i, RANGE);
+    assert!((on_i + on_j).length() < 1e-3 * on_i.length());
+    assert_eq!(
+        dipolar.interaction(1.01 * RANGE * Vec3::X, mu_i, mu_j, RANGE),
+        (Vec3::ZERO, 0.0)
+    );
+
+    let err = SimulationPrototype::new()
+        .set_dipolar(Dipolar {
+            strength: -1.0,
+            field: [0.0; 3],
+        })
+        .compile()
+        .err()
+        .expect("a negative dipole strength is invalid");
+    assert!(err.contains(ErrorKind::Dipole));
+}
+
+#[test]
+fn dipoles_align_with_the_applied_field() {
+    // without the pair coupling each dipole follows the Langevin function
+    // <cos> = coth(x) - 1 / x with x = mu E / T
+    let mut state = SimulationConfig::from_toml(
+        "dt = 0.001\nseed = 5\nintegrator = { kind = \"langevin\", gamma = 5.0 }\n\
+         [thermostat]\ntarget_temp = 0.5\ninject_rate = 0.0\n\
+         [dipoles]\nmoments = [[1.0, 0.0, 0.0]]\nstrength = 0.0\nfield = [0.0, 0.0, 5.0]\n\
+         [initializer]\nkind = \"spherical_cloud\"\nn = 30\nsigma = 1.0\ntemp = 0.5\n",
+    )
+    .unwrap()
+    .to_prototype()
+    .compile()
+    .unwrap()
+    .into_state();
+    assert!(state.particles.iter().all(|p| p.get_dipole() == Vec3::X));
+    for _ in 0..2000 {
+        state.step();
+    }
+    let mut alignment = 0.0;
+    let samples = 2000;
+    for _ in 0..samples {
+        state.step();
+        alignment += state
+            .particles
+            .iter()
+            .map(|p| p.world_dipole().z)
+            .sum::<f32>()
+            / (state.particles.len() * samples) as f32;
+    }
+    let x: f32 = 1.0 * 5.0 / 0.5;
+    assert_close(alignment, 1.0 / x.tanh() - 1.0 / x, 0.05, "mean alignment");
+}
@@ -0,0 +1,409 @@
+// Building simulations: generators, groups and seeds, and how invalid setups are refused and reported
+// Run with `cargo test -p vdw_core`
+mod common;
+
+use common::{assert_close, compile, total_energy};
+use glam::Vec3;
+use vdw_core::state::bond::Bond;
+use vdw_core::state::config::SimulationConfig;
+use vdw_core::state::error::{ConfigError, ErrorKind, FileError, SimulationError};
+use vdw_core::state::experiment::{self, Quench};
+use vdw_core::state::group::Selection;
+use vdw_core::state::integrator::Integrator;
+use vdw_core::state::particle::Particle;
+use vdw_core::state::schedule::{Event, ScheduleParameter};
+use vdw_core::state::seed::Seed;
+use vdw_core::state::snapshot::Snapshot;
+use vdw_core::state::state_generator::Initialize;
+use vdw_core::state::watchdog::{self, Cause, Divergence, Watchdog};
+use vdw_core::state::SimulationPrototype;
+
+#[test]
+fn chains_longer_than_the_box_are_folded() {
+    // 4 chains of 20 with a bond of 0.5 would stick out of a box of 3 on every side
+    let cube = || {
+        SimulationPrototype::new()
+            .set_bound_x(3.0)
+            .set_bound_y(3.0)
+            .set_bound_z(3.0)
+    };
+    let mut state = compile(cube().initialize_chains(4, 20, 0.5, 50.0, 0.1));
+    assert_eq!(state.particles.len(), 80);
+    for (i, a) in state.particles.iter().enumerate() {
+        assert!(
+            state.bound.contains_position(a.get_pos()),
+            "particles[{}] at {:?} is outside the box",
+            i,
+            a.get_pos()
+        );
+        for b in state.particles[i + 1..].iter() {
+            assert!(
+                a.get_pos() != b.get_pos(),
+                "coincident particles at {:?}",
+                a.get_pos()
+            );
+        }
+    }
+    for bond in state.bonds.iter() {
+        let r = (state.particles[bond.i].get_pos() - state.particles[bond.j].get_pos()).length();
+        assert_close(r, 0.5, 1e-4, "bond length");
+    }
+    state.step();
+    assert!(total_energy(&mut state).is_finite());
+
+    // a few more particles than lattice sites of a bond length, the lattice is made finer
+    let state = compile(cube().initialize_chains(5, 50, 0.5, 50.0, 0.1));
+    assert_eq!(state.particles.len(), 250);
+    assert!(state
+        .particles
+        .iter()
+        .all(|p| state.bound.contains_position(p.get_pos())));
+
+    // far more, the bonds would start squeezed together
+    let err = cube()
+        .initialize_chains(10, 40, 0.5, 50.0, 0.1)
+        .compile()
+        .err()
+        .expect("chains folded far inside their bond length are invalid");
+    assert!(err.contains(ErrorKind::Bond));
+
+    // bonded particles at the same position are refused
+    let err = SimulationPrototype::new()
+        .set_particles(vec![Particle::new().set_pos(1.0, 1.0, 1.0); 2])
+        .set_bonds(vec![Bond::new(0, 1, 1.0, 0.5)])
+        .compile()
+        .err()
+        .expect("coincident bonded particles are invalid");
+    assert!(err.contains(ErrorKind::Bond));
+}
+
+#[test]
+fn groups_freeze_kick_and_delete_their_particles() {
+    let particles = (0..4)
+        .map(|i| {
+            Particle::new()
+                .set_pos(0.5 + 0.6 * i as f32, 1.0, 1.0)
+                .set_vel(0.0, 0.3, 0.0)
+                .set_species(i % 2)
+        })
+        .collect();
+    let mut state = compile(
+        SimulationPrototype::new()
+            .set_particles(particles)
+            .set_bonds(vec![Bond::new(0, 1, 1.0, 0.6), Bond::new(2, 3, 1.0, 0.6)]),
+    );
+
+    // the two particles left of x = 1.2
+    let left = state.add_group(
+        "left".to_string(),
+        &Selection::Region {
+            lo: Vec3::ZERO,
+            hi: Vec3::new(1.2, 2.0, 2.0),
+        },
+    );
+    assert_eq!(state.groups.get(left).unwrap().get_members(), &[0, 1]);
+    let odd = state.add_group("odd".to_string(), &Selection::Species(1));
+    assert_eq!(state.groups.get(odd).unwrap().get_members(), &[1, 3]);
+
+    state.freeze_group(left, true);
+    let before: Vec<Vec3> = state.particles.iter().map(|p| p.get_pos()).collect();
+    state.advance_frame();
+    for (particle, &pos) in state.particles.iter().zip(before.iter()).take(2) {
+        assert_eq!(particle.get_pos(), pos);
+        assert_eq!(particle.get_vel(), Vec3::ZERO);
+    }
+    assert!((state.particles[2].get_pos() - before[2]).length() > 0.0);
+
+    // frozen members stay at rest when kicked
+    let v3 = state.particles[3].get_vel();
+    state.kick_group(odd, Vec3::new(1.0, 0.0, 0.0));
+    assert_eq!(state.particles[1].get_vel(), Vec3::ZERO);
+    assert_close(state.particles[3].get_vel().x, v3.x + 1.0, 1e-6, "kicked");
+
+    // deleting the left group renumbers the bond between the others and the odd group
+    assert_eq!(state.delete_group(left), 2);
+    assert_eq!(state.particles.len(), 2);
+    assert_eq!(state.groups.len(), 1);
+    assert_eq!(state.groups.get(0).unwrap().get_members(), &[1]);
+    assert_eq!(state.bonds.len(), 1);
+    assert_eq!((state.bonds[0].i, state.bonds[0].j), (0, 1));
+}
+
+#[test]
+fn crystal_seeds_clear_their_place_in_the_fluid() {
+    let mut state = compile(
+        SimulationPrototype::new()
+            .set_bound_x(2.0)
+            .set_bound_y(2.0)
+            .set_bound_z(2.0)
+            .initialize_uniform(400, 0.5),
+    );
+    let seed = Seed {
+        center: state.bound.center(),
+        ..Seed::default()
+    };
+    let sites = seed.sites();
+    // a site at the center and its twelve nearest neighbors
+    assert!(sites.contains(&seed.center));
+    let nearest = sites
+        .iter()
+        .filter(|&&pos| (pos - seed.center).length() > 0.0)
+        .filter(|&&pos| ((pos - seed.center).length() - seed.spacing).abs() < 1e-4)
+        .count();
+    assert_eq!(nearest, 12);
+
+    let fluid = state.particles.len();
+    let group = state.insert_seed(seed.particles(&state));
+    let members = state.groups.get(group).unwrap().get_members().to_vec();
+    assert_eq!(members.len(), sites.len());
+    assert!(state.groups.get(group).unwrap().frozen);
+    assert!(
+        state.particles.len() < fluid + sites.len(),
+        "clears the fluid"
+    );
+    for &i in members.iter() {
+        assert!(state.particles[i].is_fixed());
+    }
+    // no fluid particle is left inside the seed, short of its surface
+    for (i, particle) in state.particles.iter().enumerate() {
+        if !members.contains(&i) {
+            let r = (particle.get_pos() - seed.center).length();
+            assert!(r > seed.radius - seed.spacing);
+        }
+    }
+}
+
+#[test]
+fn watchdog_catches_diverged_particles() {
+    let mut state = compile(
+        SimulationPrototype::new()
+            .set_bound_x(3.0)
+            .set_bound_y(3.0)
+            .set_bound_z(3.0)
+            .initialize_uniform(100, 0.5),
+    );
+    let mut dog = Watchdog::default();
+    assert!(watchdog::diverged_particles(&state).is_empty());
+    assert!(!dog.update(&state));
+    let good = dog.last_good().expect("a good state").steps;
+
+    // a NaN velocity and one that crosses the interaction range in a step
+    let pos = state.particles[3].get_pos();
+    state.particles[3].move_to(pos, Vec3::new(f32::NAN, 0.0, 0.0));
+    let (pos, speed) = (
+        state.particles[5].get_pos(),
+        state.get_grid_range() / state.dt,
+    );
+    state.particles[5].move_to(pos, Vec3::X * speed);
+    state.steps += 1;
+    assert_eq!(watchdog::diverged_particles(&state), vec![3, 5]);
+    assert!(dog.update(&state));
+    let divergence = dog.divergence.clone().unwrap();
+    assert_eq!(divergence.particles, vec![3, 5]);
+    assert!(divergence.non_finite);
+    // the particles were apart in the good state
+    assert_eq!(divergence.cause, Cause::TimeStep);
+    // held until dismissed
+    state.steps += 1;
+    assert!(!dog.update(&state));
+    assert_eq!(dog.rewind().unwrap().steps, good);
+    assert!(dog.divergence.is_none());
+
+    // a particle sitting on another one in the last good state
+    let mut overlapped = dog.last_good().unwrap().clone();
+    let pos = overlapped.particles[8].get_pos();
+    overlapped.particles[7].move_to(pos, Vec3::ZERO);
+    let divergence = Divergence::of(&state, vec![7], Some(&overlapped));
+    assert_eq!(divergence.cause, Cause::Overlap);
+    assert!(!divergence.non_finite);
+}
+
+#[test]
+fn errors_tell_what_failed_and_where() {
+    // config errors point at the line
+    match SimulationConfig::from_toml("dt = 0.001\nsteps_per_frame = \"many\"\n") {
+        Err(ConfigError::Parse { path, line, .. }) => {
+            assert!(path.is_none());
+            assert_eq!(line, Some(2));
+        }
+        _ => panic!("expected a parse error"),
+    }
+    let missing = std::env::temp_dir().join("vdw_missing_config.toml");
+    match SimulationConfig::from_file(&missing) {
+        Err(err @ ConfigError::Io { .. }) => assert_eq!(err.path(), Some(missing.as_path())),
+        _ => panic!("expected an io error"),
+    }
+
+    // snapshots read back the particles they saved
+    let state = compile(
+        SimulationPrototype::new()
+            .set_bound_x(2.0)
+            .set_bound_y(2.0)
+            .set_bound_z(2.0)
+            .initialize_uniform(20, 0.5),
+    );
+    let path = std::env::temp_dir().join(format!("vdw_snapshot_{}.ron", std::process::id()));
+    Snapshot::of(&state).save(&path).unwrap();
+    let particles = Snapshot::load(&path).unwrap().particles();
+    assert_eq!(particles.len(), state.particles.len());
+    for (loaded, saved) in particles.iter().zip(state.particles.iter()) {
+        assert_eq!(loaded.get_pos(), saved.get_pos());
+        assert_eq!(loaded.get_vel(), saved.get_vel());
+        assert!(loaded
+            .get_orientation()
+            .abs_diff_eq(saved.get_orientation(), 1e-6));
+    }
+    std::fs::write(&path, "(\n  time: 0.0,\n  steps: oops,\n)").unwrap();
+    match Snapshot::load(&path) {
+        Err(FileError::Format { line, .. }) => assert_eq!(line, Some(3)),
+        _ => panic!("expected a format error"),
+    }
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(Snapshot::load(&path), Err(FileError::Read { .. })));
+
+    // headless runs tell invalid setups from diverged ones
+    match experiment::run(
+        &SimulationPrototype::new().set_dt(0.0),
+        Quench::new(1.0, 0.5),
+    ) {
+        Err(SimulationError::Invalid(err)) => assert!(err.contains(ErrorKind::Dt)),
+        _ => panic!("expected an invalid setup"),
+    }
+    // the wrapper shows the text of the error it holds, so the chain doesn't repeat it
+    let invalid = match SimulationPrototype::new().set_dt(0.0).compile() {
+        Err(err) => err,
+        Ok(_) => panic!("expected an invalid setup"),
+    };
+    let wrapped = SimulationError::from(invalid);
+    assert!(wrapped.to_string().contains("Dt"));
+    assert!(std::error::Error::source(&wrapped).is_none());
+    // one step per frame, so it's caught before the particles fly off
+    let overlapping = SimulationPrototype::new()
+        .set_steps_per_frame(1)
+        .set_bound_x(10.0)
+        .set_bound_y(10.0)
+        .set_bound_z(10.0)
+        .set_particles(vec![
+            Particle::new().set_pos(5.0, 5.0, 5.0),
+            Particle::new().set_pos(5.01, 5.0, 5.0),
+        ]);
+    match experiment::run(&overlapping, Quench::new(1.0, 0.5)) {
+        Err(SimulationError::Diverged(err)) => {
+            assert_eq!(err.particles, vec![0, 1]);
+            assert_eq!(err.cause, Cause::Overlap);
+        }
+        other => panic!("expected a divergence, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn invalid_setups_explain_how_to_fix_them() {
+    let err = SimulationPrototype::new()
+        .set_bound_x(1.0)
+        .set_target_temp(1.0)
+        .set_inject_rate(100.0)
+        .set_particles(vec![
+            Particle::new().set_pos(0.5, 1.0, 1.0),
+            Particle::new().set_pos(0.5, 9.0, 1.0),
+            Particle::new().set_pos(0.5, 9.0, 9.0),
+        ])
+        .compile()
+        .err()
+        .expect("the setup is invalid");
+    assert_eq!(
+        err.kinds(),
+        vec![ErrorKind::Bound, ErrorKind::InjectRate, ErrorKind::Particle]
+    );
+    let messages: Vec<&str> = err.problems().iter().map(|p| p.message.as_str()).collect();
+    assert!(messages[0].starts_with("bound.x = 1.0 < MIN_LEN 2.0"));
+    // gain of 2 * 1.0 * 100 * 20 steps * 0.001
+    assert!(messages[1].contains("gain of 4.00"));
+    assert!(messages[2].starts_with("particles[1] at"));
+    assert!(messages[2].contains("(and 1 more)"));
+    assert!(err.to_string().contains(messages[0]));
+
+    // suspicious setups compile with warnings
+    let simulation = SimulationPrototype::new()
+        .set_target_temp(1.0)
+        .set_inject_rate(30.0)
+        .compile()
+        .unwrap();
+    let warnings = simulation.warnings();
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(warnings[0].kind, ErrorKind::InjectRate);
+    assert!(warnings[0].message.contains("overshoots"));
+    assert_eq!(warnings[1].kind, ErrorKind::Particle);
+    let langevin = SimulationPrototype::new()
+        .set_integrator(Integrator::Langevin { gamma: 1.0 })
+        .set_inject_rate(0.1)
+        .initialize_uniform(10, 0.5);
+    assert!(langevin.warnings()[0]
+        .message
+        .contains("ignored by the Langevin"));
+    let crowded = SimulationPrototype::new().set_particles(
+        (0..1000)
+            .map(|i| {
+                Particle::new()
+                    .set_pos(
+                        1.0 + (i % 10) as f32 * 0.3,
+                        1.0 + (i / 10 % 10) as f32 * 0.3,
+                        1.0 + (i / 100) as f32 * 0.3,
+                    )
+                    .set_radius(0.5)
+            })
+            .collect(),
+    );
+    assert!(crowded
+        .warnings()
+        .iter()
+        .any(|w| w.message.contains("close packing")));
+    assert!(crowded.estimated_memory() > 1000 * std::mem::size_of::<Particle>());
+}
+
+#[test]
+fn minimization_is_reported_to_the_caller() {
+    let crowded = || {
+        SimulationPrototype::new().set_particles(vec![
+            Particle::new().set_pos(2.0, 2.0, 2.0),
+            Particle::new().set_pos(2.1, 2.0, 2.0),
+        ])
+    };
+    let plain = match crowded().compile() {
+        Ok(simulation) => simulation,
+        Err(err) => panic!("{}", err),
+    };
+    assert!(plain.minimization().is_none());
+
+    let relaxed = match crowded().set_minimization(1000, 1e-3).compile() {
+        Ok(simulation) => simulation,
+        Err(err) => panic!("{}", err),
+    };
+    let report = relaxed.minimization().expect("a minimization report");
+    assert!(report.iterations > 0);
+    assert!(report.to_string().starts_with("minimization:"));
+}
+
+#[test]
+fn schedules_with_values_out_of_range_are_refused() {
+    let err = SimulationPrototype::new()
+        .add_event(Event::set(10, ScheduleParameter::Dt, f32::NAN))
+        .compile()
+        .err()
+        .expect("a NaN time step is invalid");
+    assert!(err.contains(ErrorKind::Schedule));
+
+    let cooling = SimulationPrototype::new()
+        .set_target_temp(1.0)
+        .add_event(Event::ramp(
+            0,
+            ScheduleParameter::TargetTemp,
+            1.0,
+            -1.0,
+            100,
+        ))
+        .compile();
+    assert!(cooling
+        .err()
+        .expect("negative temperatures")
+        .contains(ErrorKind::Schedule));
+}
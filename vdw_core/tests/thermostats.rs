@@ -0,0 +1,242 @@
+// Temperature control: the stochastic integrators, replica exchange, annealing and the heat gun
+// Run with `cargo test -p vdw_core`
+mod common;
+
+use common::{assert_close, compile, lattice};
+use glam::Vec3;
+use vdw_core::state::anneal::{AnnealMode, Annealer};
+use vdw_core::state::integrator::Integrator;
+use vdw_core::state::replica::ReplicaExchange;
+use vdw_core::state::sim_space::{Grid, WallModel};
+use vdw_core::state::state_generator::Initialize;
+use vdw_core::state::{SimulationPrototype, SimulationState};
+
+#[test]
+fn langevin_bath_reaches_target_temperature() {
+    let langevin = |seed: u64| {
+        compile(
+            SimulationPrototype::new()
+                .set_wall_model(WallModel::HardReflect)
+                .set_integrator(Integrator::Langevin { gamma: 5.0 })
+                .set_seed(seed)
+                .set_target_temp(1.0)
+                .set_dt(0.001)
+                .set_particles(lattice(5, 0.9, 0.0)),
+        )
+    };
+
+    let mut state = langevin(7);
+    for _ in 0..2000 {
+        state.step();
+    }
+    let mut mean_temp = 0.0;
+    let samples = 2000;
+    for _ in 0..samples {
+        state.step();
+        state.recalculate_kinetic_energy();
+        mean_temp += state.energy.kinetic / state.particles.len() as f32 / samples as f32;
+    }
+    assert_close(mean_temp, 1.0, 0.1, "mean kinetic energy per particle");
+
+    // the same seed gives the same trajectory
+    let (mut a, mut b) = (langevin(3), langevin(3));
+    for _ in 0..100 {
+        a.step();
+        b.step();
+    }
+    for (pa, pb) in a.particles.iter().zip(b.particles.iter()) {
+        assert_eq!(pa.get_pos(), pb.get_pos());
+    }
+}
+
+#[test]
+fn thermostats_heat_the_rotations_to_the_target() {
+    // the Langevin bath kicks the rotations along the body axes like the velocities
+    let mut state = compile(
+        SimulationPrototype::new()
+            .set_wall_model(WallModel::HardReflect)
+            .set_integrator(Integrator::Langevin { gamma: 5.0 })
+            .set_seed(11)
+            .set_target_temp(1.0)
+            .set_dt(0.001)
+            .set_particles(lattice(5, 0.9, 0.0)),
+    );
+    for _ in 0..2000 {
+        state.step();
+    }
+    let mut mean_temp = 0.0;
+    let samples = 2000;
+    for _ in 0..samples {
+        state.step();
+        state.recalculate_kinetic_energy();
+        mean_temp += state.rotational_temperature() / samples as f32;
+    }
+    assert_close(mean_temp, 1.0, 0.1, "mean rotational energy per particle");
+
+    // the rescaling thermostat pulls the rotations up to the target on their own
+    let spinning = lattice(4, 1.0, 0.5)
+        .into_iter()
+        .map(|p| p.set_ang_vel(10.0, 0.0, 0.0))
+        .collect();
+    let mut state = compile(
+        SimulationPrototype::new()
+            .set_wall_model(WallModel::HardReflect)
+            .set_target_temp(1.0)
+            .set_inject_rate(2.0)
+            .set_dt(0.001)
+            .set_particles(spinning),
+    );
+    state.recalculate_kinetic_energy();
+    assert!(state.rotational_temperature() < 0.2);
+    for step in 0..5000 {
+        state.step();
+        if step % 10 == 0 {
+            state.recalculate_kinetic_energy();
+        }
+    }
+    state.recalculate_kinetic_energy();
+    assert_close(
+        state.rotational_temperature(),
+        1.0,
+        0.05,
+        "rotational temperature",
+    );
+}
+
+#[test]
+fn brownian_particles_diffuse_at_einstein_rate() {
+    let (gamma, target_temp) = (10.0, 1.5);
+    let mut state = compile(
+        SimulationPrototype::new()
+            .set_wall_model(WallModel::HardReflect)
+            .set_integrator(Integrator::Brownian { gamma })
+            .set_seed(11)
+            .set_target_temp(target_temp)
+            .set_dt(0.001)
+            .set_particles(lattice(5, 0.9, 0.0)),
+    );
+    let start: Vec<Vec3> = state.particles.iter().map(|p| p.get_pos()).collect();
+
+    let steps = 200;
+    for _ in 0..steps {
+        state.step();
+    }
+    let msd = state
+        .particles
+        .iter()
+        .zip(start.iter())
+        .map(|(p, &x0)| (p.get_pos() - x0).length_squared())
+        .sum::<f32>()
+        / start.len() as f32;
+
+    // D = kT / (m gamma), with kT two thirds of the kinetic temperature
+    let diffusion = 2.0 / 3.0 * target_temp / gamma;
+    let elapsed = steps as f32 * 0.001;
+    assert_close(msd / (6.0 * diffusion * elapsed), 1.0, 0.25, "scaled MSD");
+    assert!(state.particles.iter().all(|p| p.get_vel() == Vec3::ZERO));
+}
+
+#[test]
+fn exchanged_configurations_take_the_new_temperature() {
+    let prototype = SimulationPrototype::new()
+        .set_wall_model(WallModel::HardReflect)
+        .set_dt(0.001)
+        .set_particles(lattice(4, 0.5, 1.0));
+    let mut exchange = ReplicaExchange::new(&prototype, &[2.0, 0.5]).unwrap();
+    let temperature = |state: &SimulationState| state.energy.kinetic / state.particles.len() as f32;
+    assert_eq!(exchange.temperatures(), vec![0.5, 2.0]);
+    assert_close(
+        temperature(&exchange.replicas[0]),
+        0.5,
+        1e-3,
+        "cold replica",
+    );
+    assert_close(temperature(&exchange.replicas[1]), 2.0, 1e-3, "hot replica");
+
+    let (cold, hot) = exchange.replicas.split_at_mut(1);
+    let cold_positions: Vec<Vec3> = cold[0].particles.iter().map(|p| p.get_pos()).collect();
+    cold[0].exchange_configuration(&mut hot[0]);
+
+    // the configurations trade places, each replica keeps its temperature
+    let moved: Vec<Vec3> = hot[0].particles.iter().map(|p| p.get_pos()).collect();
+    assert_eq!(moved, cold_positions);
+    assert_close(
+        temperature(&cold[0]),
+        0.5,
+        1e-3,
+        "cold replica after the swap",
+    );
+    assert_close(
+        temperature(&hot[0]),
+        2.0,
+        1e-3,
+        "hot replica after the swap",
+    );
+}
+
+#[test]
+fn annealing_drives_the_thermostat_target() {
+    let mut state = SimulationPrototype::new()
+        .set_target_temp(1.0)
+        .set_inject_rate(0.1)
+        .compile()
+        .unwrap()
+        .into_state();
+    state.annealer = Annealer::default();
+    state.annealer.start_temp = 1.0;
+    state.annealer.end_temp = 0.2;
+    state.annealer.duration = 0.5;
+    state.annealer.mode = AnnealMode::Reverse;
+    state.annealer.start(state.time);
+    let dt = state.dt;
+    let run_until = |state: &mut SimulationState, time: f32| {
+        while state.time < time - 0.5 * dt {
+            state.step();
+        }
+    };
+
+    run_until(&mut state, 0.25);
+    assert_close(state.target_temp, 0.6, 1e-2, "halfway down");
+    run_until(&mut state, 0.5);
+    assert_close(state.target_temp, 0.2, 1e-2, "end of the ramp");
+    run_until(&mut state, 1.0);
+    assert_close(state.target_temp, 1.0, 1e-2, "back at the start");
+
+    // a ramp run once stops at the end temperature
+    state.annealer.mode = AnnealMode::Once;
+    state.annealer.start(state.time);
+    run_until(&mut state, 2.0);
+    assert!(!state.annealer.running);
+    assert_close(state.target_temp, 0.2, 1e-6, "after the ramp");
+}
+
+#[test]
+fn heat_gun_kicks_only_the_particles_in_its_ball() {
+    let mut state = compile(
+        SimulationPrototype::new()
+            .set_bound_x(3.0)
+            .set_bound_y(3.0)
+            .set_bound_z(3.0)
+            .initialize_uniform(300, 0.0),
+    );
+    let center = Vec3::new(1.0, 1.5, 2.0);
+    let radius = 0.7;
+    let positions: Vec<Vec3> = state.particles.iter().map(|p| p.get_pos()).collect();
+    let inside: Vec<usize> = (0..positions.len())
+        .filter(|&i| (positions[i] - center).length() <= radius)
+        .collect();
+    assert!(!inside.is_empty());
+    assert_eq!(
+        Grid::new(0.3, 2).find_within(&positions, center, radius),
+        inside
+    );
+
+    state.particles[inside[0]].fix(true);
+    let before: Vec<Vec3> = state.particles.iter().map(|p| p.get_vel()).collect();
+    assert_eq!(state.heat_region(center, radius, 1.0, 7), inside.len() - 1);
+    for (i, (particle, &vel)) in state.particles.iter().zip(before.iter()).enumerate() {
+        let kicked = i != inside[0] && inside.contains(&i);
+        assert_eq!(particle.get_vel() != vel, kicked, "particle {}", i);
+    }
+    assert!(state.energy.kinetic > 0.0);
+}